@@ -5,7 +5,7 @@ pub mod prelude;
 use crate::BoxedFuture;
 use futures::future::FutureExt;
 use serde::Deserialize;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
 
 use std::borrow::Cow;
 use std::future::Future;
@@ -66,21 +66,35 @@ macro_rules! define_blocks {
 }
 
 define_blocks!(
+    alarm,
     apt,
     backlight,
+    backup,
     battery,
     bluetooth,
+    calendar,
+    cert_expiry,
     cpu,
+    crypto,
     custom,
     custom_dbus,
     disk_space,
     dnf,
+    dns,
     docker,
     external_ip,
+    failed_units,
     focused_window,
     github,
+    github_actions,
+    gitlab,
     hueshift,
+    http_check,
+    imap,
+    jenkins,
     kdeconnect,
+    kubernetes,
+    libvirt,
     load,
     #[cfg(feature = "maildir")]
     maildir,
@@ -88,15 +102,22 @@ define_blocks!(
     memory,
     music,
     net,
+    nextcloud,
     notify,
     #[cfg(feature = "notmuch")]
     notmuch,
     nvidia_gpu,
     pacman,
+    ping,
     pomodoro,
     rofication,
+    rss,
     sound,
     speedtest,
+    ssh_sessions,
+    stopwatch,
+    syncthing,
+    tailscale,
     keyboard_layout,
     taskwarrior,
     temperature,
@@ -104,8 +125,10 @@ define_blocks!(
     tea_timer,
     toggle,
     uptime,
+    vpn,
     watson,
     weather,
+    wireguard,
     xrandr,
 );
 
@@ -119,7 +142,8 @@ pub enum BlockEvent {
 
 pub struct CommonApi {
     pub id: usize,
-    pub shared_config: SharedConfig,
+    /// Updated by the bar when the theme or the icons are reloaded
+    pub shared_config: watch::Receiver<SharedConfig>,
     pub event_receiver: mpsc::Receiver<BlockEvent>,
 
     pub request_sender: mpsc::Sender<Request>,
@@ -233,6 +257,7 @@ impl CommonApi {
 
     pub fn get_icon(&self, icon: &str) -> Result<String> {
         self.shared_config
+            .borrow()
             .get_icon(icon)
             .or_error(|| format!("Icon '{icon}' not found"))
     }
@@ -0,0 +1,241 @@
+//! Recurring alarms
+//!
+//! Shows the next of one or more daily or weekly alarms and the time left until it. When an alarm
+//! goes off, `alarm_cmd` is run and the block switches to the critical state until the alarm is
+//! snoozed or dismissed. Dismissing while no alarm is ringing skips the next alarm, e.g. on a day
+//! off, or cancels a snoozed one.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `alarms` | The alarms, see below | **Required**
+//! `format` | A string to customise the output of this block. See below for available placeholders. | <code>" $icon {$name &vert;}$time{ in $until&vert;} "</code>
+//! `alarm_cmd` | A command to run in `sh` when an alarm goes off | `None`
+//! `snooze` | Minutes to snooze an alarm for | `9`
+//!
+//! Each alarm has the following options:
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `time` | The time of day of the alarm, as `"HH:MM"` | **Required**
+//! `days` | The days of the week on which the alarm goes off, e.g. `["mon", "fri"]`. Every day if empty. | `[]`
+//! `name` | A name for the alarm | `None`
+//!
+//! Placeholder | Value                                                                  | Type | Unit
+//! ------------|------------------------------------------------------------------------|------|-----
+//! `icon`      | A static icon                                                          | Icon | -
+//! `name`      | The name of the next or ringing alarm. Missing if it has none.         | Text | -
+//! `time`      | The time of the next or ringing alarm, e.g. `"07:00"` or `"Mon 07:00"` | Text | -
+//! `until`     | The time until the next alarm, e.g. `"8h 20m"`. Missing while ringing. | Text | -
+//! `ringing`   | Present while an alarm is ringing                                      | Flag | -
+//! `snoozed`   | Present if the next alarm is a snoozed one                             | Flag | -
+//!
+//! Action    | Description                                                  | Default button
+//! ----------|--------------------------------------------------------------|---------------
+//! `snooze`  | Snooze the ringing alarm                                     | Left
+//! `dismiss` | Dismiss the ringing alarm, or skip or cancel the next alarm  | Right
+//!
+//! # Example
+//!
+//! ```toml
+//! [[block]]
+//! block = "alarm"
+//! alarm_cmd = "notify-send -u critical Alarm; paplay /usr/share/sounds/freedesktop/stereo/alarm-clock-elapsed.oga"
+//! [[block.alarms]]
+//! time = "07:00"
+//! days = ["mon", "tue", "wed", "thu", "fri"]
+//! [[block.alarms]]
+//! time = "09:30"
+//! days = ["sat", "sun"]
+//! name = "weekend"
+//! ```
+//!
+//! # Icons Used
+//! - `bell`
+
+use super::prelude::*;
+use crate::subprocess::spawn_shell;
+use chrono::{DateTime, Datelike, Local, NaiveTime, TimeZone, Timelike, Weekday};
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(deny_unknown_fields, default)]
+pub struct Config {
+    alarms: Vec<AlarmConfig>,
+    format: FormatConfig,
+    alarm_cmd: Option<String>,
+    #[default(9)]
+    snooze: i64,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct AlarmConfig {
+    time: String,
+    #[serde(default)]
+    days: Vec<String>,
+    name: Option<String>,
+}
+
+struct Alarm {
+    time: NaiveTime,
+    days: Vec<Weekday>,
+    name: Option<String>,
+}
+
+impl Alarm {
+    /// The first time this alarm goes off after `after`
+    fn next(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        let mut date = after.date_naive();
+        for _ in 0..=7 {
+            if self.days.is_empty() || self.days.contains(&date.weekday()) {
+                if let Some(time) = Local
+                    .from_local_datetime(&date.and_time(self.time))
+                    .earliest()
+                {
+                    if time > after {
+                        return Some(time);
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+}
+
+/// The next time an alarm goes off, and which alarm it is
+#[derive(Clone, Copy)]
+struct Upcoming {
+    time: DateTime<Local>,
+    alarm: usize,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[
+        (MouseButton::Left, None, "snooze"),
+        (MouseButton::Right, None, "dismiss"),
+    ])
+    .await?;
+
+    let mut widget = Widget::new().with_format(
+        config
+            .format
+            .with_default(" $icon {$name |}$time{ in $until|} ")?,
+    );
+
+    let alarms = config
+        .alarms
+        .iter()
+        .map(|alarm| {
+            Ok(Alarm {
+                time: NaiveTime::parse_from_str(&alarm.time, "%H:%M")
+                    .or_error(|| format!("Invalid alarm time '{}'", alarm.time))?,
+                days: alarm
+                    .days
+                    .iter()
+                    .map(|day| day.parse().or_error(|| format!("Invalid day '{day}'")))
+                    .collect::<Result<_>>()?,
+                name: alarm.name.clone(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if alarms.is_empty() {
+        return Err(Error::new("`alarms` must not be empty"));
+    }
+    let snooze = chrono::Duration::minutes(config.snooze);
+
+    // Alarms up to this time have gone off or were skipped
+    let mut done_until = Local::now();
+    let mut ringing: Option<Upcoming> = None;
+    let mut snoozed: Option<Upcoming> = None;
+
+    loop {
+        let now = Local::now();
+        let scheduled = alarms
+            .iter()
+            .enumerate()
+            .filter_map(|(i, alarm)| {
+                Some(Upcoming {
+                    time: alarm.next(done_until)?,
+                    alarm: i,
+                })
+            })
+            .min_by_key(|u| u.time)
+            .error("No upcoming alarm")?;
+        let next = match snoozed {
+            Some(snoozed) if snoozed.time < scheduled.time => snoozed,
+            _ => scheduled,
+        };
+        let is_snoozed = snoozed.map_or(false, |s| s.time == next.time);
+
+        if next.time <= now {
+            if is_snoozed {
+                snoozed = None;
+            } else {
+                done_until = next.time;
+            }
+            ringing = Some(next);
+            if let Some(cmd) = &config.alarm_cmd {
+                spawn_shell(cmd).error("alarm_cmd error")?;
+            }
+            continue;
+        }
+
+        let shown = ringing.unwrap_or(next);
+        let alarm = &alarms[shown.alarm];
+        let time = if shown.time.date_naive() == now.date_naive() {
+            shown.time.format("%H:%M").to_string()
+        } else {
+            shown.time.format("%a %H:%M").to_string()
+        };
+        // Round up, so that an alarm in 30 seconds is one minute away
+        let minutes = ((next.time - now).num_seconds() + 59) / 60;
+        let until = if minutes >= 60 {
+            format!("{}h {}m", minutes / 60, minutes % 60)
+        } else {
+            format!("{minutes}m")
+        };
+        widget.state = if ringing.is_some() {
+            State::Critical
+        } else {
+            State::Idle
+        };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("bell")?),
+            "time" => Value::text(time),
+            [if ringing.is_none()] "until" => Value::text(until),
+            [if let Some(name) = &alarm.name] "name" => Value::text(name.clone()),
+            [if ringing.is_some()] "ringing" => Value::flag(),
+            [if is_snoozed] "snoozed" => Value::flag(),
+        });
+        api.set_widget(&widget).await?;
+
+        // Wake up for the next alarm or to update the time until it
+        let wait = (next.time - now)
+            .min(chrono::Duration::seconds(
+                60 - i64::from(now.time().second()),
+            ))
+            .to_std()
+            .unwrap_or_default();
+        select! {
+            _ = sleep(wait) => (),
+            event = api.event() => match event {
+                UpdateRequest => (),
+                Action(action) => match (action.as_ref(), ringing) {
+                    ("snooze", Some(alarm)) => {
+                        ringing = None;
+                        snoozed = Some(Upcoming {
+                            time: Local::now() + snooze,
+                            alarm: alarm.alarm,
+                        });
+                    }
+                    ("dismiss", Some(_)) => ringing = None,
+                    ("dismiss", None) if is_snoozed => snoozed = None,
+                    ("dismiss", None) => done_until = scheduled.time,
+                    _ => (),
+                },
+            }
+        }
+    }
+}
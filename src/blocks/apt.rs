@@ -12,13 +12,24 @@
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $count.eng(w:1) "`
 //! `format_singular` | Same as `format`, but for when exactly one update is available. | `" $icon $count.eng(w:1) "`
 //! `format_up_to_date` | Same as `format`, but for when no updates are available. | `" $icon $count.eng(w:1) "`
+//! `format_security` | If set, used instead of `format` and `format_singular` when security updates are available. | `None`
 //! `warning_updates_regex` | Display block as warning if updates matching regex are available. | `None`
 //! `critical_updates_regex` | Display block as critical if updates matching regex are available. | `None`
+//! `critical_security_updates` | Display block as critical if security updates are available. | `false`
+//! `upgrade_command` | Shell command to run on `upgrade` action, e.g. `"alacritty -e sudo apt upgrade"`. The block is updated when the command exits. | `None`
+//! `preview_lines` | Maximum number of packages shown by the `show_updates` action. | `10`
+//! `preview_cmd` | Shell command which receives the list of pending updates on stdin on `show_updates` action. If not set, the list is sent as a notification using `notify-send`. | `None`
 //!
-//! Placeholder | Value                       | Type   | Unit
-//! ------------|-----------------------------|--------|------
-//! `icon`      | A static icon               | Icon   | -
-//! `count`     | Number of updates available | Number | -
+//! Placeholder | Value                                                     | Type   | Unit
+//! ------------|-----------------------------------------------------------|--------|------
+//! `icon`      | A static icon                                             | Icon   | -
+//! `count`     | Number of updates available                               | Number | -
+//! `security`  | Number of updates available from `-security` repositories | Number | -
+//!
+//! Action         | Default button
+//! ---------------|---------------
+//! `upgrade`      | Right
+//! `show_updates` | Middle
 //!
 //! # Example
 //!
@@ -32,14 +43,13 @@
 //! format_singular = " $icon One update available "
 //! format_up_to_date = " $icon system up to date "
 //! critical_updates_regex = "(linux|linux-lts|linux-zen)"
+//! format_security = " $icon $count ($security security) "
+//! critical_security_updates = true
+//! upgrade_command = "alacritty -e sudo apt upgrade"
 //! [[block.click]]
 //! # shows dmenu with cached available updates. Any dmenu alternative should also work.
 //! button = "left"
 //! cmd = "APT_CONFIG=/tmp/i3rs-apt/apt.conf apt list --upgradable | tail -n +2 | rofi -dmenu"
-//! [[block.click]]
-//! # Updates the block on right click
-//! button = "right"
-//! update = true
 //! ```
 //!
 //! # Icons Used
@@ -52,9 +62,10 @@ use std::process::Stdio;
 use regex::Regex;
 
 use tokio::fs::{create_dir_all, File};
-use tokio::process::Command;
+use tokio::process::{Child, Command};
 
 use super::prelude::*;
+use crate::subprocess::spawn_process;
 
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(default)]
@@ -64,11 +75,23 @@ pub struct Config {
     format: FormatConfig,
     format_singular: FormatConfig,
     format_up_to_date: FormatConfig,
+    format_security: Option<FormatConfig>,
     warning_updates_regex: Option<String>,
     critical_updates_regex: Option<String>,
+    critical_security_updates: bool,
+    upgrade_command: Option<String>,
+    #[default(10)]
+    preview_lines: usize,
+    preview_cmd: Option<String>,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[
+        (MouseButton::Right, None, "upgrade"),
+        (MouseButton::Middle, None, "show_updates"),
+    ])
+    .await?;
+
     let mut widget = Widget::new();
 
     let format = config.format.with_default(" $icon $count.eng(w:1) ")?;
@@ -78,6 +101,10 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     let format_up_to_date = config
         .format_up_to_date
         .with_default(" $icon $count.eng(w:1) ")?;
+    let format_security = match config.format_security {
+        Some(f) => Some(f.with_default("")?),
+        None => None,
+    };
 
     let warning_updates_regex = config
         .warning_updates_regex
@@ -119,17 +146,22 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         .await
         .error("Failed to write to config file")?;
 
+    let mut upgrade: Option<Child> = None;
+
     loop {
         let updates = get_updates_list(config_file.to_str().unwrap()).await?;
         let count = get_update_count(&updates);
+        let security = get_security_update_count(&updates);
 
-        widget.set_format(match count {
-            0 => format_up_to_date.clone(),
-            1 => format_singular.clone(),
+        widget.set_format(match (count, security, &format_security) {
+            (0, _, _) => format_up_to_date.clone(),
+            (_, 1.., Some(format_security)) => format_security.clone(),
+            (1, _, _) => format_singular.clone(),
             _ => format.clone(),
         });
         widget.set_values(map!(
             "count" => Value::number(count),
+            "security" => Value::number(security),
             "icon" => Value::icon(api.get_icon("update")?)
         ));
 
@@ -142,7 +174,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         widget.state = match count {
             0 => State::Idle,
             _ => {
-                if critical {
+                if critical || (config.critical_security_updates && security > 0) {
                     State::Critical
                 } else if warning {
                     State::Warning
@@ -154,13 +186,86 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
 
         api.set_widget(&widget).await?;
 
-        select! {
-            _ = sleep(config.interval.0) => (),
-            _ = api.wait_for_update_request() => (),
+        let timer = sleep(config.interval.0);
+        tokio::pin!(timer);
+        loop {
+            select! {
+                _ = &mut timer => break,
+                _ = async { upgrade.as_mut().unwrap().wait().await }, if upgrade.is_some() => {
+                    upgrade = None;
+                    break;
+                }
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "upgrade" => {
+                        if let (Some(cmd), None) = (&config.upgrade_command, &upgrade) {
+                            upgrade = Some(
+                                Command::new("sh")
+                                    .args(["-c", cmd])
+                                    .stdin(Stdio::null())
+                                    .stdout(Stdio::null())
+                                    .spawn()
+                                    .error("Failed to run upgrade_command")?,
+                            );
+                        }
+                    }
+                    Action(a) if a == "show_updates" => {
+                        let preview = updates_preview(&updates, config.preview_lines);
+                        show_preview(config.preview_cmd.as_deref(), &preview).await?;
+                    }
+                    _ => (),
+                }
+            }
         }
     }
 }
 
+/// The first `max_lines` pending updates, one `<package> <version>` per line
+fn updates_preview(updates: &str, max_lines: usize) -> String {
+    let packages: Vec<&str> = updates
+        .lines()
+        .filter(|line| line.contains("[upgradable"))
+        .collect();
+    let mut preview: Vec<String> = packages
+        .iter()
+        .take(max_lines)
+        .map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next().unwrap_or_default();
+            let name = name.split_once('/').map_or(name, |(name, _)| name);
+            let version = fields.next().unwrap_or_default();
+            format!("{name} {version}")
+        })
+        .collect();
+    if packages.len() > max_lines {
+        preview.push(format!("... and {} more", packages.len() - max_lines));
+    }
+    preview.join("\n")
+}
+
+async fn show_preview(cmd: Option<&str>, preview: &str) -> Result<()> {
+    match cmd {
+        Some(cmd) => {
+            let mut child = Command::new("sh")
+                .args(["-c", cmd])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .spawn()
+                .error("Failed to run preview_cmd")?;
+            let mut stdin = child.stdin.take().unwrap();
+            stdin
+                .write_all(preview.as_bytes())
+                .await
+                .error("Failed to write to preview_cmd")?;
+        }
+        None => {
+            spawn_process("notify-send", &["Pending updates", preview])
+                .error("Failed to run notify-send")?;
+        }
+    }
+    Ok(())
+}
+
 async fn get_updates_list(config_path: &str) -> Result<String> {
     Command::new("apt")
         .env("APT_CONFIG", config_path)
@@ -189,6 +294,24 @@ fn get_update_count(updates: &str) -> usize {
         .count()
 }
 
+/// Count the updates which come from a `-security` pocket, e.g.
+/// `libssl3/jammy-updates,jammy-security 3.0.2-0ubuntu1.10 amd64 [upgradable from: ...]`
+fn get_security_update_count(updates: &str) -> usize {
+    updates
+        .lines()
+        .filter(|line| line.contains("[upgradable"))
+        .filter(|line| {
+            line.split_once('/')
+                .and_then(|(_, rest)| rest.split_whitespace().next())
+                .map_or(false, |pockets| {
+                    pockets
+                        .split(',')
+                        .any(|pocket| pocket.ends_with("-security"))
+                })
+        })
+        .count()
+}
+
 fn has_matching_update(updates: &str, regex: &Regex) -> bool {
     updates.lines().any(|line| regex.is_match(line))
 }
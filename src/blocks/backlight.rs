@@ -9,6 +9,8 @@
 //!
 //! Some devices expose raw values that are best handled with nonlinear scaling. The human perception of lightness is close to the cube root of relative luminance, so settings for `root_scaling` between 2.4 and 3.0 are worth trying. For devices with few discrete steps this should be 1.0 (linear). More information: <https://en.wikipedia.org/wiki/Lightness>
 //!
+//! Alternatively, `perceptual = true` uses the CIE 1976 lightness curve, so that every step at the low end feels as large as one at the high end. `brightness`, `step_width`, `minimum`, `maximum` and `cycle` then refer to the perceived brightness and `root_scaling` is ignored.
+//!
 //! # Configuration
 //!
 //! Key | Values | Default
@@ -16,16 +18,20 @@
 //! `device` | The `/sys/class/backlight` device to read brightness information from.  When there is no `device` specified, this block will display information from the first device found in the `/sys/class/backlight` directory. If you only have one display, this approach should find it correctly.| Default device
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $brightness "`
 //! `step_width` | The brightness increment to use when scrolling, in percent | `5`
-//! `minimum` | The minimum brightness that can be scrolled down to | `5`
+//! `minimum` | The minimum brightness that can be scrolled down to or cycled to. Keeps the screen from going completely black. | `5`
 //! `maximum` | The maximum brightness that can be scrolled up to | `100`
 //! `cycle` | The brightnesses to cycle through on each click | `[minimum, maximum]`
 //! `root_scaling` | Scaling exponent reciprocal (ie. root) | `1.0`
+//! `perceptual` | Use the CIE lightness curve instead of `root_scaling`, see above | `false`
 //! `invert_icons` | Invert icons' ordering, useful if you have colorful emoji | `false`
+//! `icon_levels` | Icons to use for ranges of brightness, see [`IconLevels`](crate::icons::IconLevels) | The icons listed below
 //!
 //! Placeholder  | Value                                     | Type   | Unit
 //! -------------|-------------------------------------------|--------|---------------
 //! `icon`       | Icon based on backlight's state           | Icon   | -
 //! `brightness` | Current brightness                        | Number | %
+//! `brightness_raw` | The raw brightness value of the device | Number | -
+//! `brightness_perceived` | Current brightness according to the CIE lightness curve | Number | %
 //!
 //! Action            | Default button
 //! ------------------|---------------
@@ -41,6 +47,16 @@
 //! device = "intel_backlight"
 //! ```
 //!
+//! Evenly feeling steps that never turn the screen off:
+//!
+//! ```toml
+//! [[block]]
+//! block = "backlight"
+//! perceptual = true
+//! step_width = 10
+//! minimum = 10
+//! ```
+//!
 //! # Icons Used
 //! - `backlight_empty` (when brightness between 0 and 6%)
 //! - `backlight_1` (when brightness between 7 and 13%)
@@ -66,6 +82,7 @@ use inotify::{Inotify, WatchMask};
 use tokio::fs::read_dir;
 
 use super::prelude::*;
+use crate::icons::IconLevels;
 use crate::util::read_file;
 
 make_log_macro!(debug, "backlight");
@@ -129,7 +146,9 @@ pub struct Config {
     cycle: Option<Vec<u8>>,
     #[default(1.0)]
     root_scaling: f64,
+    perceptual: bool,
     invert_icons: bool,
+    icon_levels: Option<IconLevels>,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
@@ -142,15 +161,32 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
 
     let mut widget = Widget::new().with_format(config.format.with_default(" $icon $brightness ")?);
 
+    let mut icon_levels = config
+        .icon_levels
+        .unwrap_or_else(|| IconLevels::evenly(BACKLIGHT_ICONS, 100.));
+    if config.invert_icons {
+        icon_levels = icon_levels.inverted();
+    }
+
     let mut cycle = config
         .cycle
         .unwrap_or_else(|| vec![config.minimum, config.maximum])
         .into_iter()
+        .map(|b| b.clamp(config.minimum, config.maximum))
         .cycle();
 
+    let scaling = if config.perceptual {
+        Scaling::Perceptual
+    } else {
+        Scaling::Root(
+            config
+                .root_scaling
+                .clamp(ROOT_SCALDING_RANGE.start, ROOT_SCALDING_RANGE.end),
+        )
+    };
     let device = match &config.device {
-        None => BacklightDevice::default(config.root_scaling).await?,
-        Some(path) => BacklightDevice::from_device(path, config.root_scaling).await?,
+        None => BacklightDevice::default(scaling).await?,
+        Some(path) => BacklightDevice::from_device(path, scaling).await?,
     };
 
     // Watch for brightness changes
@@ -163,15 +199,15 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         .error("Failed to create event stream")?;
 
     loop {
-        let brightness = device.brightness().await?;
-        let mut icon_index = (usize::from(brightness) * BACKLIGHT_ICONS.len()) / 101;
-        if config.invert_icons {
-            icon_index = BACKLIGHT_ICONS.len() - icon_index - 1;
-        }
+        let raw = device.raw_brightness().await?;
+        let brightness = device.to_percent(raw)?;
+        let perceived = Scaling::Perceptual.from_ratio(raw as f64 / device.max_brightness as f64);
 
         widget.set_values(map! {
-            "icon" => Value::icon(api.get_icon(BACKLIGHT_ICONS[icon_index])?),
-            "brightness" => Value::percents(brightness)
+            "icon" => Value::icon(api.get_icon(icon_levels.get(brightness.into()))?),
+            "brightness" => Value::percents(brightness),
+            "brightness_raw" => Value::number(raw),
+            "brightness_perceived" => Value::percents((perceived * 100.).round()),
         });
         api.set_widget(&widget).await?;
 
@@ -219,17 +255,47 @@ async fn read_brightness_raw(device_file: &Path) -> Result<u64> {
         .error("Failed to read value from brightness file")
 }
 
+/// How the raw brightness ratio is mapped to the displayed percentage
+#[derive(Debug, Clone, Copy)]
+enum Scaling {
+    /// Scaling exponent reciprocal
+    Root(f64),
+    /// CIE 1976 lightness
+    Perceptual,
+}
+
+impl Scaling {
+    /// Map a ratio of the maximum raw brightness to a ratio of the displayed brightness
+    #[allow(clippy::wrong_self_convention)]
+    fn from_ratio(self, ratio: f64) -> f64 {
+        match self {
+            Self::Root(root) => ratio.powf(root.recip()),
+            Self::Perceptual if ratio > 216. / 24389. => 1.16 * ratio.cbrt() - 0.16,
+            Self::Perceptual => ratio * 24389. / 2700.,
+        }
+    }
+
+    /// The inverse of [`Self::from_ratio`]
+    fn to_ratio(self, brightness: f64) -> f64 {
+        match self {
+            Self::Root(root) => brightness.powf(root),
+            Self::Perceptual if brightness > 0.08 => ((brightness + 0.16) / 1.16).powi(3),
+            Self::Perceptual => brightness * 2700. / 24389.,
+        }
+    }
+}
+
 /// Represents a physical backlight device whose brightness level can be queried.
 struct BacklightDevice {
     device_name: String,
     brightness_file: PathBuf,
     max_brightness: u64,
-    root_scaling: f64,
+    scaling: Scaling,
     dbus_proxy: SessionProxy<'static>,
 }
 
 impl BacklightDevice {
-    async fn new(device_path: PathBuf, root_scaling: f64) -> Result<Self> {
+    async fn new(device_path: PathBuf, scaling: Scaling) -> Result<Self> {
         let dbus_conn = new_system_dbus_connection().await?;
         Ok(Self {
             brightness_file: device_path.join({
@@ -244,7 +310,7 @@ impl BacklightDevice {
                 .map(|x| x.to_str().unwrap().into())
                 .error("Malformed device path")?,
             max_brightness: read_brightness_raw(&device_path.join(FILE_MAX_BRIGHTNESS)).await?,
-            root_scaling: root_scaling.clamp(ROOT_SCALDING_RANGE.start, ROOT_SCALDING_RANGE.end),
+            scaling,
             dbus_proxy: SessionProxy::new(&dbus_conn)
                 .await
                 .error("failed to create SessionProxy")?,
@@ -253,7 +319,7 @@ impl BacklightDevice {
 
     /// Use the default backlight device, i.e. the first one found in the
     /// `/sys/class/backlight` directory.
-    async fn default(root_scaling: f64) -> Result<Self> {
+    async fn default(scaling: Scaling) -> Result<Self> {
         let device = read_dir(DEVICES_PATH)
             .await
             .error("Failed to read backlight device directory")?
@@ -261,21 +327,25 @@ impl BacklightDevice {
             .await
             .error("No backlight devices found")?
             .error("Failed to read default device file")?;
-        Self::new(device.path(), root_scaling).await
+        Self::new(device.path(), scaling).await
     }
 
     /// Use the backlight device `device`. Returns an error if a directory for
     /// that device is not found.
-    async fn from_device(device: &str, root_scaling: f64) -> Result<Self> {
-        Self::new(Path::new(DEVICES_PATH).join(device), root_scaling).await
+    async fn from_device(device: &str, scaling: Scaling) -> Result<Self> {
+        Self::new(Path::new(DEVICES_PATH).join(device), scaling).await
     }
 
-    /// Query the brightness value for this backlight device, as a percent.
-    async fn brightness(&self) -> Result<u8> {
-        let raw = read_brightness_raw(&self.brightness_file).await?;
+    /// Query the raw brightness value for this backlight device.
+    async fn raw_brightness(&self) -> Result<u64> {
+        read_brightness_raw(&self.brightness_file).await
+    }
 
-        let brightness_ratio =
-            (raw as f64 / self.max_brightness as f64).powf(self.root_scaling.recip());
+    /// Convert a raw brightness value to a percent.
+    fn to_percent(&self, raw: u64) -> Result<u8> {
+        let brightness_ratio = self
+            .scaling
+            .from_ratio(raw as f64 / self.max_brightness as f64);
 
         ((brightness_ratio * 100.0).round() as i64)
             .try_into()
@@ -287,7 +357,7 @@ impl BacklightDevice {
     /// Set the brightness value for this backlight device, as a percent.
     async fn set_brightness(&self, value: u8) -> Result<()> {
         let value = value.clamp(0, 100);
-        let ratio = (value as f64 / 100.0).powf(self.root_scaling);
+        let ratio = self.scaling.to_ratio(value as f64 / 100.0);
         let raw = max(1, (ratio * (self.max_brightness as f64)).round() as u32);
         self.dbus_proxy
             .set_brightness("backlight", &self.device_name, raw)
@@ -295,3 +365,18 @@ impl BacklightDevice {
             .error("Failed to send D-Bus message")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perceptual_scaling_roundtrip() {
+        for percent in 0..=100 {
+            let brightness = percent as f64 / 100.;
+            let ratio = Scaling::Perceptual.to_ratio(brightness);
+            assert!((Scaling::Perceptual.from_ratio(ratio) - brightness).abs() < 1e-9);
+        }
+        assert!((Scaling::Perceptual.from_ratio(0.18) - 0.4947).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,183 @@
+//! Backup freshness
+//!
+//! Shows how long ago the latest borg archive or restic snapshot was made, and turns warning or
+//! critical when it is older than the configured limits.
+//!
+//! Listing a repository needs its passphrase, so it has to be available in the environment of
+//! i3status-rust, e.g. with `BORG_PASSCOMMAND` or `RESTIC_PASSWORD_COMMAND`. For remote
+//! repositories, or when listing is slow, `list_cmd` can instead print the output of a previous
+//! listing, for example one saved by the backup job itself.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `tool` | `"borg"` or `"restic"` | `"borg"`
+//! `repo` | The repository to list. Required unless `list_cmd` is set. | `None`
+//! `list_cmd` | A shell command printing the output of `borg list --last 1 --json <repo>` or `restic -r <repo> snapshots --latest 1 --json`, which is used instead of running these | `None`
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $age "`
+//! `interval` | Update interval in seconds | `600`
+//! `warning_age` | Age in hours, from which the state is set to warning | `26`
+//! `critical_age` | Age in hours, from which the state is set to critical | `50`
+//!
+//! Placeholder | Value                                                   | Type   | Unit
+//! ------------|---------------------------------------------------------|--------|-----
+//! `icon`      | A static icon                                           | Icon   | -
+//! `age`       | The age of the latest backup, e.g. `"5h"` or `"2d"`     | Text   | -
+//! `hours`     | The age of the latest backup in whole hours             | Number | -
+//!
+//! The placeholders are missing and the state is critical if there is no backup at all.
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "backup"
+//! tool = "restic"
+//! repo = "/mnt/backup/restic"
+//! warning_age = 30
+//! ```
+//!
+//! ```toml
+//! [[block]]
+//! block = "backup"
+//! list_cmd = "cat ~/.cache/borg-last.json"
+//! ```
+//!
+//! # Icons Used
+//! - `disk_drive`
+
+use super::prelude::*;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use tokio::process::Command;
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    tool: Tool,
+    repo: Option<String>,
+    list_cmd: Option<String>,
+    format: FormatConfig,
+    #[default(600.into())]
+    interval: Seconds,
+    #[default(26)]
+    warning_age: i64,
+    #[default(50)]
+    critical_age: i64,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, SmartDefault)]
+#[serde(rename_all = "lowercase")]
+enum Tool {
+    #[default]
+    Borg,
+    Restic,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    let mut widget = Widget::new().with_format(config.format.clone().with_default(" $icon $age ")?);
+
+    if config.repo.is_none() && config.list_cmd.is_none() {
+        return Err(Error::new("Either `repo` or `list_cmd` must be set"));
+    }
+    let mut timer = config.interval.timer();
+
+    loop {
+        let latest = api.recoverable(|| latest_backup(&config)).await?;
+        let hours = latest.map(|time| (Local::now() - time).num_hours());
+
+        widget.state = match hours {
+            Some(h) if h < config.warning_age => State::Idle,
+            Some(h) if h < config.critical_age => State::Warning,
+            _ => State::Critical,
+        };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("disk_drive")?),
+            [if let Some(h) = hours] "hours" => Value::number(h),
+            [if let Some(h) = hours] "age" => Value::text(format_age(h)),
+        });
+        api.set_widget(&widget).await?;
+
+        select! {
+            _ = timer.tick() => (),
+            _ = api.wait_for_update_request() => (),
+        }
+    }
+}
+
+/// The time of the latest backup, if there is any
+async fn latest_backup(config: &Config) -> Result<Option<DateTime<Local>>> {
+    let mut command = match (&config.list_cmd, &config.repo, config.tool) {
+        (Some(cmd), _, _) => {
+            let mut command = Command::new("sh");
+            command.args(["-c", cmd]);
+            command
+        }
+        (None, Some(repo), Tool::Borg) => {
+            let mut command = Command::new("borg");
+            command.args(["list", "--last", "1", "--json", repo]);
+            command
+        }
+        (None, Some(repo), Tool::Restic) => {
+            let mut command = Command::new("restic");
+            command.args(["-r", repo, "snapshots", "--latest", "1", "--json"]);
+            command
+        }
+        (None, None, _) => unreachable!(),
+    };
+    let output = command.output().await.error("Failed to list backups")?;
+    if !output.status.success() {
+        return Err(Error::new(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    match config.tool {
+        Tool::Borg => {
+            #[derive(Deserialize)]
+            struct List {
+                archives: Vec<Archive>,
+            }
+            #[derive(Deserialize)]
+            struct Archive {
+                /// Local time without an offset, e.g. `2023-05-04T03:00:12.000000`
+                start: String,
+            }
+
+            let list: List =
+                serde_json::from_slice(&output.stdout).error("Failed to parse borg output")?;
+            let Some(archive) = list.archives.last() else {
+                return Ok(None);
+            };
+            let start = NaiveDateTime::parse_from_str(&archive.start, "%Y-%m-%dT%H:%M:%S%.f")
+                .error("Failed to parse the archive time")?;
+            Ok(Local.from_local_datetime(&start).earliest())
+        }
+        Tool::Restic => {
+            #[derive(Deserialize)]
+            struct Snapshot {
+                /// e.g. `2023-05-04T03:00:12.123456789+02:00`
+                time: String,
+            }
+
+            let snapshots: Vec<Snapshot> =
+                serde_json::from_slice(&output.stdout).error("Failed to parse restic output")?;
+            let mut latest = None;
+            for snapshot in snapshots {
+                let time = DateTime::parse_from_rfc3339(&snapshot.time)
+                    .error("Failed to parse the snapshot time")?
+                    .with_timezone(&Local);
+                latest = latest.max(Some(time));
+            }
+            Ok(latest)
+        }
+    }
+}
+
+fn format_age(hours: i64) -> String {
+    if hours < 48 {
+        format!("{hours}h")
+    } else {
+        format!("{}d", hours / 24)
+    }
+}
@@ -7,7 +7,7 @@
 //!
 //! Key | Values | Default
 //! ----|--------|--------
-//! `device` | The device in `/sys/class/power_supply/` to read from. When using UPower, this can also be `"DisplayDevice"`. Regular expressions can be used. | Any battery device
+//! `device` | The device in `/sys/class/power_supply/` to read from. When using UPower, this can also be `"DisplayDevice"`. Regular expressions can be used. Use `"all"` to combine all system batteries (e.g. `BAT0` and `BAT1` on ThinkPads); with UPower this is the same as `"DisplayDevice"`. | Any battery device
 //! `driver` | One of `"sysfs"`, `"apc_ups"`, or `"upower"` | `"sysfs"`
 //! `interval` | Update interval, in seconds. Only relevant for `driver = "sysfs"` \|\| "apc_ups"`. | `10`
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $percentage "`
@@ -21,13 +21,26 @@
 //! `critical` | Minimum battery level, where state is set to critical | `15`
 //! `full_threshold` | Percentage above which the battery is considered full (`full_format` shown) | `95`
 //! `empty_threshold` | Percentage below which the battery is considered empty | `7.5`
+//! `power_samples` | Number of power readings to average when estimating `time`. Using the average of several readings keeps the estimate from jumping around with short spikes of power draw. Set to `1` to use the instantaneous power draw. | `6`
+//! `charge_thresholds` | Charge thresholds to cycle through on each click, in percent. Only supported by `driver = "sysfs"` for batteries with `charge_control_end_threshold` (e.g. ThinkPads), which is only writable by root by default, see the example below. | `[]`
+//! `icon_levels` | Icons to use for ranges of battery level, see [`IconLevels`](crate::icons::IconLevels). `bat_charging` is always used while charging. | `bat_10` to `bat_90` in 10% steps, `bat_full` above
 //!
 //! Placeholder  | Value                                                                   | Type              | Unit
 //! -------------|-------------------------------------------------------------------------|-------------------|-----
 //! `icon`       | Icon based on battery's state                                           | Icon   | -
 //! `percentage` | Battery level, in percent                                               | Number | Percents
-//! `time`       | Time remaining until (dis)charge is complete. Presented only if battery's status is (dis)charging. | String | -
-//! `power`      | Power consumption by the battery or from the power supply when charging | String or Float   | Watts
+//! `time`       | Time remaining until (dis)charge is complete, based on the average power over the last `power_samples` readings. Presented only if battery's status is (dis)charging. | String | -
+//! `power`      | Current power consumption by the battery or from the power supply when charging. Read from `power_now` or computed from `current_now` and `voltage_now`. | Float | Watts
+//! `threshold`  | Level at which the battery stops charging. Presented only if the battery supports charge thresholds. | Number | Percents
+//! `percentage<N>` | Level of Nth battery, in percent. Only with `device = "all"` and `driver = "sysfs"`. | Number | Percents
+//! `time<N>`    | Time remaining until (dis)charge of Nth battery is complete. Only with `device = "all"` and `driver = "sysfs"`. | String | -
+//!
+//! Action                   | Default button
+//! -------------------------|---------------
+//! `cycle_charge_threshold` | Left (only with `charge_thresholds`)
+//!
+//! With `driver = "sysfs"` the block is updated immediately when the AC adapter is (un)plugged if
+//! UPower is running. Otherwise, changes are noticed on the next update.
 //!
 //! # Examples
 //!
@@ -47,6 +60,34 @@
 //! driver = "upower"
 //! ```
 //!
+//! Combined level of two batteries, followed by the level of each one:
+//!
+//! ```toml
+//! [[block]]
+//! block = "battery"
+//! device = "all"
+//! format = " $icon $percentage ($percentage1 + $percentage2) {$time |}"
+//! ```
+//!
+//! Switch between charging up to 80% and 100% on click, showing the current threshold:
+//!
+//! ```toml
+//! [[block]]
+//! block = "battery"
+//! format = " $icon $percentage {($threshold)|}"
+//! charge_thresholds = [80, 100]
+//! ```
+//!
+//! Setting the threshold requires write access to `charge_control_end_threshold`. It can be
+//! granted to the members of a group, e.g. `wheel`, with a udev rule in
+//! `/etc/udev/rules.d/99-charge-threshold.rules`:
+//!
+//! ```text
+//! SUBSYSTEM=="power_supply", KERNEL=="BAT*", RUN+="/bin/chgrp wheel /sys%p/charge_control_end_threshold", RUN+="/bin/chmod g+w /sys%p/charge_control_end_threshold"
+//! ```
+//!
+//! If setting the threshold fails, the error is shown for a while and the block carries on.
+//!
 //! Hide missing battery:
 //!
 //! ```toml
@@ -70,11 +111,12 @@
 //! - `bat_full`
 
 use regex::Regex;
+use std::collections::VecDeque;
 use std::convert::Infallible;
 use std::str::FromStr;
 
 use super::prelude::*;
-use crate::util::battery_level_icon;
+use crate::icons::IconLevels;
 
 mod apc_ups;
 mod sysfs;
@@ -106,6 +148,11 @@ pub struct Config {
     full_threshold: f64,
     #[default(7.5)]
     empty_threshold: f64,
+    #[default(6)]
+    power_samples: usize,
+    charge_thresholds: Vec<u8>,
+    #[default(IconLevels::battery())]
+    icon_levels: IconLevels,
 }
 
 #[derive(Deserialize, Debug, SmartDefault)]
@@ -125,13 +172,20 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     let missing_format = config.missing_format.with_default(" $icon ")?;
     let mut widget = Widget::new();
 
+    if !config.charge_thresholds.is_empty() {
+        api.set_default_actions(&[(MouseButton::Left, None, "cycle_charge_threshold")])
+            .await?;
+    }
+
     let dev_name = DeviceName::new(config.device)?;
     let mut device: Box<dyn BatteryDevice + Send + Sync> = match config.driver {
-        BatteryDriver::Sysfs => Box::new(sysfs::Device::new(dev_name, config.interval)),
+        BatteryDriver::Sysfs => Box::new(sysfs::Device::new(dev_name, config.interval).await),
         BatteryDriver::ApcUps => Box::new(apc_ups::Device::new(dev_name, config.interval).await?),
         BatteryDriver::Upower => Box::new(upower::Device::new(dev_name).await?),
     };
 
+    let mut power_average = PowerAverage::new(config.power_samples);
+
     loop {
         let mut info = device.get_info().await?;
 
@@ -141,6 +195,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             } else if info.capacity <= config.empty_threshold {
                 info.status = BatteryStatus::Empty;
             }
+            power_average.smooth_time_remaining(info);
         }
 
         match info {
@@ -158,24 +213,32 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
 
                 info.power
                     .map(|p| values.insert("power".into(), Value::watts(p)));
-                info.time_remaining.map(|t| {
+                info.time_remaining
+                    .map(|t| values.insert("time".into(), format_time(t)));
+                if let Some(threshold) = device.charge_threshold().await? {
+                    values.insert("threshold".into(), Value::percents(threshold));
+                }
+                for (i, battery) in info.per_battery.iter().enumerate() {
                     values.insert(
-                        "time".into(),
-                        Value::text(format!(
-                            "{}:{:02}",
-                            (t / 3600.) as i32,
-                            (t % 3600. / 60.) as i32
-                        )),
-                    )
-                });
+                        format!("percentage{}", i + 1).into(),
+                        Value::percents(battery.capacity),
+                    );
+                    battery
+                        .time_remaining
+                        .map(|t| values.insert(format!("time{}", i + 1).into(), format_time(t)));
+                }
 
                 let (icon, state) = match (info.status, info.capacity) {
-                    (BatteryStatus::Empty, _) => (battery_level_icon(0, false), State::Critical),
+                    (BatteryStatus::Empty, _) => (config.icon_levels.get(0.), State::Critical),
                     (BatteryStatus::Full | BatteryStatus::NotCharging, _) => {
-                        (battery_level_icon(100, false), State::Idle)
+                        (config.icon_levels.get(100.), State::Idle)
                     }
                     (status, capacity) => (
-                        battery_level_icon(capacity as u8, status == BatteryStatus::Charging),
+                        if status == BatteryStatus::Charging {
+                            "bat_charging"
+                        } else {
+                            config.icon_levels.get(capacity)
+                        },
                         if status == BatteryStatus::Charging {
                             State::Good
                         } else if capacity <= config.critical {
@@ -205,23 +268,115 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             }
         }
 
-        select! {
-            update = device.wait_for_change() => update?,
-            _ = api.wait_for_update_request() => (),
+        loop {
+            select! {
+                update = device.wait_for_change() => {
+                    update?;
+                    break;
+                }
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "cycle_charge_threshold" => {
+                        if config.charge_thresholds.is_empty() {
+                            continue;
+                        }
+                        let current = device.charge_threshold().await?;
+                        let next = current
+                            .and_then(|c| config.charge_thresholds.iter().position(|&t| t == c))
+                            .map_or(0, |i| (i + 1) % config.charge_thresholds.len());
+                        if let Err(e) = device
+                            .set_charge_threshold(config.charge_thresholds[next])
+                            .await
+                        {
+                            // Most likely missing permissions, which shouldn't stop the block
+                            api.set_error(e).await?;
+                            select! {
+                                _ = sleep(api.error_interval) => (),
+                                _ = api.wait_for_update_request() => (),
+                            }
+                        }
+                        break;
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+/// Rolling average of the power draw, used to smooth out the time estimates
+struct PowerAverage {
+    samples: VecDeque<f64>,
+    max_samples: usize,
+    status: Option<BatteryStatus>,
+}
+
+impl PowerAverage {
+    fn new(max_samples: usize) -> Self {
+        let max_samples = max_samples.max(1);
+        Self {
+            samples: VecDeque::with_capacity(max_samples),
+            max_samples,
+            status: None,
+        }
+    }
+
+    /// Record the current power draw and rescale `info.time_remaining` so that it is based on the
+    /// average power instead of the current one. Samples are discarded when the status changes,
+    /// because power draw while charging says nothing about power draw while discharging.
+    fn smooth_time_remaining(&mut self, info: &mut BatteryInfo) {
+        if self.status != Some(info.status) {
+            self.samples.clear();
+            self.status = Some(info.status);
+        }
+
+        let power = match info.power {
+            Some(power) if power > 0.0 => power,
+            _ => return,
+        };
+        if self.samples.len() == self.max_samples {
+            self.samples.pop_front();
         }
+        self.samples.push_back(power);
+
+        let average = self.samples.iter().sum::<f64>() / self.samples.len() as f64;
+        // Remaining energy is `time * power`, so the time at the average power is
+        // `time * power / average`
+        info.time_remaining = info.time_remaining.map(|t| t * power / average);
     }
 }
 
+fn format_time(seconds: f64) -> Value {
+    Value::text(format!(
+        "{}:{:02}",
+        (seconds / 3600.) as i32,
+        (seconds % 3600. / 60.) as i32
+    ))
+}
+
 #[async_trait]
 trait BatteryDevice {
     async fn get_info(&mut self) -> Result<Option<BatteryInfo>>;
     async fn wait_for_change(&mut self) -> Result<()>;
+
+    /// The level in percents at which the battery stops charging, if supported
+    async fn charge_threshold(&mut self) -> Result<Option<u8>> {
+        Ok(None)
+    }
+
+    async fn set_charge_threshold(&mut self, _threshold: u8) -> Result<()> {
+        Err(Error::new(
+            "Charge thresholds are not supported by this driver",
+        ))
+    }
 }
 
 /// `Option<Regex>`, but more intuitive
 #[derive(Debug)]
 enum DeviceName {
     Any,
+    /// All system batteries combined
+    All,
     Regex(Regex),
 }
 
@@ -229,26 +384,27 @@ impl DeviceName {
     fn new(pat: Option<String>) -> Result<Self> {
         Ok(match pat {
             None => Self::Any,
+            Some(pat) if pat == "all" => Self::All,
             Some(pat) => Self::Regex(pat.parse().error("failed to parse regex")?),
         })
     }
 
     fn matches(&self, name: &str) -> bool {
         match self {
-            Self::Any => true,
+            Self::Any | Self::All => true,
             Self::Regex(pat) => pat.is_match(name),
         }
     }
 
     fn exact(&self) -> Option<&str> {
         match self {
-            Self::Any => None,
+            Self::Any | Self::All => None,
             Self::Regex(pat) => Some(pat.as_str()),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 struct BatteryInfo {
     /// Current status, e.g. "charging", "discharging", etc.
     status: BatteryStatus,
@@ -258,6 +414,8 @@ struct BatteryInfo {
     power: Option<f64>,
     /// Time in seconds
     time_remaining: Option<f64>,
+    /// Individual batteries, if this info combines several of them
+    per_battery: Vec<BatteryInfo>,
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, SmartDefault)]
@@ -182,6 +182,7 @@ impl BatteryDevice for Device {
             capacity,
             power,
             time_remaining,
+            per_battery: Vec::new(),
         }))
     }
 
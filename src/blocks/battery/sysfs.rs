@@ -4,10 +4,11 @@ use std::str::FromStr;
 
 use tokio::fs::read_dir;
 use tokio::time::Interval;
+use zbus::fdo::{PropertiesChangedStream, PropertiesProxy};
 
 use super::{BatteryDevice, BatteryInfo, BatteryStatus, DeviceName};
 use crate::blocks::prelude::*;
-use crate::util::read_file;
+use crate::util::{new_system_dbus_connection, read_file};
 
 make_log_macro!(debug, "battery");
 
@@ -52,20 +53,69 @@ impl CapacityLevel {
     }
 }
 
+/// Info about a single battery, together with its energy, which is needed to combine several
+/// batteries
+struct Reading {
+    info: BatteryInfo,
+    /// Remaining energy in Wh
+    energy_now: Option<f64>,
+    /// Energy when full in Wh
+    energy_full: Option<f64>,
+}
+
 /// Represents a physical power supply device, as known to sysfs.
 /// <https://www.kernel.org/doc/html/v5.15/power/power_supply_class.html>
 pub(super) struct Device {
     dev_name: DeviceName,
     dev_path: Option<PathBuf>,
     interval: Interval,
+    /// Changes of UPower's properties, such as `OnBattery`, used to update the block as soon as
+    /// the AC adapter is (un)plugged
+    upower_changes: Option<PropertiesChangedStream<'static>>,
 }
 
 impl Device {
-    pub(super) fn new(dev_name: DeviceName, interval: Seconds) -> Self {
+    pub(super) async fn new(dev_name: DeviceName, interval: Seconds) -> Self {
+        let upower_changes = match Self::upower_changes().await {
+            Ok(changes) => Some(changes),
+            Err(e) => {
+                debug!("UPower is not available, only polling: {e}");
+                None
+            }
+        };
         Self {
             dev_name,
             dev_path: None,
             interval: interval.timer(),
+            upower_changes,
+        }
+    }
+
+    async fn upower_changes() -> Result<PropertiesChangedStream<'static>> {
+        let dbus_conn = new_system_dbus_connection().await?;
+        PropertiesProxy::builder(&dbus_conn)
+            .destination("org.freedesktop.UPower")
+            .and_then(|x| x.path("/org/freedesktop/UPower"))
+            .unwrap()
+            .build()
+            .await
+            .error("Failed to create PropertiesProxy")?
+            .receive_properties_changed()
+            .await
+            .error("Failed to create PropertiesChangedStream")
+    }
+
+    /// Returns the paths of all batteries this device refers to
+    async fn get_device_paths(&mut self) -> Result<Vec<PathBuf>> {
+        if let DeviceName::All = self.dev_name {
+            Self::get_system_battery_paths().await
+        } else {
+            Ok(self
+                .get_device_path()
+                .await?
+                .map(Path::to_path_buf)
+                .into_iter()
+                .collect())
         }
     }
 
@@ -125,6 +175,33 @@ impl Device {
         })
     }
 
+    /// Returns all available system batteries, sorted by name. Peripherals (devices with `scope`
+    /// set to `Device`) are ignored.
+    async fn get_system_battery_paths() -> Result<Vec<PathBuf>> {
+        let mut paths = Vec::new();
+
+        let mut sysfs_dir = read_dir(POWER_SUPPLY_DEVICES_PATH)
+            .await
+            .error("failed to read /sys/class/power_supply direcory")?;
+        while let Some(dir) = sysfs_dir
+            .next_entry()
+            .await
+            .error("failed to read /sys/class/power_supply direcory")?
+        {
+            let path = dir.path();
+            if Self::read_prop::<String>(&path, "type").await.as_deref() == Some("Battery")
+                && Self::read_prop::<String>(&path, "scope").await.as_deref() != Some("Device")
+                && Self::device_available(&path).await
+            {
+                paths.push(path);
+            }
+        }
+
+        paths.sort();
+        debug!("Found system batteries: {:?}", paths);
+        Ok(paths)
+    }
+
     async fn read_prop<T: FromStr + Send + Sync>(path: &Path, prop: &str) -> Option<T> {
         read_file(path.join(prop))
             .await
@@ -139,17 +216,8 @@ impl Device {
         Self::read_prop::<String>(path, "scope").await.as_deref() == Some("Device")
             || Self::read_prop::<u8>(path, "present").await == Some(1)
     }
-}
-
-#[async_trait]
-impl BatteryDevice for Device {
-    async fn get_info(&mut self) -> Result<Option<BatteryInfo>> {
-        // Check if the battery is available
-        let path = match self.get_device_path().await? {
-            Some(path) => path,
-            None => return Ok(None),
-        };
 
+    async fn read_battery(path: &Path) -> Result<Option<Reading>> {
         // Read all the necessary data
         let (
             status,
@@ -247,16 +315,180 @@ impl BatteryDevice for Device {
             _ => None,
         };
 
-        Ok(Some(BatteryInfo {
-            status,
-            capacity,
-            power,
-            time_remaining,
+        Ok(Some(Reading {
+            info: BatteryInfo {
+                status,
+                capacity,
+                power,
+                time_remaining,
+                per_battery: Vec::new(),
+            },
+            energy_now: energy_now.or_else(|| charge_now.zip(voltage_now).map(|(c, v)| c * v)),
+            energy_full: energy_full.or_else(|| charge_full.zip(voltage_now).map(|(c, v)| c * v)),
         }))
     }
+}
+
+#[async_trait]
+impl BatteryDevice for Device {
+    async fn get_info(&mut self) -> Result<Option<BatteryInfo>> {
+        if let DeviceName::All = self.dev_name {
+            let mut readings = Vec::new();
+            for path in Self::get_system_battery_paths().await? {
+                if let Some(reading) = Self::read_battery(&path).await? {
+                    readings.push(reading);
+                }
+            }
+            return Ok(combine(readings));
+        }
+
+        // Check if the battery is available
+        let path = match self.get_device_path().await? {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        Ok(Self::read_battery(path).await?.map(|r| r.info))
+    }
 
     async fn wait_for_change(&mut self) -> Result<()> {
-        self.interval.tick().await;
+        let changes = match &mut self.upower_changes {
+            Some(changes) => changes,
+            None => {
+                self.interval.tick().await;
+                return Ok(());
+            }
+        };
+        let upower_gone = select! {
+            _ = self.interval.tick() => false,
+            change = changes.next() => change.is_none(),
+        };
+        if upower_gone {
+            debug!("UPower signal stream ended, only polling");
+            self.upower_changes = None;
+        }
         Ok(())
     }
+
+    async fn charge_threshold(&mut self) -> Result<Option<u8>> {
+        for path in self.get_device_paths().await? {
+            if let Some(threshold) =
+                Self::read_prop::<u8>(&path, "charge_control_end_threshold").await
+            {
+                return Ok(Some(threshold));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn set_charge_threshold(&mut self, threshold: u8) -> Result<()> {
+        let paths = self.get_device_paths().await?;
+        if paths.is_empty() {
+            return Err(Error::new("No battery to set the charge threshold for"));
+        }
+        for path in paths {
+            tokio::fs::write(
+                path.join("charge_control_end_threshold"),
+                threshold.to_string(),
+            )
+            .await
+            .or_error(|| format!("Failed to set charge threshold of '{}'", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Combine several batteries into one. The capacity is weighted by the energy of each battery if
+/// it is known for all of them.
+fn combine(readings: Vec<Reading>) -> Option<BatteryInfo> {
+    if readings.is_empty() {
+        return None;
+    }
+
+    let any_status = |status| readings.iter().any(|r| r.info.status == status);
+    let status = if any_status(BatteryStatus::Charging) {
+        BatteryStatus::Charging
+    } else if any_status(BatteryStatus::Discharging) {
+        BatteryStatus::Discharging
+    } else if readings
+        .iter()
+        .all(|r| r.info.status == BatteryStatus::Full)
+    {
+        BatteryStatus::Full
+    } else if any_status(BatteryStatus::NotCharging) {
+        BatteryStatus::NotCharging
+    } else {
+        readings[0].info.status
+    };
+
+    let energy: Option<(f64, f64)> = readings
+        .iter()
+        .map(|r| r.energy_now.zip(r.energy_full))
+        .try_fold((0.0, 0.0), |(now, full), e| {
+            e.map(|(n, f)| (now + n, full + f))
+        })
+        .filter(|&(_, full)| full > 0.0);
+
+    let capacity = match energy {
+        Some((now, full)) => now / full * 100.0,
+        None => readings.iter().map(|r| r.info.capacity).sum::<f64>() / readings.len() as f64,
+    };
+
+    let power = readings
+        .iter()
+        .filter_map(|r| r.info.power)
+        .reduce(|a, b| a + b);
+
+    // Wh / W = h
+    let time_remaining = match (status, energy, power) {
+        (BatteryStatus::Charging, Some((now, full)), Some(p)) if p > 0.0 => {
+            Some((full - now) / p * 3600.0)
+        }
+        (BatteryStatus::Discharging, Some((now, _)), Some(p)) if p > 0.0 => Some(now / p * 3600.0),
+        _ => None,
+    };
+
+    Some(BatteryInfo {
+        status,
+        capacity,
+        power,
+        time_remaining,
+        per_battery: readings.into_iter().map(|r| r.info).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(status: BatteryStatus, now: f64, full: f64, power: f64) -> Reading {
+        Reading {
+            info: BatteryInfo {
+                status,
+                capacity: now / full * 100.0,
+                power: Some(power),
+                time_remaining: None,
+                per_battery: Vec::new(),
+            },
+            energy_now: Some(now),
+            energy_full: Some(full),
+        }
+    }
+
+    #[test]
+    fn combine_batteries() {
+        assert!(combine(Vec::new()).is_none());
+
+        let info = combine(vec![
+            reading(BatteryStatus::NotCharging, 20.0, 20.0, 0.0),
+            reading(BatteryStatus::Discharging, 10.0, 40.0, 15.0),
+        ])
+        .unwrap();
+        assert_eq!(info.status, BatteryStatus::Discharging);
+        assert_eq!(info.capacity, 50.0);
+        assert_eq!(info.power, Some(15.0));
+        assert_eq!(info.time_remaining, Some(2.0 * 3600.0));
+        assert_eq!(info.per_battery.len(), 2);
+        assert_eq!(info.per_battery[1].capacity, 25.0);
+    }
 }
@@ -15,7 +15,11 @@ impl Device {
     pub(super) async fn new(device: DeviceName) -> Result<Self> {
         let dbus_conn = new_system_dbus_connection().await?;
 
-        let (device_path, device_proxy) = if device.exact() == Some("DisplayDevice") {
+        // UPower's display device already combines all batteries
+        let display_device =
+            matches!(device, DeviceName::All) || device.exact() == Some("DisplayDevice");
+
+        let (device_path, device_proxy) = if display_device {
             let path: ObjectPath = "/org/freedesktop/UPower/devices/DisplayDevice"
                 .try_into()
                 .unwrap();
@@ -132,6 +136,7 @@ impl BatteryDevice for Device {
             capacity,
             power: Some(power),
             time_remaining,
+            per_battery: Vec::new(),
         }))
     }
 
@@ -3,30 +3,44 @@
 //! This block displays the connectivity of a given Bluetooth device and the battery level if this
 //! is supported. Relies on the Bluez D-Bus API.
 //!
-//! When the device can be identified as an audio headset, a keyboard, joystick, or mouse, use the
-//! relevant icon. Otherwise, fall back on the generic Bluetooth symbol.
+//! If `mac` is a list, the block shows the icon and battery level of each connected device in
+//! `$devices`, and disconnected devices are left out. Clicking the block then disconnects all
+//! connected devices, or connects all of them if none is connected.
 //!
-//! Right-clicking the block will attempt to connect (or disconnect) the device.
+//! When the device can be identified as an audio headset, a keyboard, joystick, mouse or phone, use
+//! the relevant icon. Otherwise, fall back on the generic Bluetooth symbol.
+//!
+//! Clicking the block will attempt to connect (or disconnect) the device.
 //!
 //! # Configuration
 //!
 //! Key | Values | Default
 //! ----|--------|--------
-//! `mac` | MAC address of the Bluetooth device | **Required**
+//! `mac` | MAC address of the Bluetooth device, or a list of them | **Required**
 //! `adapter_mac` | MAC Address of the Bluetooth adapter (in case your device was connected to multiple currently available adapters) | `None`
-//! `format` | A string to customise the output of this block. See below for available placeholders. | <code>" $icon $name{ $percentage&vert;} "</code>
-//! `disconnected_format` | A string to customise the output of this block. See below for available placeholders. | <code>" $icon{ $name&vert;} "</code>
+//! `format` | A string to customise the output of this block. See below for available placeholders. | <code>" $icon $name{ $percentage&vert;} "</code>, or `" $icon $devices "` for a list of devices
+//! `disconnected_format` | A string to customise the output of this block. See below for available placeholders. | <code>" $icon{ $name&vert;} "</code>, or `" $icon "` for a list of devices
+//! `device_separator` | The separator between devices in `$devices` | `" "`
 //!
 //! Placeholder  | Value                                                                 | Type   | Unit
 //! -------------|-----------------------------------------------------------------------|--------|------
 //! `icon`       | Icon based on what type of device is connected                        | Icon   | -
 //! `name`       | Device's name                                                         | Text   | -
 //! `percentage` | Device's battery level (may be absent if the device is not supported) | Number | %
+//! `battery_icon` | An icon for the battery level (absent if `percentage` is absent)  | Icon   | -
 //! `available`  | Present if the device is available                                    | Flag   | -
 //!
+//! With a list of devices, these placeholders are available instead:
+//!
+//! Placeholder | Value                                                 | Type   | Unit
+//! ------------|-------------------------------------------------------|--------|------
+//! `icon`      | A static icon                                         | Icon   | -
+//! `devices`   | Icon and battery level of each connected device       | Text   | -
+//! `connected` | The number of connected devices                       | Number | -
+//!
 //! Action   | Default button
 //! ---------|---------------
-//! `toggle` | Right
+//! `toggle` | Left, Right
 //!
 //! # Examples
 //!
@@ -40,93 +54,193 @@
 //! format = " $icon "
 //! ```
 //!
+//! Show the battery level of headphones as an icon:
+//!
+//! ```toml
+//! [[block]]
+//! block = "bluetooth"
+//! mac = "00:18:09:92:1B:BA"
+//! format = " $icon{ $battery_icon|} "
+//! ```
+//!
+//! Show all connected peripherals in one block:
+//!
+//! ```toml
+//! [[block]]
+//! block = "bluetooth"
+//! mac = ["00:18:09:92:1B:BA", "F4:73:35:21:0C:8E", "C8:2A:DD:48:D1:0A"]
+//! disconnected_format = ""
+//! ```
+//!
 //! # Icons Used
-//! - `headphones` for bluetooth devices identifying as "audio-card" or "audio-headset"
+//! - `headphones` for bluetooth devices identifying as "audio-card", "audio-headset" or
+//!   "audio-headphones"
 //! - `joystick` for bluetooth devices identifying as "input-gaming"
 //! - `keyboard` for bluetooth devices identifying as "input-keyboard"
 //! - `mouse` for bluetooth devices identifying as "input-mouse"
+//! - `phone` for bluetooth devices identifying as "phone"
 //! - `bluetooth` for all other devices
+//! - `bat_10`, `bat_20`, ..., `bat_90`, `bat_full` for `battery_icon`
 
 use super::prelude::*;
+use crate::icons::IconLevels;
 use zbus::fdo::{DBusProxy, ObjectManagerProxy, PropertiesProxy};
 
 make_log_macro!(debug, "bluetooth");
 
 #[derive(Deserialize, Debug)]
 pub struct Config {
-    mac: String,
+    mac: Macs,
     #[serde(default)]
     adapter_mac: Option<String>,
     #[serde(default)]
     format: FormatConfig,
     #[serde(default)]
     disconnected_format: FormatConfig,
+    #[serde(default = "default_device_separator")]
+    device_separator: String,
 }
 
-pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
-    api.set_default_actions(&[(MouseButton::Right, None, "toggle")])
-        .await?;
+fn default_device_separator() -> String {
+    " ".into()
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Macs {
+    Single(String),
+    Multiple(Vec<String>),
+}
 
-    let format = config.format.with_default(" $icon $name{ $percentage|} ")?;
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[
+        (MouseButton::Left, None, "toggle"),
+        (MouseButton::Right, None, "toggle"),
+    ])
+    .await?;
+
+    let macs = match config.mac {
+        Macs::Single(mac) => vec![mac],
+        Macs::Multiple(macs) if macs.is_empty() => return Err(Error::new("mac must not be empty")),
+        Macs::Multiple(macs) => macs,
+    };
+    let multiple = macs.len() > 1;
+
+    let (default_format, default_disconnected_format) = if multiple {
+        (" $icon $devices ", " $icon ")
+    } else {
+        (" $icon $name{ $percentage|} ", " $icon{ $name|} ")
+    };
+    let format = config.format.with_default(default_format)?;
     let disconnected_format = config
         .disconnected_format
-        .with_default(" $icon{ $name|} ")?;
+        .with_default(default_disconnected_format)?;
     let mut widget = Widget::new();
+    let battery_levels = IconLevels::battery();
 
-    let mut monitor = DeviceMonitor::new(config.mac, config.adapter_mac).await?;
+    let mut monitors = Vec::with_capacity(macs.len());
+    for mac in macs {
+        monitors.push(DeviceMonitor::new(mac, config.adapter_mac.clone()).await?);
+    }
 
     loop {
-        match monitor.get_device_info().await {
-            // Available
-            Some(device) => {
-                let mut values = map! {
-                    "icon" => Value::icon(api.get_icon(device.icon)?),
-                    "name" => Value::text(device.name),
-                    "available" => Value::flag()
-                };
-                if let Some(p) = device.battery_percentage {
-                    values.insert("percentage".into(), Value::percents(p));
-                }
-                if device.connected {
-                    debug!("Showing device as connected");
-                    widget.state = State::Good;
-                    widget.set_format(format.clone());
-                } else {
-                    debug!("Showing device as disconnected");
-                    widget.set_format(disconnected_format.clone());
-                    widget.state = State::Idle;
+        if multiple {
+            let mut devices = String::new();
+            let mut connected = 0;
+            for monitor in &mut monitors {
+                match monitor.get_device_info().await {
+                    Some(device) if device.connected => {
+                        connected += 1;
+                        if !devices.is_empty() {
+                            devices.push_str(&config.device_separator);
+                        }
+                        devices.push_str(&api.get_icon(device.icon)?);
+                        if let Some(p) = device.battery_percentage {
+                            let _ = write!(devices, " {p}%");
+                        }
+                    }
+                    _ => (),
                 }
-                widget.set_values(values);
-
-                api.set_widget(&widget).await?;
             }
-            // Unavailable
-            None => {
-                debug!("Showing device as unavailable");
+            let mut values = map! {
+                "icon" => Value::icon(api.get_icon("bluetooth")?),
+                "connected" => Value::number(connected),
+            };
+            if connected > 0 {
+                debug!("Showing {} devices as connected", connected);
+                values.insert("devices".into(), Value::text(devices));
+                widget.state = State::Good;
+                widget.set_format(format.clone());
+            } else {
+                debug!("Showing devices as disconnected");
                 widget.state = State::Idle;
                 widget.set_format(disconnected_format.clone());
-                widget.set_values(map!("icon" => Value::icon(api.get_icon("bluetooth")?)));
-                api.set_widget(&widget).await?;
+            }
+            widget.set_values(values);
+            api.set_widget(&widget).await?;
+        } else {
+            match monitors[0].get_device_info().await {
+                // Available
+                Some(device) => {
+                    let mut values = map! {
+                        "icon" => Value::icon(api.get_icon(device.icon)?),
+                        "name" => Value::text(device.name),
+                        "available" => Value::flag()
+                    };
+                    if let Some(p) = device.battery_percentage {
+                        values.insert("percentage".into(), Value::percents(p));
+                        let battery_icon = api.get_icon(battery_levels.get(p as f64))?;
+                        values.insert("battery_icon".into(), Value::icon(battery_icon));
+                    }
+                    if device.connected {
+                        debug!("Showing device as connected");
+                        widget.state = State::Good;
+                        widget.set_format(format.clone());
+                    } else {
+                        debug!("Showing device as disconnected");
+                        widget.set_format(disconnected_format.clone());
+                        widget.state = State::Idle;
+                    }
+                    widget.set_values(values);
+
+                    api.set_widget(&widget).await?;
+                }
+                // Unavailable
+                None => {
+                    debug!("Showing device as unavailable");
+                    widget.state = State::Idle;
+                    widget.set_format(disconnected_format.clone());
+                    widget.set_values(map!("icon" => Value::icon(api.get_icon("bluetooth")?)));
+                    api.set_widget(&widget).await?;
+                }
             }
         }
 
         loop {
             select! {
-                res = monitor.wait_for_change() => {
+                res = wait_for_change(&mut monitors) => {
                     res?;
                     break;
                 },
                 event = api.event() => match event {
                     Action(a) if a == "toggle" => {
-                        if let Some(dev) = &monitor.device {
+                        // Disconnect all connected devices, or connect all if none is connected
+                        let mut devices = Vec::new();
+                        for dev in monitors.iter().filter_map(|m| m.device.as_ref()) {
                             if let Ok(connected) = dev.device.connected().await {
-                                if connected {
+                                devices.push((dev, connected));
+                            }
+                        }
+                        if !devices.is_empty() {
+                            let any_connected = devices.iter().any(|(_, connected)| *connected);
+                            for (dev, connected) in devices {
+                                if any_connected && connected {
                                     let _ = dev.device.disconnect().await;
-                                } else {
+                                } else if !any_connected {
                                     let _ = dev.device.connect().await;
                                 }
-                                break;
                             }
+                            break;
                         }
                     }
                     _ => (),
@@ -136,6 +250,12 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     }
 }
 
+/// Wait until any of the devices changes
+async fn wait_for_change(monitors: &mut [DeviceMonitor]) -> Result<()> {
+    let changes = monitors.iter_mut().map(|m| Box::pin(m.wait_for_change()));
+    futures::future::select_all(changes).await.0
+}
+
 struct DeviceMonitor {
     mac: String,
     adapter_mac: Option<String>,
@@ -269,10 +389,11 @@ impl DeviceMonitor {
         Some(DeviceInfo {
             connected,
             icon: match icon.as_str() {
-                "audio-card" | "audio-headset" => "headphones",
+                "audio-card" | "audio-headset" | "audio-headphones" => "headphones",
                 "input-gaming" => "joystick",
                 "input-keyboard" => "keyboard",
                 "input-mouse" => "mouse",
+                "phone" => "phone",
                 _ => "bluetooth",
             },
             name,
@@ -0,0 +1,547 @@
+//! The next calendar event
+//!
+//! Shows the title and start time of the next upcoming event. Shortly before the event, the time
+//! is replaced by a countdown like `"in 12 min"`, and the block switches to the warning state.
+//!
+//! Events are read from iCalendar files, directories of them (e.g. synchronised from a CalDAV
+//! server by vdirsyncer), or `http(s)` URLs of iCalendar exports, and/or from the output of
+//! `command`. Of recurring iCalendar events, only daily and weekly recurrences are supported
+//! (including `BYDAY`, removed and modified occurrences), and other rules show only their first
+//! occurrence. `command` must print one event per line, as the start date and time in the form
+//! `YYYY-MM-DD HH:MM` or `YYYY-MM-DD` for all-day events, a tab and the title, e.g.
+//! `2023-05-04 14:30<tab>Standup`. For khal, whose output handles any recurrence, this is
+//! `khal list --day-format "" --format "{start}\t{title}" now 7d`, with `datetimeformat` set to
+//! `%Y-%m-%d %H:%M` and `dateformat` to `%Y-%m-%d` in khal's configuration.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `ics` | iCalendar files, directories of them or URLs to read events from. Supports path expansions e.g. `~`. | `[]`
+//! `command` | A shell command printing events as described above | `None`
+//! `format` | A string to customise the output of this block. See below for available placeholders. | <code>" $icon {$title $time&vert;} "</code>
+//! `interval` | Update interval in seconds | `60`
+//! `reload_interval` | How often events are read again, in seconds | `600`
+//! `days` | How many days ahead to look for events | `7`
+//! `countdown_minutes` | Minutes before an event from which `time` shows a countdown | `60`
+//! `warning_minutes` | Minutes before an event from which the state is set to warning | `15`
+//! `all_day` | Whether to show all-day events | `false`
+//!
+//! Placeholder | Value                                                                     | Type   | Unit
+//! ------------|---------------------------------------------------------------------------|--------|-----
+//! `icon`      | A static icon                                                             | Icon   | -
+//! `title`     | The title of the next event                                               | Text   | -
+//! `time`      | The start time, e.g. `"14:30"` or `"Tue 09:00"`, or a countdown like `"in 12 min"` | Text | -
+//! `minutes`   | The number of minutes until the event starts                              | Number | -
+//!
+//! The placeholders are missing if there is no upcoming event.
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "calendar"
+//! ics = ["~/.calendars/work", "https://example.org/holidays.ics"]
+//! format = " $icon {$title.str(max_w:20) $time|no events} "
+//! ```
+//!
+//! # Icons Used
+//! - `calendar`
+
+use super::prelude::*;
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use std::collections::HashSet;
+use tokio::process::Command;
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    ics: Vec<ShellString>,
+    command: Option<String>,
+    format: FormatConfig,
+    #[default(60.into())]
+    interval: Seconds,
+    #[default(600.into())]
+    reload_interval: Seconds,
+    #[default(7)]
+    days: i64,
+    #[default(60)]
+    countdown_minutes: i64,
+    #[default(15)]
+    warning_minutes: i64,
+    all_day: bool,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    let mut widget = Widget::new().with_format(
+        config
+            .format
+            .clone()
+            .with_default(" $icon {$title $time|} ")?,
+    );
+
+    if config.ics.is_empty() && config.command.is_none() {
+        return Err(Error::new("Either `ics` or `command` must be set"));
+    }
+    let mut timer = config.interval.timer();
+    let mut reload_timer = config.reload_interval.timer();
+
+    loop {
+        let mut events = api.recoverable(|| load_events(&config)).await?;
+        events.sort_by_key(|e| e.start);
+
+        loop {
+            let now = Local::now();
+            let next = events.iter().find(|e| e.start > now);
+            let minutes = next.map(|e| (e.start - now).num_minutes());
+
+            widget.state = match minutes {
+                Some(m) if m < config.warning_minutes => State::Warning,
+                _ => State::Idle,
+            };
+            widget.set_values(map! {
+                "icon" => Value::icon(api.get_icon("calendar")?),
+                [if let Some(e) = next] "title" => Value::text(e.title.clone()),
+                [if let Some(e) = next] "time" => Value::text(format_time(e, now, config.countdown_minutes)),
+                [if let Some(m) = minutes] "minutes" => Value::number(m),
+            });
+            api.set_widget(&widget).await?;
+
+            select! {
+                _ = timer.tick() => (),
+                _ = reload_timer.tick() => break,
+                _ = api.wait_for_update_request() => break,
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Event {
+    start: DateTime<Local>,
+    all_day: bool,
+    title: String,
+}
+
+fn format_time(event: &Event, now: DateTime<Local>, countdown_minutes: i64) -> String {
+    let minutes = (event.start - now).num_minutes();
+    if minutes < 1 {
+        "now".into()
+    } else if minutes < countdown_minutes {
+        format!("in {minutes} min")
+    } else if event.all_day {
+        event.start.format("%a").to_string()
+    } else if event.start.date_naive() == now.date_naive() {
+        event.start.format("%H:%M").to_string()
+    } else {
+        event.start.format("%a %H:%M").to_string()
+    }
+}
+
+/// The upcoming events of all sources, in no particular order
+async fn load_events(config: &Config) -> Result<Vec<Event>> {
+    let now = Local::now();
+    let until = now + chrono::Duration::days(config.days);
+    let mut events = Vec::new();
+
+    for source in &config.ics {
+        let source = source.expand()?;
+        if source.starts_with("http://") || source.starts_with("https://") {
+            let ics = REQWEST_CLIENT
+                .get(&*source)
+                .send()
+                .await
+                .error("Failed to send request")?
+                .error_for_status()
+                .or_error(|| format!("Failed to fetch {source}"))?
+                .text()
+                .await
+                .error("Failed to read the calendar")?;
+            events.extend(parse_ics(&ics, now, until));
+        } else if tokio::fs::metadata(&*source)
+            .await
+            .or_error(|| format!("Failed to read {source}"))?
+            .is_dir()
+        {
+            let mut dir = tokio::fs::read_dir(&*source)
+                .await
+                .or_error(|| format!("Failed to read {source}"))?;
+            while let Some(entry) = dir.next_entry().await.error("Failed to read directory")? {
+                let path = entry.path();
+                if path.extension().map_or(false, |e| e == "ics") {
+                    let ics = tokio::fs::read_to_string(&path)
+                        .await
+                        .or_error(|| format!("Failed to read {}", path.display()))?;
+                    events.extend(parse_ics(&ics, now, until));
+                }
+            }
+        } else {
+            let ics = tokio::fs::read_to_string(&*source)
+                .await
+                .or_error(|| format!("Failed to read {source}"))?;
+            events.extend(parse_ics(&ics, now, until));
+        }
+    }
+
+    if let Some(command) = &config.command {
+        let output = Command::new("sh")
+            .args(["-c", command])
+            .output()
+            .await
+            .error("Failed to run command")?;
+        events.extend(
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(parse_command_line),
+        );
+    }
+
+    events.retain(|e| e.start > now && e.start <= until && (config.all_day || !e.all_day));
+    Ok(events)
+}
+
+/// Parse a line like `2023-05-04 14:30<tab>Standup`
+fn parse_command_line(line: &str) -> Option<Event> {
+    let (start, title) = line.split_once('\t')?;
+    let start = start.trim();
+    let (start, all_day) = match NaiveDateTime::parse_from_str(start, "%Y-%m-%d %H:%M") {
+        Ok(start) => (start, false),
+        Err(_) => (
+            NaiveDate::parse_from_str(start, "%Y-%m-%d")
+                .ok()?
+                .and_hms_opt(0, 0, 0)?,
+            true,
+        ),
+    };
+    Some(Event {
+        start: Local.from_local_datetime(&start).earliest()?,
+        all_day,
+        title: title.trim().into(),
+    })
+}
+
+/// A `VEVENT` of an iCalendar file
+#[derive(Default)]
+struct IcsEvent {
+    uid: String,
+    title: String,
+    start: Option<(DateTime<Local>, bool)>,
+    rrule: Option<String>,
+    /// Occurrences which were removed from the recurrence
+    exdates: Vec<DateTime<Local>>,
+    /// The occurrence which this event replaces
+    recurrence_id: Option<DateTime<Local>>,
+    cancelled: bool,
+}
+
+/// The first occurrence of each event in `ics` which starts after `from`, if it starts before
+/// `until`. Unsupported recurrences only count their first occurrence.
+fn parse_ics(ics: &str, from: DateTime<Local>, until: DateTime<Local>) -> Vec<Event> {
+    // Long lines are folded by inserting a line break followed by a space or tab
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.into()),
+        }
+    }
+
+    let mut ics_events = Vec::new();
+    let mut event: Option<IcsEvent> = None;
+    for line in &lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+        match name {
+            "BEGIN" if value == "VEVENT" => event = Some(IcsEvent::default()),
+            "END" if value == "VEVENT" => ics_events.extend(event.take()),
+            _ => {
+                let Some(e) = &mut event else {
+                    continue;
+                };
+                match name {
+                    "UID" => e.uid = value.into(),
+                    "SUMMARY" => e.title = unescape(value),
+                    "DTSTART" => e.start = parse_ics_time(params, value),
+                    "RRULE" => e.rrule = Some(value.into()),
+                    "EXDATE" => e.exdates.extend(
+                        value
+                            .split(',')
+                            .filter_map(|v| parse_ics_time(params, v).map(|(t, _)| t)),
+                    ),
+                    "RECURRENCE-ID" => {
+                        e.recurrence_id = parse_ics_time(params, value).map(|(t, _)| t);
+                    }
+                    "STATUS" => e.cancelled = value == "CANCELLED",
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    // Occurrences of recurring events which are replaced by another event with the same UID
+    let overridden: HashSet<(&str, DateTime<Local>)> = ics_events
+        .iter()
+        .filter_map(|e| Some((e.uid.as_str(), e.recurrence_id?)))
+        .collect();
+
+    let mut events = Vec::new();
+    for event in &ics_events {
+        if event.cancelled {
+            continue;
+        }
+        let Some((first, all_day)) = event.start else {
+            continue;
+        };
+        let next = match &event.rrule {
+            Some(rrule) if event.recurrence_id.is_none() => {
+                let mut next = next_occurrence(first, rrule, from);
+                // Skip the removed and replaced occurrences
+                while let Some(n) = next.filter(|n| {
+                    *n > from
+                        && *n <= until
+                        && (event.exdates.contains(n)
+                            || overridden.contains(&(event.uid.as_str(), *n)))
+                }) {
+                    next = next_occurrence(first, rrule, n).filter(|next| *next > n);
+                }
+                next
+            }
+            _ => Some(first),
+        };
+        if let Some(start) = next.filter(|s| *s > from && *s <= until) {
+            events.push(Event {
+                start,
+                all_day,
+                title: event.title.clone(),
+            });
+        }
+    }
+    events
+}
+
+/// The first occurrence of a recurring event starting at `start` which is after `from`
+fn next_occurrence(
+    start: DateTime<Local>,
+    rrule: &str,
+    from: DateTime<Local>,
+) -> Option<DateTime<Local>> {
+    let mut weekly = None;
+    let mut interval = 1;
+    let mut count = None;
+    let mut until = None;
+    let mut weekdays = Vec::new();
+    for part in rrule.split(';') {
+        match part.split_once('=') {
+            Some(("FREQ", "DAILY")) => weekly = Some(false),
+            Some(("FREQ", "WEEKLY")) => weekly = Some(true),
+            Some(("INTERVAL", i)) => interval = i.parse().ok()?,
+            Some(("COUNT", c)) => count = Some(c.parse::<i64>().ok()?),
+            Some(("UNTIL", u)) => until = parse_ics_time("", u).map(|(u, _)| u),
+            Some(("BYDAY", days)) => {
+                // Days with an ordinal like `1MO` are only valid in monthly and yearly rules
+                for day in days.split(',') {
+                    weekdays.push(match day {
+                        "MO" => Weekday::Mon,
+                        "TU" => Weekday::Tue,
+                        "WE" => Weekday::Wed,
+                        "TH" => Weekday::Thu,
+                        "FR" => Weekday::Fri,
+                        "SA" => Weekday::Sat,
+                        "SU" => Weekday::Sun,
+                        _ => return None,
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+    let Some(weekly) = weekly else {
+        return Some(start);
+    };
+    if start > from {
+        return Some(start);
+    }
+    if weekly && weekdays.is_empty() {
+        weekdays.push(start.weekday());
+    }
+
+    // Step on the local date and time, so that events keep their time when DST changes
+    let start_date = start.date_naive();
+    let period_days = if weekly { 7 * interval } else { interval };
+    let is_occurrence = |date: NaiveDate| {
+        let in_period = if weekly {
+            let weeks = (week_start(date) - week_start(start_date)).num_days() / 7;
+            weeks % interval == 0
+        } else {
+            (date - start_date).num_days() % interval == 0
+        };
+        in_period && (weekdays.is_empty() || weekdays.contains(&date.weekday()))
+    };
+
+    // With COUNT, all the occurrences since the start have to be counted. Otherwise skip ahead to
+    // the period before `from`.
+    let mut date = match count {
+        Some(_) => start_date,
+        None => {
+            let periods = ((from.date_naive() - start_date).num_days() / period_days - 1).max(0);
+            start_date + chrono::Duration::days(periods * period_days)
+        }
+    };
+    // The start is always the first occurrence
+    let mut n = 1;
+    loop {
+        date = date.succ_opt()?;
+        if !is_occurrence(date) {
+            continue;
+        }
+        n += 1;
+        if count.map_or(false, |c| n > c) {
+            return None;
+        }
+        let Some(next) = Local
+            .from_local_datetime(&date.and_time(start.time()))
+            .earliest()
+        else {
+            continue;
+        };
+        if until.map_or(false, |u| next > u) {
+            return None;
+        }
+        if next > from {
+            return Some(next);
+        }
+    }
+}
+
+/// The Monday of the week of `date`
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+/// Parse a date or date-time value, returning whether it is a date
+fn parse_ics_time(params: &str, value: &str) -> Option<(DateTime<Local>, bool)> {
+    if value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        let start = Local.from_local_datetime(&date.and_hms_opt(0, 0, 0)?);
+        return Some((start.earliest()?, true));
+    }
+    let time = NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S").ok()?;
+    let tz = params
+        .split(';')
+        .find_map(|p| p.strip_prefix("TZID="))
+        .and_then(|tz| tz.trim_matches('"').parse::<Tz>().ok());
+    let time = if value.ends_with('Z') {
+        Utc.from_utc_datetime(&time).with_timezone(&Local)
+    } else if let Some(tz) = tz {
+        tz.from_local_datetime(&time)
+            .earliest()?
+            .with_timezone(&Local)
+    } else {
+        Local.from_local_datetime(&time).earliest()?
+    };
+    Some((time, false))
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local(date: &str) -> DateTime<Local> {
+        let time = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M").unwrap();
+        Local.from_local_datetime(&time).unwrap()
+    }
+
+    #[test]
+    fn test_parse_ics() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:Weekly\r\n  sync\r\n\
+                   DTSTART:20230419T093000\r\n\
+                   RRULE:FREQ=WEEKLY;COUNT=10\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:Past\r\n\
+                   DTSTART:20230420T100000\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   SUMMARY:Holiday\\, yay\r\n\
+                   DTSTART;VALUE=DATE:20230505\r\n\
+                   END:VEVENT\r\n\
+                   END:VCALENDAR\r\n";
+        let from = local("2023-05-01 00:00");
+        let until = local("2023-05-08 00:00");
+        assert_eq!(
+            parse_ics(ics, from, until),
+            [
+                Event {
+                    start: local("2023-05-03 09:30"),
+                    all_day: false,
+                    title: "Weekly sync".into(),
+                },
+                Event {
+                    start: local("2023-05-05 00:00"),
+                    all_day: true,
+                    title: "Holiday, yay".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_ics_recurrence() {
+        let ics = "BEGIN:VCALENDAR\r\n\
+                   BEGIN:VEVENT\r\n\
+                   UID:standup\r\n\
+                   SUMMARY:Standup\r\n\
+                   DTSTART:20230417T090000\r\n\
+                   RRULE:FREQ=WEEKLY;BYDAY=MO,WE,FR\r\n\
+                   EXDATE:20230501T090000\r\n\
+                   END:VEVENT\r\n\
+                   BEGIN:VEVENT\r\n\
+                   UID:standup\r\n\
+                   SUMMARY:Standup (moved)\r\n\
+                   RECURRENCE-ID:20230503T090000\r\n\
+                   DTSTART:20230503T110000\r\n\
+                   END:VEVENT\r\n\
+                   END:VCALENDAR\r\n";
+        let from = local("2023-05-01 00:00");
+        let until = local("2023-05-08 00:00");
+        assert_eq!(
+            parse_ics(ics, from, until),
+            [
+                Event {
+                    start: local("2023-05-05 09:00"),
+                    all_day: false,
+                    title: "Standup".into(),
+                },
+                Event {
+                    start: local("2023-05-03 11:00"),
+                    all_day: false,
+                    title: "Standup (moved)".into(),
+                },
+            ]
+        );
+
+        let start = local("2023-04-17 09:00");
+        let rrule = "FREQ=WEEKLY;BYDAY=MO,WE;COUNT=4";
+        assert_eq!(
+            next_occurrence(start, rrule, local("2023-04-25 00:00")),
+            Some(local("2023-04-26 09:00"))
+        );
+        assert_eq!(
+            next_occurrence(start, rrule, local("2023-04-27 00:00")),
+            None
+        );
+    }
+}
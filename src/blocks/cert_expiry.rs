@@ -0,0 +1,157 @@
+//! TLS certificate expiry
+//!
+//! Checks when the certificates of the configured hosts expire and shows the number of days left
+//! for the one expiring first. The certificates are fetched with `openssl s_client`, so `openssl`
+//! needs to be installed.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `hosts` | The hosts to check, as `"host"` or `"host:port"`. The port defaults to 443. | **Required**
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $host $days "`
+//! `interval` | Update interval in seconds | `3600`
+//! `warning_days` | Days left from which the state is set to warning | `14`
+//! `critical_days` | Days left from which the state is set to critical | `3`
+//!
+//! Placeholder | Value                                                   | Type   | Unit
+//! ------------|---------------------------------------------------------|--------|-----
+//! `icon`      | A static icon                                           | Icon   | -
+//! `host`      | The host whose certificate expires first                | Text   | -
+//! `days`      | The number of days until that certificate expires       | Number | -
+//! `failed`    | The number of hosts whose certificate could not be read | Number | -
+//!
+//! The block is in the critical state if a certificate could not be read.
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "cert_expiry"
+//! hosts = ["example.org", "mail.example.org:993"]
+//! format = " $icon $host expires in $days days "
+//! ```
+//!
+//! # Icons Used
+//! - `calendar`
+
+use super::prelude::*;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use std::process::Stdio;
+use tokio::process::Command;
+
+make_log_macro!(debug, "cert_expiry");
+
+/// How long to wait for a host before counting its certificate as failed
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    hosts: Vec<String>,
+    format: FormatConfig,
+    #[default(3600.into())]
+    interval: Seconds,
+    #[default(14)]
+    warning_days: i64,
+    #[default(3)]
+    critical_days: i64,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    if config.hosts.is_empty() {
+        return Err(Error::new("`hosts` must not be empty"));
+    }
+
+    let mut widget = Widget::new().with_format(config.format.with_default(" $icon $host $days ")?);
+    let mut timer = config.interval.timer();
+
+    loop {
+        let results =
+            futures::future::join_all(config.hosts.iter().map(|host| expiry_date(host))).await;
+        let mut failed = 0;
+        let mut soonest: Option<(&String, DateTime<Utc>)> = None;
+        for (host, result) in config.hosts.iter().zip(results) {
+            match result {
+                Ok(date) => {
+                    if soonest.map_or(true, |(_, s)| date < s) {
+                        soonest = Some((host, date));
+                    }
+                }
+                Err(e) => {
+                    debug!("{host}: {e}");
+                    failed += 1;
+                }
+            }
+        }
+        let days = soonest.map(|(_, date)| (date - Utc::now()).num_days());
+
+        widget.state = match days {
+            _ if failed > 0 => State::Critical,
+            Some(days) if days <= config.critical_days => State::Critical,
+            Some(days) if days <= config.warning_days => State::Warning,
+            _ => State::Idle,
+        };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("calendar")?),
+            "failed" => Value::number(failed),
+            [if let Some((host, _)) = soonest] "host" => Value::text(host.clone()),
+            [if let Some(days) = days] "days" => Value::number(days),
+        });
+        api.set_widget(&widget).await?;
+
+        select! {
+            _ = timer.tick() => (),
+            _ = api.wait_for_update_request() => (),
+        }
+    }
+}
+
+/// Fetch the certificate of `host` and return its expiry date
+async fn expiry_date(host: &str) -> Result<DateTime<Utc>> {
+    let (name, address) = match host.rsplit_once(':') {
+        Some((name, _)) => (name, host.to_string()),
+        None => (host, format!("{host}:443")),
+    };
+
+    // An unreachable host would otherwise keep `s_client` hanging in the TCP connect
+    let s_client = Command::new("openssl")
+        .args(["s_client", "-connect", &address, "-servername", name])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let s_client = tokio::time::timeout(TIMEOUT, s_client)
+        .await
+        .error("`openssl s_client` timed out")?
+        .error("Failed to run `openssl s_client`")?;
+    let mut x509 = Command::new("openssl")
+        .args(["x509", "-noout", "-enddate"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .error("Failed to run `openssl x509`")?;
+    let mut stdin = x509.stdin.take().unwrap();
+    tokio::io::AsyncWriteExt::write_all(&mut stdin, &s_client.stdout)
+        .await
+        .error("Failed to pass the certificate to `openssl x509`")?;
+    drop(stdin);
+    let output = x509
+        .wait_with_output()
+        .await
+        .error("Failed to run `openssl x509`")?;
+    let output = String::from_utf8(output.stdout).error("openssl produced non-UTF8 output")?;
+
+    // e.g. `notAfter=Mar  1 12:00:00 2025 GMT`
+    let date = output
+        .trim()
+        .strip_prefix("notAfter=")
+        .error("Failed to read the certificate")?
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let date = NaiveDateTime::parse_from_str(&date, "%b %d %H:%M:%S %Y GMT")
+        .error("Failed to parse the expiry date")?;
+    Ok(Utc.from_utc_datetime(&date))
+}
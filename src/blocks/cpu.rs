@@ -7,22 +7,30 @@
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $utilization "`
 //! `format_alt` | If set, block will switch between `format` and `format_alt` on every click | `None`
 //! `interval` | Update interval in seconds | `5`
+//! `governors` | The cpufreq governors to cycle through with `cycle_governor` | Governors listed in `scaling_available_governors`
+//! `governor_cmd` | A shell command which switches the governor, usually through a privileged helper. `{governor}` will be substituted with the governor to switch to. | `None`
+//! `boost_cmd` | A shell command which switches turbo boost, usually through a privileged helper. `{boost}` will be substituted with `1` to enable or `0` to disable boost. | `None`
+//! `icon_levels` | Icons to use for ranges of average utilization, in percent, see [`IconLevels`](crate::icons::IconLevels) | `cpu_low` up to 33%, `cpu_med` up to 67%, `cpu_high` above
 //!
 //! Placeholder      | Value                                                          | Type   | Unit
 //! -----------------|----------------------------------------------------------------|--------|---------------
 //! `icon`           | An icon                                                        | Icon   | -
 //! `utilization`    | Average CPU utilization                                        | Number | %
-//! `utilization<N>` | Utilization of Nth logical CPU                                 | Number | %
-//! `barchart`       | Utilization of all logical CPUs presented as a barchart        | Text   | -
+//! `utilization<N>` | Utilization of Nth logical CPU, counting from 1 (`utilization1` is `cpu0`) | Number | %
+//! `barchart`       | Utilization of all logical CPUs presented as a barchart, one character per CPU | Text   | -
 //! `frequency`      | Average CPU frequency                                          | Number | Hz
-//! `frequency<N>`   | Frequency of Nth logical CPU                                   | Number | Hz
+//! `frequency<N>`   | Frequency of Nth logical CPU, counting from 1                  | Number | Hz
+//! `max_frequency`  | Maximum frequency of the CPU                                   | Number | Hz
+//! `governor`       | Active cpufreq governor (absent if cpufreq is not available)  | Text   | -
 //! `boost`          | CPU turbo boost status (may be absent if CPU is not supported) | Text   | -
 //!
 //! Action          | Description                               | Default button
 //! ----------------|-------------------------------------------|---------------
 //! `toggle_format` | Toggles between `format` and `format_alt` | Left
+//! `cycle_governor` | Switches to the next governor using `governor_cmd` | Right
+//! `toggle_boost`  | Toggles turbo boost using `boost_cmd`     | Middle
 //!
-//! # Example
+//! # Examples
 //!
 //! ```toml
 //! [[block]]
@@ -32,6 +40,25 @@
 //! format_alt = " $icon $frequency{ $boost|} "
 //! ```
 //!
+//! Show the governor and switch it or turbo boost with `cpupower` and `pkexec`:
+//!
+//! ```toml
+//! [[block]]
+//! block = "cpu"
+//! format = " $icon $frequency/$max_frequency $governor{ $boost|} "
+//! governors = ["powersave", "performance"]
+//! governor_cmd = "pkexec cpupower frequency-set -g {governor}"
+//! boost_cmd = "echo {boost} | pkexec tee /sys/devices/system/cpu/cpufreq/boost"
+//! ```
+//!
+//! Utilization of the first two cores of a dual-core CPU:
+//!
+//! ```toml
+//! [[block]]
+//! block = "cpu"
+//! format = " $icon $utilization1 $utilization2 "
+//! ```
+//!
 //! # Icons Used
 //! - `cpu_low`
 //! - `cpu_med`
@@ -45,10 +72,13 @@ use tokio::fs::File;
 use tokio::io::{AsyncBufReadExt, BufReader};
 
 use super::prelude::*;
+use crate::icons::IconLevels;
+use crate::subprocess::spawn_shell_sync;
 use crate::util::read_file;
 
 const CPU_BOOST_PATH: &str = "/sys/devices/system/cpu/cpufreq/boost";
 const CPU_NO_TURBO_PATH: &str = "/sys/devices/system/cpu/intel_pstate/no_turbo";
+const CPUFREQ_PATH: &str = "/sys/devices/system/cpu/cpu0/cpufreq";
 
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(default)]
@@ -57,11 +87,20 @@ pub struct Config {
     format_alt: Option<FormatConfig>,
     #[default(5.into())]
     interval: Seconds,
+    #[default(IconLevels::new(&[(33., "cpu_low"), (67., "cpu_med"), (100., "cpu_high")]))]
+    icon_levels: IconLevels,
+    governors: Option<Vec<String>>,
+    governor_cmd: Option<String>,
+    boost_cmd: Option<String>,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
-    api.set_default_actions(&[(MouseButton::Left, None, "toggle_format")])
-        .await?;
+    api.set_default_actions(&[
+        (MouseButton::Left, None, "toggle_format"),
+        (MouseButton::Right, None, "cycle_governor"),
+        (MouseButton::Middle, None, "toggle_boost"),
+    ])
+    .await?;
 
     let mut format = config.format.with_default(" $icon $utilization ")?;
     let mut format_alt = match config.format_alt {
@@ -78,6 +117,18 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     let mut cputime = read_proc_stat().await?;
     let cores = cputime.1.len();
 
+    let max_freq = read_cpufreq("cpuinfo_max_freq")
+        .await
+        .and_then(|f| f.parse::<f64>().ok())
+        .map(|f| f * 1e3); // kHz -> Hz
+    let governors = match config.governors {
+        Some(governors) => governors,
+        None => read_cpufreq("scaling_available_governors")
+            .await
+            .map(|g| g.split_ascii_whitespace().map(String::from).collect())
+            .unwrap_or_default(),
+    };
+
     let mut timer = config.interval.timer();
 
     loop {
@@ -104,16 +155,14 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         }
 
         // Read boot state on intel CPUs
-        let boost = boost_status().await.map(|status| match status {
+        let boost_enabled = boost_status().await;
+        let boost = boost_enabled.map(|status| match status {
             true => boost_icon_on.clone(),
             false => boost_icon_off.clone(),
         });
+        let governor = read_cpufreq("scaling_governor").await;
 
-        let icon = match utilization_avg {
-            x if x <= 0.33 => "cpu_low",
-            x if x <= 0.67 => "cpu_med",
-            _ => "cpu_high",
-        };
+        let icon = config.icon_levels.get(utilization_avg * 100.);
 
         let mut values = map!(
             "icon" => Value::icon(api.get_icon(icon)?),
@@ -122,6 +171,10 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             "utilization" => Value::percents(utilization_avg * 100.),
         );
         boost.map(|b| values.insert("boost".into(), Value::icon(b)));
+        max_freq.map(|f| values.insert("max_frequency".into(), Value::hertz(f)));
+        governor
+            .as_ref()
+            .map(|g| values.insert("governor".into(), Value::text(g.clone())));
         for (i, freq) in freqs.iter().enumerate() {
             values.insert(format!("frequency{}", i + 1).into(), Value::hertz(*freq));
         }
@@ -153,6 +206,27 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                             break;
                         }
                     }
+                    Action(a) if a == "cycle_governor" => {
+                        if let (Some(cmd), false) = (&config.governor_cmd, governors.is_empty()) {
+                            let next = governor
+                                .as_ref()
+                                .and_then(|g| governors.iter().position(|x| x == g))
+                                .map_or(0, |i| (i + 1) % governors.len());
+                            spawn_shell_sync(&cmd.replace("{governor}", &governors[next]))
+                                .await
+                                .error("failed to run governor_cmd")?;
+                            break;
+                        }
+                    }
+                    Action(a) if a == "toggle_boost" => {
+                        if let (Some(cmd), Some(enabled)) = (&config.boost_cmd, boost_enabled) {
+                            let boost = if enabled { "0" } else { "1" };
+                            spawn_shell_sync(&cmd.replace("{boost}", boost))
+                                .await
+                                .error("failed to run boost_cmd")?;
+                            break;
+                        }
+                    }
                     _ => (),
                 }
             }
@@ -246,6 +320,11 @@ async fn read_proc_stat() -> Result<(CpuTime, Vec<CpuTime>)> {
     Ok((total.error("failed to parse /proc/stat")?, utilizations))
 }
 
+/// Read a cpufreq attribute of the first CPU
+async fn read_cpufreq(attr: &str) -> Option<String> {
+    read_file(format!("{CPUFREQ_PATH}/{attr}")).await.ok()
+}
+
 /// Read the cpu turbo boost status from kernel sys interface
 /// or intel pstate interface
 async fn boost_status() -> Option<bool> {
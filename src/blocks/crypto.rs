@@ -0,0 +1,165 @@
+//! Cryptocurrency prices
+//!
+//! Shows the price and 24 hour change of one coin at a time, cycling through the configured coins
+//! by scrolling. The block is in the warning or critical state if the price of the shown coin has
+//! dropped by at least `change_warning` or `change_critical` percent.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `coins` | The ids of the coins to show, as used by the service, e.g. `["bitcoin", "ethereum"]` | **Required**
+//! `currency` | The currency to show prices in | `"usd"`
+//! `service` | The service to get prices from. See below for available services. | `{ name = "coingecko" }`
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $coin $price.eng(w:4) $change.eng(w:2) "`
+//! `interval` | Update interval in seconds | `300`
+//! `change_warning` | 24 hour change in percent at or below which the state is set to warning | `-5.0`
+//! `change_critical` | 24 hour change in percent at or below which the state is set to critical | `-10.0`
+//!
+//! # CoinGecko Options
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `name` | `coingecko` | **Required**
+//! `api_key` | A demo API key, which raises the rate limit. Can also be passed using the `I3RS_COINGECKO_API_KEY` environment variable. | `None`
+//!
+//! # CoinCap Options
+//!
+//! Only supports `"usd"` as the currency. An API key from <https://pro.coincap.io> is required.
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `name` | `coincap` | **Required**
+//! `api_key` | The API key. Can also be passed using the `I3RS_COINCAP_API_KEY` environment variable. | **Required**
+//!
+//! Placeholder | Value                                 | Type   | Unit
+//! ------------|---------------------------------------|--------|-----
+//! `coin`      | The id of the shown coin              | Text   | -
+//! `price`     | The price of the coin in `currency`   | Number | -
+//! `currency`  | The value of `currency`, upper case   | Text   | -
+//! `change`    | The change of the price over 24 hours | Number | %
+//!
+//! Action | Description            | Default button
+//! -------|------------------------|---------------
+//! `next` | Show the next coin     | Wheel Up
+//! `prev` | Show the previous coin | Wheel Down
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "crypto"
+//! coins = ["bitcoin", "ethereum", "monero"]
+//! currency = "eur"
+//! format = " $coin $price.eng(w:4) $currency $change.eng(w:2) "
+//! ```
+//!
+//! ```toml
+//! [[block]]
+//! block = "crypto"
+//! coins = ["bitcoin"]
+//! change_warning = -3.0
+//! [block.service]
+//! name = "coincap"
+//! api_key = "..."
+//! ```
+
+use super::prelude::*;
+
+mod coincap;
+mod coingecko;
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    coins: Vec<String>,
+    #[default("usd".into())]
+    currency: String,
+    service: CryptoService,
+    format: FormatConfig,
+    #[default(300.into())]
+    interval: Seconds,
+    #[default(-5.0)]
+    change_warning: f64,
+    #[default(-10.0)]
+    change_critical: f64,
+}
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(tag = "name", rename_all = "lowercase")]
+enum CryptoService {
+    #[default]
+    CoinGecko(coingecko::Config),
+    CoinCap(coincap::Config),
+}
+
+#[async_trait]
+trait PriceProvider {
+    /// The prices of `coins`, in the same order
+    async fn get_prices(&self, coins: &[String], currency: &str) -> Result<Vec<Price>>;
+}
+
+struct Price {
+    price: f64,
+    /// In percent
+    change: f64,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[
+        (MouseButton::WheelUp, None, "next"),
+        (MouseButton::WheelDown, None, "prev"),
+    ])
+    .await?;
+
+    let mut widget = Widget::new().with_format(
+        config
+            .format
+            .with_default(" $coin $price.eng(w:4) $change.eng(w:2) ")?,
+    );
+
+    if config.coins.is_empty() {
+        return Err(Error::new("`coins` must not be empty"));
+    }
+    let currency = config.currency.to_lowercase();
+    let provider: Box<dyn PriceProvider + Send + Sync> = match config.service {
+        CryptoService::CoinGecko(service) => Box::new(coingecko::Service::new(service)),
+        CryptoService::CoinCap(service) => Box::new(coincap::Service::new(service, &currency)?),
+    };
+    let mut timer = config.interval.timer();
+    let mut shown = 0;
+
+    loop {
+        let prices = api
+            .recoverable(|| provider.get_prices(&config.coins, &currency))
+            .await?;
+
+        loop {
+            let price = &prices[shown];
+            widget.state = if price.change <= config.change_critical {
+                State::Critical
+            } else if price.change <= config.change_warning {
+                State::Warning
+            } else {
+                State::Idle
+            };
+            widget.set_values(map! {
+                "coin" => Value::text(config.coins[shown].clone()),
+                "price" => Value::number(price.price),
+                "currency" => Value::text(currency.to_uppercase()),
+                "change" => Value::percents(price.change),
+            });
+            api.set_widget(&widget).await?;
+
+            select! {
+                _ = timer.tick() => break,
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "next" => shown = (shown + 1) % prices.len(),
+                    Action(a) if a == "prev" => shown = (shown + prices.len() - 1) % prices.len(),
+                    _ => (),
+                }
+            }
+        }
+    }
+}
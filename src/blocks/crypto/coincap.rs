@@ -0,0 +1,83 @@
+use super::*;
+
+const URL: &str = "https://rest.coincap.io/v3/assets";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub(super) struct Config {
+    api_key: Option<String>,
+}
+
+pub(super) struct Service {
+    api_key: String,
+}
+
+impl Service {
+    pub(super) fn new(config: Config, currency: &str) -> Result<Self> {
+        if currency != "usd" {
+            return Err(Error::new("CoinCap only supports \"usd\" as the currency"));
+        }
+        Ok(Self {
+            api_key: config
+                .api_key
+                .or_else(|| std::env::var("I3RS_COINCAP_API_KEY").ok())
+                .error("CoinCap API key not found")?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct AssetsResponse {
+    data: Vec<Asset>,
+}
+
+/// Numbers are sent as strings, e.g. `"27000.123"`
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Asset {
+    id: String,
+    price_usd: Option<String>,
+    change_percent_24_hr: Option<String>,
+}
+
+#[async_trait]
+impl PriceProvider for Service {
+    async fn get_prices(&self, coins: &[String], _currency: &str) -> Result<Vec<Price>> {
+        let ids = coins.join(",");
+        let response: AssetsResponse = REQWEST_CLIENT
+            .get(URL)
+            .query(&[("ids", ids.as_str())])
+            .bearer_auth(&self.api_key)
+            .send()
+            .await
+            .error("Failed to send request")?
+            .error_for_status()
+            .error("CoinCap API error")?
+            .json()
+            .await
+            .error("Failed to get JSON")?;
+
+        coins
+            .iter()
+            .map(|coin| {
+                let asset = response
+                    .data
+                    .iter()
+                    .find(|a| &a.id == coin)
+                    .or_error(|| format!("Unknown coin '{coin}'"))?;
+                Ok(Price {
+                    price: asset
+                        .price_usd
+                        .as_deref()
+                        .and_then(|p| p.parse().ok())
+                        .or_error(|| format!("No price of '{coin}'"))?,
+                    change: asset
+                        .change_percent_24_hr
+                        .as_deref()
+                        .and_then(|c| c.parse().ok())
+                        .unwrap_or(0.0),
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,66 @@
+use super::*;
+
+const URL: &str = "https://api.coingecko.com/api/v3/simple/price";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub(super) struct Config {
+    api_key: Option<String>,
+}
+
+pub(super) struct Service {
+    api_key: Option<String>,
+}
+
+impl Service {
+    pub(super) fn new(config: Config) -> Self {
+        Self {
+            api_key: config
+                .api_key
+                .or_else(|| std::env::var("I3RS_COINGECKO_API_KEY").ok()),
+        }
+    }
+}
+
+#[async_trait]
+impl PriceProvider for Service {
+    async fn get_prices(&self, coins: &[String], currency: &str) -> Result<Vec<Price>> {
+        let ids = coins.join(",");
+        let mut request = REQWEST_CLIENT.get(URL).query(&[
+            ("ids", ids.as_str()),
+            ("vs_currencies", currency),
+            ("include_24hr_change", "true"),
+        ]);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("x-cg-demo-api-key", api_key);
+        }
+        // e.g. `{"bitcoin": {"usd": 27000.0, "usd_24h_change": -1.5}}`
+        let response: HashMap<String, HashMap<String, Option<f64>>> = request
+            .send()
+            .await
+            .error("Failed to send request")?
+            .error_for_status()
+            .error("CoinGecko API error")?
+            .json()
+            .await
+            .error("Failed to get JSON")?;
+
+        let change_key = format!("{currency}_24h_change");
+        coins
+            .iter()
+            .map(|coin| {
+                let prices = response
+                    .get(coin)
+                    .or_error(|| format!("Unknown coin '{coin}'"))?;
+                Ok(Price {
+                    price: prices
+                        .get(currency)
+                        .copied()
+                        .flatten()
+                        .or_error(|| format!("No price of '{coin}' in '{currency}'"))?,
+                    change: prices.get(&change_key).copied().flatten().unwrap_or(0.0),
+                })
+            })
+            .collect()
+    }
+}
@@ -5,7 +5,7 @@
 //! {"icon": "...", "state": "...", "text": "...", "short_text": "..."}
 //! ```
 //! `icon` is optional (default "")
-//! `state` is optional, it may be Idle, Info, Good, Warning, Critical (default Idle)
+//! `state` is optional, it may be Idle, Info, Good, Warning, Critical (default Idle). Lowercase names (e.g. `warning`) are accepted as well.
 //! `short_text` is optional.
 //!
 //! # Configuration
@@ -15,10 +15,10 @@
 //! `format` | A string to customise the output of this block. See below for available placeholders. | <code>"{ $icon&vert;} $text.pango-str() "</code>
 //! `command` | Shell command to execute & display | `None`
 //! `persistent` | Run command in the background; update display for each output line of the command | `false`
-//! `cycle` | Commands to execute and change when the button is clicked | `None`
+//! `cycle` | Commands to execute in turn. The `cycle` action (left click by default) switches to the next one and displays its output. | `None`
 //! `interval` | Update interval in seconds (or "once" to update only once) | `10`
 //! `json` | Use JSON from command output to format the block. If the JSON is not valid, the block will error out. | `false`
-//! `watch_files` | Watch files to trigger update on file modification | `None`
+//! `watch_files` | Files (or directories) to watch. The command is re-run immediately when any of them is modified or replaced. Supports path expansions e.g. `~`. | `None`
 //! `hide_when_empty` | Hides the block when the command output (or json text field) is empty | `false`
 //! `shell` | Specify the shell to use when running commands | `$SHELL` if set, otherwise fallback to `sh`
 //!
@@ -42,7 +42,7 @@
 //! command = ''' cat /sys/class/thermal/thermal_zone0/temp | awk '{printf("%.1f\n",$1/1000)}' '''
 //! ```
 //!
-//! Cycle between "ON" and "OFF", update every 1 second, run `<command>` when block is clicked:
+//! Cycle between "ON" and "OFF" on each left click, update every 1 second, also run `<command>` when block is clicked:
 //!
 //! ```toml
 //! [[block]]
@@ -54,7 +54,7 @@
 //! cmd = "<command>"
 //! ```
 //!
-//! Use JSON output:
+//! Use JSON output to set the text, state and icon of the block:
 //!
 //! ```toml
 //! [[block]]
@@ -63,6 +63,15 @@
 //! json = true
 //! ```
 //!
+//! Display the currently playing song, updated each time `playerctl` prints a new line:
+//!
+//! ```toml
+//! [[block]]
+//! block = "custom"
+//! command = "playerctl --follow metadata --format '{{artist}} - {{title}}'"
+//! persistent = true
+//! ```
+//!
 //! Display kernel, update the block only once:
 //!
 //! ```toml
@@ -87,16 +96,14 @@
 //! ```toml
 //! [[block]]
 //! block = "custom"
-//! command = "cat custom_status"
-//! watch_files = ["custom_status"]
+//! command = "cat ~/.cache/mystatus"
+//! watch_files = ["~/.cache/mystatus"]
 //! interval = "once"
 //! ```
-//!
-//! # TODO:
-//! - Use `shellexpand`
 
 use super::prelude::*;
 use inotify::{Inotify, WatchMask};
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use tokio::io::{self, AsyncBufReadExt, BufReader};
 use tokio::process::Command;
@@ -113,7 +120,7 @@ pub struct Config {
     json: bool,
     hide_when_empty: bool,
     shell: Option<String>,
-    watch_files: Vec<String>,
+    watch_files: Vec<ShellString>,
 }
 
 async fn update_bar(
@@ -166,15 +173,47 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         [] => Box::pin(futures::stream::pending()),
         files => {
             let mut notify = Inotify::init().error("Failed to start inotify")?;
+            let mut watched = Vec::with_capacity(files.len());
             for file in files {
-                notify
-                    .add_watch(file, WatchMask::MODIFY | WatchMask::CLOSE_WRITE)
-                    .error("Failed to file")?;
+                let path = PathBuf::from(file.expand()?.as_ref());
+                // Watch the parent directory, because files are often replaced rather than
+                // modified. Directories are watched directly.
+                let (dir, name) = match (path.parent(), path.file_name()) {
+                    (Some(dir), Some(name)) if !path.is_dir() => {
+                        let dir = if dir.as_os_str().is_empty() {
+                            Path::new(".")
+                        } else {
+                            dir
+                        };
+                        (dir.to_owned(), Some(name.to_owned()))
+                    }
+                    _ => (path.clone(), None),
+                };
+                let wd = notify
+                    .add_watch(
+                        &dir,
+                        WatchMask::MODIFY
+                            | WatchMask::CLOSE_WRITE
+                            | WatchMask::MOVED_TO
+                            | WatchMask::CREATE
+                            | WatchMask::DELETE,
+                    )
+                    .or_error(|| format!("Failed to watch '{}'", dir.display()))?;
+                watched.push((wd, name));
             }
             Box::pin(
                 notify
                     .event_stream([0; 1024])
-                    .error("Failed to create event stream")?,
+                    .error("Failed to create event stream")?
+                    .filter(move |event| {
+                        let relevant = match event {
+                            Ok(event) => watched.iter().any(|(wd, name)| {
+                                *wd == event.wd && (name.is_none() || *name == event.name)
+                            }),
+                            Err(_) => true,
+                        };
+                        async move { relevant }
+                    }),
             )
         }
     };
@@ -198,6 +237,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                     .error("'command' must be specified when 'persistent' is set")?,
             ])
             .stdout(Stdio::piped())
+            .kill_on_drop(true)
             .spawn()
             .error("failed to run command")?;
 
@@ -207,10 +247,6 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             .expect("child did not have a handle to stdout");
         let mut reader = BufReader::new(stdout).lines();
 
-        tokio::spawn(async move {
-            let _ = process.wait().await;
-        });
-
         loop {
             select! {
                 line = reader.next_line() => {
@@ -228,7 +264,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             .error("either 'command' or 'cycle' must be specified")?
             .into_iter()
             .cycle();
-        let mut cmd = cycle.next().unwrap();
+        let mut cmd = cycle.next().error("'cycle' must not be empty")?;
 
         loop {
             // Run command
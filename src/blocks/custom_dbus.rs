@@ -7,22 +7,34 @@
 //! ```text
 //! NAME                                TYPE      SIGNATURE RESULT/VALUE FLAGS
 //! rs.i3status.custom                  interface -         -            -
-//! .SetIcon                            method    s         s            -
-//! .SetState                           method    s         s            -
-//! .SetText                            method    ss        s            -
+//! .RemoveValue                        method    s         -            -
+//! .SetIcon                            method    s         -            -
+//! .SetState                           method    s         -            -
+//! .SetText                            method    ss        -            -
+//! .SetValue                           method    ss        -            -
+//! .Icon                               property  s         ""           emits-change
+//! .ShortText                          property  s         ""           emits-change
+//! .State                              property  s         "idle"       emits-change
+//! .Text                               property  s         ""           emits-change
+//! .Values                             property  a{ss}     0            emits-change
 //! ```
 //!
+//! `SetValue` sets a named value which can be used as a placeholder in `format`, and
+//! `RemoveValue` removes it. The names `icon`, `text` and `short_text` are reserved. The
+//! properties can be used to query the current content of the block.
+//!
 //! # Configuration
 //!
 //! Key | Values | Default
 //! ----|--------|--------
 //! `format` | A string to customise the output of this block. | <code>"{ $icon&vert;}{ $text.str(pango:true)&vert;} "</code>
 //!
-//! Placeholder  | Value                                  | Type   | Unit
+//! Placeholder  | Value                                                             | Type   | Unit
 //! -------------|-------------------------------------------------------------------|--------|---------------
 //! `icon`       | Value of icon set via `SetIcon` if the value is non-empty string. | Icon   | -
 //! `text`       | Value of the first string from SetText                            | Text   | -
 //! `short_text` | Value of the second string from SetText                           | Text   | -
+//! Any name     | Value set via `SetValue`                                          | Text   | -
 //!
 //! # Example
 //!
@@ -41,6 +53,18 @@
 //! busctl --user call rs.i3status /my_path rs.i3status.custom SetIcon s music
 //! # set state to 'good'
 //! busctl --user call rs.i3status /my_path rs.i3status.custom SetState s good
+//! # set the value of the $unread placeholder to 3
+//! busctl --user call rs.i3status /my_path rs.i3status.custom SetValue ss unread 3
+//! # query the current text
+//! busctl --user get-property rs.i3status /my_path rs.i3status.custom Text
+//! ```
+//!
+//! Use named values in the format:
+//! ```toml
+//! [[block]]
+//! block = "custom_dbus"
+//! path = "/mail"
+//! format = " $icon{ $unread|} "
 //! ```
 //!
 //! Because it's impossible to publish objects to the same name from different
@@ -54,7 +78,7 @@
 
 use super::prelude::*;
 use std::env;
-use zbus::{dbus_interface, fdo};
+use zbus::{dbus_interface, fdo, SignalContext};
 
 // Share DBus connection between multiple block instances
 static DBUS_CONNECTION: async_once_cell::OnceCell<Result<zbus::Connection>> =
@@ -75,19 +99,30 @@ struct Block {
     icon: Option<String>,
     text: Option<String>,
     short_text: Option<String>,
+    values: HashMap<String, String>,
 }
 
+const RESERVED_NAMES: &[&str] = &["icon", "text", "short_text"];
+
 fn block_values(block: &Block, api: &CommonApi) -> Result<HashMap<Cow<'static, str>, Value>> {
-    Ok(map! {
+    let mut values = map! {
         [if let Some(icon) = &block.icon] "icon" => Value::icon(api.get_icon(icon)?),
         [if let Some(text) = &block.text] "text" => Value::text(text.to_string()),
         [if let Some(short_text) = &block.short_text] "short_text" => Value::text(short_text.to_string()),
-    })
+    };
+    for (name, value) in &block.values {
+        values.insert(name.clone().into(), Value::text(value.clone()));
+    }
+    Ok(values)
 }
 
 #[dbus_interface(name = "rs.i3status.custom")]
 impl Block {
-    async fn set_icon(&mut self, icon: &str) -> fdo::Result<()> {
+    async fn set_icon(
+        &mut self,
+        icon: &str,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> fdo::Result<()> {
         self.icon = if icon.is_empty() {
             None
         } else {
@@ -95,18 +130,30 @@ impl Block {
         };
         self.widget.set_values(block_values(self, &self.api)?);
         self.api.set_widget(&self.widget).await?;
+        self.icon_changed(&ctxt).await?;
         Ok(())
     }
 
-    async fn set_text(&mut self, full: String, short: String) -> fdo::Result<()> {
+    async fn set_text(
+        &mut self,
+        full: String,
+        short: String,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> fdo::Result<()> {
         self.text = Some(full);
         self.short_text = Some(short);
         self.widget.set_values(block_values(self, &self.api)?);
         self.api.set_widget(&self.widget).await?;
+        self.text_changed(&ctxt).await?;
+        self.short_text_changed(&ctxt).await?;
         Ok(())
     }
 
-    async fn set_state(&mut self, state: &str) -> fdo::Result<()> {
+    async fn set_state(
+        &mut self,
+        state: &str,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> fdo::Result<()> {
         self.widget.state = match state {
             "idle" => State::Idle,
             "info" => State::Info,
@@ -116,8 +163,70 @@ impl Block {
             _ => return Err(Error::new(format!("'{state}' is not a valid state")).into()),
         };
         self.api.set_widget(&self.widget).await?;
+        self.state_changed(&ctxt).await?;
+        Ok(())
+    }
+
+    async fn set_value(
+        &mut self,
+        name: String,
+        value: String,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> fdo::Result<()> {
+        if RESERVED_NAMES.contains(&name.as_str()) {
+            return Err(Error::new(format!("'{name}' is a reserved name")).into());
+        }
+        self.values.insert(name, value);
+        self.widget.set_values(block_values(self, &self.api)?);
+        self.api.set_widget(&self.widget).await?;
+        self.values_changed(&ctxt).await?;
         Ok(())
     }
+
+    async fn remove_value(
+        &mut self,
+        name: &str,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> fdo::Result<()> {
+        if self.values.remove(name).is_some() {
+            self.widget.set_values(block_values(self, &self.api)?);
+            self.api.set_widget(&self.widget).await?;
+            self.values_changed(&ctxt).await?;
+        }
+        Ok(())
+    }
+
+    #[dbus_interface(property)]
+    fn icon(&self) -> String {
+        self.icon.clone().unwrap_or_default()
+    }
+
+    #[dbus_interface(property)]
+    fn text(&self) -> String {
+        self.text.clone().unwrap_or_default()
+    }
+
+    #[dbus_interface(property)]
+    fn short_text(&self) -> String {
+        self.short_text.clone().unwrap_or_default()
+    }
+
+    #[dbus_interface(property)]
+    fn state(&self) -> String {
+        match self.widget.state {
+            State::Idle => "idle",
+            State::Info => "info",
+            State::Good => "good",
+            State::Warning => "warning",
+            State::Critical => "critical",
+        }
+        .into()
+    }
+
+    #[dbus_interface(property)]
+    fn values(&self) -> HashMap<String, String> {
+        self.values.clone()
+    }
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
@@ -146,6 +255,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                 icon: None,
                 text: None,
                 short_text: None,
+                values: HashMap::new(),
             },
         )
         .await
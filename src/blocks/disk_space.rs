@@ -5,23 +5,40 @@
 //! Key | Values | Default
 //! ----|--------|--------
 //! `path` | Path to collect information from. Supports path expansions e.g. `~`. | `"/"`
+//! `device` | The block device in `/proc/diskstats` to collect IO statistics from | The device mounted at `path`
 //! `interval` | Update time in seconds | `20`
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $available "`
 //! `format_alt` | If set, block will switch between `format` and `format_alt` on every click | `None`
 //! `warning` | A value which will trigger warning block state | `20.0`
 //! `alert` | A value which will trigger critical block state | `10.0`
-//! `info_type` | Determines which information will affect the block state. Possible values are `"available"`, `"free"` and `"used"` | `"available"`
-//! `alert_unit` | The unit of `alert` and `warning` options. If not set, percents are uesd. Possible values are `"B"`, `"KB"`, `"MB"`, `"GB"` and `"TB"` | `None`
+//! `info_type` | Determines which information will affect the block state. Possible values are `"available"`, `"free"`, `"used"`, `"inodes_free"` and `"inodes_used"` | `"available"`
+//! `alert_unit` | The unit of `alert` and `warning` options. If not set, percents are uesd. Possible values are `"B"`, `"KB"`, `"MB"`, `"GB"` and `"TB"`. Not applicable to inodes, for which `alert` and `warning` are always in percents. | `None`
 //!
 //! Placeholder  | Value                                                              | Type   | Unit
 //! -------------|--------------------------------------------------------------------|--------|-------
 //! `icon`       | A static icon                                                      | Icon   | -
 //! `path`       | The value of `path` option                                         | Text   | -
-//! `percentage` | Free or used percentage. Depends on `info_type`                    | Number | %
+//! `percentage` | Free or used percentage of space or inodes. Depends on `info_type` | Number | %
 //! `total`      | Total disk space                                                   | Number | Bytes
 //! `used`       | Used disk space                                                    | Number | Bytes
 //! `free`       | Free disk space                                                    | Number | Bytes
 //! `available`  | Available disk space (free disk space minus reserved system space) | Number | Bytes
+//! `inodes_total` | Total number of inodes                                           | Number | -
+//! `inodes_used` | Number of used inodes                                             | Number | -
+//! `inodes_used_percent` | Used inodes, as a percentage of all inodes                | Number | %
+//! `read_speed` | Read throughput of `device`                                      | Number | Bytes per second
+//! `write_speed` | Write throughput of `device`                                    | Number | Bytes per second
+//! `read_iops`  | Completed reads per second                                         | Number | -
+//! `write_iops` | Completed writes per second                                        | Number | -
+//! `graph_read` | Read throughput graph                                              | Text   | -
+//! `graph_write` | Write throughput graph                                            | Text   | -
+//!
+//! The IO placeholders are absent if `device` is not set and the device mounted at `path` cannot
+//! be found (e.g. for `tmpfs`). They are averaged over the last `interval`.
+//!
+//! On btrfs, the space reported by `statvfs` does not take RAID profiles and unallocated space
+//! into account, so the space is read from `/sys/fs/btrfs` instead. `free` and `available` are then
+//! the estimated free space for data, like "Free (estimated)" of `btrfs filesystem usage`.
 //!
 //! Action          | Description                               | Default button
 //! ----------------|-------------------------------------------|---------------
@@ -40,6 +57,26 @@
 //! format_alt = " $icon $available / $total "
 //! ```
 //!
+//! Warn when running out of inodes:
+//!
+//! ```toml
+//! [[block]]
+//! block = "disk_space"
+//! info_type = "inodes_used"
+//! warning = 80.0
+//! alert = 95.0
+//! format = " $icon $available ($inodes_used_percent) "
+//! ```
+//!
+//! Disk throughput:
+//!
+//! ```toml
+//! [[block]]
+//! block = "disk_space"
+//! interval = 2
+//! format = " $icon R $read_speed.eng(prefix:K) $graph_read W $write_speed.eng(prefix:K) $graph_write "
+//! ```
+//!
 //! Update block on right click:
 //!
 //! ```toml
@@ -53,19 +90,26 @@
 //! # Icons Used
 //! - `disk_drive`
 
-// make_log_macro!(debug, "disk_space");
+make_log_macro!(debug, "disk_space");
+
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use super::prelude::*;
 use crate::formatting::prefix::Prefix;
+use crate::util::{self, read_file};
+use nix::sys::statfs::{statfs, BTRFS_SUPER_MAGIC};
 use nix::sys::statvfs::statvfs;
 
 #[derive(Copy, Clone, Debug, Deserialize, SmartDefault)]
-#[serde(rename_all = "lowercase")]
+#[serde(rename_all = "snake_case")]
 pub enum InfoType {
     #[default]
     Available,
     Free,
     Used,
+    InodesFree,
+    InodesUsed,
 }
 
 #[derive(Deserialize, Debug, SmartDefault)]
@@ -73,6 +117,7 @@ pub enum InfoType {
 pub struct Config {
     #[default("/".into())]
     path: ShellString,
+    device: Option<String>,
     info_type: InfoType,
     format: FormatConfig,
     format_alt: Option<FormatConfig>,
@@ -109,24 +154,69 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
 
     let path = config.path.expand()?;
 
+    let io_device = match config.device {
+        Some(device) => Some(device),
+        None => match mount_source(Path::new(&*path)).await {
+            Ok(device) => device.file_name().map(|n| n.to_string_lossy().into_owned()),
+            Err(e) => {
+                debug!("IO statistics are not available: {e}");
+                None
+            }
+        },
+    };
+    let mut io_stats: Option<(DiskStats, Instant)> = None;
+    let mut read_hist = [0f64; 8];
+    let mut write_hist = [0f64; 8];
+
     let mut timer = config.interval.timer();
 
     loop {
         let statvfs = statvfs(&*path).error("failed to retrieve statvfs")?;
 
-        let total = statvfs.blocks() * statvfs.fragment_size();
-        let used = (statvfs.blocks() - statvfs.blocks_free()) * statvfs.fragment_size();
-        let available = statvfs.blocks_available() * statvfs.block_size();
-        let free = statvfs.blocks_free() * statvfs.block_size();
+        let mut total = statvfs.blocks() * statvfs.fragment_size();
+        let mut used = (statvfs.blocks() - statvfs.blocks_free()) * statvfs.fragment_size();
+        let mut available = statvfs.blocks_available() * statvfs.block_size();
+        let mut free = statvfs.blocks_free() * statvfs.block_size();
+
+        let is_btrfs = statfs(&*path)
+            .map(|s| s.filesystem_type() == BTRFS_SUPER_MAGIC)
+            .unwrap_or(false);
+        if is_btrfs {
+            match btrfs_usage(Path::new(&*path)).await {
+                Ok(usage) => {
+                    total = usage.total;
+                    used = usage.used;
+                    available = usage.free;
+                    free = usage.free;
+                }
+                Err(e) => {
+                    debug!("falling back to statvfs: {e}");
+                }
+            }
+        }
+
+        let inodes_total = statvfs.files();
+        let inodes_used = statvfs.files() - statvfs.files_free();
+        // Some filesystems, such as btrfs, do not have a fixed number of inodes
+        let inodes_used_percent = if inodes_total == 0 {
+            0.
+        } else {
+            inodes_used as f64 / inodes_total as f64 * 100.
+        };
 
         let result = match config.info_type {
-            InfoType::Available => available,
-            InfoType::Free => free,
-            InfoType::Used => used,
-        } as f64;
+            InfoType::Available => available as f64,
+            InfoType::Free => free as f64,
+            InfoType::Used => used as f64,
+            InfoType::InodesFree => 100. - inodes_used_percent,
+            InfoType::InodesUsed => inodes_used_percent,
+        };
 
-        let percentage = result / (total as f64) * 100.;
-        widget.set_values(map! {
+        let percentage = match config.info_type {
+            InfoType::InodesFree | InfoType::InodesUsed => result,
+            _ => result / (total as f64) * 100.,
+        };
+        let mut values = map! {
             "icon" => Value::icon(api.get_icon("disk_drive")?),
             "path" => Value::text(path.to_string()),
             "percentage" => Value::percents(percentage),
@@ -134,21 +224,57 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             "used" => Value::bytes(used as f64),
             "available" => Value::bytes(available as f64),
             "free" => Value::bytes(free as f64),
-        });
+            "inodes_total" => Value::number(inodes_total as f64),
+            "inodes_used" => Value::number(inodes_used as f64),
+            "inodes_used_percent" => Value::percents(inodes_used_percent),
+        };
+
+        if let Some(device) = &io_device {
+            if let Some(new_stats) = DiskStats::read(device).await? {
+                let (mut read_speed, mut write_speed) = (0., 0.);
+                let (mut read_iops, mut write_iops) = (0., 0.);
+                if let Some((old_stats, time)) = io_stats {
+                    let elapsed = time.elapsed().as_secs_f64();
+                    let rate = |new: u64, old: u64| new.saturating_sub(old) as f64 / elapsed;
+                    read_speed = rate(new_stats.sectors_read, old_stats.sectors_read) * 512.;
+                    write_speed = rate(new_stats.sectors_written, old_stats.sectors_written) * 512.;
+                    read_iops = rate(new_stats.reads, old_stats.reads);
+                    write_iops = rate(new_stats.writes, old_stats.writes);
+                }
+                io_stats = Some((new_stats, Instant::now()));
+                util::push_to_hist(&mut read_hist, read_speed);
+                util::push_to_hist(&mut write_hist, write_speed);
+
+                values.insert("read_speed".into(), Value::bytes(read_speed));
+                values.insert("write_speed".into(), Value::bytes(write_speed));
+                values.insert("read_iops".into(), Value::number(read_iops));
+                values.insert("write_iops".into(), Value::number(write_iops));
+                values.insert(
+                    "graph_read".into(),
+                    Value::text(util::format_bar_graph(&read_hist)),
+                );
+                values.insert(
+                    "graph_write".into(),
+                    Value::text(util::format_bar_graph(&write_hist)),
+                );
+            }
+        }
+        widget.set_values(values);
 
         // Send percentage to alert check if we don't want absolute alerts
-        let alert_val_in_config_units = match unit {
-            Some(Prefix::Tera) => result * 1e-12,
-            Some(Prefix::Giga) => result * 1e-9,
-            Some(Prefix::Mega) => result * 1e-6,
-            Some(Prefix::Kilo) => result * 1e-3,
-            Some(_) => result,
-            None => percentage,
+        let alert_val_in_config_units = match (unit, config.info_type) {
+            (_, InfoType::InodesFree | InfoType::InodesUsed) => percentage,
+            (Some(Prefix::Tera), _) => result * 1e-12,
+            (Some(Prefix::Giga), _) => result * 1e-9,
+            (Some(Prefix::Mega), _) => result * 1e-6,
+            (Some(Prefix::Kilo), _) => result * 1e-3,
+            (Some(_), _) => result,
+            (None, _) => percentage,
         };
 
         // Compute state
         widget.state = match config.info_type {
-            InfoType::Used => {
+            InfoType::Used | InfoType::InodesUsed => {
                 if alert_val_in_config_units >= config.alert {
                     State::Critical
                 } else if alert_val_in_config_units >= config.warning {
@@ -157,7 +283,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                     State::Idle
                 }
             }
-            InfoType::Free | InfoType::Available => {
+            InfoType::Free | InfoType::Available | InfoType::InodesFree => {
                 if alert_val_in_config_units <= config.alert {
                     State::Critical
                 } else if alert_val_in_config_units <= config.warning {
@@ -188,3 +314,151 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         }
     }
 }
+
+/// Counters of a block device from `/proc/diskstats`
+/// Reference: <https://www.kernel.org/doc/html/latest/admin-guide/iostats.html>
+#[derive(Debug, Clone, Copy)]
+struct DiskStats {
+    reads: u64,
+    sectors_read: u64,
+    writes: u64,
+    sectors_written: u64,
+}
+
+impl DiskStats {
+    /// Returns `None` if the device does not exist
+    async fn read(device: &str) -> Result<Option<Self>> {
+        let diskstats = read_file("/proc/diskstats")
+            .await
+            .error("failed to read /proc/diskstats")?;
+        for line in diskstats.lines() {
+            let fields: Vec<&str> = line.split_ascii_whitespace().collect();
+            if fields.get(2) != Some(&device) {
+                continue;
+            }
+            let field = |i: usize| -> Result<u64> {
+                fields
+                    .get(i)
+                    .and_then(|x| x.parse().ok())
+                    .error("failed to parse /proc/diskstats")
+            };
+            return Ok(Some(Self {
+                reads: field(3)?,
+                sectors_read: field(5)?,
+                writes: field(7)?,
+                sectors_written: field(9)?,
+            }));
+        }
+        Ok(None)
+    }
+}
+
+struct BtrfsUsage {
+    total: u64,
+    used: u64,
+    free: u64,
+}
+
+/// Compute the usage of the btrfs filesystem mounted at or containing `path` from
+/// `/sys/fs/btrfs/<UUID>`. This follows the "Free (estimated)" calculation of
+/// `btrfs filesystem usage`: unallocated space is divided by the ratio of the data profile (e.g.
+/// 2 for RAID1) and added to the free space in the already allocated data chunks.
+async fn btrfs_usage(path: &Path) -> Result<BtrfsUsage> {
+    let device = mount_source(path).await?;
+    let fs_dir = btrfs_fs_dir(&device).await?;
+    let read_u64 = |path: PathBuf| async move {
+        read_file(&path)
+            .await
+            .ok()
+            .and_then(|x| x.parse::<u64>().ok())
+            .or_error(|| format!("failed to read {}", path.display()))
+    };
+
+    let allocation = fs_dir.join("allocation");
+    let data_total = read_u64(allocation.join("data/total_bytes")).await?;
+    let data_used = read_u64(allocation.join("data/bytes_used")).await?;
+    let data_disk_total = read_u64(allocation.join("data/disk_total")).await?;
+
+    let mut allocated = data_disk_total;
+    for kind in ["metadata", "system"] {
+        allocated += read_u64(allocation.join(kind).join("disk_total")).await?;
+    }
+
+    let mut size = 0;
+    let mut devices = tokio::fs::read_dir(fs_dir.join("devices"))
+        .await
+        .error("failed to read btrfs devices")?;
+    while let Some(dev) = devices
+        .next_entry()
+        .await
+        .error("failed to read btrfs devices")?
+    {
+        // In 512 byte sectors
+        size += read_u64(dev.path().join("size")).await? * 512;
+    }
+
+    let data_ratio = if data_total == 0 {
+        1.
+    } else {
+        data_disk_total as f64 / data_total as f64
+    };
+    let unallocated = size.saturating_sub(allocated);
+    let free = data_total.saturating_sub(data_used) + (unallocated as f64 / data_ratio) as u64;
+
+    Ok(BtrfsUsage {
+        total: data_used + free,
+        used: data_used,
+        free,
+    })
+}
+
+/// Find the device mounted at the mount point containing `path`, resolving symlinks such as
+/// `/dev/mapper/*`
+async fn mount_source(path: &Path) -> Result<PathBuf> {
+    let path = tokio::fs::canonicalize(path)
+        .await
+        .error("failed to canonicalize path")?;
+    let mountinfo = read_file("/proc/self/mountinfo")
+        .await
+        .error("failed to read /proc/self/mountinfo")?;
+
+    // Format: ID PARENT_ID MAJOR:MINOR ROOT MOUNT_POINT OPTIONS... - FSTYPE SOURCE SUPER_OPTIONS
+    let (_, source) = mountinfo
+        .lines()
+        .filter_map(|line| {
+            let mount_point = line.split(' ').nth(4)?;
+            let source = line.split(" - ").nth(1)?.split(' ').nth(1)?;
+            Some((Path::new(mount_point), source))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.as_os_str().len())
+        .error("failed to find mount point")?;
+
+    tokio::fs::canonicalize(source)
+        .await
+        .or_error(|| format!("failed to resolve device {source}"))
+}
+
+/// Find the `/sys/fs/btrfs/<UUID>` directory of the filesystem on `device`
+async fn btrfs_fs_dir(device: &Path) -> Result<PathBuf> {
+    let name = device.file_name().error("invalid device name")?;
+    let mut filesystems = tokio::fs::read_dir("/sys/fs/btrfs")
+        .await
+        .error("failed to read /sys/fs/btrfs")?;
+    while let Some(fs) = filesystems
+        .next_entry()
+        .await
+        .error("failed to read /sys/fs/btrfs")?
+    {
+        if tokio::fs::metadata(fs.path().join("devices").join(name))
+            .await
+            .is_ok()
+        {
+            return Ok(fs.path());
+        }
+    }
+    Err(Error::new(format!(
+        "failed to find btrfs filesystem on {}",
+        device.display()
+    )))
+}
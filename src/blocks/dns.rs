@@ -0,0 +1,152 @@
+//! DNS resolver check
+//!
+//! Resolves a test name with the system resolver and, optionally, against specific DNS servers,
+//! and shows the query time of the slowest of them. The block is in the critical state if any of
+//! the lookups fails, which makes DNS breakage, e.g. behind a captive portal or a VPN, visible at
+//! a glance.
+//!
+//! Querying specific servers requires `dig`.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `name` | The name to resolve | `"example.com"`
+//! `servers` | DNS servers to query in addition to the system resolver | `[]`
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon {$time\|failed} "`
+//! `interval` | Update interval in seconds | `30`
+//! `timeout` | How long to wait for each lookup, in seconds | `5`
+//! `slow_query` | Query time in milliseconds, from which the state is set to warning | `500`
+//!
+//! Placeholder | Value                                                                             | Type   | Unit
+//! ------------|-----------------------------------------------------------------------------------|--------|--------
+//! `icon`      | A static icon                                                                     | Icon   | -
+//! `server`    | The slowest resolver or the first failing one, `"system"` for the system resolver | Text   | -
+//! `time`      | The query time of the slowest resolver. Missing if a lookup failed.               | Number | Seconds
+//! `failed`    | The number of failed lookups                                                      | Number | -
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "dns"
+//! name = "example.org"
+//! servers = ["9.9.9.9"]
+//! format = " $icon $server $time.eng(w:3) "
+//! ```
+//!
+//! # Icons Used
+//! - `ping`
+
+use super::prelude::*;
+use std::process::Stdio;
+use std::time::Instant;
+use tokio::process::Command;
+
+make_log_macro!(debug, "dns");
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    #[default("example.com".into())]
+    name: String,
+    servers: Vec<String>,
+    format: FormatConfig,
+    #[default(30.into())]
+    interval: Seconds,
+    #[default(5.into())]
+    timeout: Seconds,
+    #[default(500)]
+    slow_query: u64,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    let mut widget =
+        Widget::new().with_format(config.format.with_default(" $icon {$time|failed} ")?);
+    let mut timer = config.interval.timer();
+
+    let resolvers: Vec<Option<&str>> = std::iter::once(None)
+        .chain(config.servers.iter().map(|s| Some(s.as_str())))
+        .collect();
+
+    loop {
+        let results = futures::future::join_all(
+            resolvers
+                .iter()
+                .map(|server| resolve(&config.name, *server, config.timeout)),
+        )
+        .await;
+
+        let mut failed = 0;
+        let mut worst: Option<(Option<&str>, Option<Duration>)> = None;
+        for (server, result) in resolvers.iter().zip(results) {
+            match result {
+                Ok(time) => {
+                    if worst.map_or(true, |(_, t)| t.map_or(false, |t| time > t)) {
+                        worst = Some((*server, Some(time)));
+                    }
+                }
+                Err(e) => {
+                    debug!("{}: {e}", server.unwrap_or("system"));
+                    if failed == 0 {
+                        worst = Some((*server, None));
+                    }
+                    failed += 1;
+                }
+            }
+        }
+        let (server, time) = worst.unwrap();
+
+        widget.state = match time {
+            None => State::Critical,
+            Some(time) if time >= Duration::from_millis(config.slow_query) => State::Warning,
+            Some(_) => State::Idle,
+        };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("ping")?),
+            "server" => Value::text(server.unwrap_or("system").into()),
+            "failed" => Value::number(failed),
+            [if let Some(time) = time] "time" => Value::seconds(time.as_secs_f64()),
+        });
+        api.set_widget(&widget).await?;
+
+        select! {
+            _ = timer.tick() => (),
+            _ = api.wait_for_update_request() => (),
+        }
+    }
+}
+
+/// Resolve `name` with the system resolver, or with `server` if given, and return the query time
+async fn resolve(name: &str, server: Option<&str>, timeout: Seconds) -> Result<Duration> {
+    let start = Instant::now();
+    match server {
+        None => {
+            let mut addrs = tokio::time::timeout(timeout.0, tokio::net::lookup_host((name, 0)))
+                .await
+                .error("Lookup timed out")?
+                .or_error(|| format!("Failed to resolve {name}"))?;
+            addrs
+                .next()
+                .or_error(|| format!("No addresses found for {name}"))?;
+        }
+        Some(server) => {
+            let output = Command::new("dig")
+                .args([
+                    "+short",
+                    "+tries=1",
+                    &format!("+time={}", timeout.0.as_secs().max(1)),
+                    &format!("@{server}"),
+                    name,
+                ])
+                .stdin(Stdio::null())
+                .output()
+                .await
+                .error("Failed to run dig")?;
+            if !output.status.success() || output.stdout.iter().all(u8::is_ascii_whitespace) {
+                return Err(Error::new(format!("Failed to resolve {name}")));
+            }
+        }
+    }
+    Ok(start.elapsed())
+}
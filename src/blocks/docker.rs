@@ -1,12 +1,28 @@
 //! Local docker daemon status
 //!
+//! The block can also show the status of specific containers, listed by name in `containers`.
+//! One container is shown at a time; right click switches to the next one. The container can be
+//! started, stopped and restarted by clicking the block.
+//!
+//! Podman works as well through its Docker compatible socket, which has to be enabled with
+//! `systemctl --user enable --now podman.socket` (or `podman.socket` as root). If neither `host`
+//! nor `socket_path` is set, the block uses the `DOCKER_HOST` environment variable, and falls back
+//! to the first existing socket of `/var/run/docker.sock`, `$XDG_RUNTIME_DIR/podman/podman.sock`
+//! and `/run/podman/podman.sock`.
+//!
+//! A remote daemon can be monitored by setting `host` to `tcp://<host>:<port>` or
+//! `ssh://[user@]<host>[:port]`. The latter runs `docker system dial-stdio` on the remote host for
+//! each request, so consider enabling `ControlMaster` for it in your SSH config.
+//!
 //! # Configuration
 //!
 //! Key | Values | Default
 //! ----|--------|--------
 //! `interval` | Update interval, in seconds. | `5`
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $running.eng(w:1) "`
-//! `socket_path` | The path to the docker socket. Supports path expansions e.g. `~`. | `"/var/run/docker.sock"`
+//! `socket_path` | The path to the docker socket. Supports path expansions e.g. `~`. | Detected automatically
+//! `host` | The daemon to connect to, in the format of `DOCKER_HOST`: `unix://<path>`, `tcp://<host>:<port>` or `ssh://[user@]<host>[:port]`. Overrides `socket_path`. | `None`
+//! `containers` | Names of containers to show. | `[]`
 //!
 //! Key       | Value                          | Type   | Unit
 //! ----------|--------------------------------|--------|-----
@@ -16,8 +32,24 @@
 //! `stopped` | Containers stopped on the host | Number | -
 //! `paused`  | Containers paused on the host  | Number | -
 //! `images`  | Total images on the host       | Number | -
+//! `name`    | The name of the selected container. Present only if `containers` is set. | Text | -
+//! `state`   | The state of the selected container, e.g. "running" or "exited" | Text | -
+//! `state_icon` | An icon for the state of the selected container | Icon | -
+//! `cpu`     | CPU usage of the selected container. Present only if it is running. | Number | %
+//! `memory`  | Memory usage of the selected container. Present only if it is running. | Number | Bytes
+//! `memory_percents` | Memory usage of the selected container relative to its limit | Number | %
 //!
-//! # Example
+//! Action           | Default button
+//! -----------------|---------------
+//! `toggle`         | Left
+//! `restart`        | Middle
+//! `next_container` | Right
+//! `start`          | -
+//! `stop`           | -
+//!
+//! `toggle` stops a running container, unpauses a paused one and starts it otherwise.
+//!
+//! # Examples
 //!
 //! ```toml
 //! [[block]]
@@ -26,13 +58,45 @@
 //! format = " $icon $running/$total "
 //! ```
 //!
+//! Monitor the containers of a home server:
+//!
+//! ```toml
+//! [[block]]
+//! block = "docker"
+//! containers = ["jellyfin", "nextcloud"]
+//! format = " $icon $state_icon $name {$cpu $memory.eng(prefix:Mi) |}"
+//! ```
+//!
+//! Rootless Podman:
+//!
+//! ```toml
+//! [[block]]
+//! block = "docker"
+//! socket_path = "$XDG_RUNTIME_DIR/podman/podman.sock"
+//! ```
+//!
+//! A remote host:
+//!
+//! ```toml
+//! [[block]]
+//! block = "docker"
+//! host = "ssh://admin@homelab"
+//! interval = 30
+//! ```
+//!
 //! # Icons Used
 //!
 //! - `docker`
+//! - `docker_running`
+//! - `docker_paused`
+//! - `docker_stopped`
 
 use super::prelude::*;
-use std::path::Path;
-use tokio::net::UnixStream;
+use std::path::PathBuf;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpStream, UnixStream};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
 
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(default)]
@@ -40,31 +104,101 @@ pub struct Config {
     #[default(5.into())]
     interval: Seconds,
     format: FormatConfig,
-    #[default("/var/run/docker.sock".into())]
-    socket_path: ShellString,
+    socket_path: Option<ShellString>,
+    host: Option<String>,
+    containers: Vec<String>,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
-    let mut widget =
-        Widget::new().with_format(config.format.with_default(" $icon $running.eng(w:1) ")?);
-    let socket_path = config.socket_path.expand()?;
+    api.set_default_actions(&[
+        (MouseButton::Left, None, "toggle"),
+        (MouseButton::Middle, None, "restart"),
+        (MouseButton::Right, None, "next_container"),
+    ])
+    .await?;
+
+    let format = config.format.with_default(" $icon $running.eng(w:1) ")?;
+    let show_stats = format.contains_key("cpu")
+        || format.contains_key("memory")
+        || format.contains_key("memory_percents");
+    let mut widget = Widget::new().with_format(format);
+    let host = match (config.host, config.socket_path) {
+        (Some(host), _) => Host::parse(&host)?,
+        (None, Some(socket_path)) => Host::Unix(socket_path.expand()?.into_owned().into()),
+        (None, None) => Host::detect()?,
+    };
+    let mut cur_container = 0;
 
     loop {
-        let status = api.recoverable(|| Status::new(&*socket_path)).await?;
+        let status = api.recoverable(|| Status::new(&host)).await?;
 
-        widget.set_values(map! {
+        let mut values = map! {
             "icon" => Value::icon(api.get_icon("docker")?),
             "total" =>   Value::number(status.total),
             "running" => Value::number(status.running),
             "paused" =>  Value::number(status.paused),
             "stopped" => Value::number(status.stopped),
             "images" =>  Value::number(status.images),
-        });
+        };
+        widget.state = State::Idle;
+
+        let container = match config.containers.get(cur_container) {
+            Some(name) => Some(
+                api.recoverable(|| Container::new(&host, name, show_stats))
+                    .await?,
+            ),
+            None => None,
+        };
+        if let Some(container) = &container {
+            let (state, icon) = match container.state.as_str() {
+                "running" => (State::Idle, "docker_running"),
+                "paused" | "restarting" => (State::Warning, "docker_paused"),
+                _ => (State::Critical, "docker_stopped"),
+            };
+            widget.state = state;
+            values.insert("name".into(), Value::text(container.name.clone()));
+            values.insert("state".into(), Value::text(container.state.clone()));
+            values.insert("state_icon".into(), Value::icon(api.get_icon(icon)?));
+            if let Some(stats) = &container.stats {
+                values.insert("cpu".into(), Value::percents(stats.cpu_percents()));
+                values.insert("memory".into(), Value::bytes(stats.memory_used()));
+                if let Some(percents) = stats.memory_percents() {
+                    values.insert("memory_percents".into(), Value::percents(percents));
+                }
+            }
+        }
+
+        widget.set_values(values);
         api.set_widget(&widget).await?;
 
-        select! {
-            _ = sleep(config.interval.0) => (),
-            _ = api.wait_for_update_request() => (),
+        loop {
+            select! {
+                _ = sleep(config.interval.0) => break,
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "next_container" => {
+                        if !config.containers.is_empty() {
+                            cur_container = (cur_container + 1) % config.containers.len();
+                            break;
+                        }
+                    }
+                    Action(a) => {
+                        if let Some(container) = &container {
+                            let action = match &*a {
+                                "toggle" => match container.state.as_str() {
+                                    "running" => "stop",
+                                    "paused" => "unpause",
+                                    _ => "start",
+                                },
+                                "start" | "stop" | "restart" => &*a,
+                                _ => continue,
+                            };
+                            container.action(&host, action).await?;
+                            break;
+                        }
+                    }
+                }
+            }
         }
     }
 }
@@ -84,27 +218,274 @@ struct Status {
 }
 
 impl Status {
-    async fn new(socket_path: impl AsRef<Path>) -> Result<Self> {
-        let socket = UnixStream::connect(socket_path)
-            .await
-            .error("Failed to connect to socket")?;
-        let (mut request_sender, connection) = hyper::client::conn::handshake(socket)
-            .await
-            .error("Failed to create request sender")?;
-        tokio::spawn(connection);
-        let request = hyper::Request::builder()
-            .header("Host", "localhost")
-            .uri("http://api/info")
-            .method("GET")
-            .body(hyper::Body::empty())
-            .error("Failed to create request")?;
-        let response = request_sender
-            .send_request(request)
-            .await
-            .error("Failed to get response")?;
-        let bytes = hyper::body::to_bytes(response.into_body())
-            .await
-            .error("Failed to get response bytes")?;
+    async fn new(host: &Host) -> Result<Self> {
+        let bytes = request(host, "GET", "/info").await?;
         serde_json::from_slice::<Self>(&bytes).error("Failed to deserialize JSON")
     }
 }
+
+#[derive(Debug)]
+struct Container {
+    name: String,
+    state: String,
+    /// Present only if requested and the container is running
+    stats: Option<ContainerStats>,
+}
+
+impl Container {
+    async fn new(host: &Host, name: &str, with_stats: bool) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Inspect {
+            #[serde(rename = "State")]
+            state: InspectState,
+        }
+        #[derive(Deserialize)]
+        struct InspectState {
+            #[serde(rename = "Status")]
+            status: String,
+        }
+
+        let bytes = request(host, "GET", &format!("/containers/{name}/json")).await?;
+        let inspect: Inspect =
+            serde_json::from_slice(&bytes).error("Failed to deserialize JSON")?;
+        let stats = if with_stats && inspect.state.status == "running" {
+            // Without `stream=false` the daemon would keep sending stats
+            let uri = format!("/containers/{name}/stats?stream=false");
+            let bytes = request(host, "GET", &uri).await?;
+            Some(serde_json::from_slice(&bytes).error("Failed to deserialize JSON")?)
+        } else {
+            None
+        };
+        Ok(Self {
+            name: name.into(),
+            state: inspect.state.status,
+            stats,
+        })
+    }
+
+    /// Start, stop, restart or unpause the container
+    async fn action(&self, host: &Host, action: &str) -> Result<()> {
+        let uri = format!("/containers/{}/{action}", self.name);
+        request(host, "POST", &uri).await.map(|_| ())
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct ContainerStats {
+    cpu_stats: CpuStats,
+    precpu_stats: CpuStats,
+    memory_stats: MemoryStats,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct CpuStats {
+    cpu_usage: CpuUsage,
+    system_cpu_usage: u64,
+    online_cpus: u64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct CpuUsage {
+    total_usage: u64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct MemoryStats {
+    usage: u64,
+    limit: u64,
+    stats: HashMap<String, u64>,
+}
+
+impl ContainerStats {
+    /// CPU usage since the previous sample, where 100% is one CPU (like `docker stats`)
+    fn cpu_percents(&self) -> f64 {
+        let cpu_delta = self
+            .cpu_stats
+            .cpu_usage
+            .total_usage
+            .saturating_sub(self.precpu_stats.cpu_usage.total_usage);
+        let system_delta = self
+            .cpu_stats
+            .system_cpu_usage
+            .saturating_sub(self.precpu_stats.system_cpu_usage);
+        if system_delta == 0 {
+            return 0.0;
+        }
+        cpu_delta as f64 / system_delta as f64 * self.cpu_stats.online_cpus.max(1) as f64 * 100.0
+    }
+
+    /// Memory usage without the page cache (like `docker stats`)
+    fn memory_used(&self) -> u64 {
+        let stats = &self.memory_stats.stats;
+        // cgroup v2 reports `inactive_file`, v1 `total_inactive_file`
+        let cache = stats
+            .get("inactive_file")
+            .or_else(|| stats.get("total_inactive_file"))
+            .copied()
+            .unwrap_or(0);
+        self.memory_stats.usage.saturating_sub(cache)
+    }
+
+    fn memory_percents(&self) -> Option<f64> {
+        (self.memory_stats.limit > 0)
+            .then(|| self.memory_used() as f64 / self.memory_stats.limit as f64 * 100.0)
+    }
+}
+
+/// Where the Docker Engine API is served
+#[derive(Debug)]
+enum Host {
+    Unix(PathBuf),
+    /// `host:port`
+    Tcp(String),
+    /// An SSH destination, e.g. `ssh://user@host`
+    Ssh(String),
+}
+
+impl Host {
+    fn parse(host: &str) -> Result<Self> {
+        if let Some(path) = host.strip_prefix("unix://") {
+            Ok(Self::Unix(path.into()))
+        } else if let Some(addr) = host
+            .strip_prefix("tcp://")
+            .or_else(|| host.strip_prefix("http://"))
+        {
+            Ok(Self::Tcp(addr.trim_end_matches('/').into()))
+        } else if host.starts_with("ssh://") {
+            Ok(Self::Ssh(host.into()))
+        } else {
+            Err(Error::new(format!("Unsupported docker host '{host}'")))
+        }
+    }
+
+    /// Use `DOCKER_HOST` or the first Docker or Podman socket which exists
+    fn detect() -> Result<Self> {
+        if let Some(host) = std::env::var("DOCKER_HOST").ok().filter(|h| !h.is_empty()) {
+            return Self::parse(&host);
+        }
+        let mut candidates = vec![PathBuf::from("/var/run/docker.sock")];
+        if let Some(runtime_dir) = std::env::var_os("XDG_RUNTIME_DIR") {
+            candidates.push(PathBuf::from(runtime_dir).join("podman/podman.sock"));
+        }
+        candidates.push("/run/podman/podman.sock".into());
+        candidates
+            .into_iter()
+            .find(|path| path.exists())
+            .map(Self::Unix)
+            .error("No docker or podman socket found")
+    }
+}
+
+/// Send a request to the Docker Engine API
+async fn request(host: &Host, method: &str, path: &str) -> Result<Vec<u8>> {
+    match host {
+        Host::Unix(socket_path) => {
+            let socket = UnixStream::connect(socket_path)
+                .await
+                .error("Failed to connect to socket")?;
+            send_request(socket, method, path).await
+        }
+        Host::Tcp(addr) => {
+            let socket = TcpStream::connect(addr)
+                .await
+                .error("Failed to connect to docker host")?;
+            send_request(socket, method, path).await
+        }
+        Host::Ssh(destination) => {
+            send_request(SshStream::connect(destination)?, method, path).await
+        }
+    }
+}
+
+async fn send_request<S>(stream: S, method: &str, path: &str) -> Result<Vec<u8>>
+where
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    #[derive(Deserialize)]
+    struct ErrorResponse {
+        message: String,
+    }
+
+    let (mut request_sender, connection) = hyper::client::conn::handshake(stream)
+        .await
+        .error("Failed to create request sender")?;
+    tokio::spawn(connection);
+    let request = hyper::Request::builder()
+        .header("Host", "localhost")
+        .uri(format!("http://api{path}"))
+        .method(method)
+        .body(hyper::Body::empty())
+        .error("Failed to create request")?;
+    let response = request_sender
+        .send_request(request)
+        .await
+        .error("Failed to get response")?;
+    let status = response.status();
+    let bytes = hyper::body::to_bytes(response.into_body())
+        .await
+        .error("Failed to get response bytes")?;
+    if status.is_client_error() || status.is_server_error() {
+        let message = serde_json::from_slice::<ErrorResponse>(&bytes)
+            .map_or_else(|_| status.to_string(), |e| e.message);
+        return Err(Error::new(format!("Docker API error: {message}")));
+    }
+    Ok(bytes.to_vec())
+}
+
+/// The standard IO of `docker system dial-stdio` running on a remote host
+struct SshStream {
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+}
+
+impl SshStream {
+    fn connect(destination: &str) -> Result<Self> {
+        let mut child = Command::new("ssh")
+            .args(["-T", "-o", "BatchMode=yes", "--", destination])
+            .args(["docker", "system", "dial-stdio"])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .error("Failed to run ssh")?;
+        Ok(Self {
+            stdin: child.stdin.take().error("Failed to open ssh stdin")?,
+            stdout: child.stdout.take().error("Failed to open ssh stdout")?,
+            _child: child,
+        })
+    }
+}
+
+impl AsyncRead for SshStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stdout).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for SshStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.stdin).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stdin).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stdin).poll_shutdown(cx)
+    }
+}
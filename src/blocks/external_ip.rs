@@ -7,6 +7,8 @@
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $ip $country_flag "`
 //! `interval` | Interval in seconds for automatic updates | `300`
 //! `with_network_manager` | If 'true', listen for NetworkManager events and update the IP immediately if there was a change | `true`
+//! `provider` | The lookup service to use, `"ipapi"` (<https://ipapi.co>) or `"ipinfo"` (<https://ipinfo.io>) | `"ipapi"`
+//! `expected_country` | A country code (2 letter, ISO 3166-1 alpha-2). If set, the state is set to warning while the IP address is located in another country, e.g. because a VPN is down. | `None`
 //!
 //!  Key | Value | Type | Unit
 //! -----|-------|------|------
@@ -38,6 +40,11 @@
 //! `org` | Organization | Text | -
 //! `asn` | Autonomous system (AS) | Text | -
 //! `country_flag` | Flag of the country | Text (glyph) | -
+//! `ipv4` | The external IPv4 address. Only queried if used in `format`. | Text | -
+//! `ipv6` | The external IPv6 address, if any. Only queried if used in `format`. | Text | -
+//!
+//! The `ipinfo` provider only fills `ip`, `version`, `city`, `region`, `country`, `country_code`,
+//! `country_flag`, `postal`, `latitude`, `longitude`, `timezone`, `org`, `asn`, `ipv4` and `ipv6`.
 //!
 //! # Example
 //!
@@ -47,9 +54,19 @@
 //! format = " $ip $country_code "
 //! ```
 //!
+//! Warn when the traffic does not leave through a VPN in Switzerland:
+//!
+//! ```toml
+//! [[block]]
+//! block = "external_ip"
+//! format = " $ip $country_flag "
+//! expected_country = "CH"
+//! ```
+//!
 //! # Notes
-//! All the information comes from <https://ipapi.co/json/>
+//! By default, all the information comes from <https://ipapi.co/json/>
 //! Check their documentation here: <https://ipapi.co/api/#complete-location5>
+//! `ipv4` and `ipv6` come from <https://www.ipify.org>.
 //!
 //! The IP is queried, 1) When i3status-rs starts, 2) When a signal is received
 //! on D-Bus about a network configuration change, 3) Every 5 minutes. This
@@ -64,7 +81,10 @@ use zbus::MatchRule;
 use super::prelude::*;
 use crate::util::{country_flag_from_iso_code, new_system_dbus_connection};
 
-const API_ENDPOINT: &str = "https://ipapi.co/json/";
+const IPAPI_ENDPOINT: &str = "https://ipapi.co/json/";
+const IPINFO_ENDPOINT: &str = "https://ipinfo.io/json";
+const IPV4_ENDPOINT: &str = "https://api.ipify.org";
+const IPV6_ENDPOINT: &str = "https://api6.ipify.org";
 
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(default)]
@@ -74,10 +94,23 @@ pub struct Config {
     interval: Seconds,
     #[default(true)]
     with_network_manager: bool,
+    provider: Provider,
+    expected_country: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, SmartDefault)]
+#[serde(rename_all = "lowercase")]
+enum Provider {
+    #[default]
+    Ipapi,
+    Ipinfo,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
-    let mut widget = Widget::new().with_format(config.format.with_default(" $ip $country_flag ")?);
+    let format = config.format.with_default(" $ip $country_flag ")?;
+    let need_ipv4 = format.contains_key("ipv4");
+    let need_ipv6 = format.contains_key("ipv6");
+    let mut widget = Widget::new().with_format(format);
 
     type UpdatesStream = Pin<Box<dyn Stream<Item = ()>>>;
     let mut stream: UpdatesStream = if config.with_network_manager {
@@ -128,7 +161,23 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     };
 
     loop {
-        let info = api.recoverable(IPAddressInfo::new).await?;
+        let info = api
+            .recoverable(|| IPAddressInfo::new(config.provider))
+            .await?;
+        widget.state = match &config.expected_country {
+            Some(expected) if !expected.eq_ignore_ascii_case(&info.country_code) => State::Warning,
+            _ => State::Idle,
+        };
+        let ipv4 = if need_ipv4 {
+            plain_ip(IPV4_ENDPOINT).await
+        } else {
+            None
+        };
+        let ipv6 = if need_ipv6 {
+            plain_ip(IPV6_ENDPOINT).await
+        } else {
+            None
+        };
         let mut values = map! {
             "ip" => Value::text(info.ip),
             "version" => Value::text(info.version),
@@ -161,6 +210,12 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         if info.in_eu {
             values.insert("in_eu".into(), Value::flag());
         }
+        if let Some(ipv4) = ipv4 {
+            values.insert("ipv4".into(), Value::text(ipv4));
+        }
+        if let Some(ipv6) = ipv6 {
+            values.insert("ipv6".into(), Value::text(ipv6));
+        }
         widget.set_values(values);
         api.set_widget(&widget).await?;
 
@@ -210,10 +265,80 @@ struct IPAddressInfo {
     org: String,
 }
 
+/// The subset of the fields which <https://ipinfo.io> provides without a token
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct IpinfoResponse {
+    ip: String,
+    city: String,
+    region: String,
+    country: String,
+    /// `"<latitude>,<longitude>"`
+    loc: String,
+    org: String,
+    postal: Option<String>,
+    timezone: String,
+}
+
+impl From<IpinfoResponse> for IPAddressInfo {
+    fn from(r: IpinfoResponse) -> Self {
+        let (latitude, longitude) = r
+            .loc
+            .split_once(',')
+            .and_then(|(lat, lon)| Some((lat.parse().ok()?, lon.parse().ok()?)))
+            .unwrap_or_default();
+        // e.g. `AS13335 Cloudflare, Inc.`
+        let (asn, org) = match r.org.split_once(' ') {
+            Some((asn, org)) if asn.starts_with("AS") => (asn.into(), org.into()),
+            _ => (String::new(), r.org),
+        };
+        Self {
+            version: if r.ip.contains(':') { "IPv6" } else { "IPv4" }.into(),
+            ip: r.ip,
+            city: r.city,
+            region: r.region,
+            country: r.country.clone(),
+            country_code: r.country,
+            postal: r.postal,
+            latitude,
+            longitude,
+            timezone: r.timezone,
+            asn,
+            org,
+            ..Default::default()
+        }
+    }
+}
+
+/// Fetch an address from a service which responds with nothing but the address
+async fn plain_ip(url: &str) -> Option<String> {
+    let ip = REQWEST_CLIENT
+        .get(url)
+        .send()
+        .await
+        .ok()?
+        .text()
+        .await
+        .ok()?;
+    Some(ip.trim().into())
+}
+
 impl IPAddressInfo {
-    async fn new() -> Result<Self> {
+    async fn new(provider: Provider) -> Result<Self> {
+        if let Provider::Ipinfo = provider {
+            return REQWEST_CLIENT
+                .get(IPINFO_ENDPOINT)
+                .send()
+                .await
+                .error("Failed to request current location")?
+                .json::<IpinfoResponse>()
+                .await
+                .error("Failed to parse JSON")
+                .map(Into::into);
+        }
+
         let info: Self = REQWEST_CLIENT
-            .get(API_ENDPOINT)
+            .get(IPAPI_ENDPOINT)
             .send()
             .await
             .error("Failed to request current location")?
@@ -0,0 +1,204 @@
+//! Failed systemd units
+//!
+//! Shows the number of failed system and user units. The block is in the critical state while any
+//! unit has failed, and clicking it cycles through the names of the failed units.
+//!
+//! If `restart_unit` is set, the `restart` action restarts that unit. Restarting a system unit
+//! usually needs a polkit authentication agent to be running, which will ask for a password.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $count{ $unit\|} "`
+//! `interval` | Update interval in seconds | `30`
+//! `system` | Whether to count failed system units | `true`
+//! `user` | Whether to count failed units of the user's service manager | `true`
+//! `restart_unit` | The name of a unit to restart with the `restart` action, e.g. `"backup.service"` | `None`
+//! `restart_scope` | Whether `restart_unit` is a `"system"` or a `"user"` unit | `"system"`
+//!
+//! Placeholder    | Value                                          | Type   | Unit
+//! ---------------|------------------------------------------------|--------|-----
+//! `icon`         | A static icon                                  | Icon   | -
+//! `count`        | The number of failed units                     | Number | -
+//! `system_count` | The number of failed system units              | Number | -
+//! `user_count`   | The number of failed user units                | Number | -
+//! `unit`         | The name of one of the failed units, if any    | Text   | -
+//!
+//! Action      | Description                           | Default button
+//! ------------|---------------------------------------|---------------
+//! `next_unit` | Show the name of the next failed unit | Left
+//! `restart`   | Restart `restart_unit`                | Right
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "failed_units"
+//! format = " $icon {$count.eng(w:1) failed: $unit|} "
+//! restart_unit = "nightly-backup.service"
+//! ```
+//!
+//! # Icons Used
+//! - `cogs`
+
+use super::prelude::*;
+use zbus::dbus_proxy;
+use zbus::zvariant::OwnedObjectPath;
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    format: FormatConfig,
+    #[default(30.into())]
+    interval: Seconds,
+    #[default(true)]
+    system: bool,
+    #[default(true)]
+    user: bool,
+    restart_unit: Option<String>,
+    restart_scope: Scope,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, SmartDefault)]
+#[serde(rename_all = "lowercase")]
+enum Scope {
+    #[default]
+    System,
+    User,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[
+        (MouseButton::Left, None, "next_unit"),
+        (MouseButton::Right, None, "restart"),
+    ])
+    .await?;
+
+    let mut widget =
+        Widget::new().with_format(config.format.with_default(" $icon $count{ $unit|} ")?);
+
+    let restart_scope = config.restart_unit.as_ref().map(|_| config.restart_scope);
+    let system = if config.system || restart_scope == Some(Scope::System) {
+        let conn = new_system_dbus_connection().await?;
+        Some(
+            ManagerProxy::new(&conn)
+                .await
+                .error("Failed to create systemd ManagerProxy")?,
+        )
+    } else {
+        None
+    };
+    let user = if config.user || restart_scope == Some(Scope::User) {
+        let conn = new_dbus_connection().await?;
+        Some(
+            ManagerProxy::new(&conn)
+                .await
+                .error("Failed to create systemd ManagerProxy")?,
+        )
+    } else {
+        None
+    };
+
+    let mut timer = config.interval.timer();
+    let mut shown = 0;
+
+    loop {
+        let system_units = match &system {
+            Some(manager) if config.system => failed_units(manager).await?,
+            _ => Vec::new(),
+        };
+        let user_units = match &user {
+            Some(manager) if config.user => failed_units(manager).await?,
+            _ => Vec::new(),
+        };
+        let units: Vec<&String> = system_units.iter().chain(&user_units).collect();
+        if shown >= units.len() {
+            shown = 0;
+        }
+
+        widget.state = if units.is_empty() {
+            State::Idle
+        } else {
+            State::Critical
+        };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("cogs")?),
+            "count" => Value::number(units.len()),
+            "system_count" => Value::number(system_units.len()),
+            "user_count" => Value::number(user_units.len()),
+            [if let Some(unit) = units.get(shown)] "unit" => Value::text((*unit).clone()),
+        });
+        api.set_widget(&widget).await?;
+
+        loop {
+            select! {
+                _ = timer.tick() => break,
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "next_unit" => {
+                        if !units.is_empty() {
+                            shown = (shown + 1) % units.len();
+                            break;
+                        }
+                    }
+                    Action(a) if a == "restart" => {
+                        let manager = match config.restart_scope {
+                            Scope::System => &system,
+                            Scope::User => &user,
+                        };
+                        if let (Some(unit), Some(manager)) = (&config.restart_unit, manager) {
+                            if let Err(e) = manager
+                                .restart_unit(unit, "replace")
+                                .await
+                                .or_error(|| format!("Failed to restart {unit}"))
+                            {
+                                api.set_error(e).await?;
+                                select! {
+                                    _ = sleep(api.error_interval) => (),
+                                    _ = api.wait_for_update_request() => (),
+                                }
+                            }
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+/// The names of the failed units
+async fn failed_units(manager: &ManagerProxy<'_>) -> Result<Vec<String>> {
+    let units = manager
+        .list_units_filtered(&["failed"])
+        .await
+        .error("Failed to list failed units")?;
+    Ok(units.into_iter().map(|unit| unit.0).collect())
+}
+
+type UnitInfo = (
+    String,
+    String,
+    String,
+    String,
+    String,
+    String,
+    OwnedObjectPath,
+    u32,
+    String,
+    OwnedObjectPath,
+);
+
+#[dbus_proxy(
+    interface = "org.freedesktop.systemd1.Manager",
+    default_service = "org.freedesktop.systemd1",
+    default_path = "/org/freedesktop/systemd1"
+)]
+trait Manager {
+    fn list_units_filtered(&self, states: &[&str]) -> zbus::Result<Vec<UnitInfo>>;
+
+    #[dbus_proxy(allow_interactive_auth)]
+    fn restart_unit(&self, name: &str, mode: &str) -> zbus::Result<OwnedObjectPath>;
+}
@@ -9,6 +9,10 @@
 //! Key | Values | Default
 //! ----|--------|--------
 //! `format` | A string to customise the output of this block. See below for available placeholders. | <code>" $title.str(max_w:21) &vert;"</code>
+//! `output` | Only show windows on this output, e.g. `"eDP-1"`. Useful with a bar per output (sway/i3 only). | `None`
+//! `max_width` | Truncate the title to this many characters | `None`
+//! `truncate` | Where to cut the title when it's longer than `max_width`: `"start"`, `"middle"` or `"end"`. An ellipsis (`…`) is inserted in its place. | `"end"`
+//! `title_rewrites` | A list of `{ pattern = "...", replacement = "..." }` tables. Each regex `pattern` is replaced by `replacement` in the title, in order. `replacement` may refer to capture groups, e.g. `$1`. | `[]`
 //! `driver` | Which driver to use. Available values: `sway_ipc` - for `i3` and `sway`, `wlr_toplevel_management` - for Wayland compositors that implement [wlr-foreign-toplevel-management-unstable-v1](https://gitlab.freedesktop.org/wlroots/wlr-protocols/-/blob/master/unstable/wlr-foreign-toplevel-management-unstable-v1.xml), `auto` - try to automatically guess which driver to use. | `"auto"`
 //!
 //! Placeholder     | Value                                                                 | Type | Unit
@@ -16,8 +20,19 @@
 //! `title`         | Window's title (may be absent)                                        | Text | -
 //! `marks`         | Window's marks (present only with sway/i3)                            | Text | -
 //! `visible_marks` | Window's marks that do not start with `_` (present only with sway/i3) | Text | -
+//! `app_id`        | Window's Wayland app_id or X11 class (may be absent)                  | Text | -
+//! `workspace`     | Name of the focused workspace (present only with sway/i3)             | Text | -
+//! `urgent`        | Present if the window is urgent (present only with sway/i3)           | Flag | -
+//! `app_icon`      | Icon of the window's application (may be absent, see below)           | Icon | -
 //!
-//! # Example
+//! To find `app_icon`, the `.desktop` file of the application is looked up by its app_id or
+//! class, and the name in its `Icon=` key is turned into an icon name by lowercasing it,
+//! replacing everything except letters and digits with `_`, and prepending `app_`. For example,
+//! Firefox uses the `app_firefox` icon and GNOME Terminal (`Icon=org.gnome.Terminal`) uses
+//! `app_org_gnome_terminal`. If no desktop file is found, the app_id itself is used. The icon
+//! sets only cover a few common applications; add more with `[icons.overrides]`.
+//!
+//! # Examples
 //!
 //! ```toml
 //! [[block]]
@@ -33,7 +48,37 @@
 //! [[block]]
 //! block = "focused_window"
 //! format = " $title.str(0,21) | Missing "
+//! ```
+//!
+//! Show the workspace and the application of the window on the laptop screen, and shorten
+//! browser titles:
+//!
+//! ```toml
+//! [[block]]
+//! block = "focused_window"
+//! format = " $workspace: $app_id{ $title|}{ !$urgent|} "
+//! output = "eDP-1"
+//! max_width = 40
+//! truncate = "middle"
+//! [[block.title_rewrites]]
+//! pattern = " [-—] Mozilla Firefox$"
+//! replacement = ""
+//! ```
+//!
+//! Show only the application's icon:
+//!
+//! ```toml
+//! [[block]]
+//! block = "focused_window"
+//! format = " $app_icon |"
+//! [block.icons_overrides]
+//! app_org_wezfurlong_wezterm = "\uf120"
+//! ```
+//!
+//! # Icons Used
+//! - `app_<name>` for `app_icon`, e.g. `app_firefox`
 
+mod desktop_entry;
 mod sway_ipc;
 mod wlr_toplevel_management;
 
@@ -41,12 +86,33 @@ use sway_ipc::SwayIpc;
 use wlr_toplevel_management::WlrToplevelManagement;
 
 use super::prelude::*;
+use regex::Regex;
 
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(default)]
 pub struct Config {
     format: FormatConfig,
     driver: Driver,
+    output: Option<String>,
+    max_width: Option<usize>,
+    truncate: Truncate,
+    title_rewrites: Vec<TitleRewrite>,
+}
+
+#[derive(Deserialize, Debug, SmartDefault, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Truncate {
+    Start,
+    Middle,
+    #[default]
+    End,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct TitleRewrite {
+    pattern: String,
+    replacement: String,
 }
 
 #[derive(Deserialize, Debug, SmartDefault)]
@@ -59,15 +125,26 @@ enum Driver {
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
-    let mut widget =
-        Widget::new().with_format(config.format.with_default(" $title.str(max_w:21) |")?);
+    let format = config.format.with_default(" $title.str(max_w:21) |")?;
+    let show_app_icon = format.contains_key("app_icon");
+    let mut widget = Widget::new().with_format(format);
+
+    let rewrites = config
+        .title_rewrites
+        .iter()
+        .map(|r| Ok((Regex::new(&r.pattern)?, r.replacement.as_str())))
+        .collect::<Result<Vec<_>, regex::Error>>()
+        .error("Invalid regex in title_rewrites")?;
+
+    // Icon names of applications, by app_id
+    let mut icon_names: HashMap<String, Option<String>> = HashMap::new();
 
     let mut backend: Box<dyn Backend> = match config.driver {
-        Driver::Auto => match SwayIpc::new().await {
+        Driver::Auto => match SwayIpc::new(config.output.clone()).await {
             Ok(swayipc) => Box::new(swayipc),
             Err(_) => Box::new(WlrToplevelManagement::new().await?),
         },
-        Driver::SwayIpc => Box::new(SwayIpc::new().await?),
+        Driver::SwayIpc => Box::new(SwayIpc::new(config.output.clone()).await?),
         Driver::WlrToplevelManagement => Box::new(WlrToplevelManagement::new().await?),
     };
 
@@ -75,7 +152,26 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         select! {
             _ = api.event() => (),
             info = backend.get_info() => {
-                let Info { title, marks } = info?;
+                let Info { mut title, marks, app_id, workspace, urgent } = info?;
+                for (regex, replacement) in &rewrites {
+                    title = regex.replace_all(&title, *replacement).into_owned();
+                }
+                if let Some(max_width) = config.max_width {
+                    title = truncate(&title, max_width, config.truncate);
+                }
+                let mut app_icon = None;
+                if let (true, Some(app_id)) = (show_app_icon, &app_id) {
+                    if !icon_names.contains_key(app_id) {
+                        let id = app_id.clone();
+                        let lookup = move || desktop_entry::icon_name(&id);
+                        let name = tokio::task::spawn_blocking(lookup)
+                            .await
+                            .error("Failed to join tokio task")?;
+                        icon_names.insert(app_id.clone(), name);
+                    }
+                    let icon_name = icon_names[app_id].as_deref().unwrap_or(app_id);
+                    app_icon = api.get_icon(&desktop_entry::icon_key(icon_name)).ok();
+                }
                 if title.is_empty() {
                     widget.set_values(default());
                 } else {
@@ -83,6 +179,10 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                         "title" => Value::text(title.clone()),
                         "marks" => Value::text(marks.iter().map(|m| format!("[{m}]")).collect()),
                         "visible_marks" => Value::text(marks.iter().filter(|m| !m.starts_with('_')).map(|m| format!("[{m}]")).collect()),
+                        [if let Some(a) = app_id] "app_id" => Value::text(a),
+                        [if let Some(w) = workspace] "workspace" => Value::text(w),
+                        [if urgent] "urgent" => Value::flag(),
+                        [if let Some(i) = app_icon] "app_icon" => Value::icon(i),
                     });
                 }
                 api.set_widget(&widget).await?;
@@ -100,4 +200,39 @@ trait Backend {
 struct Info {
     title: String,
     marks: Vec<String>,
+    app_id: Option<String>,
+    workspace: Option<String>,
+    urgent: bool,
+}
+
+/// Shorten `text` to `max_width` characters, replacing the removed part with an ellipsis
+fn truncate(text: &str, max_width: usize, mode: Truncate) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= max_width {
+        return text.into();
+    }
+    let keep = max_width.saturating_sub(1);
+    let (head, tail) = match mode {
+        Truncate::Start => (0, keep),
+        Truncate::Middle => (keep - keep / 2, keep / 2),
+        Truncate::End => (keep, 0),
+    };
+    let mut truncated: String = chars[..head].iter().collect();
+    truncated.push('…');
+    truncated.extend(&chars[chars.len() - tail..]);
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncation() {
+        assert_eq!(truncate("short", 10, Truncate::End), "short");
+        assert_eq!(truncate("abcdefghij", 5, Truncate::End), "abcd…");
+        assert_eq!(truncate("abcdefghij", 5, Truncate::Start), "…ghij");
+        assert_eq!(truncate("abcdefghij", 5, Truncate::Middle), "ab…ij");
+        assert_eq!(truncate("abcdefghij", 6, Truncate::Middle), "abc…ij");
+    }
 }
@@ -0,0 +1,142 @@
+//! Lookup of application icons in `.desktop` files
+
+use std::fs;
+use std::path::PathBuf;
+
+/// Directories which contain `.desktop` files, in the order of precedence
+fn application_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    match std::env::var_os("XDG_DATA_HOME") {
+        Some(data_home) => dirs.push(PathBuf::from(data_home)),
+        None => dirs.extend(dirs_next::home_dir().map(|home| home.join(".local/share"))),
+    }
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    dirs.extend(data_dirs.split(':').map(PathBuf::from));
+    dirs.into_iter()
+        .map(|dir| dir.join("applications"))
+        .collect()
+}
+
+/// Find the icon name of the application with the given Wayland app_id or X11 class.
+///
+/// The desktop file is looked up by its name first and by its `StartupWMClass` key second.
+pub(super) fn icon_name(app_id: &str) -> Option<String> {
+    let dirs = application_dirs();
+    let file_names = [
+        format!("{app_id}.desktop"),
+        format!("{}.desktop", app_id.to_lowercase()),
+    ];
+    for dir in &dirs {
+        for file_name in &file_names {
+            if let Ok(contents) = fs::read_to_string(dir.join(file_name)) {
+                if let Some(entry) = parse(&contents) {
+                    return entry.icon;
+                }
+            }
+        }
+    }
+    for dir in &dirs {
+        for file in fs::read_dir(dir).into_iter().flatten().flatten() {
+            if file.path().extension().map_or(true, |ext| ext != "desktop") {
+                continue;
+            }
+            if let Some(entry) = fs::read_to_string(file.path())
+                .ok()
+                .as_deref()
+                .and_then(parse)
+            {
+                if entry
+                    .startup_wm_class
+                    .map_or(false, |class| class.eq_ignore_ascii_case(app_id))
+                {
+                    return entry.icon;
+                }
+            }
+        }
+    }
+    None
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct DesktopEntry {
+    icon: Option<String>,
+    startup_wm_class: Option<String>,
+}
+
+/// Read the keys we need from the `[Desktop Entry]` group
+fn parse(contents: &str) -> Option<DesktopEntry> {
+    let mut in_group = false;
+    let mut found_group = false;
+    let mut entry = DesktopEntry {
+        icon: None,
+        startup_wm_class: None,
+    };
+    for line in contents.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_group = line == "[Desktop Entry]";
+            found_group |= in_group;
+            continue;
+        }
+        if !in_group {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            match key.trim() {
+                "Icon" => entry.icon = Some(value.trim().into()),
+                "StartupWMClass" => entry.startup_wm_class = Some(value.trim().into()),
+                _ => (),
+            }
+        }
+    }
+    found_group.then_some(entry)
+}
+
+/// The icon set key for an icon name, e.g. `app_org_gnome_terminal` for `org.gnome.Terminal`
+pub(super) fn icon_key(icon_name: &str) -> String {
+    let name: String = icon_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("app_{name}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_desktop_entry() {
+        let contents = "\
+[Desktop Entry]
+Name=Firefox
+Icon=firefox
+StartupWMClass=firefox
+
+[Desktop Action new-window]
+Icon=other
+";
+        assert_eq!(
+            parse(contents),
+            Some(DesktopEntry {
+                icon: Some("firefox".into()),
+                startup_wm_class: Some("firefox".into()),
+            })
+        );
+        assert_eq!(parse("Icon=firefox"), None);
+    }
+
+    #[test]
+    fn icon_keys() {
+        assert_eq!(icon_key("firefox"), "app_firefox");
+        assert_eq!(icon_key("org.gnome.Terminal"), "app_org_gnome_terminal");
+    }
+}
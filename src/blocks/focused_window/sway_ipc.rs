@@ -4,20 +4,54 @@ use swayipc_async::{Connection, Event, EventStream, EventType, WindowChange, Wor
 
 pub(super) struct SwayIpc {
     events: EventStream,
+    /// Used to query the focused workspace
+    conn: Connection,
     info: Info,
+    /// Only report windows on this output
+    output: Option<String>,
+    /// Output of the focused workspace
+    focused_output: Option<String>,
 }
 
 impl SwayIpc {
-    pub(super) async fn new() -> Result<Self> {
-        Ok(Self {
+    pub(super) async fn new(output: Option<String>) -> Result<Self> {
+        let mut this = Self {
             events: Connection::new()
                 .await
                 .error("failed to open connection with swayipc")?
                 .subscribe(&[EventType::Window, EventType::Workspace])
                 .await
                 .error("could not subscribe to window events")?,
+            conn: Connection::new()
+                .await
+                .error("failed to open connection with swayipc")?,
             info: default(),
-        })
+            output,
+            focused_output: None,
+        };
+        this.update_workspace().await?;
+        Ok(this)
+    }
+
+    async fn update_workspace(&mut self) -> Result<()> {
+        let focused = self
+            .conn
+            .get_workspaces()
+            .await
+            .error("failed to get workspaces")?
+            .into_iter()
+            .find(|w| w.focused);
+        if let Some(workspace) = focused {
+            self.focused_output = Some(workspace.output);
+            if self.is_own_output() {
+                self.info.workspace = Some(workspace.name);
+            }
+        }
+        Ok(())
+    }
+
+    fn is_own_output(&self) -> bool {
+        self.output.is_none() || self.output == self.focused_output
     }
 }
 
@@ -32,6 +66,7 @@ impl Backend for SwayIpc {
                 .error("swayipc channel closed")?
                 .error("bad event")?;
             match event {
+                Event::Window(_) if !self.is_own_output() => continue,
                 Event::Window(e) => match e.change {
                     WindowChange::Mark => {
                         self.info.marks = e.container.marks;
@@ -42,6 +77,13 @@ impl Backend for SwayIpc {
                             self.info.title.push_str(new_title);
                         }
                         self.info.marks = e.container.marks;
+                        self.info.urgent = e.container.urgent;
+                        // Native Wayland windows have an app_id, X11 windows a class
+                        let window_properties = e.container.window_properties;
+                        self.info.app_id = e
+                            .container
+                            .app_id
+                            .or_else(|| window_properties.and_then(|properties| properties.class));
                     }
                     WindowChange::Title => {
                         if e.container.focused {
@@ -53,15 +95,35 @@ impl Backend for SwayIpc {
                             continue;
                         }
                     }
+                    WindowChange::Urgent => {
+                        if e.container.focused {
+                            self.info.urgent = e.container.urgent;
+                        } else {
+                            continue;
+                        }
+                    }
                     WindowChange::Close => {
                         self.info.title.clear();
                         self.info.marks.clear();
+                        self.info.app_id = None;
+                        self.info.urgent = false;
                     }
                     _ => continue,
                 },
+                Event::Workspace(e) if e.change == WorkspaceChange::Focus => {
+                    self.update_workspace().await?;
+                    if !self.is_own_output() {
+                        continue;
+                    }
+                }
                 Event::Workspace(e) if e.change == WorkspaceChange::Init => {
+                    if !self.is_own_output() {
+                        continue;
+                    }
                     self.info.title.clear();
                     self.info.marks.clear();
+                    self.info.app_id = None;
+                    self.info.urgent = false;
                 }
                 _ => continue,
             }
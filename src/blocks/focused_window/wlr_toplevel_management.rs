@@ -14,7 +14,7 @@ pub(super) struct WlrToplevelManagement {
 #[derive(Default)]
 struct State {
     error: Option<Error>,
-    new_title: Option<String>,
+    new_info: Option<Info>,
     toplevels: HashMap<ZwlrForeignToplevelHandleV1, Toplevel>,
     active_toplevel: Option<ZwlrForeignToplevelHandleV1>,
 }
@@ -22,6 +22,7 @@ struct State {
 #[derive(Default)]
 struct Toplevel {
     title: Option<String>,
+    app_id: Option<String>,
     is_active: bool,
 }
 
@@ -57,11 +58,8 @@ impl Backend for WlrToplevelManagement {
             }
             self.conn.async_flush().await.error("wayland error")?;
 
-            if let Some(title) = self.state.new_title.take() {
-                return Ok(Info {
-                    title,
-                    marks: default(),
-                });
+            if let Some(info) = self.state.new_info.take() {
+                return Ok(info);
             }
         }
     }
@@ -99,6 +97,9 @@ fn toplevel_cb(
         Event::Title(title) => {
             toplevel.title = Some(String::from_utf8_lossy(title.as_bytes()).into());
         }
+        Event::AppId(app_id) => {
+            toplevel.app_id = Some(String::from_utf8_lossy(app_id.as_bytes()).into());
+        }
         Event::State(state) => {
             toplevel.is_active = state
                 .chunks_exact(4)
@@ -108,7 +109,7 @@ fn toplevel_cb(
         Event::Closed => {
             if state.active_toplevel == Some(wlr_toplevel) {
                 state.active_toplevel = None;
-                state.new_title = Some(default());
+                state.new_info = Some(default());
             }
 
             wlr_toplevel.destroy(conn);
@@ -117,10 +118,14 @@ fn toplevel_cb(
         Event::Done => {
             if toplevel.is_active {
                 state.active_toplevel = Some(wlr_toplevel);
-                state.new_title = Some(toplevel.title.clone().unwrap_or_default());
+                state.new_info = Some(Info {
+                    title: toplevel.title.clone().unwrap_or_default(),
+                    app_id: toplevel.app_id.clone(),
+                    ..default()
+                });
             } else if state.active_toplevel == Some(wlr_toplevel) {
                 state.active_toplevel = None;
-                state.new_title = Some(default());
+                state.new_info = Some(default());
             }
         }
         _ => (),
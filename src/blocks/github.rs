@@ -2,6 +2,10 @@
 //!
 //! This block shows the unread notification count for a GitHub account. A GitHub [personal access token](https://github.com/settings/tokens/new) with the "notifications" scope is required, and must be passed using the `I3RS_GITHUB_TOKEN` environment variable or `token` configuration option. Optionally the colour of the block is determined by the highest notification in the following lists from highest to lowest: `critical`,`warning`,`info`,`good`
 //!
+//! Several accounts, including ones on GitHub Enterprise servers, can be combined into one block with the `accounts` option. All counts are then summed over the accounts.
+//!
+//! Notifications are fetched with conditional requests, which don't count against the API rate limit if nothing has changed. When the remaining requests get low, the block polls less often so that the quota lasts until it is reset.
+//!
 //! # Configuration
 //!
 //! Key | Values | Default
@@ -9,6 +13,8 @@
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $total.eng(w:1) "`
 //! `interval` | Update interval in seconds | `30`
 //! `token` | A GitHub personal access token with the "notifications" scope | `None`
+//! `api_server` | The API server to use with `token` | `"https://api.github.com"`
+//! `accounts` | A list of accounts, each with a `token` and an optional `api_server`. Overrides `token` and `api_server` if set. | `[]`
 //! `hide_if_total_is_zero` | Hide this block if the total count of notifications is zero | `false`
 //! `critical` | List of notification types that change the block to the critical colour | `None`
 //! `warning` | List of notification types that change the block to the warning colour | `None`
@@ -16,7 +22,7 @@
 //! `good` | List of notification types that change the block to the good colour | `None`
 //!
 //!
-//! All the placeholders are numbers without a unit.
+//! All the placeholders are numbers without a unit. `review_requests`, `assigned_issues` and `mentions` count open issues and pull requests rather than notifications, so they need the "repo" scope to include private repositories. They are only fetched if used in `format`.
 //!
 //! Placeholder        | Value
 //! -------------------|------
@@ -34,6 +40,10 @@
 //! `state_change`     | You changed the thread state (for example, closing an issue or merging a pull request)
 //! `subscribed`       | You're watching the repository
 //! `team_mention`     | You were on a team that was mentioned
+//! `review_requests`  | Open pull requests which request your review
+//! `assigned_issues`  | Open issues assigned to you
+//! `mentions`         | Open issues and pull requests which mention you
+//! `rate_limit_remaining` | The number of API requests left until the rate limit is reset (lowest of all accounts). Absent until known.
 //!
 //! # Examples
 //!
@@ -55,6 +65,20 @@
 //! hide_if_total_is_zero = true
 //! ```
 //!
+//! Combine a personal and a GitHub Enterprise account:
+//!
+//! ```toml
+//! [[block]]
+//! block = "github"
+//! format = " $icon $total.eng(w:1) $review_requests.eng(w:1) "
+//! warning = ["review_requests"]
+//! [[block.accounts]]
+//! token = "..."
+//! [[block.accounts]]
+//! token = "..."
+//! api_server = "https://github.example.com/api/v3"
+//! ```
+//!
 //! # Icons Used
 //! - `github`
 
@@ -67,6 +91,9 @@ pub struct Config {
     interval: Seconds,
     format: FormatConfig,
     token: Option<String>,
+    #[default(DEFAULT_API_SERVER.into())]
+    api_server: String,
+    accounts: Vec<Account>,
     hide_if_total_is_zero: bool,
     good: Option<Vec<String>>,
     info: Option<Vec<String>>,
@@ -74,18 +101,66 @@ pub struct Config {
     critical: Option<Vec<String>>,
 }
 
+const DEFAULT_API_SERVER: &str = "https://api.github.com";
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Account {
+    token: String,
+    #[default(DEFAULT_API_SERVER.into())]
+    api_server: String,
+}
+
+/// Search queries for the placeholders which count issues and pull requests
+const SEARCHES: [(&str, &str); 3] = [
+    ("review_requests", "is:open is:pr review-requested:@me"),
+    ("assigned_issues", "is:open is:issue assignee:@me"),
+    ("mentions", "is:open mentions:@me"),
+];
+
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
-    let mut widget =
-        Widget::new().with_format(config.format.with_default(" $icon $total.eng(w:1) ")?);
+    let format = config.format.with_default(" $icon $total.eng(w:1) ")?;
+    let searches: Vec<_> = SEARCHES
+        .iter()
+        .filter(|(key, _)| format.contains_key(key))
+        .collect();
+    let mut widget = Widget::new().with_format(format);
 
-    let mut interval = config.interval.timer();
-    let token = config
-        .token
-        .or_else(|| std::env::var("I3RS_GITHUB_TOKEN").ok())
-        .error("Github token not found")?;
+    let accounts = if config.accounts.is_empty() {
+        let token = config
+            .token
+            .or_else(|| std::env::var("I3RS_GITHUB_TOKEN").ok())
+            .error("Github token not found")?;
+        vec![Account {
+            token,
+            api_server: config.api_server,
+        }]
+    } else {
+        config.accounts
+    };
+    let mut account_states = vec![AccountState::default(); accounts.len()];
 
     loop {
-        let stats = api.recoverable(|| get_stats(&token)).await?;
+        let accounts = &accounts;
+        let searches = &searches;
+        let prev_states = &account_states;
+        account_states = api
+            .recoverable(|| async move {
+                let mut states = Vec::with_capacity(accounts.len());
+                for (account, prev) in accounts.iter().zip(prev_states) {
+                    states.push(get_stats(account, prev, searches).await?);
+                }
+                Ok(states)
+            })
+            .await?;
+
+        let mut stats = HashMap::new();
+        for account_state in &account_states {
+            for (key, value) in account_state.stats() {
+                *stats.entry(key).or_insert(0) += value;
+            }
+        }
+
         if stats.get("total").map_or(false, |x| *x > 0) || !config.hide_if_total_is_zero {
             let mut state = State::Idle;
             'outer: for (list_opt, ret) in [
@@ -108,6 +183,13 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                 .map(|(k, v)| (k.into(), Value::number(v)))
                 .collect();
             values.insert("icon".into(), Value::icon(api.get_icon("github")?));
+            if let Some(remaining) = account_states
+                .iter()
+                .filter_map(|s| s.rate_limit.map(|r| r.remaining))
+                .min()
+            {
+                values.insert("rate_limit_remaining".into(), Value::number(remaining));
+            }
             widget.set_values(values);
             widget.state = state;
             api.set_widget(&widget).await?;
@@ -115,8 +197,13 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             api.hide().await?;
         }
 
+        let delay = account_states
+            .iter()
+            .map(|s| s.delay(config.interval.0))
+            .max()
+            .unwrap_or(config.interval.0);
         select! {
-            _ = interval.tick() => (),
+            _ = sleep(delay) => (),
             _ = api.wait_for_update_request() => (),
         }
     }
@@ -127,37 +214,143 @@ struct Notification {
     reason: String,
 }
 
-async fn get_stats(token: &str) -> Result<HashMap<String, usize>> {
-    let mut stats = HashMap::new();
+const REASONS: [&str; 12] = [
+    "assign",
+    "author",
+    "comment",
+    "ci_activity",
+    "invitation",
+    "manual",
+    "mention",
+    "review_requested",
+    "security_alert",
+    "state_change",
+    "subscribed",
+    "team_mention",
+];
+
+#[derive(Debug, Clone, Copy)]
+struct RateLimit {
+    remaining: u64,
+    /// The time of the reset, in seconds since the Unix epoch
+    reset: u64,
+}
+
+impl RateLimit {
+    fn from_headers(headers: &reqwest::header::HeaderMap) -> Option<Self> {
+        let get = |name: &str| headers.get(name)?.to_str().ok()?.parse().ok();
+        Some(Self {
+            remaining: get("x-ratelimit-remaining")?,
+            reset: get("x-ratelimit-reset")?,
+        })
+    }
+}
+
+/// The results of the last update of an account, used for conditional requests
+#[derive(Debug, Clone, Default)]
+struct AccountState {
+    /// `Last-Modified` header of the first page of notifications
+    last_modified: Option<String>,
+    notifications: HashMap<String, usize>,
+    /// `ETag` header and result of each search
+    searches: HashMap<&'static str, (Option<String>, usize)>,
+    /// The rate limit of the notifications API
+    rate_limit: Option<RateLimit>,
+    /// Minimum poll interval requested by the server
+    poll_interval: Option<Duration>,
+    /// The number of requests the last update counted against the rate limit
+    requests: u64,
+}
+
+impl AccountState {
+    fn stats(&self) -> impl Iterator<Item = (String, usize)> + '_ {
+        self.notifications
+            .iter()
+            .map(|(k, v)| (k.clone(), *v))
+            .chain(self.searches.iter().map(|(k, (_, v))| ((*k).into(), *v)))
+    }
+
+    /// How long to wait until the next update, so that the rate limit is not exceeded
+    fn delay(&self, interval: Duration) -> Duration {
+        let mut delay = interval.max(self.poll_interval.unwrap_or_default());
+        if let Some(rate_limit) = self.rate_limit {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            let until_reset = rate_limit.reset.saturating_sub(now);
+            // Spread the remaining requests evenly over the time until the reset
+            let needed = (until_reset * self.requests.max(1))
+                .checked_div(rate_limit.remaining)
+                .unwrap_or(until_reset);
+            delay = delay.max(Duration::from_secs(needed));
+        }
+        delay
+    }
+}
+
+async fn get_stats(
+    account: &Account,
+    prev: &AccountState,
+    searches: &[&(&'static str, &str)],
+) -> Result<AccountState> {
+    let mut state = AccountState::default();
     let mut total = 0;
     for page in 1..100 {
-        let on_page = get_on_page(token, page).await?;
+        let last_modified = if page == 1 {
+            prev.last_modified.as_deref()
+        } else {
+            None
+        };
+        let response = get_on_page(account, page, last_modified).await?;
+        if page == 1 {
+            state.rate_limit = response.rate_limit;
+            state.poll_interval = response.poll_interval;
+            state.last_modified = response.last_modified;
+        }
+        let on_page = match response.notifications {
+            Some(n) => n,
+            None => {
+                // Nothing has changed since the last update
+                state.notifications = prev.notifications.clone();
+                break;
+            }
+        };
+        state.requests += 1;
         if on_page.is_empty() {
             break;
         }
         total += on_page.len();
         for n in on_page {
-            stats.entry(n.reason).and_modify(|x| *x += 1).or_insert(1);
+            *state.notifications.entry(n.reason).or_insert(0) += 1;
         }
     }
-    stats.insert("total".into(), total);
-    stats.entry("total".into()).or_insert(0);
-    stats.entry("assign".into()).or_insert(0);
-    stats.entry("author".into()).or_insert(0);
-    stats.entry("comment".into()).or_insert(0);
-    stats.entry("ci_activity".into()).or_insert(0);
-    stats.entry("invitation".into()).or_insert(0);
-    stats.entry("manual".into()).or_insert(0);
-    stats.entry("mention".into()).or_insert(0);
-    stats.entry("review_requested".into()).or_insert(0);
-    stats.entry("security_alert".into()).or_insert(0);
-    stats.entry("state_change".into()).or_insert(0);
-    stats.entry("subscribed".into()).or_insert(0);
-    stats.entry("team_mention".into()).or_insert(0);
-    Ok(stats)
+    if state.requests > 0 {
+        state.notifications.insert("total".into(), total);
+    }
+    for reason in REASONS {
+        state.notifications.entry(reason.into()).or_insert(0);
+    }
+    for (key, query) in searches {
+        let etag = prev.searches.get(key).and_then(|(etag, _)| etag.as_deref());
+        let (etag, count) = match search_count(account, query, etag).await? {
+            Some(result) => result,
+            None => prev.searches[key].clone(),
+        };
+        state.searches.insert(*key, (etag, count));
+    }
+    Ok(state)
 }
 
-async fn get_on_page(token: &str, page: usize) -> Result<Vec<Notification>> {
+struct Page {
+    /// `None` if the notifications have not been modified
+    notifications: Option<Vec<Notification>>,
+    last_modified: Option<String>,
+    rate_limit: Option<RateLimit>,
+    poll_interval: Option<Duration>,
+}
+
+async fn get_on_page(account: &Account, page: usize, last_modified: Option<&str>) -> Result<Page> {
     #[derive(Deserialize)]
     #[serde(untagged)]
     enum Response {
@@ -166,21 +359,88 @@ async fn get_on_page(token: &str, page: usize) -> Result<Vec<Notification>> {
     }
 
     // https://docs.github.com/en/rest/reference/activity#notifications
-    let request = REQWEST_CLIENT
+    let mut request = REQWEST_CLIENT
         .get(format!(
-            "https://api.github.com/notifications?per_page=100&page={page}",
+            "{}/notifications?per_page=100&page={page}",
+            account.api_server.trim_end_matches('/'),
         ))
-        .header("Authorization", format!("token {token}"));
-    let responce = request
-        .send()
-        .await
-        .error("Failed to send request")?
+        .header("Authorization", format!("token {}", account.token));
+    if let Some(last_modified) = last_modified {
+        request = request.header("If-Modified-Since", last_modified);
+    }
+    let responce = request.send().await.error("Failed to send request")?;
+
+    let headers = responce.headers();
+    let mut page = Page {
+        notifications: None,
+        last_modified: headers
+            .get("last-modified")
+            .and_then(|v| v.to_str().ok())
+            .map(Into::into)
+            .or_else(|| last_modified.map(Into::into)),
+        rate_limit: RateLimit::from_headers(headers),
+        poll_interval: headers
+            .get("x-poll-interval")
+            .and_then(|v| v.to_str().ok()?.parse().ok())
+            .map(Duration::from_secs),
+    };
+    if responce.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(page);
+    }
+
+    match responce
         .json::<Response>()
         .await
-        .error("Failed to get JSON")?;
+        .error("Failed to get JSON")?
+    {
+        Response::Notifications(n) => {
+            page.notifications = Some(n);
+            Ok(page)
+        }
+        Response::ErrorMessage { message } => Err(Error::new(format!("API error: {message}"))),
+    }
+}
+
+/// Returns the `ETag` and the number of results, or `None` if the results have not changed
+async fn search_count(
+    account: &Account,
+    query: &str,
+    etag: Option<&str>,
+) -> Result<Option<(Option<String>, usize)>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Response {
+        Results { total_count: usize },
+        ErrorMessage { message: String },
+    }
+
+    // https://docs.github.com/en/rest/search#search-issues-and-pull-requests
+    let mut request = REQWEST_CLIENT
+        .get(format!(
+            "{}/search/issues",
+            account.api_server.trim_end_matches('/')
+        ))
+        .query(&[("q", query), ("per_page", "1")])
+        .header("Authorization", format!("token {}", account.token));
+    if let Some(etag) = etag {
+        request = request.header("If-None-Match", etag);
+    }
+    let response = request.send().await.error("Failed to send request")?;
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+    let etag = response
+        .headers()
+        .get("etag")
+        .and_then(|v| v.to_str().ok())
+        .map(Into::into);
 
-    match responce {
-        Response::Notifications(n) => Ok(n),
+    match response
+        .json::<Response>()
+        .await
+        .error("Failed to get JSON")?
+    {
+        Response::Results { total_count } => Ok(Some((etag, total_count))),
         Response::ErrorMessage { message } => Err(Error::new(format!("API error: {message}"))),
     }
 }
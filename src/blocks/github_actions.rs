@@ -0,0 +1,210 @@
+//! GitHub Actions workflow status
+//!
+//! Watches the latest run of one or more workflows and shows whether they passed, failed or are
+//! still in progress. The block is in the critical state if any of the latest runs failed, and
+//! clicking it opens the most relevant run (the first failed one, else the first one in progress,
+//! else the first one) with `xdg-open`.
+//!
+//! A token is only needed for private repositories or to get a higher rate limit. It can be passed
+//! using the `I3RS_GITHUB_TOKEN` environment variable or the `token` configuration option.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `workflows` | The workflows to watch, each with a `repo` (`"owner/name"`), an optional `workflow` (the file name or id of the workflow, all workflows if not set) and an optional `branch` | **Required**
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $status "`
+//! `interval` | Update interval in seconds | `120`
+//! `token` | A GitHub personal access token | `None`
+//! `api_server` | The API server to use | `"https://api.github.com"`
+//!
+//! Placeholder   | Value                                                             | Type   | Unit
+//! --------------|-------------------------------------------------------------------|--------|-----
+//! `icon`        | A static icon                                                     | Icon   | -
+//! `status`      | `"failing"`, `"running"` or `"passing"`                           | Text   | -
+//! `failed`      | The number of workflows whose latest run failed                   | Number | -
+//! `in_progress` | The number of workflows whose latest run is queued or in progress | Number | -
+//! `passed`      | The number of workflows whose latest run succeeded                | Number | -
+//! `name`        | The name of the most relevant run                                 | Text   | -
+//!
+//! Action     | Description                             | Default button
+//! -----------|-----------------------------------------|---------------
+//! `open_run` | Open the most relevant run in a browser | Left
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "github_actions"
+//! format = " $icon $name $status "
+//! [[block.workflows]]
+//! repo = "greshake/i3status-rust"
+//! workflow = "rust.yml"
+//! branch = "master"
+//! ```
+//!
+//! # Icons Used
+//! - `github`
+
+use super::prelude::*;
+use crate::subprocess::spawn_process;
+
+const DEFAULT_API_SERVER: &str = "https://api.github.com";
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    workflows: Vec<Workflow>,
+    format: FormatConfig,
+    #[default(120.into())]
+    interval: Seconds,
+    token: Option<String>,
+    #[default(DEFAULT_API_SERVER.into())]
+    api_server: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Workflow {
+    repo: String,
+    workflow: Option<String>,
+    branch: Option<String>,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[(MouseButton::Left, None, "open_run")])
+        .await?;
+
+    if config.workflows.is_empty() {
+        return Err(Error::new("`workflows` must not be empty"));
+    }
+
+    let mut widget = Widget::new().with_format(config.format.with_default(" $icon $status ")?);
+    let token = config
+        .token
+        .clone()
+        .or_else(|| std::env::var("I3RS_GITHUB_TOKEN").ok());
+    let mut timer = config.interval.timer();
+
+    loop {
+        let mut runs = Vec::new();
+        for workflow in &config.workflows {
+            let run = api
+                .recoverable(|| latest_run(&config.api_server, token.as_deref(), workflow))
+                .await?;
+            runs.extend(run);
+        }
+
+        let failed = runs.iter().filter(|r| r.failed()).count();
+        let in_progress = runs.iter().filter(|r| r.status != "completed").count();
+        let passed = runs
+            .iter()
+            .filter(|r| r.conclusion.as_deref() == Some("success"))
+            .count();
+        let relevant = runs
+            .iter()
+            .find(|r| r.failed())
+            .or_else(|| runs.iter().find(|r| r.status != "completed"))
+            .or_else(|| runs.first());
+
+        let status = if failed > 0 {
+            widget.state = State::Critical;
+            "failing"
+        } else if in_progress > 0 {
+            widget.state = State::Info;
+            "running"
+        } else {
+            widget.state = State::Good;
+            "passing"
+        };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("github")?),
+            "status" => Value::text(status.into()),
+            "failed" => Value::number(failed),
+            "in_progress" => Value::number(in_progress),
+            "passed" => Value::number(passed),
+            [if let Some(r) = relevant] "name" => Value::text(r.name.clone()),
+        });
+        api.set_widget(&widget).await?;
+
+        loop {
+            select! {
+                _ = timer.tick() => break,
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "open_run" => {
+                        if let Some(run) = relevant {
+                            spawn_process("xdg-open", &[&run.html_url])
+                                .error("Failed to run xdg-open")?;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Run {
+    name: String,
+    status: String,
+    conclusion: Option<String>,
+    html_url: String,
+}
+
+impl Run {
+    fn failed(&self) -> bool {
+        matches!(
+            self.conclusion.as_deref(),
+            Some("failure" | "timed_out" | "startup_failure")
+        )
+    }
+}
+
+/// The latest run of the workflow, if it has ever run
+async fn latest_run(
+    api_server: &str,
+    token: Option<&str>,
+    workflow: &Workflow,
+) -> Result<Option<Run>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Response {
+        Runs { workflow_runs: Vec<Run> },
+        ErrorMessage { message: String },
+    }
+
+    // https://docs.github.com/en/rest/actions/workflow-runs
+    let url = match &workflow.workflow {
+        Some(id) => format!(
+            "{}/repos/{}/actions/workflows/{id}/runs",
+            api_server.trim_end_matches('/'),
+            workflow.repo,
+        ),
+        None => format!(
+            "{}/repos/{}/actions/runs",
+            api_server.trim_end_matches('/'),
+            workflow.repo,
+        ),
+    };
+    let mut request = REQWEST_CLIENT.get(url).query(&[("per_page", "1")]);
+    if let Some(branch) = &workflow.branch {
+        request = request.query(&[("branch", branch)]);
+    }
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("token {token}"));
+    }
+
+    match request
+        .send()
+        .await
+        .error("Failed to send request")?
+        .json::<Response>()
+        .await
+        .error("Failed to get JSON")?
+    {
+        Response::Runs { mut workflow_runs } => Ok(workflow_runs.pop()),
+        Response::ErrorMessage { message } => Err(Error::new(format!("API error: {message}"))),
+    }
+}
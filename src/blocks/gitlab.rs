@@ -0,0 +1,203 @@
+//! GitLab merge requests and pipelines
+//!
+//! Shows the number of open merge requests assigned to you and awaiting your review and, if
+//! `project` is set, the status of its latest pipeline. The block is in the critical state while
+//! that pipeline has failed.
+//!
+//! A GitLab [personal access token](https://docs.gitlab.com/ee/user/profile/personal_access_tokens.html)
+//! with the "read_api" scope is required, and must be passed using the `I3RS_GITLAB_TOKEN`
+//! environment variable or `token` configuration option.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $assigned.eng(w:1) $review_requested.eng(w:1){ $pipeline\|} "`
+//! `interval` | Update interval in seconds | `120`
+//! `server` | The base URL of the GitLab instance | `"https://gitlab.com"`
+//! `token` | A personal access token | `None`
+//! `project` | The path (e.g. `"group/project"`) or id of the project whose pipelines to show | `None`
+//! `ref` | The branch or tag to show the latest pipeline of. All pipelines of the project if not set. | `None`
+//!
+//! Placeholder        | Value                                                             | Type   | Unit
+//! -------------------|-------------------------------------------------------------------|--------|-----
+//! `icon`             | A static icon                                                     | Icon   | -
+//! `assigned`         | The number of open merge requests assigned to you                 | Number | -
+//! `review_requested` | The number of open merge requests which request your review       | Number | -
+//! `pipeline`         | The status of the latest pipeline, e.g. `"success"` or `"failed"` | Text   | -
+//!
+//! Action          | Description                           | Default button
+//! ----------------|---------------------------------------|---------------
+//! `open_pipeline` | Open the latest pipeline in a browser | Left
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "gitlab"
+//! server = "https://gitlab.example.org"
+//! project = "infra/deploy"
+//! ref = "main"
+//! ```
+//!
+//! # Icons Used
+//! - `github`
+
+use super::prelude::*;
+use crate::subprocess::spawn_process;
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    format: FormatConfig,
+    #[default(120.into())]
+    interval: Seconds,
+    #[default("https://gitlab.com".into())]
+    server: String,
+    token: Option<String>,
+    project: Option<String>,
+    #[serde(rename = "ref")]
+    ref_: Option<String>,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[(MouseButton::Left, None, "open_pipeline")])
+        .await?;
+
+    let mut widget = Widget::new().with_format(
+        config
+            .format
+            .with_default(" $icon $assigned.eng(w:1) $review_requested.eng(w:1){ $pipeline|} ")?,
+    );
+    let token = config
+        .token
+        .clone()
+        .or_else(|| std::env::var("I3RS_GITLAB_TOKEN").ok())
+        .error("GitLab token not found")?;
+    let client = Client {
+        api: format!("{}/api/v4", config.server.trim_end_matches('/')),
+        token,
+    };
+    let user_id = api.recoverable(|| client.user_id()).await?;
+    let assigned_query = [("scope", "assigned_to_me"), ("state", "opened")];
+    // The default scope only includes merge requests created by the user
+    let review_query = [
+        ("scope", "all"),
+        ("state", "opened"),
+        ("reviewer_id", user_id.as_str()),
+    ];
+    let mut timer = config.interval.timer();
+
+    loop {
+        let assigned = api
+            .recoverable(|| client.count_merge_requests(&assigned_query))
+            .await?;
+        let review_requested = api
+            .recoverable(|| client.count_merge_requests(&review_query))
+            .await?;
+        let pipeline = match &config.project {
+            Some(project) => {
+                api.recoverable(|| client.latest_pipeline(project, config.ref_.as_deref()))
+                    .await?
+            }
+            None => None,
+        };
+
+        widget.state = match pipeline.as_ref().map(|p| p.status.as_str()) {
+            Some("failed") => State::Critical,
+            Some("running" | "pending") => State::Info,
+            _ => State::Idle,
+        };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("github")?),
+            "assigned" => Value::number(assigned),
+            "review_requested" => Value::number(review_requested),
+            [if let Some(p) = &pipeline] "pipeline" => Value::text(p.status.clone()),
+        });
+        api.set_widget(&widget).await?;
+
+        loop {
+            select! {
+                _ = timer.tick() => break,
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "open_pipeline" => {
+                        if let Some(pipeline) = &pipeline {
+                            spawn_process("xdg-open", &[&pipeline.web_url])
+                                .error("Failed to run xdg-open")?;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+struct Client {
+    /// e.g. `https://gitlab.com/api/v4`
+    api: String,
+    token: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Pipeline {
+    status: String,
+    web_url: String,
+}
+
+impl Client {
+    async fn get(&self, path: &str, query: &[(&str, &str)]) -> Result<reqwest::Response> {
+        REQWEST_CLIENT
+            .get(format!("{}{path}", self.api))
+            .query(query)
+            .header("PRIVATE-TOKEN", &self.token)
+            .send()
+            .await
+            .error("Failed to send request")?
+            .error_for_status()
+            .error("GitLab API error")
+    }
+
+    async fn user_id(&self) -> Result<String> {
+        #[derive(Deserialize)]
+        struct User {
+            id: u64,
+        }
+
+        let user: User = self
+            .get("/user", &[])
+            .await?
+            .json()
+            .await
+            .error("Failed to get JSON")?;
+        Ok(user.id.to_string())
+    }
+
+    /// The number of open merge requests matching `query`, from the `X-Total` header
+    async fn count_merge_requests(&self, query: &[(&str, &str)]) -> Result<usize> {
+        let mut query = query.to_vec();
+        query.push(("per_page", "1"));
+        let response = self.get("/merge_requests", &query).await?;
+        response
+            .headers()
+            .get("x-total")
+            .and_then(|v| v.to_str().ok()?.parse().ok())
+            .error("Failed to get the number of merge requests")
+    }
+
+    async fn latest_pipeline(&self, project: &str, ref_: Option<&str>) -> Result<Option<Pipeline>> {
+        let mut query = vec![("per_page", "1")];
+        if let Some(ref_) = ref_ {
+            query.push(("ref", ref_));
+        }
+        let project = project.replace('/', "%2F");
+        let mut pipelines: Vec<Pipeline> = self
+            .get(&format!("/projects/{project}/pipelines"), &query)
+            .await?
+            .json()
+            .await
+            .error("Failed to get JSON")?;
+        Ok(pipelines.pop())
+    }
+}
@@ -0,0 +1,144 @@
+//! HTTP endpoint health check
+//!
+//! Fetches a URL on an interval and shows the status code and response time. The block is in the
+//! critical state if the request fails, the status code is not the expected one or the body does
+//! not match `expected_body`, and in the warning state if the response was slow.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `url` | The URL to fetch | **Required**
+//! `name` | A name for the endpoint, available as the `name` placeholder | The value of `url`
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $name {$status\|down} "`
+//! `interval` | Update interval in seconds | `60`
+//! `timeout` | How long to wait for a response, in seconds | `10`
+//! `expected_status` | The expected status code. If not set, any 2xx status code is accepted. | `None`
+//! `expected_body` | A regex which the response body is expected to match | `None`
+//! `slow_response` | Response time in milliseconds, from which the state is set to warning | `1000`
+//!
+//! Placeholder  | Value                                             | Type   | Unit
+//! -------------|---------------------------------------------------|--------|--------
+//! `icon`       | A static icon                                     | Icon   | -
+//! `name`       | The value of `name`                               | Text   | -
+//! `status`     | The status code. Missing if the request failed.   | Number | -
+//! `time`       | The response time. Missing if the request failed. | Number | Seconds
+//! `body_match` | Present if `expected_body` matched                | Flag   | -
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "http_check"
+//! name = "nextcloud"
+//! url = "https://cloud.example.org/status.php"
+//! expected_body = '"maintenance":false'
+//! format = " $icon $name $time.eng(w:3) "
+//! ```
+//!
+//! # Icons Used
+//! - `ping`
+
+use super::prelude::*;
+use regex::Regex;
+use std::time::Instant;
+
+make_log_macro!(debug, "http_check");
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    url: String,
+    name: Option<String>,
+    format: FormatConfig,
+    #[default(60.into())]
+    interval: Seconds,
+    #[default(10.into())]
+    timeout: Seconds,
+    expected_status: Option<u16>,
+    expected_body: Option<String>,
+    #[default(1000)]
+    slow_response: u64,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    if config.url.is_empty() {
+        return Err(Error::new("`url` must be set"));
+    }
+
+    let mut widget =
+        Widget::new().with_format(config.format.with_default(" $icon $name {$status|down} ")?);
+    let expected_body = config
+        .expected_body
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .error("invalid expected body regex")?;
+    let name = config.name.as_ref().unwrap_or(&config.url);
+    let mut timer = config.interval.timer();
+
+    loop {
+        let response = check(&config.url, config.timeout).await;
+        if let Err(e) = &response {
+            debug!("{e}");
+        }
+        let response = response.ok();
+
+        let body_match = match (&response, &expected_body) {
+            (Some(r), Some(re)) => re.is_match(&r.body),
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+        let status_ok = response
+            .as_ref()
+            .map_or(false, |r| match config.expected_status {
+                Some(expected) => r.status == expected,
+                None => (200..300).contains(&r.status),
+            });
+        widget.state = match &response {
+            Some(_) if !status_ok || !body_match => State::Critical,
+            Some(r) if r.time >= Duration::from_millis(config.slow_response) => State::Warning,
+            Some(_) => State::Good,
+            None => State::Critical,
+        };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("ping")?),
+            "name" => Value::text(name.clone()),
+            [if let Some(r) = &response] "status" => Value::number(r.status),
+            [if let Some(r) = &response] "time" => Value::seconds(r.time.as_secs_f64()),
+            [if body_match && expected_body.is_some()] "body_match" => Value::flag(),
+        });
+        api.set_widget(&widget).await?;
+
+        select! {
+            _ = timer.tick() => (),
+            _ = api.wait_for_update_request() => (),
+        }
+    }
+}
+
+struct Response {
+    status: u16,
+    time: Duration,
+    body: String,
+}
+
+async fn check(url: &str, timeout: Seconds) -> Result<Response> {
+    let start = Instant::now();
+    let response = REQWEST_CLIENT
+        .get(url)
+        .timeout(timeout.0)
+        .send()
+        .await
+        .or_error(|| format!("Failed to fetch {url}"))?;
+    let status = response.status().as_u16();
+    let body = response
+        .text()
+        .await
+        .or_error(|| format!("Failed to read the response from {url}"))?;
+    Ok(Response {
+        status,
+        time: start.elapsed(),
+        body,
+    })
+}
@@ -3,6 +3,8 @@
 //! This block displays the current color temperature in Kelvin. When scrolling upon the block the color temperature is changed.
 //! A left click on the block sets the color temperature to `click_temp` that is by default to `6500K`.
 //! A right click completely resets the color temperature to its default value (`6500K`).
+//! A middle click turns the hue shifter off, and another one turns it back on with the previous
+//! temperature.
 //!
 //! # Configuration
 //!
@@ -18,11 +20,13 @@
 //! Placeholder           | Value                        | Type   | Unit
 //! ----------------------|------------------------------|--------|---------------
 //! `temperature`         | Current temperature          | Number | -
+//! `disabled`            | Present if toggled off       | Flag   | -
 //!
 //! Action             | Default button
 //! -------------------|---------------
 //! `set_click_temp`   | Left
 //! `reset`            | Right
+//! `toggle`           | Middle
 //! `temperature_up`   | Wheel Up
 //! `temperature_down` | Wheel Down
 //!
@@ -42,6 +46,8 @@
 //! subscribe to the events and update the bar when the temperature is modified extenrally. Also,
 //! these are the only drivers at the moment that work under Wayland without flickering.
 //!
+//! When detecting the hue shifter under Wayland, the X11 only ones are skipped.
+//!
 //! # Example
 //!
 //! ```toml
@@ -52,6 +58,14 @@
 //! click_temp = 3500
 //! ```
 //!
+//! ```toml
+//! [[block]]
+//! block = "hueshift"
+//! hue_shifter = "wlsunset"
+//! format = " {$disabled{off}|$temperature K} "
+//! step = 250
+//! ```
+//!
 //! A hard limit is set for the `max_temp` to `10000K` and the same for the `min_temp` which is `1000K`.
 //! The `step` has a hard limit as well, defined to `500K` to avoid too brutal changes.
 
@@ -85,6 +99,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     api.set_default_actions(&[
         (MouseButton::Left, None, "set_click_temp"),
         (MouseButton::Right, None, "reset"),
+        (MouseButton::Middle, None, "toggle"),
         (MouseButton::WheelUp, None, "temperature_up"),
         (MouseButton::WheelDown, None, "temperature_down"),
     ])
@@ -93,10 +108,11 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     let mut widget = Widget::new().with_format(config.format.with_default(" $temperature ")?);
 
     // limit too big steps at 500K to avoid too brutal changes
-    let step = config.step.max(500);
+    let step = config.step.min(500);
     let max_temp = config.max_temp.min(10_000);
     let min_temp = config.min_temp.clamp(1_000, max_temp);
 
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
     let hue_shifter = match config.hue_shifter {
         Some(driver) => driver,
         None => {
@@ -104,9 +120,9 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                 HueShifter::WlGammarelayRs
             } else if has_command("wl-gammarelay").await? {
                 HueShifter::WlGammarelay
-            } else if has_command("redshift").await? {
+            } else if !wayland && has_command("redshift").await? {
                 HueShifter::Redshift
-            } else if has_command("sct").await? {
+            } else if !wayland && has_command("sct").await? {
                 HueShifter::Sct
             } else if has_command("gammastep").await? {
                 HueShifter::Gammastep
@@ -128,9 +144,13 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     };
 
     let mut current_temp = driver.get().await?.unwrap_or(config.current_temp);
+    let mut enabled = true;
 
     loop {
-        widget.set_values(map!("temperature" => Value::number(current_temp)));
+        widget.set_values(map!(
+            "temperature" => Value::number(current_temp),
+            [if !enabled] "disabled" => Value::flag(),
+        ));
         api.set_widget(&widget).await?;
 
         select! {
@@ -146,8 +166,17 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                     }
                     Action(a) if a == "set_click_temp" => {
                         current_temp = config.click_temp;
+                        enabled = true;
                         driver.update(current_temp).await?;
                     }
+                    Action(a) if a == "toggle" => {
+                        if enabled {
+                            driver.reset().await?;
+                        } else {
+                            driver.update(current_temp).await?;
+                        }
+                        enabled = !enabled;
+                    }
                     Action(a) if a == "reset" => {
                         if max_temp > 6500 {
                             current_temp = 6500;
@@ -159,10 +188,12 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                     }
                     Action(a) if a == "temperature_up" => {
                         current_temp = (current_temp + step).min(max_temp);
+                        enabled = true;
                         driver.update(current_temp).await?;
                     }
                     Action(a) if a == "temperature_down" => {
                         current_temp = current_temp.saturating_sub(step).max(min_temp);
+                        enabled = true;
                         driver.update(current_temp).await?;
                     }
                     _ => (),
@@ -0,0 +1,515 @@
+//! Unread mail on an IMAP server
+//!
+//! Shows the number of unseen messages in one or more folders of an IMAP account, without needing
+//! a local copy of the mail. If the server supports IDLE, the first folder is watched and the block
+//! is updated as soon as mail arrives there. Other folders are checked every `interval` seconds.
+//!
+//! The password can also be passed using the `I3RS_IMAP_PASSWORD` environment variable. To show
+//! several accounts, add one block per account.
+//!
+//! The connection is encrypted using TLS, or STARTTLS with `security = "starttls"`. The password is
+//! only ever sent unencrypted if this is explicitly allowed with `security = "none"`, which should
+//! be limited to servers on the local machine.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `host` | The IMAP server | **Required**
+//! `port` | The port of the server | `993` for `"tls"`, `143` otherwise
+//! `security` | How to encrypt the connection: `"tls"`, `"starttls"` or `"none"` | `"tls"`
+//! `user` | The user to log in as | **Required**
+//! `password` | The password of `user` | `None`
+//! `folders` | The folders to count unseen messages in | `["INBOX"]`
+//! `idle` | Whether to watch the first folder using IDLE | `true`
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $unread "`
+//! `interval` | Update interval in seconds | `300`
+//! `threshold_warning` | Number of unseen messages where state is set to warning | `1`
+//! `threshold_critical` | Number of unseen messages where state is set to critical | `10`
+//!
+//! Placeholder | Value                                                                          | Type   | Unit
+//! ------------|--------------------------------------------------------------------------------|--------|-----
+//! `icon`      | A static icon                                                                  | Icon   | -
+//! `unread`    | The number of unseen messages in all folders                                   | Number | -
+//! `<folder>`  | The number of unseen messages in a folder, named in lower case with anything but letters and digits replaced by `_`, e.g. `lists_rust` for `"Lists/Rust"` | Number | -
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "imap"
+//! host = "imap.example.org"
+//! user = "alice@example.org"
+//! folders = ["INBOX", "Lists/Rust"]
+//! format = " $icon $inbox / $lists_rust "
+//! ```
+//!
+//! # Icons Used
+//! - `mail`
+
+use super::prelude::*;
+use tokio::io::{split, AsyncRead, AsyncWrite, BufReader, Lines, ReadHalf, WriteHalf};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_native_tls::{native_tls, TlsConnector};
+
+make_log_macro!(debug, "imap");
+
+/// How long to wait for the response to a command
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Servers may drop clients which stay idle for 30 minutes (RFC 2177), so IDLE is restarted before
+const IDLE_TIMEOUT: Duration = Duration::from_secs(28 * 60);
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    host: String,
+    port: Option<u16>,
+    security: Security,
+    user: String,
+    password: Option<String>,
+    #[default(vec!["INBOX".into()])]
+    folders: Vec<String>,
+    #[default(true)]
+    idle: bool,
+    format: FormatConfig,
+    #[default(300.into())]
+    interval: Seconds,
+    #[default(1)]
+    threshold_warning: usize,
+    #[default(10)]
+    threshold_critical: usize,
+}
+
+#[derive(Deserialize, Debug, SmartDefault, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Security {
+    #[default]
+    Tls,
+    StartTls,
+    /// Send everything, including the password, in plaintext
+    None,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    let mut widget =
+        Widget::new().with_format(config.format.clone().with_default(" $icon $unread ")?);
+
+    if config.host.is_empty() || config.user.is_empty() {
+        return Err(Error::new("`host` and `user` must be set"));
+    }
+    if config.folders.is_empty() {
+        return Err(Error::new("`folders` must not be empty"));
+    }
+    let password = config
+        .password
+        .clone()
+        .or_else(|| std::env::var("I3RS_IMAP_PASSWORD").ok())
+        .error("IMAP password not found")?;
+    let mut timer = config.interval.timer();
+    let mut connection: Option<Connection> = None;
+
+    loop {
+        let result = async {
+            if connection.is_none() {
+                connection = Some(Connection::open(&config, &password).await?);
+            }
+            connection.as_mut().unwrap().unseen(&config.folders).await
+        }
+        .await;
+        let counts = match result {
+            Ok(counts) => counts,
+            Err(err) => {
+                // Reconnect after the error interval
+                connection = None;
+                api.set_error(err).await?;
+                select! {
+                    _ = sleep(api.error_interval) => (),
+                    _ = api.wait_for_update_request() => (),
+                }
+                continue;
+            }
+        };
+
+        let unread: usize = counts.iter().sum();
+        let mut values = map! {
+            "icon" => Value::icon(api.get_icon("mail")?),
+            "unread" => Value::number(unread),
+        };
+        for (folder, count) in config.folders.iter().zip(&counts) {
+            values.insert(placeholder_name(folder).into(), Value::number(*count));
+        }
+        widget.state = if unread >= config.threshold_critical {
+            State::Critical
+        } else if unread >= config.threshold_warning {
+            State::Warning
+        } else {
+            State::Idle
+        };
+        widget.set_values(values);
+        api.set_widget(&widget).await?;
+
+        let conn = connection.as_mut().unwrap();
+        if conn.can_idle && config.idle {
+            if let Err(err) = conn.idle(&mut timer, &mut api).await {
+                debug!("IDLE failed: {err}");
+                connection = None;
+            }
+        } else {
+            select! {
+                _ = timer.tick() => (),
+                _ = api.wait_for_update_request() => (),
+            }
+        }
+    }
+}
+
+/// The name of the placeholder of a folder, e.g. `lists_rust` for `Lists/Rust`
+fn placeholder_name(folder: &str) -> String {
+    folder
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Quote a string for use in an IMAP command
+fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Encode a folder name using the modified UTF-7 of RFC 3501, e.g. `Entw&APw-rfe` for `Entwürfe`
+fn encode_folder(folder: &str) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+,";
+
+    let mut encoded = String::new();
+    let mut chars = folder.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => encoded.push_str("&-"),
+            ' '..='~' => encoded.push(c),
+            _ => {
+                // Base64 encode the UTF-16 of all the following non-printable characters
+                let mut utf16 = Vec::new();
+                let mut buf = [0; 2];
+                for c in std::iter::once(c).chain(std::iter::from_fn(|| {
+                    chars.next_if(|c| !matches!(c, ' '..='~'))
+                })) {
+                    for unit in c.encode_utf16(&mut buf) {
+                        utf16.extend(unit.to_be_bytes());
+                    }
+                }
+                encoded.push('&');
+                for chunk in utf16.chunks(3) {
+                    let bits = chunk
+                        .iter()
+                        .enumerate()
+                        .fold(0u32, |bits, (i, &b)| bits | ((b as u32) << (16 - 8 * i)));
+                    for i in 0..=chunk.len() {
+                        encoded.push(ALPHABET[((bits >> (18 - 6 * i)) & 0x3f) as usize] as char);
+                    }
+                }
+                encoded.push('-');
+            }
+        }
+    }
+    encoded
+}
+
+/// The length of the literal announced at the end of a line, e.g. `10` for `* STATUS {10}\r\n`
+fn literal_len(line: &[u8]) -> Option<usize> {
+    let line = line.strip_suffix(b"\n")?;
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let line = line.strip_suffix(b"}")?;
+    let start = line.iter().rposition(|&b| b == b'{')?;
+    std::str::from_utf8(&line[start + 1..]).ok()?.parse().ok()
+}
+
+trait ImapStream: AsyncRead + AsyncWrite + Unpin {}
+impl<T: AsyncRead + AsyncWrite + Unpin> ImapStream for T {}
+
+async fn tls_connect(host: &str, tcp: TcpStream) -> Result<Box<dyn ImapStream>> {
+    let connector = TlsConnector::from(
+        native_tls::TlsConnector::new().error("Failed to create a TLS connector")?,
+    );
+    Ok(Box::new(
+        connector
+            .connect(host, tcp)
+            .await
+            .error("TLS handshake failed")?,
+    ))
+}
+
+async fn next_line<R: AsyncBufRead + Unpin>(lines: &mut Lines<R>) -> Result<String> {
+    lines
+        .next_line()
+        .await
+        .error("Failed to read from the server")?
+        .error("The server closed the connection")
+}
+
+fn check_greeting(greeting: &str) -> Result<()> {
+    if greeting.starts_with("* OK") {
+        Ok(())
+    } else {
+        Err(Error::new(format!("Unexpected greeting: {greeting}")))
+    }
+}
+
+/// Read the greeting and ask the server to switch to TLS
+async fn starttls(tcp: &mut TcpStream) -> Result<()> {
+    let (reader, mut writer) = tcp.split();
+    // The server sends nothing after its response until the TLS handshake, so no buffered data is
+    // lost when the reader is dropped
+    let mut lines = BufReader::new(reader).lines();
+    check_greeting(&next_line(&mut lines).await?)?;
+    writer
+        .write_all(b"s1 STARTTLS\r\n")
+        .await
+        .error("Failed to write to the server")?;
+    loop {
+        if let Some(result) = next_line(&mut lines).await?.strip_prefix("s1 ") {
+            if result.starts_with("OK") {
+                return Ok(());
+            }
+            return Err(Error::new(format!("STARTTLS failed: {result}")));
+        }
+    }
+}
+
+struct Connection {
+    reader: BufReader<ReadHalf<Box<dyn ImapStream>>>,
+    /// The part of the current response which was read so far
+    buf: Vec<u8>,
+    /// The number of bytes of a literal which are still to be read
+    literal: usize,
+    writer: WriteHalf<Box<dyn ImapStream>>,
+    next_tag: u32,
+    can_idle: bool,
+}
+
+impl Connection {
+    /// Connect, log in and examine the first folder
+    async fn open(config: &Config, password: &str) -> Result<Self> {
+        let port = config.port.unwrap_or(match config.security {
+            Security::Tls => 993,
+            Security::StartTls | Security::None => 143,
+        });
+        let mut tcp = TcpStream::connect((config.host.as_str(), port))
+            .await
+            .or_error(|| format!("Failed to connect to {}:{port}", config.host))?;
+        let stream: Box<dyn ImapStream> = match config.security {
+            Security::Tls => tls_connect(&config.host, tcp).await?,
+            Security::StartTls => {
+                starttls(&mut tcp).await?;
+                tls_connect(&config.host, tcp).await?
+            }
+            Security::None => Box::new(tcp),
+        };
+        let (reader, writer) = split(stream);
+        let mut connection = Self {
+            reader: BufReader::new(reader),
+            buf: Vec::new(),
+            literal: 0,
+            writer,
+            next_tag: 0,
+            can_idle: false,
+        };
+
+        // With STARTTLS, the greeting was sent before the TLS handshake
+        if config.security != Security::StartTls {
+            check_greeting(&connection.read_line().await?)?;
+        }
+        connection
+            .command(&format!(
+                "LOGIN {} {}",
+                quote(&config.user),
+                quote(password)
+            ))
+            .await?;
+        connection.can_idle = connection
+            .command("CAPABILITY")
+            .await?
+            .iter()
+            .any(|line| line.starts_with("* CAPABILITY") && line.contains(" IDLE"));
+        connection
+            .command(&format!(
+                "EXAMINE {}",
+                quote(&encode_folder(&config.folders[0]))
+            ))
+            .await?;
+        Ok(connection)
+    }
+
+    /// Read a response line, including the literals in it
+    async fn read_line(&mut self) -> Result<String> {
+        timeout(RESPONSE_TIMEOUT, self.read_response())
+            .await
+            .error("Timed out waiting for the server")?
+    }
+
+    /// Like `read_line`, but without a timeout. This is cancel safe, the partially read response
+    /// is kept until the next call.
+    async fn read_response(&mut self) -> Result<String> {
+        loop {
+            if self.literal > 0 {
+                let available = self
+                    .reader
+                    .fill_buf()
+                    .await
+                    .error("Failed to read from the server")?;
+                if available.is_empty() {
+                    return Err(Error::new("The server closed the connection"));
+                }
+                let len = available.len().min(self.literal);
+                self.buf.extend_from_slice(&available[..len]);
+                self.reader.consume(len);
+                self.literal -= len;
+                continue;
+            }
+            self.reader
+                .read_until(b'\n', &mut self.buf)
+                .await
+                .error("Failed to read from the server")?;
+            if !self.buf.ends_with(b"\n") {
+                return Err(Error::new("The server closed the connection"));
+            }
+            match literal_len(&self.buf) {
+                Some(len) => self.literal = len,
+                None => {
+                    let line = String::from_utf8_lossy(&self.buf).trim_end().to_string();
+                    self.buf.clear();
+                    return Ok(line);
+                }
+            }
+        }
+    }
+
+    async fn write(&mut self, line: &str) -> Result<()> {
+        self.writer
+            .write_all(format!("{line}\r\n").as_bytes())
+            .await
+            .error("Failed to write to the server")
+    }
+
+    /// Send a command and return the untagged responses to it
+    async fn command(&mut self, command: &str) -> Result<Vec<String>> {
+        self.next_tag += 1;
+        let tag = format!("a{}", self.next_tag);
+        self.write(&format!("{tag} {command}")).await?;
+        let mut responses = Vec::new();
+        loop {
+            let line = self.read_line().await?;
+            match line.strip_prefix(&tag) {
+                Some(result) if result.starts_with(" OK") => return Ok(responses),
+                Some(result) => {
+                    let command = command.split(' ').next().unwrap_or_default();
+                    return Err(Error::new(format!("{command} failed:{result}")));
+                }
+                None => responses.push(line),
+            }
+        }
+    }
+
+    /// The number of unseen messages in each folder, of which the first is the examined one
+    async fn unseen(&mut self, folders: &[String]) -> Result<Vec<usize>> {
+        let mut counts = Vec::with_capacity(folders.len());
+        // STATUS must not be used for the selected folder
+        let found = self.command("SEARCH UNSEEN").await?;
+        counts.push(
+            found
+                .iter()
+                .filter_map(|line| line.strip_prefix("* SEARCH"))
+                .map(|ids| ids.split_whitespace().count())
+                .sum(),
+        );
+        for folder in &folders[1..] {
+            let status = self
+                .command(&format!(
+                    "STATUS {} (UNSEEN)",
+                    quote(&encode_folder(folder))
+                ))
+                .await?;
+            let count = status
+                .iter()
+                .find_map(|line| {
+                    let (_, rest) = line.split_once("UNSEEN ")?;
+                    rest.split(|c: char| !c.is_ascii_digit())
+                        .next()?
+                        .parse()
+                        .ok()
+                })
+                .or_error(|| format!("Failed to get the status of {folder}"))?;
+            counts.push(count);
+        }
+        Ok(counts)
+    }
+
+    /// Wait until the examined folder changes, the timer ticks, an update is requested or IDLE has
+    /// to be restarted
+    async fn idle(&mut self, timer: &mut tokio::time::Interval, api: &mut CommonApi) -> Result<()> {
+        self.next_tag += 1;
+        let tag = format!("a{}", self.next_tag);
+        self.write(&format!("{tag} IDLE")).await?;
+        let line = self.read_line().await?;
+        if !line.starts_with('+') {
+            return Err(Error::new(format!("IDLE failed: {line}")));
+        }
+
+        select! {
+            res = self.wait_for_change() => res?,
+            _ = timer.tick() => (),
+            _ = api.wait_for_update_request() => (),
+            _ = sleep(IDLE_TIMEOUT) => (),
+        }
+
+        self.write("DONE").await?;
+        loop {
+            let line = self.read_line().await?;
+            if line.starts_with(&tag) {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn wait_for_change(&mut self) -> Result<()> {
+        loop {
+            let line = self.read_response().await?;
+            if ["EXISTS", "EXPUNGE", "FETCH", "RECENT"]
+                .iter()
+                .any(|event| line.ends_with(event) || line.contains(&format!(" {event} ")))
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_folder() {
+        assert_eq!(encode_folder("INBOX"), "INBOX");
+        assert_eq!(encode_folder("Lists & Rust"), "Lists &- Rust");
+        assert_eq!(encode_folder("Entwürfe"), "Entw&APw-rfe");
+        assert_eq!(
+            encode_folder("~peter/mail/台北/日本語"),
+            "~peter/mail/&U,BTFw-/&ZeVnLIqe-"
+        );
+    }
+
+    #[test]
+    fn test_literal_len() {
+        assert_eq!(literal_len(b"* STATUS {10}\r\n"), Some(10));
+        assert_eq!(literal_len(b"* STATUS \"INBOX\" (UNSEEN 3)\r\n"), None);
+        assert_eq!(literal_len(b"* 3 EXISTS\r\n"), None);
+    }
+}
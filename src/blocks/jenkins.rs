@@ -0,0 +1,286 @@
+//! Jenkins job monitor
+//!
+//! Polls one or more Jenkins jobs through the JSON API and shows the result of their last build
+//! and whether a build is in progress. The block is in the critical state if the last build of any
+//! job failed, and in the warning state if it was unstable.
+//!
+//! Authentication uses a user name and an API token, which can also be passed using the
+//! `I3RS_JENKINS_TOKEN` environment variable. Without them, only anonymously readable jobs work.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `server` | The base URL of the Jenkins server, e.g. `"https://ci.example.org"` | **Required**
+//! `jobs` | The jobs to watch, each with a `name` and an `id`. Jobs in folders are named like `"folder/job"`. The `id` is used in the names of the per-job placeholders. | **Required**
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $result{ $building building\|} "`
+//! `interval` | Update interval in seconds | `60`
+//! `user` | The user to authenticate as | `None`
+//! `token` | The API token of `user` | `None`
+//!
+//! Placeholder     | Value                                                            | Type   | Unit
+//! ----------------|------------------------------------------------------------------|--------|-----
+//! `icon`          | A static icon                                                    | Icon   | -
+//! `job`           | The name of the selected job, which `build` triggers             | Text   | -
+//! `result`        | The worst result of the last completed builds, e.g. `"SUCCESS"`  | Text   | -
+//! `failed`        | The number of jobs whose last completed build failed             | Number | -
+//! `building`      | The number of jobs which are being built. Missing if none is.    | Number | -
+//! `<id>_result`   | The result of the last completed build of the job with this `id` | Text   | -
+//! `<id>_building` | Present if the job with this `id` is being built                 | Flag   | -
+//!
+//! Action     | Description                         | Default button
+//! -----------|-------------------------------------|---------------
+//! `build`    | Trigger a build of the selected job | Right
+//! `next_job` | Select the next job                 | Wheel Up
+//! `prev_job` | Select the previous job             | Wheel Down
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "jenkins"
+//! server = "https://ci.example.org"
+//! user = "alice"
+//! format = " $icon app: $app_result{ (building)|} lib: $lib_result "
+//! [[block.jobs]]
+//! name = "app/main"
+//! id = "app"
+//! [[block.jobs]]
+//! name = "lib"
+//! id = "lib"
+//! ```
+//!
+//! # Icons Used
+//! - `cogs`
+
+use super::prelude::*;
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    server: String,
+    jobs: Vec<Job>,
+    format: FormatConfig,
+    #[default(60.into())]
+    interval: Seconds,
+    user: Option<String>,
+    token: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Job {
+    name: String,
+    id: String,
+}
+
+impl Job {
+    /// The URL of the job, e.g. `<server>/job/folder/job/name`
+    fn url(&self, server: &str) -> String {
+        let mut url = server.trim_end_matches('/').to_string();
+        for part in self.name.split('/') {
+            url.push_str("/job/");
+            url.push_str(part);
+        }
+        url
+    }
+}
+
+struct Auth {
+    user: String,
+    token: String,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[
+        (MouseButton::Right, None, "build"),
+        (MouseButton::WheelUp, None, "next_job"),
+        (MouseButton::WheelDown, None, "prev_job"),
+    ])
+    .await?;
+
+    if config.server.is_empty() {
+        return Err(Error::new("`server` must be set"));
+    }
+    if config.jobs.is_empty() {
+        return Err(Error::new("`jobs` must not be empty"));
+    }
+
+    let mut widget = Widget::new().with_format(
+        config
+            .format
+            .with_default(" $icon $result{ $building building|} ")?,
+    );
+    let token = config
+        .token
+        .clone()
+        .or_else(|| std::env::var("I3RS_JENKINS_TOKEN").ok());
+    let auth = match (config.user.clone(), token) {
+        (Some(user), Some(token)) => Some(Auth { user, token }),
+        _ => None,
+    };
+    let mut timer = config.interval.timer();
+    let mut selected = 0;
+
+    loop {
+        let mut values = map! {
+            "icon" => Value::icon(api.get_icon("cogs")?),
+        };
+        let mut worst = BuildResult::Success;
+        let mut failed = 0;
+        let mut building = 0;
+        for job in &config.jobs {
+            let url = job.url(&config.server);
+            let status = api.recoverable(|| job_status(&url, auth.as_ref())).await?;
+            if let Some(result) = status.result {
+                worst = worst.max(result);
+                if result == BuildResult::Failure {
+                    failed += 1;
+                }
+                values.insert(
+                    format!("{}_result", job.id).into(),
+                    Value::text(result.as_str().into()),
+                );
+            }
+            if status.building {
+                building += 1;
+                values.insert(format!("{}_building", job.id).into(), Value::flag());
+            }
+        }
+        values.insert("result".into(), Value::text(worst.as_str().into()));
+        values.insert("failed".into(), Value::number(failed));
+        if building > 0 {
+            values.insert("building".into(), Value::number(building));
+        }
+
+        widget.state = match worst {
+            BuildResult::Failure => State::Critical,
+            BuildResult::Unstable => State::Warning,
+            _ if building > 0 => State::Info,
+            _ => State::Idle,
+        };
+
+        loop {
+            values.insert(
+                "job".into(),
+                Value::text(config.jobs[selected].name.clone()),
+            );
+            widget.set_values(values.clone());
+            api.set_widget(&widget).await?;
+
+            select! {
+                _ = timer.tick() => break,
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "build" => {
+                        let url = config.jobs[selected].url(&config.server);
+                        if let Err(e) = trigger_build(&url, auth.as_ref()).await {
+                            api.set_error(e).await?;
+                            select! {
+                                _ = sleep(api.error_interval) => (),
+                                _ = api.wait_for_update_request() => (),
+                            }
+                        }
+                        break;
+                    }
+                    Action(a) if a == "next_job" => selected = (selected + 1) % config.jobs.len(),
+                    Action(a) if a == "prev_job" => {
+                        selected = (selected + config.jobs.len() - 1) % config.jobs.len();
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+/// Ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum BuildResult {
+    Success,
+    NotBuilt,
+    Aborted,
+    Unstable,
+    Failure,
+}
+
+impl BuildResult {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Success => "SUCCESS",
+            Self::NotBuilt => "NOT_BUILT",
+            Self::Aborted => "ABORTED",
+            Self::Unstable => "UNSTABLE",
+            Self::Failure => "FAILURE",
+        }
+    }
+}
+
+struct JobStatus {
+    /// The result of the last completed build
+    result: Option<BuildResult>,
+    building: bool,
+}
+
+async fn job_status(url: &str, auth: Option<&Auth>) -> Result<JobStatus> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ApiResponse {
+        last_build: Option<LastBuild>,
+        last_completed_build: Option<LastCompletedBuild>,
+    }
+    #[derive(Deserialize)]
+    struct LastBuild {
+        building: bool,
+    }
+    #[derive(Deserialize)]
+    struct LastCompletedBuild {
+        result: Option<String>,
+    }
+
+    let mut request = REQWEST_CLIENT
+        .get(format!("{url}/api/json"))
+        .query(&[("tree", "lastBuild[building],lastCompletedBuild[result]")]);
+    if let Some(auth) = auth {
+        request = request.basic_auth(&auth.user, Some(&auth.token));
+    }
+    let response: ApiResponse = request
+        .send()
+        .await
+        .error("Failed to send request")?
+        .error_for_status()
+        .or_error(|| format!("Failed to get the status of {url}"))?
+        .json()
+        .await
+        .error("Failed to get JSON")?;
+
+    let result = response
+        .last_completed_build
+        .and_then(|b| b.result)
+        .map(|r| match r.as_str() {
+            "SUCCESS" => BuildResult::Success,
+            "UNSTABLE" => BuildResult::Unstable,
+            "FAILURE" => BuildResult::Failure,
+            "ABORTED" => BuildResult::Aborted,
+            _ => BuildResult::NotBuilt,
+        });
+    Ok(JobStatus {
+        result,
+        building: response.last_build.map_or(false, |b| b.building),
+    })
+}
+
+async fn trigger_build(url: &str, auth: Option<&Auth>) -> Result<()> {
+    let mut request = REQWEST_CLIENT.post(format!("{url}/build"));
+    // Requests authenticated with an API token don't need a CSRF crumb
+    if let Some(auth) = auth {
+        request = request.basic_auth(&auth.user, Some(&auth.token));
+    }
+    request
+        .send()
+        .await
+        .error("Failed to send request")?
+        .error_for_status()
+        .or_error(|| format!("Failed to trigger a build of {url}"))?;
+    Ok(())
+}
@@ -5,6 +5,9 @@
 //! Block colours are updated based on the battery level, unless all bat_* thresholds are set to 0,
 //! in which case the block colours will depend on the notification count instead.
 //!
+//! The media placeholders need the "Multimedia control receiver" plugin, `ring` needs "Ring my
+//! phone" and `send_clipboard` needs "Clipboard" to be enabled for the device.
+//!
 //! # Configuration
 //!
 //! Key | Values | Default
@@ -16,18 +19,30 @@
 //! `bat_warning` | Min battery level below which state is set to warning. | `30`
 //! `bat_critical` | Min battery level below which state is set to critical. | `15`
 //! `hide_disconnected` | Whether to hide this block when disconnected | `true`
+//! `bat_icon_levels` | Icons to use for ranges of battery level, see [`IconLevels`](crate::icons::IconLevels). `bat_charging` is always used while charging. | `bat_10` to `bat_90` in 10% steps, `bat_full` above
+//!
+//! Placeholder     | Value                                                                    | Type   | Unit
+//! ----------------|--------------------------------------------------------------------------|--------|-----
+//! `icon`          | Icon based on connection's status                                        | Icon   | -
+//! `bat_icon`      | Battery level indicator (only when connected and if supported)           | Icon   | -
+//! `bat_charge`    | Battery charge level (only when connected and if supported)              | Number | %
+//! `notif_icon`    | Only when connected and there are notifications                          | Icon   | -
+//! `notif_count`   | Number of notifications on your phone (only when connected and non-zero) | Number | -
+//! `name`          | Name of your device as reported by KDEConnect (if available)             | Text   | -
+//! `connected`     | Present if your device is connected                                      | Flag   | -
+//! `media_icon`    | Only when connected and media is playing on your device                  | Icon   | -
+//! `media_title`   | Title of the media playing on your device (only when connected)          | Text   | -
+//! `media_artist`  | Artist of the media playing on your device (only when connected)         | Text   | -
+//! `media_player`  | The player on your device (only when connected)                          | Text   | -
+//! `media_playing` | Present if media is playing on your device                               | Flag   | -
 //!
-//! Placeholder   | Value                                                                    | Type   | Unit
-//! --------------|--------------------------------------------------------------------------|--------|-----
-//! `icon`        | Icon based on connection's status                                        | Icon   | -
-//! `bat_icon`    | Battery level indicator (only when connected and if supported)           | Icon   | -
-//! `bat_charge`  | Battery charge level (only when connected and if supported)              | Number | %
-//! `notif_icon`  | Only when connected and there are notifications                          | Icon   | -
-//! `notif_count` | Number of notifications on your phone (only when connected and non-zero) | Number | -
-//! `name`        | Name of your device as reported by KDEConnect (if available)             | Text   | -
-//! `connected`   | Present if your device is connected                                      | Flag   | -
+//! Action           | Description                                       | Default button
+//! -----------------|---------------------------------------------------|---------------
+//! `ring`           | Make your device ring to find it                  | Right
+//! `send_clipboard` | Send the contents of the clipboard to your device | Middle
+//! `play_pause`     | Play or pause the media on your device            | -
 //!
-//! # Example
+//! # Examples
 //!
 //! Do not show the name, do not set the "good" state.
 //!
@@ -38,6 +53,14 @@
 //! bat_good = 101
 //! ```
 //!
+//! Show the media playing on the phone.
+//!
+//! ```toml
+//! [[block]]
+//! block = "kdeconnect"
+//! format = " $icon $name{ $media_icon $media_title.str(max_w:20)|} "
+//! ```
+//!
 //! # Icons Used
 //! - `bat_charging`,
 //! - `bat_10`,
@@ -50,6 +73,7 @@
 //! - `bat_80`,
 //! - `bat_90`,
 //! - `bat_full`,
+//! - `music`
 //! - `notification`
 //! - `phone`
 //! - `phone_disconnected`
@@ -58,7 +82,7 @@ use tokio::sync::mpsc;
 use zbus::dbus_proxy;
 
 use super::prelude::*;
-use crate::util::battery_level_icon;
+use crate::icons::IconLevels;
 
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(default)]
@@ -75,9 +99,17 @@ pub struct Config {
     bat_critical: u8,
     #[default(true)]
     hide_disconnected: bool,
+    #[default(IconLevels::battery())]
+    bat_icon_levels: IconLevels,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[
+        (MouseButton::Right, None, "ring"),
+        (MouseButton::Middle, None, "send_clipboard"),
+    ])
+    .await?;
+
     let mut widget = Widget::new().with_format(
         config
             .format
@@ -121,7 +153,11 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                     values.insert("bat_charge".into(), Value::percents(level));
                     values.insert(
                         "bat_icon".into(),
-                        Value::icon(api.get_icon(battery_level_icon(level, charging))?),
+                        Value::icon(api.get_icon(if charging {
+                            "bat_charging"
+                        } else {
+                            config.bat_icon_levels.get(level.into())
+                        })?),
                     );
                     if battery_state {
                         widget.state = if charging {
@@ -153,6 +189,21 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                         State::Info
                     };
                 }
+
+                let media = device.media().await;
+                if let Some(title) = media.title {
+                    values.insert("media_title".into(), Value::text(title));
+                }
+                if let Some(artist) = media.artist {
+                    values.insert("media_artist".into(), Value::text(artist));
+                }
+                if let Some(player) = media.player {
+                    values.insert("media_player".into(), Value::text(player));
+                }
+                if media.playing {
+                    values.insert("media_icon".into(), Value::icon(api.get_icon("music")?));
+                    values.insert("media_playing".into(), Value::flag());
+                }
             } else {
                 values.insert(
                     "icon".into(),
@@ -169,7 +220,23 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         loop {
             select! {
                 _ = rx.recv() => break,
-                _ = api.event() => (),
+                event = api.event() => {
+                    let result = match event {
+                        Action(a) if a == "ring" => device.ring().await,
+                        Action(a) if a == "send_clipboard" => device.send_clipboard().await,
+                        Action(a) if a == "play_pause" => device.play_pause().await,
+                        _ => Ok(()),
+                    };
+                    // E.g. the device went away or doesn't have the plugin enabled
+                    if let Err(e) = result {
+                        api.set_error(e).await?;
+                        select! {
+                            _ = sleep(api.error_interval) => (),
+                            _ = api.wait_for_update_request() => (),
+                        }
+                        break;
+                    }
+                }
             }
         }
     }
@@ -179,6 +246,17 @@ struct Device {
     device_proxy: DeviceDbusProxy<'static>,
     battery_proxy: BatteryDbusProxy<'static>,
     notifications_proxy: NotificationsDbusProxy<'static>,
+    mpris_proxy: MprisRemoteDbusProxy<'static>,
+    find_my_phone_proxy: FindMyPhoneDbusProxy<'static>,
+    clipboard_proxy: ClipboardDbusProxy<'static>,
+}
+
+#[derive(Debug, Default)]
+struct Media {
+    title: Option<String>,
+    artist: Option<String>,
+    player: Option<String>,
+    playing: bool,
 }
 
 impl Device {
@@ -186,6 +264,9 @@ impl Device {
         let device_path = format!("/modules/kdeconnect/devices/{id}");
         let battery_path = format!("{device_path}/battery");
         let notifications_path = format!("{device_path}/notifications");
+        let mpris_path = format!("{device_path}/mprisremote");
+        let find_my_phone_path = format!("{device_path}/findmyphone");
+        let clipboard_path = format!("{device_path}/clipboard");
 
         let device_proxy = DeviceDbusProxy::builder(conn)
             .cache_properties(zbus::CacheProperties::No)
@@ -208,6 +289,25 @@ impl Device {
             .build()
             .await
             .error("Failed to create BatteryDbusProxy")?;
+        let mpris_proxy = MprisRemoteDbusProxy::builder(conn)
+            .cache_properties(zbus::CacheProperties::No)
+            .path(mpris_path)
+            .error("Failed to set mprisremote path")?
+            .build()
+            .await
+            .error("Failed to create MprisRemoteDbusProxy")?;
+        let find_my_phone_proxy = FindMyPhoneDbusProxy::builder(conn)
+            .path(find_my_phone_path)
+            .error("Failed to set findmyphone path")?
+            .build()
+            .await
+            .error("Failed to create FindMyPhoneDbusProxy")?;
+        let clipboard_proxy = ClipboardDbusProxy::builder(conn)
+            .path(clipboard_path)
+            .error("Failed to set clipboard path")?
+            .build()
+            .await
+            .error("Failed to create ClipboardDbusProxy")?;
 
         let mut s1 = device_proxy
             .receive_all_signals()
@@ -221,6 +321,10 @@ impl Device {
             .receive_all_signals()
             .await
             .error("Failed to receive signals")?;
+        let mut s4 = mpris_proxy
+            .receive_all_signals()
+            .await
+            .error("Failed to receive signals")?;
 
         tokio::spawn(async move {
             loop {
@@ -228,6 +332,7 @@ impl Device {
                     _ = s1.next() => tx.send(()).await.unwrap(),
                     _ = s2.next() => tx.send(()).await.unwrap(),
                     _ = s3.next() => tx.send(()).await.unwrap(),
+                    _ = s4.next() => tx.send(()).await.unwrap(),
                 }
             }
         });
@@ -236,6 +341,9 @@ impl Device {
             device_proxy,
             battery_proxy,
             notifications_proxy,
+            mpris_proxy,
+            find_my_phone_proxy,
+            clipboard_proxy,
         })
     }
 
@@ -265,6 +373,37 @@ impl Device {
             .error("Failed to read notifications")
             .map(|n| n.len())
     }
+
+    async fn media(&self) -> Media {
+        let non_empty = |s: zbus::Result<String>| s.ok().filter(|s| !s.is_empty());
+        Media {
+            title: non_empty(self.mpris_proxy.title().await),
+            artist: non_empty(self.mpris_proxy.artist().await),
+            player: non_empty(self.mpris_proxy.player().await),
+            playing: self.mpris_proxy.is_playing().await.unwrap_or(false),
+        }
+    }
+
+    async fn ring(&self) -> Result<()> {
+        self.find_my_phone_proxy
+            .ring()
+            .await
+            .error("Failed to ring the device")
+    }
+
+    async fn send_clipboard(&self) -> Result<()> {
+        self.clipboard_proxy
+            .send_clipboard()
+            .await
+            .error("Failed to send the clipboard")
+    }
+
+    async fn play_pause(&self) -> Result<()> {
+        self.mpris_proxy
+            .send_action("PlayPause")
+            .await
+            .error("Failed to play or pause")
+    }
 }
 
 async fn any_device_id(conn: &zbus::Connection) -> Result<String> {
@@ -339,3 +478,45 @@ trait NotificationsDbus {
     #[dbus_proxy(signal, name = "notificationRemoved")]
     fn notification_removed(&self, id: &str) -> zbus::Result<()>;
 }
+
+#[dbus_proxy(
+    interface = "org.kde.kdeconnect.device.mprisremote",
+    default_service = "org.kde.kdeconnect"
+)]
+trait MprisRemoteDbus {
+    #[dbus_proxy(name = "sendAction")]
+    fn send_action(&self, action: &str) -> zbus::Result<()>;
+
+    #[dbus_proxy(property, name = "title")]
+    fn title(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(property, name = "artist")]
+    fn artist(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(property, name = "player")]
+    fn player(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(property, name = "isPlaying")]
+    fn is_playing(&self) -> zbus::Result<bool>;
+
+    #[dbus_proxy(signal, name = "propertiesChanged")]
+    fn properties_changed_(&self) -> zbus::Result<()>;
+}
+
+#[dbus_proxy(
+    interface = "org.kde.kdeconnect.device.findmyphone",
+    default_service = "org.kde.kdeconnect"
+)]
+trait FindMyPhoneDbus {
+    #[dbus_proxy(name = "ring")]
+    fn ring(&self) -> zbus::Result<()>;
+}
+
+#[dbus_proxy(
+    interface = "org.kde.kdeconnect.device.clipboard",
+    default_service = "org.kde.kdeconnect"
+)]
+trait ClipboardDbus {
+    #[dbus_proxy(name = "sendClipboard")]
+    fn send_clipboard(&self) -> zbus::Result<()>;
+}
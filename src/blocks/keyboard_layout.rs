@@ -1,10 +1,11 @@
 //! Keyboard layout indicator
 //!
-//! Four drivers are available:
+//! Five drivers are available:
 //! - `setxkbmap` which polls setxkbmap to get the current layout
 //! - `localebus` which can read asynchronous updates from the systemd `org.freedesktop.locale1` D-Bus path
 //! - `kbddbus` which uses [kbdd](https://github.com/qnikst/kbdd) to monitor per-window layout changes via DBus
 //! - `sway` which can read asynchronous updates from the sway IPC
+//! - `fcitx5` which polls the current input method of [fcitx5](https://github.com/fcitx/fcitx5) via DBus
 //!
 //! Which of these methods is appropriate will depend on your system setup.
 //!
@@ -12,8 +13,8 @@
 //!
 //! Key | Values | Default
 //! ----|--------|--------
-//! `driver` | One of `"setxkbmap"`, `"localebus"`, `"kbddbus"` or `"sway"`, depending on your system. | `"setxkbmap"`
-//! `interval` | Update interval, in seconds. Only used by the `"setxkbmap"` driver. | `60`
+//! `driver` | One of `"setxkbmap"`, `"localebus"`, `"kbddbus"`, `"sway"` or `"fcitx5"`, depending on your system. | `"setxkbmap"`
+//! `interval` | Update interval, in seconds. Only used by the `"setxkbmap"` and `"fcitx5"` drivers. | `60`
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $layout "`
 //! `sway_kb_identifier` | Identifier of the device you want to monitor, as found in the output of `swaymsg -t get_inputs`. | Defaults to first input found
 //! `mappings` | Map `layout (variant)` to custom short name. | `None`
@@ -22,6 +23,15 @@
 //! ---------|-------|-----
 //! `layout` | Keyboard layout name | String
 //! `variant`| Keyboard variant. Only `localebus`, `sway` and `kbddbus` are supported so far. | String
+//! `layout_short` | Short XKB name of the layout, e.g. `us`. Looked up in `/usr/share/X11/xkb/rules/evdev.lst`, falls back to `layout`. | String
+//! `variant_short` | Short XKB name of the variant, e.g. `dvorak`. Absent if there is no variant. | String
+//! `inactive` | Present if the input method is switched off (`fcitx5` only). | Flag
+//!
+//! With `fcitx5`, `layout` is the name of the current input method, e.g. `keyboard-us` or `mozc`.
+//!
+//! Action        | Description                                              | Default button
+//! --------------|----------------------------------------------------------|---------------
+//! `next_layout` | Switch the monitored device to its next layout (`sway`), or toggle the input method (`fcitx5`). Not supported by other drivers. | Left (`sway` and `fcitx5` only)
 //!
 //! # Examples
 //!
@@ -68,6 +78,28 @@
 //! sway_kb_identifier = "1133:49706:Gaming_Keyboard_G110"
 //! ```
 //!
+//! Show the short name of the layout of a specific keyboard and switch layouts on click:
+//!
+//! ```toml
+//! [[block]]
+//! block = "keyboard_layout"
+//! driver = "sway"
+//! format = " $layout_short{ $variant_short|} "
+//! sway_kb_identifier = "1133:49706:Gaming_Keyboard_G110"
+//! ```
+//!
+//! Show the fcitx5 input method, dimmed while it is switched off:
+//!
+//! ```toml
+//! [[block]]
+//! block = "keyboard_layout"
+//! driver = "fcitx5"
+//! interval = 1
+//! format = " {$inactive{EN}|$layout} "
+//! [block.mappings]
+//! "mozc (N/A)" = "JP"
+//! ```
+//!
 //! Listen to sway for changes and override mappings:
 //! ```toml
 //! [[block]]
@@ -80,10 +112,14 @@
 //! ```
 
 use super::prelude::*;
+use crate::util::read_file;
 use swayipc_async::{Connection, Event, EventType};
 use tokio::process::Command;
 use zbus::dbus_proxy;
 
+/// Lists the XKB layouts and variants with their descriptions
+const XKB_RULES_PATH: &str = "/usr/share/X11/xkb/rules/evdev.lst";
+
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(default)]
 pub struct Config {
@@ -103,24 +139,53 @@ enum KeyboardLayoutDriver {
     LocaleBus,
     KbddBus,
     Sway,
+    Fcitx5,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
-    let mut widget = Widget::new().with_format(config.format.with_default(" $layout ")?);
+    if matches!(
+        config.driver,
+        KeyboardLayoutDriver::Sway | KeyboardLayoutDriver::Fcitx5
+    ) {
+        api.set_default_actions(&[(MouseButton::Left, None, "next_layout")])
+            .await?;
+    }
+
+    let format = config.format.with_default(" $layout ")?;
+    let xkb_names = if format.contains_key("layout_short") || format.contains_key("variant_short") {
+        read_file(XKB_RULES_PATH)
+            .await
+            .map(|rules| parse_xkb_rules(&rules))
+            .unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
+    let mut widget = Widget::new().with_format(format);
 
     let mut backend: Box<dyn Backend> = match config.driver {
         KeyboardLayoutDriver::SetXkbMap => Box::new(SetXkbMap(config.interval)),
         KeyboardLayoutDriver::LocaleBus => Box::new(LocaleBus::new().await?),
         KeyboardLayoutDriver::KbddBus => Box::new(KbddBus::new().await?),
         KeyboardLayoutDriver::Sway => Box::new(Sway::new(config.sway_kb_identifier).await?),
+        KeyboardLayoutDriver::Fcitx5 => Box::new(Fcitx5::new(config.interval).await?),
     };
 
     loop {
         let Info {
             mut layout,
             variant,
+            inactive,
         } = backend.get_info().await?;
 
+        let full_name = match &variant {
+            Some(variant) => format!("{layout} ({variant})"),
+            None => layout.clone(),
+        };
+        let (layout_short, variant_short) = match xkb_names.get(&full_name) {
+            Some((layout, variant)) => (layout.clone(), variant.clone()),
+            None => (layout.clone(), None),
+        };
+
         let variant = variant.unwrap_or_else(|| "N/A".into());
         if let Some(mappings) = &config.mappings {
             if let Some(mapped) = mappings.get(&format!("{layout} ({variant})")) {
@@ -131,26 +196,54 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         widget.set_values(map! {
             "layout" => Value::text(layout),
             "variant" => Value::text(variant),
+            "layout_short" => Value::text(layout_short),
+            [if let Some(v) = variant_short] "variant_short" => Value::text(v),
+            [if inactive] "inactive" => Value::flag(),
         });
         api.set_widget(&widget).await?;
 
-        select! {
-            update = backend.wait_for_change() => update?,
-            _ = api.wait_for_update_request() => (),
+        loop {
+            select! {
+                update = backend.wait_for_change() => {
+                    update?;
+                    break;
+                }
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "next_layout" => {
+                        if let Err(e) = backend.next_layout().await {
+                            api.set_error(e).await?;
+                            select! {
+                                _ = sleep(api.error_interval) => (),
+                                _ = api.wait_for_update_request() => (),
+                            }
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+            }
         }
     }
 }
 
 #[async_trait]
-trait Backend {
+trait Backend: Send {
     async fn get_info(&mut self) -> Result<Info>;
     async fn wait_for_change(&mut self) -> Result<()>;
+
+    /// Switch to the next layout. Only supported by the `sway` and `fcitx5` drivers.
+    async fn next_layout(&mut self) -> Result<()> {
+        Err(Error::new("This driver does not support switching layouts"))
+    }
 }
 
 #[derive(Clone)]
 struct Info {
     layout: String,
     variant: Option<String>,
+    /// Whether the input method is switched off
+    inactive: bool,
 }
 
 struct SetXkbMap(Seconds);
@@ -176,6 +269,7 @@ impl Backend for SetXkbMap {
         Ok(Info {
             layout: layout.into(),
             variant: None,
+            inactive: false,
         })
     }
 
@@ -216,6 +310,7 @@ impl Backend for LocaleBus {
         Ok(Info {
             layout,
             variant: Some(variant),
+            inactive: false,
         })
     }
 
@@ -296,6 +391,101 @@ impl Backend for Sway {
             }
         }
     }
+
+    async fn next_layout(&mut self) -> Result<()> {
+        let input = match &self.kbd {
+            Some(id) => format!("\"{id}\""),
+            None => "type:keyboard".into(),
+        };
+        let status = Command::new("swaymsg")
+            .args(["input", &input, "xkb_switch_layout", "next"])
+            .status()
+            .await
+            .error("Failed to execute swaymsg")?;
+        if !status.success() {
+            return Err(Error::new("swaymsg failed to switch the layout"));
+        }
+        Ok(())
+    }
+}
+
+struct Fcitx5 {
+    proxy: Fcitx5ControllerProxy<'static>,
+    interval: Seconds,
+}
+
+impl Fcitx5 {
+    async fn new(interval: Seconds) -> Result<Self> {
+        let conn = new_dbus_connection().await?;
+        let proxy = Fcitx5ControllerProxy::new(&conn)
+            .await
+            .error("Failed to create Fcitx5ControllerProxy")?;
+        Ok(Self { proxy, interval })
+    }
+}
+
+#[async_trait]
+impl Backend for Fcitx5 {
+    async fn get_info(&mut self) -> Result<Info> {
+        let layout = self
+            .proxy
+            .current_input_method()
+            .await
+            .error("Failed to get the current input method")?;
+        // 1 means inactive, 2 means active
+        let state = self
+            .proxy
+            .state()
+            .await
+            .error("Failed to get the input method state")?;
+        Ok(Info {
+            layout,
+            variant: None,
+            inactive: state != 2,
+        })
+    }
+
+    async fn wait_for_change(&mut self) -> Result<()> {
+        // fcitx5 has no signal for input method changes
+        sleep(self.interval.0).await;
+        Ok(())
+    }
+
+    async fn next_layout(&mut self) -> Result<()> {
+        self.proxy
+            .toggle()
+            .await
+            .error("Failed to toggle the input method")
+    }
+}
+
+/// Map layout descriptions (e.g. "English (Dvorak)") to the short layout and variant names (e.g.
+/// "us" and "dvorak") using the `evdev.lst` file
+fn parse_xkb_rules(rules: &str) -> HashMap<String, (String, Option<String>)> {
+    let mut names = HashMap::new();
+    let mut section = "";
+    for line in rules.lines() {
+        if let Some(name) = line.strip_prefix("! ") {
+            section = name.trim();
+            continue;
+        }
+        let (name, description) = match line.trim().split_once(char::is_whitespace) {
+            Some((name, description)) => (name, description.trim_start()),
+            None => continue,
+        };
+        match section {
+            "layout" => {
+                names.insert(description.into(), (name.into(), None));
+            }
+            "variant" => {
+                if let Some((layout, description)) = description.split_once(": ") {
+                    names.insert(description.into(), (layout.into(), Some(name.into())));
+                }
+            }
+            _ => (),
+        }
+    }
+    names
 }
 
 fn parse_layout(layout: &str) -> Info {
@@ -303,11 +493,13 @@ fn parse_layout(layout: &str) -> Info {
         Info {
             layout: layout[..i].trim_end().into(),
             variant: Some(layout[(i + 1)..].trim_end_matches(')').into()),
+            inactive: false,
         }
     } else {
         Info {
             layout: layout.into(),
             variant: None,
+            inactive: false,
         }
     }
 }
@@ -390,3 +582,43 @@ impl Backend for KbddBus {
         Ok(())
     }
 }
+
+#[dbus_proxy(
+    interface = "org.fcitx.Fcitx.Controller1",
+    default_service = "org.fcitx.Fcitx5",
+    default_path = "/controller"
+)]
+trait Fcitx5Controller {
+    fn current_input_method(&self) -> zbus::Result<String>;
+    fn state(&self) -> zbus::Result<i32>;
+    fn toggle(&self) -> zbus::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xkb_rules() {
+        let rules = "\
+! model
+  pc105           Generic 105-key PC
+
+! layout
+  us              English (US)
+  fi              Finnish
+
+! variant
+  dvorak          us: English (Dvorak)
+  classic         fi: Finnish (classic)
+";
+        let names = parse_xkb_rules(rules);
+        assert_eq!(names["English (US)"], ("us".into(), None));
+        assert_eq!(names["Finnish"], ("fi".into(), None));
+        assert_eq!(
+            names["English (Dvorak)"],
+            ("us".into(), Some("dvorak".into()))
+        );
+        assert!(!names.contains_key("Generic 105-key PC"));
+    }
+}
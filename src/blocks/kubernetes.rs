@@ -0,0 +1,216 @@
+//! Current Kubernetes context and workload health
+//!
+//! Shows the current context and namespace of `kubectl` and, if `selector` is set, the number of
+//! matching pods which are not ready. Clicking the block switches to the next context.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $context{/$namespace\|}{ $not_ready\|} "`
+//! `interval` | Update interval in seconds | `30`
+//! `selector` | A label selector for the pods to watch, e.g. `"app=web"`. Use `""` for all pods. | `None`
+//! `namespace` | The namespace to watch the pods in, instead of the context's one | `None`
+//!
+//! Placeholder | Value                                                      | Type   | Unit
+//! ------------|------------------------------------------------------------|--------|-----
+//! `icon`      | A static icon                                              | Icon   | -
+//! `context`   | The current context                                        | Text   | -
+//! `namespace` | The namespace of the current context, if set               | Text   | -
+//! `pods`      | The number of pods matching `selector`                     | Number | -
+//! `not_ready` | The number of pods matching `selector` which are not ready | Number | -
+//!
+//! Action         | Description                | Default button
+//! ---------------|----------------------------|---------------
+//! `next_context` | Switch to the next context | Left
+//!
+//! The block is in the warning state while any watched pod is not ready, and in the critical state
+//! while the pods can't be listed, e.g. because the cluster is unreachable.
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "kubernetes"
+//! selector = "app.kubernetes.io/part-of=shop"
+//! namespace = "shop"
+//! ```
+//!
+//! # Icons Used
+//! - `cogs`
+
+use super::prelude::*;
+use tokio::process::Command;
+
+make_log_macro!(debug, "kubernetes");
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    format: FormatConfig,
+    #[default(30.into())]
+    interval: Seconds,
+    selector: Option<String>,
+    namespace: Option<String>,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[(MouseButton::Left, None, "next_context")])
+        .await?;
+
+    let mut widget = Widget::new().with_format(
+        config
+            .format
+            .with_default(" $icon $context{/$namespace|}{ $not_ready|} ")?,
+    );
+    let mut timer = config.interval.timer();
+
+    loop {
+        let context = api
+            .recoverable(|| kubectl(&["config", "current-context"]))
+            .await?;
+        let namespace = kubectl(&["config", "view", "--minify", "-o", "jsonpath={..namespace}"])
+            .await
+            .ok()
+            .filter(|ns| !ns.is_empty());
+        // The context is still worth showing when the cluster itself can't be reached
+        let mut unreachable = false;
+        let pods = match &config.selector {
+            Some(selector) => {
+                let namespace = config.namespace.as_deref().or(namespace.as_deref());
+                match get_pods(selector, namespace).await {
+                    Ok(pods) => Some(pods),
+                    Err(e) => {
+                        debug!("failed to list pods: {e}");
+                        unreachable = true;
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+        let not_ready = pods
+            .as_ref()
+            .map(|pods| pods.iter().filter(|p| !p.is_ready()).count());
+
+        widget.state = match not_ready {
+            _ if unreachable => State::Critical,
+            Some(n) if n > 0 => State::Warning,
+            _ => State::Idle,
+        };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("cogs")?),
+            "context" => Value::text(context.clone()),
+            [if let Some(ns) = namespace] "namespace" => Value::text(ns),
+            [if let Some(pods) = &pods] "pods" => Value::number(pods.len()),
+            [if let Some(n) = not_ready] "not_ready" => Value::number(n),
+        });
+        api.set_widget(&widget).await?;
+
+        loop {
+            select! {
+                _ = timer.tick() => break,
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "next_context" => {
+                        if let Err(e) = next_context(&context).await {
+                            api.set_error(e).await?;
+                            select! {
+                                _ = sleep(api.error_interval) => (),
+                                _ = api.wait_for_update_request() => (),
+                            }
+                        }
+                        break;
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+/// Run `kubectl` and return its trimmed output
+async fn kubectl(args: &[&str]) -> Result<String> {
+    let output = Command::new("kubectl")
+        .args(args)
+        .output()
+        .await
+        .error("Failed to run kubectl")?;
+    if !output.status.success() {
+        return Err(Error::new(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    let output = String::from_utf8(output.stdout).error("kubectl produced non-UTF8 output")?;
+    Ok(output.trim().into())
+}
+
+/// Switch to the context after `current`, wrapping around at the end
+async fn next_context(current: &str) -> Result<()> {
+    let contexts = kubectl(&["config", "get-contexts", "-o", "name"]).await?;
+    let contexts: Vec<&str> = contexts.lines().collect();
+    let next = contexts
+        .iter()
+        .position(|c| *c == current)
+        .map_or(0, |i| (i + 1) % contexts.len());
+    if let Some(next) = contexts.get(next) {
+        kubectl(&["config", "use-context", next]).await?;
+    }
+    Ok(())
+}
+
+async fn get_pods(selector: &str, namespace: Option<&str>) -> Result<Vec<Pod>> {
+    #[derive(Deserialize)]
+    struct PodList {
+        items: Vec<Pod>,
+    }
+
+    // Don't hang for the default of no timeout when the cluster is unreachable
+    let mut args = vec![
+        "get",
+        "pods",
+        "--request-timeout=5s",
+        "-o",
+        "json",
+        "-l",
+        selector,
+    ];
+    if let Some(namespace) = namespace {
+        args.extend(["-n", namespace]);
+    }
+    let output = kubectl(&args).await?;
+    let list: PodList = serde_json::from_str(&output).error("Failed to parse kubectl output")?;
+    Ok(list.items)
+}
+
+#[derive(Deserialize, Debug)]
+struct Pod {
+    status: PodStatus,
+}
+
+#[derive(Deserialize, Debug)]
+struct PodStatus {
+    #[serde(default)]
+    phase: String,
+    #[serde(default)]
+    conditions: Vec<PodCondition>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PodCondition {
+    #[serde(rename = "type")]
+    type_: String,
+    status: String,
+}
+
+impl Pod {
+    /// Completed pods, e.g. of jobs, are never ready but nothing is wrong with them
+    fn is_ready(&self) -> bool {
+        self.status.phase == "Succeeded"
+            || self
+                .status
+                .conditions
+                .iter()
+                .any(|c| c.type_ == "Ready" && c.status == "True")
+    }
+}
@@ -0,0 +1,128 @@
+//! libvirt virtual machines
+//!
+//! Shows the number of running and defined virtual machines using `virsh`. If `domain` is set,
+//! its state is available as well, and clicking the block starts the domain or shuts it down.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $running/$total "`
+//! `interval` | Update interval in seconds | `10`
+//! `uri` | The libvirt connection URI | `"qemu:///system"`
+//! `domain` | The name of a domain to show the state of and to start or shut down | `None`
+//!
+//! Placeholder | Value                                                   | Type   | Unit
+//! ------------|---------------------------------------------------------|--------|-----
+//! `icon`      | A static icon                                           | Icon   | -
+//! `total`     | The number of defined domains                           | Number | -
+//! `running`   | The number of running domains                           | Number | -
+//! `stopped`   | The number of domains which are not running             | Number | -
+//! `domain`    | The value of `domain`                                   | Text   | -
+//! `state`     | The state of `domain`, e.g. `"running"` or `"shut off"` | Text   | -
+//!
+//! Action   | Description                    | Default button
+//! ---------|--------------------------------|---------------
+//! `toggle` | Start `domain` or shut it down | Left
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "libvirt"
+//! domain = "win11"
+//! format = " $icon $domain: $state "
+//! ```
+//!
+//! # Icons Used
+//! - `cogs`
+
+use super::prelude::*;
+use tokio::process::Command;
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    format: FormatConfig,
+    #[default(10.into())]
+    interval: Seconds,
+    #[default("qemu:///system".into())]
+    uri: String,
+    domain: Option<String>,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[(MouseButton::Left, None, "toggle")])
+        .await?;
+
+    let mut widget =
+        Widget::new().with_format(config.format.with_default(" $icon $running/$total ")?);
+    let mut timer = config.interval.timer();
+
+    loop {
+        let all = api
+            .recoverable(|| virsh(&config.uri, &["list", "--all", "--name"]))
+            .await?;
+        let running = virsh(&config.uri, &["list", "--name"]).await?;
+        let total = all.lines().filter(|l| !l.is_empty()).count();
+        let running = running.lines().filter(|l| !l.is_empty()).count();
+        let state = match &config.domain {
+            Some(domain) => Some(virsh(&config.uri, &["domstate", domain]).await?),
+            None => None,
+        };
+
+        widget.state = match state.as_deref() {
+            Some("running") => State::Good,
+            Some("paused" | "crashed") => State::Warning,
+            _ => State::Idle,
+        };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("cogs")?),
+            "total" => Value::number(total),
+            "running" => Value::number(running),
+            "stopped" => Value::number(total - running),
+            [if let Some(d) = &config.domain] "domain" => Value::text(d.clone()),
+            [if let Some(s) = &state] "state" => Value::text(s.clone()),
+        });
+        api.set_widget(&widget).await?;
+
+        loop {
+            select! {
+                _ = timer.tick() => break,
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "toggle" => {
+                        if let Some(domain) = &config.domain {
+                            let cmd = if state.as_deref() == Some("running") {
+                                "shutdown"
+                            } else {
+                                "start"
+                            };
+                            virsh(&config.uri, &[cmd, domain]).await?;
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+/// Run `virsh` and return its trimmed output
+async fn virsh(uri: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new("virsh")
+        .env("LC_ALL", "C")
+        .args(["-c", uri])
+        .args(args)
+        .output()
+        .await
+        .error("Failed to run virsh")?;
+    if !output.status.success() {
+        return Err(Error::new(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    let output = String::from_utf8(output.stdout).error("virsh produced non-UTF8 output")?;
+    Ok(output.trim().into())
+}
@@ -2,20 +2,28 @@
 //!
 //! # Configuration
 //!
-//! Key        | Values                                                                                | Default
-//! -----------|---------------------------------------------------------------------------------------|--------
-//! `format`   | A string to customise the output of this block. See below for available placeholders. | `" $icon $1m "`
-//! `interval` | Update interval in seconds                                                            | `3`
-//! `info`     | Minimum load, where state is set to info                                              | `0.3`
-//! `warning`  | Minimum load, where state is set to warning                                           | `0.6`
-//! `critical` | Minimum load, where state is set to critical                                          | `0.9`
+//! Key              | Values                                                                                | Default
+//! -----------------|---------------------------------------------------------------------------------------|--------
+//! `format`         | A string to customise the output of this block. See below for available placeholders. | `" $icon $1m "`
+//! `interval`       | Update interval in seconds                                                            | `3`
+//! `info`           | Minimum load, where state is set to info                                              | `0.3`
+//! `warning`        | Minimum load, where state is set to warning                                           | `0.6`
+//! `critical`       | Minimum load, where state is set to critical                                          | `0.9`
+//! `threshold_type` | Whether `info`, `warning` and `critical` apply to the `"normalized"` or `"absolute"` 1 minute load | `"normalized"`
 //!
-//! Placeholder  | Value                  | Type   | Unit
-//! -------------|------------------------|--------|-----
-//! `icon`       | A static icon          | Icon   | -
-//! `1m`         | 1 minute load average  | Number | -
-//! `5m`         | 5 minute load average  | Number | -
-//! `15m`        | 15 minute load average | Number | -
+//! The normalized load is the load divided by the number of logical cores, so `1.0` means that
+//! every core is busy, whether the machine has 4 or 32 of them.
+//!
+//! Placeholder      | Value                                       | Type   | Unit
+//! -----------------|---------------------------------------------|--------|-----
+//! `icon`           | A static icon                               | Icon   | -
+//! `1m`             | 1 minute load average                       | Number | -
+//! `5m`             | 5 minute load average                       | Number | -
+//! `15m`            | 15 minute load average                      | Number | -
+//! `1m_normalized`  | 1 minute load average per logical core      | Number | -
+//! `5m_normalized`  | 5 minute load average per logical core      | Number | -
+//! `15m_normalized` | 15 minute load average per logical core     | Number | -
+//! `cores`          | Number of logical cores                     | Number | -
 //!
 //! # Example
 //!
@@ -26,6 +34,18 @@
 //! interval = 1
 //! ```
 //!
+//! Use thresholds on the plain load average, while showing the load per core:
+//!
+//! ```toml
+//! [[block]]
+//! block = "load"
+//! format = " $icon $1m_normalized.eng(w:4) "
+//! threshold_type = "absolute"
+//! info = 2
+//! warning = 4
+//! critical = 8
+//! ```
+//!
 //! # Icons Used
 //! - `cogs`
 
@@ -44,6 +64,15 @@ pub struct Config {
     warning: f64,
     #[default(0.9)]
     critical: f64,
+    threshold_type: ThresholdType,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, SmartDefault)]
+#[serde(rename_all = "lowercase")]
+enum ThresholdType {
+    #[default]
+    Normalized,
+    Absolute,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
@@ -75,7 +104,12 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             .and_then(|x| x.parse().ok())
             .error("bad /proc/loadavg file")?;
 
-        widget.state = match m1 / logical_cores as f64 {
+        let cores = logical_cores as f64;
+        let load = match config.threshold_type {
+            ThresholdType::Normalized => m1 / cores,
+            ThresholdType::Absolute => m1,
+        };
+        widget.state = match load {
             x if x > config.critical => State::Critical,
             x if x > config.warning => State::Warning,
             x if x > config.info => State::Info,
@@ -86,6 +120,10 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             "1m" => Value::number(m1),
             "5m" => Value::number(m5),
             "15m" => Value::number(m15),
+            "1m_normalized" => Value::number(m1 / cores),
+            "5m_normalized" => Value::number(m5 / cores),
+            "15m_normalized" => Value::number(m15 / cores),
+            "cores" => Value::number(logical_cores),
         });
         api.set_widget(&widget).await?;
 
@@ -10,16 +10,33 @@
 //! Key | Values | Default
 //! ----|--------|--------
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $status "`
-//! `inboxes` | List of maildir inboxes to look for mails in. Supports path expansions e.g. `~`. | **Required**
+//! `inboxes` | List of maildir inboxes to look for mails in. Supports path expansions e.g. `~`. | `[]`
+//! `accounts` | List of named accounts, see below. | `[]`
 //! `threshold_warning` | Number of unread mails where state is set to warning. | `1`
 //! `threshold_critical` | Number of unread mails where state is set to critical. | `10`
 //! `interval` | Update interval, in seconds. | `5`
 //! `display_type` | Which part of the maildir to count: `"new"`, `"cur"`, or `"all"`. | `"new"`
 //!
-//! Placeholder  | Value                  | Type   | Unit
-//! -------------|------------------------|--------|-----
-//! `icon`       | A static icon          | Icon   | -
-//! `status`     | Number of emails       | Number | -
+//! At least one of `inboxes` and `accounts` must be set.
+//!
+//! Each account has the following options:
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `name` | Name of the account, used as its placeholder. | **Required**
+//! `inboxes` | List of maildir inboxes belonging to this account. Supports path expansions e.g. `~`. | **Required**
+//! `threshold_warning` | Number of unread mails in this account where state is set to warning. | The block's `threshold_warning`
+//! `threshold_critical` | Number of unread mails in this account where state is set to critical. | The block's `threshold_critical`
+//!
+//! The block is redrawn as soon as mail is delivered to or moved out of any of the inboxes. Inboxes
+//! that don't exist are only checked every `interval`. The block's state is the most severe state
+//! of any account, so a single account going over its threshold is enough to turn the block red.
+//!
+//! Placeholder  | Value                                        | Type   | Unit
+//! -------------|----------------------------------------------|--------|-----
+//! `icon`       | A static icon                                | Icon   | -
+//! `status`     | Number of emails in all inboxes and accounts | Number | -
+//! `<name>`     | Number of emails in the account `<name>`     | Number | -
 //!
 //! # Examples
 //!
@@ -33,11 +50,29 @@
 //! display_type = "new"
 //! ```
 //!
+//! Separate counts for two accounts, with work mail turning critical sooner:
+//!
+//! ```toml
+//! [[block]]
+//! block = "maildir"
+//! interval = 300
+//! format = " $icon $personal / $work "
+//! [[block.accounts]]
+//! name = "personal"
+//! inboxes = ["~/mail/personal/Inbox"]
+//! [[block.accounts]]
+//! name = "work"
+//! inboxes = ["~/mail/work/Inbox"]
+//! threshold_critical = 1
+//! ```
+//!
 //! # Icons Used
 //! - `mail`
 
 use super::prelude::*;
+use inotify::{Inotify, WatchMask};
 use maildir::Maildir;
+use std::path::Path;
 
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(default)]
@@ -46,6 +81,7 @@ pub struct Config {
     #[default(5.into())]
     interval: Seconds,
     inboxes: Vec<String>,
+    accounts: Vec<Account>,
     #[default(1)]
     threshold_warning: usize,
     #[default(10)]
@@ -54,47 +90,128 @@ pub struct Config {
     display_type: MailType,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Account {
+    name: String,
+    inboxes: Vec<String>,
+    threshold_warning: Option<usize>,
+    threshold_critical: Option<usize>,
+}
+
 pub async fn run(mut config: Config, mut api: CommonApi) -> Result<()> {
     let mut widget = Widget::new().with_format(config.format.with_default(" $icon $status ")?);
 
-    for inbox in &mut config.inboxes {
+    if config.inboxes.is_empty() && config.accounts.is_empty() {
+        return Err(Error::new("Either `inboxes` or `accounts` must be set"));
+    }
+
+    for inbox in config
+        .inboxes
+        .iter_mut()
+        .chain(config.accounts.iter_mut().flat_map(|a| &mut a.inboxes))
+    {
         *inbox = shellexpand::full(inbox)
             .error("Failed to expand string")?
             .to_string();
     }
 
+    let mut notify = Inotify::init().error("Failed to start inotify")?;
+    for inbox in config
+        .inboxes
+        .iter()
+        .chain(config.accounts.iter().flat_map(|a| &a.inboxes))
+    {
+        for subdir in ["new", "cur"] {
+            // A missing or misconfigured inbox is still polled every `interval`, it just doesn't
+            // get the instant updates
+            let path = format!("{inbox}/{subdir}");
+            if !Path::new(&path).is_dir() {
+                continue;
+            }
+            notify
+                .add_watch(
+                    path,
+                    WatchMask::CREATE
+                        | WatchMask::DELETE
+                        | WatchMask::MOVED_TO
+                        | WatchMask::MOVED_FROM,
+                )
+                .error("Failed to watch maildir")?;
+        }
+    }
+    let mut updates = notify
+        .event_stream([0; 1024])
+        .error("Failed to create event stream")?;
+
     loop {
-        let mut newmails = 0;
-        for inbox in &config.inboxes {
-            let isl: &str = &inbox[..];
-            // TODO: spawn_blocking?
-            let maildir = Maildir::from(isl);
-            newmails += match config.display_type {
-                MailType::New => maildir.count_new(),
-                MailType::Cur => maildir.count_cur(),
-                MailType::All => maildir.count_new() + maildir.count_cur(),
-            };
+        let mut values = Values::new();
+        let mut state = State::Idle;
+
+        let mut total = count_mails(&config.inboxes, &config.display_type);
+        if !config.inboxes.is_empty() {
+            update_state(
+                &mut state,
+                total,
+                config.threshold_warning,
+                config.threshold_critical,
+            );
+        }
+
+        for account in &config.accounts {
+            let count = count_mails(&account.inboxes, &config.display_type);
+            update_state(
+                &mut state,
+                count,
+                account
+                    .threshold_warning
+                    .unwrap_or(config.threshold_warning),
+                account
+                    .threshold_critical
+                    .unwrap_or(config.threshold_critical),
+            );
+            values.insert(account.name.clone().into(), Value::number(count));
+            total += count;
         }
-        widget.state = if newmails >= config.threshold_critical {
-            State::Critical
-        } else if newmails >= config.threshold_warning {
-            State::Warning
-        } else {
-            State::Idle
-        };
-        widget.set_values(map!(
-            "icon" => Value::icon(api.get_icon("mail")?),
-            "status" => Value::number(newmails)
-        ));
+
+        values.insert("icon".into(), Value::icon(api.get_icon("mail")?));
+        values.insert("status".into(), Value::number(total));
+        widget.state = state;
+        widget.set_values(values);
         api.set_widget(&widget).await?;
 
         select! {
             _ = sleep(config.interval.0) => (),
+            _ = updates.next() => (),
             _ = api.wait_for_update_request() => (),
         }
     }
 }
 
+fn count_mails(inboxes: &[String], display_type: &MailType) -> usize {
+    inboxes
+        .iter()
+        .map(|inbox| {
+            // TODO: spawn_blocking?
+            let maildir = Maildir::from(inbox.as_str());
+            match display_type {
+                MailType::New => maildir.count_new(),
+                MailType::Cur => maildir.count_cur(),
+                MailType::All => maildir.count_new() + maildir.count_cur(),
+            }
+        })
+        .sum()
+}
+
+/// Raise `state` to whatever `count` warrants, never lowering it.
+fn update_state(state: &mut State, count: usize, warning: usize, critical: usize) {
+    if count >= critical {
+        *state = State::Critical;
+    } else if count >= warning && *state != State::Critical {
+        *state = State::Warning;
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "lowercase")]
 enum MailType {
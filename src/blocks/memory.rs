@@ -11,6 +11,10 @@
 //! `warning_swap` | Percentage of swap usage, where state is set to warning | `80.0`
 //! `critical_mem` | Percentage of memory usage, where state is set to critical | `95.0`
 //! `critical_swap` | Percentage of swap usage, where state is set to critical | `95.0`
+//! `top_format` | A string to customise the output of this block while the process using the most memory is shown. See below for available placeholders. | `" $icon $top_name $top_rss.eng(prefix:M) "`
+//! `top_duration` | For how many seconds the process using the most memory is shown | `5`
+//! `warning_pressure` | Memory pressure (`pressure_some`), where state is set to warning | `None`
+//! `critical_pressure` | Memory pressure (`pressure_some`), where state is set to critical | `None`
 //!
 //! Placeholder               | Value                                                                           | Type   | Unit
 //! --------------------------|---------------------------------------------------------------------------------|--------|-------
@@ -34,10 +38,24 @@
 //! `swap_free_percents`      | as above but as a percentage of total memory                                    | Number | Percents
 //! `swap_used`               | Swap used                                                                       | Number | Bytes
 //! `swap_used_percents`      | as above but as a percentage of total memory                                    | Number | Percents
+//! `pressure_some`           | Share of time in the last 10 seconds in which at least one task was stalled on memory. Absent if PSI is not available. | Number | Percents
+//! `pressure_full`           | Share of time in the last 10 seconds in which all non-idle tasks were stalled on memory. Absent if PSI is not available. | Number | Percents
+//! `zram_orig`               | Uncompressed size of the data stored in all zram devices. Absent if there are no zram devices. | Number | Bytes
+//! `zram_compressed`         | Compressed size of the data stored in all zram devices                          | Number | Bytes
+//! `zram_ratio`              | Compression ratio of zram (`zram_orig / zram_compressed`)                       | Number | -
+//!
+//! Placeholders available in `top_format`:
+//!
+//! Placeholder | Value                                     | Type   | Unit
+//! ------------|-------------------------------------------|--------|------
+//! `icon`      | Memory icon                               | Icon   | -
+//! `top_name`  | Name of the process using the most memory | Text   | -
+//! `top_rss`   | Resident memory of that process           | Number | Bytes
 //!
 //! Action          | Description                               | Default button
 //! ----------------|-------------------------------------------|---------------
 //! `toggle_format` | Toggles between `format` and `format_alt` | Left
+//! `show_top`      | Shows the process using the most memory for `top_duration` seconds | Right
 //!
 //! # Example
 //!
@@ -51,13 +69,34 @@
 //! critical_mem = 90
 //! ```
 //!
+//! Memory pressure is usually a better indicator of memory trouble than the amount of used memory,
+//! because cached memory is counted as used but can be reclaimed easily:
+//!
+//! ```toml
+//! [[block]]
+//! block = "memory"
+//! format = " $icon $mem_used_percents.eng(w:1) {$pressure_some.eng(w:2) |}"
+//! warning_mem = 100
+//! critical_mem = 100
+//! warning_pressure = 10
+//! critical_pressure = 40
+//! ```
+//!
+//! Compression ratio of zram swap:
+//!
+//! ```toml
+//! [[block]]
+//! block = "memory"
+//! format = " $icon_swap $zram_orig.eng(w:3,u:B,p:M) {$zram_ratio.eng(w:3)|}"
+//! ```
+//!
 //! # Icons Used
 //! - `memory_mem`
 //! - `memory_swap`
 
 use std::cmp::min;
 use std::str::FromStr;
-use tokio::fs::File;
+use tokio::fs::{read_dir, File};
 use tokio::io::{AsyncBufReadExt, BufReader};
 
 use super::prelude::*;
@@ -78,11 +117,19 @@ pub struct Config {
     critical_mem: f64,
     #[default(95.0)]
     critical_swap: f64,
+    top_format: FormatConfig,
+    #[default(5.into())]
+    top_duration: Seconds,
+    warning_pressure: Option<f64>,
+    critical_pressure: Option<f64>,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
-    api.set_default_actions(&[(MouseButton::Left, None, "toggle_format")])
-        .await?;
+    api.set_default_actions(&[
+        (MouseButton::Left, None, "toggle_format"),
+        (MouseButton::Right, None, "show_top"),
+    ])
+    .await?;
 
     let mut widget = Widget::new();
 
@@ -93,6 +140,9 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         Some(f) => Some(f.with_default("")?),
         None => None,
     };
+    let top_format = config
+        .top_format
+        .with_default(" $icon $top_name $top_rss.eng(prefix:M) ")?;
 
     let mut timer = config.interval.timer();
 
@@ -144,8 +194,11 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         let swap_cached = mem_state.swap_cached as f64 * 1024.;
         let swap_used = swap_total - swap_free - swap_cached;
 
+        let pressure = read_pressure().await;
+        let zram = read_zram().await?;
+
         widget.set_format(format.clone());
-        widget.set_values(map! {
+        let mut values = map! {
             "icon" => Value::icon(api.get_icon("memory_mem")?),
             "icon_swap" => Value::icon(api.get_icon("memory_swap")?),
             "mem_total" => Value::bytes(mem_total),
@@ -166,7 +219,22 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             "buffers_percent" => Value::percents(buffers / mem_total * 100.),
             "cached" => Value::bytes(cached),
             "cached_percent" => Value::percents(cached / mem_total * 100.)
-        });
+        };
+        if let Some(pressure) = pressure {
+            values.insert("pressure_some".into(), Value::percents(pressure.some));
+            values.insert("pressure_full".into(), Value::percents(pressure.full));
+        }
+        if let Some(zram) = zram {
+            values.insert("zram_orig".into(), Value::bytes(zram.orig));
+            values.insert("zram_compressed".into(), Value::bytes(zram.compressed));
+            if zram.compressed > 0.0 {
+                values.insert(
+                    "zram_ratio".into(),
+                    Value::number(zram.orig / zram.compressed),
+                );
+            }
+        }
+        widget.set_values(values);
 
         let mem_state = match mem_used / mem_total * 100. {
             x if x > config.critical_mem => State::Critical,
@@ -180,9 +248,16 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             _ => State::Idle,
         };
 
-        widget.state = if mem_state == State::Critical || swap_state == State::Critical {
+        let pressure_state = match pressure.map(|p| p.some) {
+            Some(x) if config.critical_pressure.map_or(false, |c| x > c) => State::Critical,
+            Some(x) if config.warning_pressure.map_or(false, |w| x > w) => State::Warning,
+            _ => State::Idle,
+        };
+
+        let states = [mem_state, swap_state, pressure_state];
+        widget.state = if states.contains(&State::Critical) {
             State::Critical
-        } else if mem_state == State::Warning || swap_state == State::Warning {
+        } else if states.contains(&State::Warning) {
             State::Warning
         } else {
             State::Idle
@@ -202,6 +277,22 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                             break;
                         }
                     }
+                    Action(a) if a == "show_top" => {
+                        if let Some((name, rss)) = top_process().await? {
+                            widget.set_format(top_format.clone());
+                            widget.set_values(map! {
+                                "icon" => Value::icon(api.get_icon("memory_mem")?),
+                                "top_name" => Value::text(name),
+                                "top_rss" => Value::bytes(rss),
+                            });
+                            api.set_widget(&widget).await?;
+                            select! {
+                                _ = tokio::time::sleep(config.top_duration.0) => (),
+                                _ = api.wait_for_update_request() => (),
+                            }
+                            break;
+                        }
+                    }
                     _ => (),
                 }
             }
@@ -286,3 +377,129 @@ impl Memstate {
         Ok(mem_state)
     }
 }
+
+/// Find the process with the largest resident set size. Returns its name and RSS in bytes.
+async fn top_process() -> Result<Option<(String, f64)>> {
+    // Safety: sysconf is always safe to call
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as f64;
+
+    let mut top: Option<(std::path::PathBuf, u64)> = None;
+    let mut proc_dir = read_dir("/proc").await.error("failed to read /proc")?;
+    while let Some(entry) = proc_dir.next_entry().await.error("failed to read /proc")? {
+        if !entry
+            .file_name()
+            .to_string_lossy()
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+        {
+            continue;
+        }
+        // Processes may exit at any moment, so errors are ignored
+        let rss = match read_file(entry.path().join("statm")).await {
+            Ok(statm) => statm
+                .split_ascii_whitespace()
+                .nth(1)
+                .and_then(|x| x.parse().ok()),
+            Err(_) => None,
+        };
+        if let Some(rss) = rss {
+            if top.as_ref().map_or(true, |(_, top_rss)| rss > *top_rss) {
+                top = Some((entry.path(), rss));
+            }
+        }
+    }
+
+    Ok(match top {
+        Some((path, rss)) => {
+            let name = read_file(path.join("comm"))
+                .await
+                .unwrap_or_else(|_| "?".into());
+            Some((name, rss as f64 * page_size))
+        }
+        None => None,
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Pressure {
+    /// `some avg10`, in percents
+    some: f64,
+    /// `full avg10`, in percents
+    full: f64,
+}
+
+/// Read memory pressure stall information. Returns `None` if the kernel does not support PSI.
+/// Reference: <https://www.kernel.org/doc/html/latest/accounting/psi.html>
+async fn read_pressure() -> Option<Pressure> {
+    parse_pressure(&read_file("/proc/pressure/memory").await.ok()?)
+}
+
+fn parse_pressure(psi: &str) -> Option<Pressure> {
+    let avg10 = |kind: &str| -> Option<f64> {
+        psi.lines()
+            .find_map(|line| line.strip_prefix(kind))?
+            .split_ascii_whitespace()
+            .find_map(|field| field.strip_prefix("avg10="))?
+            .parse()
+            .ok()
+    };
+    Some(Pressure {
+        some: avg10("some ")?,
+        full: avg10("full ")?,
+    })
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Zram {
+    /// Uncompressed size of the stored data, in bytes
+    orig: f64,
+    /// Compressed size of the stored data, in bytes
+    compressed: f64,
+}
+
+/// Sum the sizes of all zram devices. Returns `None` if there are no zram devices.
+/// Reference: <https://www.kernel.org/doc/html/latest/admin-guide/blockdev/zram.html>
+async fn read_zram() -> Result<Option<Zram>> {
+    let mut zram = None;
+    let mut dir = read_dir("/sys/block")
+        .await
+        .error("failed to read /sys/block")?;
+    while let Some(entry) = dir.next_entry().await.error("failed to read /sys/block")? {
+        if !entry.file_name().to_string_lossy().starts_with("zram") {
+            continue;
+        }
+        let mm_stat = match read_file(entry.path().join("mm_stat")).await {
+            Ok(mm_stat) => mm_stat,
+            // The device is not initialized
+            Err(_) => continue,
+        };
+        let mut fields = mm_stat
+            .split_ascii_whitespace()
+            .map(|x| x.parse::<f64>().error("failed to parse zram mm_stat"));
+        let orig = fields.next().error("failed to parse zram mm_stat")??;
+        let compressed = fields.next().error("failed to parse zram mm_stat")??;
+        let total = zram.get_or_insert_with(Zram::default);
+        total.orig += orig;
+        total.compressed += compressed;
+    }
+    Ok(zram)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pressure() {
+        let psi = "some avg10=1.53 avg60=0.87 avg300=0.22 total=12345\n\
+                   full avg10=0.40 avg60=0.10 avg300=0.02 total=6789";
+        assert_eq!(
+            parse_pressure(psi),
+            Some(Pressure {
+                some: 1.53,
+                full: 0.40
+            })
+        );
+        assert_eq!(parse_pressure("some avg10=1.00"), None);
+    }
+}
@@ -14,6 +14,9 @@
 //! will cycle it to the next player. You can pin the widget to a given player via the "player"
 //! setting.
 //!
+//! A player that is currently playing takes priority: when one starts playing while the displayed
+//! player is paused or stopped, the block switches to it.
+//!
 //! # Configuration
 //!
 //! Key | Values | Default
@@ -22,7 +25,9 @@
 //! `player` | Name(s) of the music player(s) MPRIS interface. This can be either a music player name or an array of music player names. Run <code>busctl --user list &vert; grep "org.mpris.MediaPlayer2." &vert; cut -d' ' -f1</code> and the name is the part after "org.mpris.MediaPlayer2.". | `None`
 //! `interface_name_exclude` | A list of regex patterns for player MPRIS interface names to ignore. | `[]`
 //! `separator` | String to insert between artist and title. | `" - "`
-//! `seek_step` | Number of microseconds to seek forward/backward when scrolling on the bar. | `1000`
+//! `seek_step_secs` | Number of seconds to seek forward/backward when scrolling on the bar. | `1`
+//! `seek_step` | Deprecated, use `seek_step_secs` instead. Number of microseconds to seek forward/backward when scrolling on the bar. | `None`
+//! `progress_width` | Width of `$progress_bar` in characters. | `10`
 //!
 //! Note: All placeholders exctpt `icon` can be absent. See the examples below to learn how to handle this.
 //!
@@ -32,10 +37,16 @@
 //! `artist`    | Current artist | Text
 //! `title`     | Current title  | Text
 //! `url`       | Current song url | Text
+//! `album`     | Current album | Text
+//! `art_url`   | URL of the album art, usually a `file://` or `https://` URL | Text
 //! `combo`     | Resolves to "`$artist[sep]$title"`, `"$artist"`, `"$title"`, or `"$url"` depending on what information is available. `[sep]` is set by `separator` option. | Text
 //! `player`    | Name of the current player (taken from the last part of its MPRIS bus name) | Text
+//! `position`  | Playback position of the current song, updated every second while playing | Text
+//! `length`    | Length of the current song | Text
+//! `progress`  | Playback position relative to the song length | Number
+//! `progress_bar` | Playback position as a bar of `progress_width` characters | Text
 //! `avail`     | Total number of players available to switch between | Number
-//! `cur`       | Total number of players available to switch between | Number
+//! `cur`       | Index of the current player, starting at 1 | Number
 //! `play`      | Play/Pause button | Clickable icon
 //! `next`      | Next button | Clickable icon
 //! `prev`      | Previous button | Clickable icon
@@ -45,7 +56,12 @@
 //! `play_pause`    | Left on `$play`
 //! `next`          | Left on `$next`
 //! `prev`          | Left on `$prev`
+//! `show_info`     | Left
 //! `next_player`   | Right
+//! `prev_player`   | -
+//!
+//! `show_info` sends a notification with the title, artist and album of the current song using
+//! `notify-send`. If the album art is a local file, it is used as the notification image.
 //! `seek_forward`  | Wheel Up
 //! `seek_backward` | Wheel Down
 //!
@@ -79,6 +95,29 @@
 //! interface_name_exclude = [".*kdeconnect.*", "mpd"]
 //! ```
 //!
+//! Show the playback progress and seek by 10 seconds when scrolling:
+//!
+//! ```toml
+//! [[block]]
+//! block = "music"
+//! format = " $icon {$combo.str(max_w:20) $position/$length $progress_bar $play |}"
+//! seek_step_secs = 10
+//! ```
+//!
+//! Scroll to switch between players instead of seeking:
+//!
+//! ```toml
+//! [[block]]
+//! block = "music"
+//! format = " $icon $player: {$combo.str(max_w:20) $play |}"
+//! [[block.click]]
+//! button = "up"
+//! action = "prev_player"
+//! [[block.click]]
+//! button = "down"
+//! action = "next_player"
+//! ```
+//!
 //! Click anywhere to paly/pause:
 //!
 //! ```toml
@@ -98,6 +137,7 @@
 //! [MediaPlayer2 Interface]: https://specifications.freedesktop.org/mpris-spec/latest/Player_Interface.html
 
 use super::prelude::*;
+use crate::subprocess::spawn_process;
 use regex::Regex;
 use zbus::fdo::{DBusProxy, NameOwnerChanged, PropertiesChanged};
 use zbus::names::{OwnedBusName, OwnedUniqueName};
@@ -119,8 +159,11 @@ pub struct Config {
     interface_name_exclude: Vec<String>,
     #[default(" - ".into())]
     separator: String,
-    #[default(1_000)]
-    seek_step: i64,
+    #[default(1.into())]
+    seek_step_secs: Seconds,
+    seek_step: Option<i64>,
+    #[default(10)]
+    progress_width: usize,
 }
 
 #[derive(Deserialize, Debug, Clone, SmartDefault)]
@@ -136,6 +179,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         (MouseButton::Left, Some(PLAY_PAUSE_BTN), "play_pause"),
         (MouseButton::Left, Some(NEXT_BTN), "next"),
         (MouseButton::Left, Some(PREV_BTN), "prev"),
+        (MouseButton::Left, None, "show_info"),
         (MouseButton::Right, None, "next_player"),
         (MouseButton::WheelUp, None, "seek_forward"),
         (MouseButton::WheelDown, None, "seek_backward"),
@@ -143,11 +187,18 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     .await?;
 
     let dbus_conn = new_dbus_connection().await?;
-    let mut widget = Widget::new().with_format(
-        config
-            .format
-            .with_default(" $icon {$combo.str(max_w:25,rot_interval:0.5) $play |}")?,
-    );
+    let format = config
+        .format
+        .with_default(" $icon {$combo.str(max_w:25,rot_interval:0.5) $play |}")?;
+    let show_position = format.contains_key("position")
+        || format.contains_key("progress")
+        || format.contains_key("progress_bar");
+    let mut widget = Widget::new().with_format(format);
+
+    let seek_step = config
+        .seek_step
+        .unwrap_or(config.seek_step_secs.0.as_micros() as i64);
+    let mut timer = Seconds::new(1).timer();
 
     let new_btn = |icon: &str, instance: &'static str, api: &mut CommonApi| -> Result<Value> {
         Ok(Value::icon(api.get_icon(icon)?).with_instance(instance))
@@ -171,13 +222,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         .error("Invalid regex")?;
 
     let mut players = get_players(&dbus_conn, &prefered_players, &exclude_regex).await?;
-    let mut cur_player = None;
-    for (i, player) in players.iter().enumerate() {
-        cur_player = Some(i);
-        if player.status == Some(PlaybackStatus::Playing) {
-            break;
-        }
-    }
+    let mut cur_player = playing_player(&players).or_else(|| players.len().checked_sub(1));
 
     let mut properties_stream = MessageStream::for_match_rule(
         MatchRule::builder()
@@ -236,6 +281,31 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                 if let Some(url) = &player.metadata.url {
                     values.insert("url".into(), Value::text(url.clone()));
                 }
+                if let Some(album) = &player.metadata.album {
+                    values.insert("album".into(), Value::text(album.clone()));
+                }
+                if let Some(art_url) = &player.metadata.art_url {
+                    values.insert("art_url".into(), Value::text(art_url.clone()));
+                }
+                if let Some(length) = player.metadata.length {
+                    values.insert("length".into(), Value::text(format_position(length)));
+                }
+                let position = if show_position {
+                    player.position().await
+                } else {
+                    None
+                };
+                if let Some(position) = position {
+                    values.insert("position".into(), Value::text(format_position(position)));
+                    if let Some(length) = player.metadata.length.filter(|&l| l > 0) {
+                        let progress = (position as f64 / length as f64).clamp(0., 1.);
+                        values.insert("progress".into(), Value::percents(progress * 100.));
+                        values.insert(
+                            "progress_bar".into(),
+                            Value::text(progress_bar(progress, config.progress_width)),
+                        );
+                    }
+                }
                 match (
                     &player.metadata.title,
                     &player.metadata.artist,
@@ -273,8 +343,11 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             }
         }
 
+        let playing = cur_player.map_or(false, |c| players[c].is_playing());
+
         loop {
             select! {
+                _ = timer.tick(), if show_position && playing => break,
                 Some(msg) = properties_stream.next() => {
                     let msg = msg.unwrap();
                     let msg = PropertiesChanged::from_message(msg).unwrap();
@@ -283,10 +356,13 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                     let sender = header.sender().unwrap().unwrap();
                     if let Some(player) = players.iter_mut().find(|p| &*p.owner == sender) {
                         let props = args.changed_properties;
-                        if let Some(status) = props.get("PlaybackStatus") {
+                        let status_changed = if let Some(status) = props.get("PlaybackStatus") {
                             let status: &str = status.downcast_ref().unwrap();
                             player.status = PlaybackStatus::from_str(status);
-                        }
+                            true
+                        } else {
+                            false
+                        };
                         if let Some(metadata) = props.get("Metadata") {
                             player.metadata =
                                 zbus_mpris::PlayerMetadata::try_from(metadata.to_owned()).unwrap();
@@ -297,6 +373,10 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                                     cur_player = players.iter().position(|p| &*p.owner == sender);
                             }
                         }
+                        let cur_playing = cur_player.map_or(false, |c| players[c].is_playing());
+                        if status_changed && !cur_playing {
+                            cur_player = playing_player(&players).or(cur_player);
+                        }
                         break;
                     }
                 }
@@ -315,7 +395,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                                     if players.is_empty() {
                                         cur_player = None;
                                     } else if pos == cur {
-                                        cur_player = Some(0);
+                                        cur_player = playing_player(&players).or(Some(0));
                                     } else if pos < cur {
                                         cur_player = Some(cur - 1);
                                     }
@@ -341,18 +421,24 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                                 "prev" => {
                                     player.prev().await?;
                                 }
+                                "show_info" => {
+                                    show_info(&player.metadata)?;
+                                }
                                 "next_player" => {
-                                    if i + 1 < players.len() {
-                                        cur_player = Some(i + 1);
-                                    } else {
-                                        cur_player = Some(0);
-                                    }
+                                    cur_player = Some((i + 1) % players.len());
+                                    break;
+                                }
+                                "prev_player" => {
+                                    cur_player = Some((i + players.len() - 1) % players.len());
+                                    break;
                                 }
                                 "seek_forward" => {
-                                    player.seek(config.seek_step).await?;
+                                    player.seek(seek_step).await?;
+                                    break;
                                 }
                                 "seek_backward" => {
-                                    player.seek(-config.seek_step).await?;
+                                    player.seek(-seek_step).await?;
+                                    break;
                                 }
                                 _ => (),
                             }
@@ -404,6 +490,8 @@ impl Player {
         let proxy = zbus_mpris::PlayerProxy::builder(dbus_conn)
             .destination(bus_name.clone())
             .error("failed to set proxy destination")?
+            // Position changes are not signaled, so it must always be queried
+            .cache_properties(zbus::CacheProperties::No)
             .build()
             .await
             .error("failed to open player proxy")?;
@@ -425,6 +513,15 @@ impl Player {
         })
     }
 
+    fn is_playing(&self) -> bool {
+        self.status == Some(PlaybackStatus::Playing)
+    }
+
+    /// Playback position in microseconds, if the player supports it
+    async fn position(&self) -> Option<i64> {
+        self.player_proxy.position().await.ok()
+    }
+
     async fn play_pause(&self) -> Result<()> {
         self.player_proxy
             .play_pause()
@@ -471,6 +568,55 @@ impl PlaybackStatus {
     }
 }
 
+/// Index of the first player which is currently playing
+fn playing_player(players: &[Player]) -> Option<usize> {
+    players.iter().position(Player::is_playing)
+}
+
+fn show_info(metadata: &zbus_mpris::PlayerMetadata) -> Result<()> {
+    let summary = metadata
+        .title
+        .as_deref()
+        .or(metadata.url.as_deref())
+        .unwrap_or("Nothing playing");
+    let body = [metadata.artist.as_deref(), metadata.album.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("\n");
+    // Notification servers can't fetch remote images
+    let hint = metadata
+        .art_url
+        .as_deref()
+        .filter(|url| url.starts_with("file://"))
+        .map(|url| format!("string:image-path:{url}"));
+
+    let mut args = vec!["--app-name=i3status-rs"];
+    if let Some(hint) = &hint {
+        args.extend(["--hint", hint.as_str()]);
+    }
+    args.extend([summary, body.as_str()]);
+    spawn_process("notify-send", &args).error("Failed to run notify-send")
+}
+
+/// Format microseconds as `m:ss` or `h:mm:ss`
+fn format_position(micros: i64) -> String {
+    let secs = micros.max(0) / 1_000_000;
+    let (h, m, s) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if h > 0 {
+        format!("{h}:{m:02}:{s:02}")
+    } else {
+        format!("{m}:{s:02}")
+    }
+}
+
+fn progress_bar(progress: f64, width: usize) -> String {
+    let played = (progress * width as f64).round() as usize;
+    let mut bar = "━".repeat(played);
+    bar.push_str(&"─".repeat(width - played));
+    bar
+}
+
 fn extract_player_name(full_name: &str) -> Option<&str> {
     const NAME_PREFIX: &str = "org.mpris.MediaPlayer2.";
     full_name
@@ -508,6 +654,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn format_position_test() {
+        assert_eq!(format_position(0), "0:00");
+        assert_eq!(format_position(83_500_000), "1:23");
+        assert_eq!(format_position(3_723_000_000), "1:02:03");
+    }
+
+    #[test]
+    fn progress_bar_test() {
+        assert_eq!(progress_bar(0.0, 4), "────");
+        assert_eq!(progress_bar(0.5, 4), "━━──");
+        assert_eq!(progress_bar(1.0, 4), "━━━━");
+    }
+
     #[test]
     fn player_matches_test() {
         let exclude = vec![Regex::new("mpd").unwrap(), Regex::new("firefox.*").unwrap()];
@@ -28,6 +28,10 @@ pub struct PlayerMetadata {
     pub title: Option<String>,
     pub artist: Option<String>,
     pub url: Option<String>,
+    pub album: Option<String>,
+    pub art_url: Option<String>,
+    /// Track length in microseconds
+    pub length: Option<i64>,
 }
 
 impl Type for PlayerMetadata {
@@ -56,8 +60,24 @@ impl TryFrom<OwnedValue> for PlayerMetadata {
         let artist = artists.and_then(|val| val.get(0)).and_then(val_to_string);
 
         let url = map.get("xesam:url").and_then(|val| val_to_string(val));
-
-        Ok(Self { title, artist, url })
+        let album = map.get("xesam:album").and_then(|val| val_to_string(val));
+        let art_url = map.get("mpris:artUrl").and_then(|val| val_to_string(val));
+
+        // Should be an i64, but some players send an u64
+        let length = map.get("mpris:length").and_then(|val| match **val {
+            Value::I64(l) => Some(l),
+            Value::U64(l) => l.try_into().ok(),
+            _ => None,
+        });
+
+        Ok(Self {
+            title,
+            artist,
+            url,
+            album,
+            art_url,
+            length,
+        })
     }
 }
 
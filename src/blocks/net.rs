@@ -6,11 +6,13 @@
 //!
 //! Key | Values | Default
 //! ----|--------|--------
-//! `device` | Network interface to monitor (as specified in `/sys/class/net/`). Supports regex. | If not set, device will be automatically selected every `interval`
+//! `device` | Network interface to monitor (as specified in `/sys/class/net/`). Supports regex. If set to `"auto"` or not set, the interface of the default route is used. It is selected again every `interval` and whenever the routes change, so the block follows e.g. switching from ethernet to WiFi. | `"auto"`
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon ^icon_net_down $speed_down.eng(prefix:K) ^icon_net_up $speed_up.eng(prefix:K) "`
 //! `format_alt` | If set, block will switch between `format` and `format_alt` on every click | `None`
 //! `interval` | Update interval in seconds | `2`
 //! `missing_format` | Same as `format` if the interface cannot be connected (or missing). | `" × "`
+//! `vpn_icon` | Use the `net_vpn` icon for `icon` while a VPN carries all traffic | `false`
+//! `month_warning` | Amount of data transferred this month, in gigabytes, above which the state is set to warning. Useful for metered connections. | `None`
 //!
 //! Action          | Description                               | Default button
 //! ----------------|-------------------------------------------|---------------
@@ -30,6 +32,14 @@
 //! `bitrate`         | WiFi connection bitrate     | Number | Bits per second
 //! `ip`              | IPv4 address of the iface   | Text   | -
 //! `ipv6`            | IPv6 address of the iface   | Text   | -
+//! `vpn`             | Present if a VPN (tun, tap, wireguard or ppp interface) carries all traffic | Flag | -
+//! `vpn_name`        | The name of that VPN interface | Text   | -
+//! `total_day`       | Data received and sent today | Number | Bytes
+//! `total_month`     | Data received and sent this month | Number | Bytes
+//!
+//! The totals are counted per interface, only while the block is running, and are kept in
+//! `$XDG_CACHE_HOME/i3status-rust/` across restarts. They are tracked only if `total_day` or
+//! `total_month` is used, or `month_warning` is set.
 //!
 //! # Example
 //!
@@ -41,6 +51,25 @@
 //! format = " $icon {$signal_strength $ssid $frequency|Wired connection} via $device "
 //! ```
 //!
+//! Show whether the traffic goes through a VPN
+//!
+//! ```toml
+//! [[block]]
+//! block = "net"
+//! format = " $icon {$vpn_name |}$speed_down.eng(prefix:K) "
+//! vpn_icon = true
+//! ```
+//!
+//! Keep an eye on a capped mobile connection
+//!
+//! ```toml
+//! [[block]]
+//! block = "net"
+//! device = "^wwan0$"
+//! format = " $icon $total_day.eng(prefix:M) / $total_month.eng(prefix:G) "
+//! month_warning = 20
+//! ```
+//!
 //! Display exact device
 //!
 //! ```toml
@@ -58,10 +87,18 @@
 //! - `net_down`
 
 use super::prelude::*;
-use crate::netlink::NetDevice;
+use crate::formatting::Format;
+use crate::netlink::{NetDevice, RouteChanges};
 use crate::util;
+use chrono::Local;
 use regex::Regex;
-use std::time::Instant;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+make_log_macro!(debug, "net");
+
+/// How often traffic totals are written to disk
+const TOTALS_SAVE_INTERVAL: Duration = Duration::from_secs(60);
 
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(default)]
@@ -72,6 +109,8 @@ pub struct Config {
     missing_format: FormatConfig,
     #[default(2.into())]
     interval: Seconds,
+    vpn_icon: bool,
+    month_warning: Option<f64>,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
@@ -90,13 +129,27 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     let mut widget = Widget::new().with_format(format.clone());
     let mut timer = config.interval.timer();
 
+    let uses_totals = |f: &Format| f.contains_key("total_day") || f.contains_key("total_month");
+    let track_totals = uses_totals(&format)
+        || format_alt.as_ref().map_or(false, uses_totals)
+        || config.month_warning.is_some();
+    let mut totals: Option<TrafficTotals> = None;
+
     let device_re = config
         .device
         .as_deref()
+        .filter(|d| *d != "auto")
         .map(Regex::new)
         .transpose()
         .error("Failed to parse device regex")?;
 
+    // Follow the default route
+    let mut routes = match device_re {
+        Some(_) => None,
+        None => Some(RouteChanges::new()?),
+    };
+    let mut last_device = None;
+
     // Stats
     let mut stats = None;
     let mut stats_timer = Instant::now();
@@ -118,8 +171,15 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             Some(device) => {
                 widget.set_format(format.clone());
 
+                // Speed can't be computed from the stats of another interface
+                if last_device.as_ref() != Some(&device.iface.name) {
+                    last_device = Some(device.iface.name.clone());
+                    stats = None;
+                }
+
                 let mut speed_down: f64 = 0.0;
                 let mut speed_up: f64 = 0.0;
+                let mut diff_bytes = 0;
 
                 // Calculate speed
                 match (stats, device.iface.stats) {
@@ -134,14 +194,42 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                         stats_timer = Instant::now();
                         speed_down = diff.rx_bytes as f64 / elapsed;
                         speed_up = diff.tx_bytes as f64 / elapsed;
+                        diff_bytes = diff.rx_bytes + diff.tx_bytes;
                         stats = Some(new_stats);
                     }
                 }
-                push_to_hist(&mut rx_hist, speed_down);
-                push_to_hist(&mut tx_hist, speed_up);
+                util::push_to_hist(&mut rx_hist, speed_down);
+                util::push_to_hist(&mut tx_hist, speed_up);
+
+                if track_totals
+                    && totals.as_ref().map(|t| t.iface.as_str()) != Some(device.iface.name.as_str())
+                {
+                    if let Some(old) = &mut totals {
+                        old.save();
+                    }
+                    // The previous stats belong to another interface, so don't count them
+                    totals = Some(TrafficTotals::load(&device.iface.name));
+                    diff_bytes = 0;
+                }
+                let totals_data = totals.as_mut().map(|t| {
+                    t.add(diff_bytes);
+                    t.data.clone()
+                });
+                widget.state = match (&totals_data, config.month_warning) {
+                    (Some(t), Some(warning)) if t.month_bytes as f64 > warning * 1e9 => {
+                        State::Warning
+                    }
+                    _ => State::Idle,
+                };
+
+                let vpn_name = NetDevice::vpn_name().await?;
+                let icon = match vpn_name {
+                    Some(_) if config.vpn_icon => "net_vpn",
+                    _ => device.icon,
+                };
 
                 widget.set_values(map! {
-                    "icon" => Value::icon(api.get_icon(device.icon)?),
+                    "icon" => Value::icon(api.get_icon(icon)?),
                     "speed_down" => Value::bytes(speed_down),
                     "speed_up" => Value::bytes(speed_up),
                     "graph_down" => Value::text(util::format_bar_graph(&rx_hist)),
@@ -152,6 +240,10 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                     [if let Some(v) = device.frequency()] "frequency" => Value::hertz(v),
                     [if let Some(v) = device.bitrate()] "bitrate" => Value::bits(v),
                     [if let Some(v) = device.signal()] "signal_strength" => Value::percents(v),
+                    [if let Some(t) = &totals_data] "total_day" => Value::bytes(t.day_bytes),
+                    [if let Some(t) = &totals_data] "total_month" => Value::bytes(t.month_bytes),
+                    [if vpn_name.is_some()] "vpn" => Value::flag(),
+                    [if let Some(v) = vpn_name] "vpn_name" => Value::text(v),
                     "device" => Value::text(device.iface.name),
                 });
 
@@ -162,6 +254,10 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         loop {
             select! {
                 _ = timer.tick() => break,
+                change = async { routes.as_mut().unwrap().wait().await }, if routes.is_some() => {
+                    change?;
+                    break;
+                }
                 event = api.event() => match event {
                     UpdateRequest => break,
                     Action(a) if a == "toggle_format" => {
@@ -177,28 +273,61 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     }
 }
 
-fn push_to_hist<T>(hist: &mut [T], elem: T) {
-    hist[0] = elem;
-    hist.rotate_left(1);
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+struct TrafficTotalsData {
+    /// The day `day_bytes` was counted for, as `YYYY-MM-DD`
+    day: String,
+    day_bytes: u64,
+    /// The month `month_bytes` was counted for, as `YYYY-MM`
+    month: String,
+    month_bytes: u64,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::push_to_hist;
-
-    #[test]
-    fn test_push_to_hist() {
-        let mut hist = [0; 4];
-        assert_eq!(&hist, &[0, 0, 0, 0]);
-        push_to_hist(&mut hist, 1);
-        assert_eq!(&hist, &[0, 0, 0, 1]);
-        push_to_hist(&mut hist, 3);
-        assert_eq!(&hist, &[0, 0, 1, 3]);
-        push_to_hist(&mut hist, 0);
-        assert_eq!(&hist, &[0, 1, 3, 0]);
-        push_to_hist(&mut hist, 10);
-        assert_eq!(&hist, &[1, 3, 0, 10]);
-        push_to_hist(&mut hist, 2);
-        assert_eq!(&hist, &[3, 0, 10, 2]);
+/// Data transferred by an interface, persisted in the cache directory
+struct TrafficTotals {
+    iface: String,
+    file: String,
+    data: TrafficTotalsData,
+    last_save: Instant,
+}
+
+impl TrafficTotals {
+    fn load(iface: &str) -> Self {
+        let file = format!("net-totals-{iface}.json");
+        let data = util::load_cache_file(&file).unwrap_or_default();
+        Self {
+            iface: iface.into(),
+            file,
+            data,
+            last_save: Instant::now(),
+        }
+    }
+
+    /// Count `bytes` for the current day and month, starting over when a new day or month begins
+    fn add(&mut self, bytes: u64) {
+        let now = Local::now();
+        let day = now.format("%Y-%m-%d").to_string();
+        let month = now.format("%Y-%m").to_string();
+        if self.data.day != day {
+            self.data.day = day;
+            self.data.day_bytes = 0;
+        }
+        if self.data.month != month {
+            self.data.month = month;
+            self.data.month_bytes = 0;
+        }
+        self.data.day_bytes += bytes;
+        self.data.month_bytes += bytes;
+
+        if self.last_save.elapsed() >= TOTALS_SAVE_INTERVAL {
+            self.save();
+        }
+    }
+
+    fn save(&mut self) {
+        self.last_save = Instant::now();
+        if let Err(e) = util::store_cache_file(&self.file, &self.data) {
+            debug!("failed to save traffic totals: {e}");
+        }
     }
 }
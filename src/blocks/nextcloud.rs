@@ -0,0 +1,268 @@
+//! Nextcloud desktop client status and notifications
+//!
+//! Shows whether the Nextcloud desktop client is up to date, syncing or has errors, using the
+//! local socket which the client offers to file manager integrations. If `server` and `user` are
+//! set, the number of unread notifications is fetched from the server as well, using an app
+//! password which can also be passed using the `I3RS_NEXTCLOUD_PASSWORD` environment variable.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $status{ $notifications\|} "`
+//! `interval` | Update interval in seconds | `30`
+//! `socket_path` | The path of the client's socket | `"$XDG_RUNTIME_DIR/Nextcloud/socket"`
+//! `server` | The URL of the Nextcloud server, e.g. `"https://cloud.example.org"` | `None`
+//! `user` | The user to fetch notifications for | `None`
+//! `password` | An app password of `user` | `None`
+//!
+//! Placeholder     | Value                                                                   | Type   | Unit
+//! ----------------|-------------------------------------------------------------------------|--------|-----
+//! `icon`          | A static icon                                                           | Icon   | -
+//! `status`        | `"up to date"`, `"syncing"`, `"error"` or `"offline"` if the client is not running | Text | -
+//! `notifications` | The number of notifications on the server. Missing if zero or not configured. | Number | -
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "nextcloud"
+//! server = "https://cloud.example.org"
+//! user = "alice"
+//! ```
+//!
+//! # Icons Used
+//! - `update`
+//! - `notification`
+
+use super::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::UnixStream;
+
+make_log_macro!(debug, "nextcloud");
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    format: FormatConfig,
+    #[default(30.into())]
+    interval: Seconds,
+    socket_path: Option<ShellString>,
+    server: Option<String>,
+    user: Option<String>,
+    password: Option<String>,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    let mut widget = Widget::new().with_format(
+        config
+            .format
+            .with_default(" $icon $status{ $notifications|} ")?,
+    );
+
+    let socket_path = match &config.socket_path {
+        Some(path) => PathBuf::from(&*path.expand()?),
+        None => {
+            let runtime_dir =
+                std::env::var("XDG_RUNTIME_DIR").error("XDG_RUNTIME_DIR is not set")?;
+            PathBuf::from(runtime_dir).join("Nextcloud").join("socket")
+        }
+    };
+    let notifications_client = match (&config.server, &config.user) {
+        (Some(server), Some(user)) => Some(NotificationsClient {
+            url: format!(
+                "{}/ocs/v2.php/apps/notifications/api/v2/notifications",
+                server.trim_end_matches('/')
+            ),
+            user: user.clone(),
+            password: config
+                .password
+                .clone()
+                .or_else(|| std::env::var("I3RS_NEXTCLOUD_PASSWORD").ok())
+                .error("Nextcloud password not found")?,
+        }),
+        _ => None,
+    };
+
+    let mut client: Option<SyncClient> = None;
+    let mut timer = config.interval.timer();
+    let mut notifications = None;
+
+    loop {
+        if client.is_none() {
+            client = SyncClient::connect(&socket_path).await;
+        }
+        if let Some(c) = &mut client {
+            if c.request_status().await.is_err() {
+                client = None;
+            }
+        }
+        if let Some(n) = &notifications_client {
+            notifications = Some(api.recoverable(|| n.count()).await?);
+        }
+
+        loop {
+            let status = client
+                .as_ref()
+                .map_or(SyncStatus::Offline, SyncClient::status);
+            widget.state = match status {
+                SyncStatus::Error => State::Critical,
+                SyncStatus::Syncing => State::Info,
+                _ => State::Idle,
+            };
+            let icon = if notifications.unwrap_or(0) > 0 {
+                "notification"
+            } else {
+                "update"
+            };
+            widget.set_values(map! {
+                "icon" => Value::icon(api.get_icon(icon)?),
+                "status" => Value::text(status.as_str().into()),
+                [if let Some(n) = notifications.filter(|n| *n > 0)] "notifications" => Value::number(n),
+            });
+            api.set_widget(&widget).await?;
+
+            select! {
+                _ = timer.tick() => break,
+                _ = api.wait_for_update_request() => break,
+                res = async { client.as_mut().unwrap().handle_message().await }, if client.is_some() => {
+                    if res.is_err() {
+                        client = None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncStatus {
+    UpToDate,
+    Syncing,
+    Error,
+    Offline,
+}
+
+impl SyncStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::UpToDate => "up to date",
+            Self::Syncing => "syncing",
+            Self::Error => "error",
+            Self::Offline => "offline",
+        }
+    }
+}
+
+/// A connection to the socket of the desktop client
+struct SyncClient {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+    /// The status of every sync folder, as sent by the client, e.g. `OK` or `SYNC`
+    folders: HashMap<String, String>,
+}
+
+impl SyncClient {
+    async fn connect(path: &Path) -> Option<Self> {
+        let stream = match UnixStream::connect(path).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                debug!("Failed to connect to {}: {e}", path.display());
+                return None;
+            }
+        };
+        let (reader, writer) = stream.into_split();
+        Some(Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            folders: HashMap::new(),
+        })
+    }
+
+    /// Ask the client for the status of every known sync folder. The answers arrive as messages.
+    async fn request_status(&mut self) -> std::io::Result<()> {
+        let mut request = String::new();
+        for folder in self.folders.keys() {
+            request.push_str(&format!("RETRIEVE_FOLDER_STATUS:{folder}\n"));
+        }
+        self.writer.write_all(request.as_bytes()).await
+    }
+
+    /// Handle a message of the client, e.g. `REGISTER_PATH:/home/alice/Nextcloud` or
+    /// `STATUS:SYNC:/home/alice/Nextcloud`
+    async fn handle_message(&mut self) -> std::io::Result<()> {
+        let line = self
+            .lines
+            .next_line()
+            .await?
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::UnexpectedEof))?;
+        if let Some(path) = line.strip_prefix("REGISTER_PATH:") {
+            self.folders.insert(path.into(), "OK".into());
+            self.writer
+                .write_all(format!("RETRIEVE_FOLDER_STATUS:{path}\n").as_bytes())
+                .await?;
+        } else if let Some(path) = line.strip_prefix("UNREGISTER_PATH:") {
+            self.folders.remove(path);
+        } else if let Some(path) = line.strip_prefix("UPDATE_VIEW:") {
+            self.writer
+                .write_all(format!("RETRIEVE_FOLDER_STATUS:{path}\n").as_bytes())
+                .await?;
+        } else if let Some((status, path)) = line
+            .strip_prefix("STATUS:")
+            .and_then(|rest| rest.split_once(':'))
+        {
+            if let Some(folder) = self.folders.get_mut(path) {
+                // Shared folders have a `+SWM` suffix
+                *folder = status.split('+').next().unwrap_or(status).into();
+            }
+        }
+        Ok(())
+    }
+
+    fn status(&self) -> SyncStatus {
+        if self.folders.values().any(|s| s == "ERROR") {
+            SyncStatus::Error
+        } else if self.folders.values().any(|s| s == "SYNC" || s == "NEW") {
+            SyncStatus::Syncing
+        } else {
+            SyncStatus::UpToDate
+        }
+    }
+}
+
+struct NotificationsClient {
+    url: String,
+    user: String,
+    password: String,
+}
+
+impl NotificationsClient {
+    async fn count(&self) -> Result<usize> {
+        #[derive(Deserialize)]
+        struct Response {
+            ocs: Ocs,
+        }
+        #[derive(Deserialize)]
+        struct Ocs {
+            data: Vec<serde_json::Value>,
+        }
+
+        let response: Response = REQWEST_CLIENT
+            .get(&self.url)
+            .basic_auth(&self.user, Some(&self.password))
+            .header("OCS-APIRequest", "true")
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .error("Failed to send request")?
+            .error_for_status()
+            .error("Failed to get notifications")?
+            .json()
+            .await
+            .error("Failed to get JSON")?;
+        Ok(response.ocs.data.len())
+    }
+}
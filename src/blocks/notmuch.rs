@@ -15,7 +15,9 @@
 //! ----|--------|--------
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $count "`
 //! `maildir` | Path to the directory containing the notmuch database. Supports path expansions e.g. `~`. | `~/.mail`
-//! `query` | Query to run on the database. | `""`
+//! `query` | Query to run on the database. Ignored if `queries` is set. | `""`
+//! `queries` | A list of named queries which can be cycled through, each with a `name` and a `query`. | `[]`
+//! `open_cmd` | A shell command run by the `open` action. `{query}` is substituted with the current query, quoted for the shell. | `None`
 //! `threshold_critical` | Mail count that triggers `critical` state. | `99999`
 //! `threshold_warning` | Mail count that triggers `warning` state. | `99999`
 //! `threshold_good` | Mail count that triggers `good` state. | `99999`
@@ -26,6 +28,13 @@
 //! ------------|--------------------------------------------|--------|-----
 //! `icon`      | A static icon                              | Icon   | -
 //! `count`     | Number of messages for the query           | Number | -
+//! `name`      | Name of the current query, if it has one   | Text   | -
+//!
+//! Action       | Description                                  | Default button
+//! -------------|----------------------------------------------|---------------
+//! `next_query` | Show the next query from `queries`           | Wheel Down
+//! `prev_query` | Show the previous query from `queries`       | Wheel Up
+//! `open`       | Open the current query using `open_cmd`      | Right
 //!
 //! # Example
 //!
@@ -40,10 +49,26 @@
 //! update = true
 //! ```
 //!
+//! Cycle between a few saved searches and open them in `alot`:
+//!
+//! ```toml
+//! [[block]]
+//! block = "notmuch"
+//! format = " $icon $name: $count "
+//! open_cmd = "alacritty -e alot search {query}"
+//! [[block.queries]]
+//! name = "inbox"
+//! query = "tag:inbox and tag:unread"
+//! [[block.queries]]
+//! name = "lists"
+//! query = "tag:lists and tag:unread"
+//! ```
+//!
 //! # Icons Used
 //! - `mail`
 
 use super::prelude::*;
+use crate::subprocess::{shell_quote, spawn_shell};
 
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(default)]
@@ -54,6 +79,8 @@ pub struct Config {
     #[default("~/.mail".into())]
     maildir: ShellString,
     query: String,
+    queries: Vec<Query>,
+    open_cmd: Option<String>,
     #[default(u32::MAX)]
     threshold_warning: u32,
     #[default(u32::MAX)]
@@ -64,19 +91,47 @@ pub struct Config {
     threshold_good: u32,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct Query {
+    name: String,
+    query: String,
+}
+
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[
+        (MouseButton::WheelDown, None, "next_query"),
+        (MouseButton::WheelUp, None, "prev_query"),
+        (MouseButton::Right, None, "open"),
+    ])
+    .await?;
+
     let mut widget = Widget::new().with_format(config.format.with_default(" $icon $count ")?);
 
     let db = config.maildir.expand()?;
     let mut timer = config.interval.timer();
 
+    let queries = if config.queries.is_empty() {
+        vec![(None, config.query.as_str())]
+    } else {
+        config
+            .queries
+            .iter()
+            .map(|q| (Some(q.name.as_str()), q.query.as_str()))
+            .collect()
+    };
+    let mut current = 0;
+
     loop {
+        let (name, query) = queries[current];
+
         // TODO: spawn_blocking?
-        let count = run_query(&db, &config.query).error("Failed to get count")?;
+        let count = run_query(&db, query).error("Failed to get count")?;
 
         widget.set_values(map! {
             "icon" => Value::icon(api.get_icon("mail")?),
-            "count" => Value::number(count)
+            "count" => Value::number(count),
+            [if let Some(name) = name] "name" => Value::text(name.into()),
         });
 
         widget.state = if count >= config.threshold_critical {
@@ -93,9 +148,28 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
 
         api.set_widget(&widget).await?;
 
-        tokio::select! {
-            _ = timer.tick() => (),
-            _ = api.wait_for_update_request() => (),
+        loop {
+            select! {
+                _ = timer.tick() => break,
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "next_query" => {
+                        current = (current + 1) % queries.len();
+                        break;
+                    }
+                    Action(a) if a == "prev_query" => {
+                        current = (current + queries.len() - 1) % queries.len();
+                        break;
+                    }
+                    Action(a) if a == "open" => {
+                        if let Some(cmd) = &config.open_cmd {
+                            spawn_shell(&cmd.replace("{query}", &shell_quote(query)))
+                                .error("Failed to run open_cmd")?;
+                        }
+                    }
+                    _ => (),
+                }
+            }
         }
     }
 }
@@ -8,13 +8,19 @@
 //! a fan speed setting mode. In this mode you can scroll the mouse wheel over the block to change
 //! the fan speeds, and left click to exit the mode.
 //!
-//! Requires `nvidia-smi` for displaying info and `nvidia_settings` for setting fan speed.
+//! The GPU stats are read with NVML, which is part of the NVIDIA driver (`libnvidia-ml.so.1`).
+//! Setting fan speed requires `nvidia-settings`.
+//!
+//! On systems with several GPUs, right click switches to the next GPU. With `aggregate = true`, the
+//! block shows the combined stats of all GPUs instead: memory, power and PCIe throughput are
+//! summed, utilization is averaged, and the highest temperature, fan speed and clocks are shown.
 //!
 //! # Configuration
 //!
 //! Key | Values | Default
 //! ----|--------|--------
-//! `gpu_id` | GPU id in system. | `0`
+//! `gpu_id` | GPU id in system, shown initially. | `0`
+//! `aggregate` | Show the combined stats of all GPUs. | `false`
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $utilization $memory $temperature "`
 //! `interval` | Update interval in seconds. | `1`
 //! `idle` | Maximum temperature, below which state is set to idle | `50`
@@ -26,12 +32,20 @@
 //! --------------|--------|---------------
 //! `icon`        | Icon   | -
 //! `name`        | Text   | -
+//! `index`       | Number | -
 //! `utilization` | Number | Percents
 //! `memory`      | Number | Bytes
 //! `temperature` | Number | Degrees
 //! `fan_speed`   | Number | Percents
 //! `clocks`      | Number | Hertz
 //! `power`       | Number | Watts
+//! `encoder`     | Number | Percents
+//! `decoder`     | Number | Percents
+//! `pcie_tx`     | Number | Bytes per second
+//! `pcie_rx`     | Number | Bytes per second
+//!
+//! `index` is absent with `aggregate = true`. `fan_speed`, `clocks`, `power`, `encoder`, `decoder`,
+//! `pcie_tx` and `pcie_rx` are absent if the GPU doesn't support them.
 //!
 //! Action                  | Default button
 //! ------------------------|----------------
@@ -39,8 +53,9 @@
 //! `toggle_fan_controlled` | Left on `$fan_speed`
 //! `fan_speed_up`          | Wheel Up on `$fan_speed`
 //! `fan_speed_down`        | Wheel Down on `$fan_speed`
+//! `next_gpu`              | Right
 //!
-//! # Example
+//! # Examples
 //!
 //! ```toml
 //! [[block]]
@@ -49,22 +64,30 @@
 //! format = " $icon GT 1030 $utilization $temperature $clocks "
 //! ```
 //!
+//! Show the video engine and PCIe load of all GPUs:
+//!
+//! ```toml
+//! [[block]]
+//! block = "nvidia_gpu"
+//! aggregate = true
+//! format = " $icon $utilization enc $encoder dec $decoder $pcie_rx.eng(prefix:M) "
+//! ```
+//!
 //! # Icons Used
 //! - `gpu`
 //!
 //! # TODO
 //! - Provide a `mappings` option similar to `keyboard_layout`'s  to map GPU names to labels?
 
-use std::process::Stdio;
-use std::str::FromStr;
+mod nvml;
 
-use tokio::io::{BufReader, Lines};
+use std::sync::Arc;
 use tokio::process::Command;
 
+use nvml::Nvml;
+
 const MEM_BTN: &str = "mem_btn";
 const FAN_BTN: &str = "fan_btn";
-const QUERY: &str = "--query-gpu=name,memory.total,utilization.gpu,memory.used,temperature.gpu,fan.speed,clocks.current.graphics,power.draw,";
-const FORMAT: &str = "--format=csv,noheader,nounits";
 
 use super::prelude::*;
 
@@ -75,7 +98,8 @@ pub struct Config {
     #[default(1.into())]
     interval: Seconds,
     #[default(0)]
-    gpu_id: u64,
+    gpu_id: u32,
+    aggregate: bool,
     #[default(50)]
     idle: u32,
     #[default(70)]
@@ -88,41 +112,50 @@ pub struct Config {
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     api.set_default_actions(&[
-        (MouseButton::Left, Some(MEM_BTN), "toggle_mem_totoal"),
+        (MouseButton::Left, Some(MEM_BTN), "toggle_mem_total"),
         (MouseButton::Left, Some(FAN_BTN), "toggle_fan_controlled"),
         (MouseButton::WheelUp, Some(FAN_BTN), "fan_speed_up"),
         (MouseButton::WheelDown, Some(FAN_BTN), "fan_speed_down"),
+        (MouseButton::Right, None, "next_gpu"),
     ])
     .await?;
 
-    let mut widget = Widget::new().with_format(
-        config
-            .format
-            .with_default(" $icon $utilization $memory $temperature ")?,
-    );
+    let format = config
+        .format
+        .with_default(" $icon $utilization $memory $temperature ")?;
+    let need_pcie = format.contains_key("pcie_tx") || format.contains_key("pcie_rx");
+    let mut widget = Widget::new().with_format(format);
 
-    // Run `nvidia-smi` command
-    let mut child = Command::new("nvidia-smi")
-        .args([
-            "-l",
-            &config.interval.seconds().to_string(),
-            "-i",
-            &config.gpu_id.to_string(),
-            QUERY,
-            FORMAT,
-        ])
-        .stdout(Stdio::piped())
-        .kill_on_drop(true)
-        .spawn()
-        .error("Failed to execute nvidia-smi")?;
-    let mut reader = BufReader::new(child.stdout.take().unwrap()).lines();
+    // NVML calls may block, so they are made outside of the async runtime
+    let (nvml, gpu_count) = tokio::task::spawn_blocking(|| -> Result<_> {
+        let nvml = Nvml::init()?;
+        let gpu_count = nvml.device_count()?;
+        Ok((Arc::new(nvml), gpu_count))
+    })
+    .await
+    .error("Failed to join tokio task")??;
+    if config.gpu_id >= gpu_count {
+        return Err(Error::new(format!("GPU {} not found", config.gpu_id)));
+    }
+    let mut gpu_id = config.gpu_id;
 
-    // Read the initial info
-    let mut info = GpuInfo::from_reader(&mut reader).await?;
+    let mut timer = config.interval.timer();
     let mut show_mem_total = false;
     let mut fan_controlled = false;
 
     loop {
+        let nvml = nvml.clone();
+        let aggregate = config.aggregate;
+        let mut info = tokio::task::spawn_blocking(move || {
+            if aggregate {
+                GpuInfo::aggregate(&nvml, gpu_count, need_pcie)
+            } else {
+                GpuInfo::new(&nvml, gpu_id, need_pcie)
+            }
+        })
+        .await
+        .error("Failed to join tokio task")??;
+
         widget.state = match info.temperature {
             t if t <= config.idle => State::Idle,
             t if t <= config.good => State::Good,
@@ -134,12 +167,17 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         widget.set_values(map! {
             "icon" => Value::icon(api.get_icon("gpu")?),
             "name" => Value::text(info.name.clone()),
+            [if !config.aggregate] "index" => Value::number(gpu_id),
             "utilization" => Value::percents(info.utilization),
             "memory" => Value::bytes(if show_mem_total {info.mem_total} else {info.mem_used}).with_instance(MEM_BTN),
             "temperature" => Value::degrees(info.temperature),
-            "fan_speed" => Value::percents(info.fan_speed).with_instance(FAN_BTN).underline(fan_controlled).italic(fan_controlled),
-            "clocks" => Value::hertz(info.clocks),
-            "power" => Value::watts(info.power_draw),
+            [if let Some(f) = info.fan_speed] "fan_speed" => Value::percents(f).with_instance(FAN_BTN).underline(fan_controlled).italic(fan_controlled),
+            [if let Some(c) = info.clocks] "clocks" => Value::hertz(c),
+            [if let Some(p) = info.power_draw] "power" => Value::watts(p),
+            [if let Some(e) = info.encoder] "encoder" => Value::percents(e),
+            [if let Some(d) = info.decoder] "decoder" => Value::percents(d),
+            [if let Some(tx) = info.pcie_tx] "pcie_tx" => Value::bytes(tx),
+            [if let Some(rx) = info.pcie_rx] "pcie_rx" => Value::bytes(rx),
         });
 
         api.set_widget(&widget).await?;
@@ -152,31 +190,38 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                         show_mem_total = !show_mem_total;
                         break;
                     }
-                    Action(a) if a == "toggle_fan_controlled" => {
-                        fan_controlled = !fan_controlled;
-                        set_fan_speed(config.gpu_id, fan_controlled.then_some(info.fan_speed)).await?;
-                        break;
+                    Action(a) if a == "toggle_fan_controlled" && !config.aggregate => {
+                        if let Some(fan_speed) = info.fan_speed {
+                            fan_controlled = !fan_controlled;
+                            set_fan_speed(gpu_id, fan_controlled.then_some(fan_speed)).await?;
+                            break;
+                        }
                     }
-                    Action(a) if a == "fan_speed_up" && fan_controlled && info.fan_speed < 100 => {
-                        info.fan_speed += 1;
-                        set_fan_speed(config.gpu_id, Some(info.fan_speed)).await?;
-                        break;
+                    Action(a) if a == "fan_speed_up" && fan_controlled => {
+                        if let Some(fan_speed) = info.fan_speed.as_mut().filter(|s| **s < 100) {
+                            *fan_speed += 1;
+                            set_fan_speed(gpu_id, Some(*fan_speed)).await?;
+                            break;
+                        }
                     }
-                    Action(a) if a == "fan_speed_down" && fan_controlled && info.fan_speed > 0 => {
-                        info.fan_speed -= 1;
-                        set_fan_speed(config.gpu_id, Some(info.fan_speed)).await?;
+                    Action(a) if a == "fan_speed_down" && fan_controlled => {
+                        if let Some(fan_speed) = info.fan_speed.as_mut().filter(|s| **s > 0) {
+                            *fan_speed -= 1;
+                            set_fan_speed(gpu_id, Some(*fan_speed)).await?;
+                            break;
+                        }
+                    }
+                    Action(a) if a == "next_gpu" && !config.aggregate && gpu_count > 1 => {
+                        if fan_controlled {
+                            set_fan_speed(gpu_id, None).await?;
+                            fan_controlled = false;
+                        }
+                        gpu_id = (gpu_id + 1) % gpu_count;
                         break;
                     }
                     _ => (),
                 },
-                new_info = GpuInfo::from_reader(&mut reader) => {
-                    info = new_info?;
-                    break;
-                }
-                code = child.wait() => {
-                    let code = code.error("failed to check nvidia-smi exit code")?;
-                    return Err(Error::new(format!("nvidia-smi exited with code {code}")));
-                }
+                _ = timer.tick() => break,
             }
         }
     }
@@ -185,62 +230,77 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
 #[derive(Debug)]
 struct GpuInfo {
     name: String,
-    mem_total: f64,   // bytes
-    mem_used: f64,    // bytes
-    utilization: f64, // percents
-    temperature: u32, // degrees
-    fan_speed: u32,   // percents
-    clocks: f64,      // hertz
-    power_draw: f64,  // watts
+    mem_total: f64,          // bytes
+    mem_used: f64,           // bytes
+    utilization: f64,        // percents
+    temperature: u32,        // degrees
+    fan_speed: Option<u32>,  // percents
+    clocks: Option<f64>,     // hertz
+    power_draw: Option<f64>, // watts
+    encoder: Option<f64>,    // percents
+    decoder: Option<f64>,    // percents
+    pcie_tx: Option<f64>,    // bytes per second
+    pcie_rx: Option<f64>,    // bytes per second
 }
 
 impl GpuInfo {
-    /// Read a line from provided reader and parse it
-    ///
-    /// # Cancel safety
-    ///
-    /// This method should be cancellation safe, because it has only one `.await` and it is on `next_line`, which is cancellation safe.
-    async fn from_reader<B: AsyncBufRead + Unpin>(reader: &mut Lines<B>) -> Result<Self> {
-        const ERR_MSG: &str = "failed to read from nvidia-smi";
-        reader
-            .next_line()
-            .await
-            .error(ERR_MSG)?
-            .error(ERR_MSG)?
-            .parse::<GpuInfo>()
-            .error("failed to parse nvidia-smi output")
+    /// Read the stats of a GPU. PCIe throughput takes a while to measure, so it is only read if
+    /// `pcie` is set.
+    fn new(nvml: &Nvml, id: u32, pcie: bool) -> Result<Self> {
+        let device = nvml.device(id)?;
+        let (mem_total, mem_used) = device.memory()?;
+        let pcie = pcie.then(|| device.pcie_throughput().ok()).flatten();
+        Ok(Self {
+            name: device.name()?,
+            mem_total: mem_total as f64,
+            mem_used: mem_used as f64,
+            utilization: device.utilization()?.into(),
+            temperature: device.temperature()?,
+            fan_speed: device.fan_speed().ok(),
+            clocks: device.clocks().ok().map(|c| c as f64 * 1e6),
+            power_draw: device.power_usage().ok().map(|p| p as f64 / 1e3),
+            encoder: device.encoder_utilization().ok().map(Into::into),
+            decoder: device.decoder_utilization().ok().map(Into::into),
+            pcie_tx: pcie.map(|(tx, _)| tx as f64 * 1024.),
+            pcie_rx: pcie.map(|(_, rx)| rx as f64 * 1024.),
+        })
     }
-}
-
-impl FromStr for GpuInfo {
-    type Err = Error;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        macro_rules! parse {
-            ($s:ident -> $($part:ident : $t:ident $(* $mul:expr)?),*) => {{
-                let mut parts = $s.trim().split(", ");
-                let info = GpuInfo {
-                    $(
-                    $part: {
-                        let $part = parts
-                            .next()
-                            .error(concat!("missing property: ", stringify!($part)))?
-                            .parse::<$t>()
-                            .error(concat!("bad property: ", stringify!($part)))?;
-                        $(let $part = $part * $mul;)?
-                        $part
-                    },
-                    )*
-                };
-                Ok(info)
-            }}
-        }
-        // `memory` and `clocks` are initially in MB and MHz, so we have to multiply them by 1_000_000
-        parse!(s -> name: String, mem_total: f64 * 1e6, utilization: f64, mem_used: f64 * 1e6, temperature: u32, fan_speed: u32, clocks: f64 * 1e6, power_draw: f64)
+    /// Combine the stats of all GPUs
+    fn aggregate(nvml: &Nvml, count: u32, pcie: bool) -> Result<Self> {
+        let gpus = (0..count)
+            .map(|id| Self::new(nvml, id, pcie))
+            .collect::<Result<Vec<_>>>()?;
+        let n = gpus.len() as f64;
+        let sum = |f: fn(&Self) -> Option<f64>| gpus.iter().map(f).sum::<Option<f64>>();
+        let max = |f: fn(&Self) -> Option<f64>| {
+            gpus.iter()
+                .map(f)
+                .collect::<Option<Vec<_>>>()
+                .and_then(|v| v.into_iter().reduce(f64::max))
+        };
+        Ok(Self {
+            name: gpus
+                .iter()
+                .map(|g| g.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            mem_total: gpus.iter().map(|g| g.mem_total).sum(),
+            mem_used: gpus.iter().map(|g| g.mem_used).sum(),
+            utilization: gpus.iter().map(|g| g.utilization).sum::<f64>() / n,
+            temperature: gpus.iter().map(|g| g.temperature).max().unwrap_or(0),
+            fan_speed: gpus.iter().map(|g| g.fan_speed).max().flatten(),
+            clocks: max(|g| g.clocks),
+            power_draw: sum(|g| g.power_draw),
+            encoder: sum(|g| g.encoder).map(|e| e / n),
+            decoder: sum(|g| g.decoder).map(|d| d / n),
+            pcie_tx: sum(|g| g.pcie_tx),
+            pcie_rx: sum(|g| g.pcie_rx),
+        })
     }
 }
 
-async fn set_fan_speed(id: u64, speed: Option<u32>) -> Result<()> {
+async fn set_fan_speed(id: u32, speed: Option<u32>) -> Result<()> {
     const ERR_MSG: &str = "Failed to execute nvidia-settings";
     let mut cmd = Command::new("nvidia-settings");
     if let Some(speed) = speed {
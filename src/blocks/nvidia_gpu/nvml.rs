@@ -0,0 +1,220 @@
+//! Minimal bindings to NVML (NVIDIA Management Library)
+//!
+//! The library is loaded at runtime, so that i3status-rs doesn't need to link against it and
+//! only users of this block need the NVIDIA driver installed.
+
+use crate::errors::*;
+use std::ffi::{c_char, c_int, c_uint, c_ulonglong, c_void, CStr};
+
+type Return = c_int;
+type DeviceHandle = *mut c_void;
+
+const SUCCESS: Return = 0;
+const TEMPERATURE_GPU: c_uint = 0;
+const CLOCK_GRAPHICS: c_uint = 0;
+const PCIE_UTIL_TX_BYTES: c_uint = 0;
+const PCIE_UTIL_RX_BYTES: c_uint = 1;
+const NAME_BUFFER_SIZE: usize = 96;
+
+#[repr(C)]
+#[derive(Default)]
+struct Utilization {
+    gpu: c_uint,
+    memory: c_uint,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct Memory {
+    total: c_ulonglong,
+    free: c_ulonglong,
+    used: c_ulonglong,
+}
+
+struct Functions {
+    init: unsafe extern "C" fn() -> Return,
+    shutdown: unsafe extern "C" fn() -> Return,
+    error_string: unsafe extern "C" fn(Return) -> *const c_char,
+    device_get_count: unsafe extern "C" fn(*mut c_uint) -> Return,
+    device_get_handle_by_index: unsafe extern "C" fn(c_uint, *mut DeviceHandle) -> Return,
+    device_get_name: unsafe extern "C" fn(DeviceHandle, *mut c_char, c_uint) -> Return,
+    device_get_utilization_rates: unsafe extern "C" fn(DeviceHandle, *mut Utilization) -> Return,
+    device_get_memory_info: unsafe extern "C" fn(DeviceHandle, *mut Memory) -> Return,
+    device_get_temperature: unsafe extern "C" fn(DeviceHandle, c_uint, *mut c_uint) -> Return,
+    device_get_fan_speed: unsafe extern "C" fn(DeviceHandle, *mut c_uint) -> Return,
+    device_get_clock_info: unsafe extern "C" fn(DeviceHandle, c_uint, *mut c_uint) -> Return,
+    device_get_power_usage: unsafe extern "C" fn(DeviceHandle, *mut c_uint) -> Return,
+    device_get_encoder_utilization:
+        unsafe extern "C" fn(DeviceHandle, *mut c_uint, *mut c_uint) -> Return,
+    device_get_decoder_utilization:
+        unsafe extern "C" fn(DeviceHandle, *mut c_uint, *mut c_uint) -> Return,
+    device_get_pcie_throughput: unsafe extern "C" fn(DeviceHandle, c_uint, *mut c_uint) -> Return,
+}
+
+pub struct Nvml {
+    lib: *mut c_void,
+    f: Functions,
+}
+
+// NVML is thread safe
+unsafe impl Send for Nvml {}
+unsafe impl Sync for Nvml {}
+
+impl Nvml {
+    /// Load `libnvidia-ml.so.1` and initialize NVML
+    pub fn init() -> Result<Self> {
+        // Safety: the function pointers are only transmuted from symbols with the given names,
+        // whose signatures are defined by the NVML API.
+        unsafe {
+            let lib = libc::dlopen(b"libnvidia-ml.so.1\0".as_ptr().cast(), libc::RTLD_NOW);
+            if lib.is_null() {
+                return Err(Error::new("Failed to load libnvidia-ml.so.1"));
+            }
+            macro_rules! symbol {
+                ($name:literal) => {{
+                    let ptr = libc::dlsym(lib, concat!($name, "\0").as_ptr().cast());
+                    if ptr.is_null() {
+                        libc::dlclose(lib);
+                        return Err(Error::new(concat!("NVML function ", $name, " not found")));
+                    }
+                    std::mem::transmute(ptr)
+                }};
+            }
+            let f = Functions {
+                init: symbol!("nvmlInit_v2"),
+                shutdown: symbol!("nvmlShutdown"),
+                error_string: symbol!("nvmlErrorString"),
+                device_get_count: symbol!("nvmlDeviceGetCount_v2"),
+                device_get_handle_by_index: symbol!("nvmlDeviceGetHandleByIndex_v2"),
+                device_get_name: symbol!("nvmlDeviceGetName"),
+                device_get_utilization_rates: symbol!("nvmlDeviceGetUtilizationRates"),
+                device_get_memory_info: symbol!("nvmlDeviceGetMemoryInfo"),
+                device_get_temperature: symbol!("nvmlDeviceGetTemperature"),
+                device_get_fan_speed: symbol!("nvmlDeviceGetFanSpeed"),
+                device_get_clock_info: symbol!("nvmlDeviceGetClockInfo"),
+                device_get_power_usage: symbol!("nvmlDeviceGetPowerUsage"),
+                device_get_encoder_utilization: symbol!("nvmlDeviceGetEncoderUtilization"),
+                device_get_decoder_utilization: symbol!("nvmlDeviceGetDecoderUtilization"),
+                device_get_pcie_throughput: symbol!("nvmlDeviceGetPcieThroughput"),
+            };
+            let nvml = Self { lib, f };
+            nvml.check((nvml.f.init)())?;
+            Ok(nvml)
+        }
+    }
+
+    fn check(&self, ret: Return) -> Result<()> {
+        if ret == SUCCESS {
+            return Ok(());
+        }
+        // Safety: nvmlErrorString returns a static string
+        let msg = unsafe { CStr::from_ptr((self.f.error_string)(ret)) };
+        Err(Error::new(format!("NVML error: {}", msg.to_string_lossy())))
+    }
+
+    pub fn device_count(&self) -> Result<u32> {
+        let mut count = 0;
+        self.check(unsafe { (self.f.device_get_count)(&mut count) })?;
+        Ok(count)
+    }
+
+    pub fn device(&self, index: u32) -> Result<Device<'_>> {
+        let mut handle = std::ptr::null_mut();
+        self.check(unsafe { (self.f.device_get_handle_by_index)(index, &mut handle) })?;
+        Ok(Device { nvml: self, handle })
+    }
+}
+
+impl Drop for Nvml {
+    fn drop(&mut self) {
+        unsafe {
+            (self.f.shutdown)();
+            libc::dlclose(self.lib);
+        }
+    }
+}
+
+pub struct Device<'a> {
+    nvml: &'a Nvml,
+    handle: DeviceHandle,
+}
+
+impl Device<'_> {
+    /// Call a getter which writes a single `c_uint`
+    fn get_uint(&self, f: impl FnOnce(DeviceHandle, *mut c_uint) -> Return) -> Result<u32> {
+        let mut val = 0;
+        self.nvml.check(f(self.handle, &mut val))?;
+        Ok(val)
+    }
+
+    pub fn name(&self) -> Result<String> {
+        let mut buf = [0 as c_char; NAME_BUFFER_SIZE];
+        self.nvml.check(unsafe {
+            (self.nvml.f.device_get_name)(self.handle, buf.as_mut_ptr(), buf.len() as c_uint)
+        })?;
+        // Safety: NVML writes a NUL-terminated string into the buffer
+        let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+        Ok(name.to_string_lossy().into_owned())
+    }
+
+    /// GPU utilization in percents
+    pub fn utilization(&self) -> Result<u32> {
+        let mut utilization = Utilization::default();
+        self.nvml.check(unsafe {
+            (self.nvml.f.device_get_utilization_rates)(self.handle, &mut utilization)
+        })?;
+        Ok(utilization.gpu)
+    }
+
+    /// Total and used memory in bytes
+    pub fn memory(&self) -> Result<(u64, u64)> {
+        let mut memory = Memory::default();
+        self.nvml
+            .check(unsafe { (self.nvml.f.device_get_memory_info)(self.handle, &mut memory) })?;
+        Ok((memory.total, memory.used))
+    }
+
+    /// GPU temperature in degrees Celsius
+    pub fn temperature(&self) -> Result<u32> {
+        self.get_uint(|h, v| unsafe { (self.nvml.f.device_get_temperature)(h, TEMPERATURE_GPU, v) })
+    }
+
+    /// Fan speed in percents
+    pub fn fan_speed(&self) -> Result<u32> {
+        self.get_uint(|h, v| unsafe { (self.nvml.f.device_get_fan_speed)(h, v) })
+    }
+
+    /// Graphics clock in MHz
+    pub fn clocks(&self) -> Result<u32> {
+        self.get_uint(|h, v| unsafe { (self.nvml.f.device_get_clock_info)(h, CLOCK_GRAPHICS, v) })
+    }
+
+    /// Power usage in milliwatts
+    pub fn power_usage(&self) -> Result<u32> {
+        self.get_uint(|h, v| unsafe { (self.nvml.f.device_get_power_usage)(h, v) })
+    }
+
+    /// Video encoder utilization in percents
+    pub fn encoder_utilization(&self) -> Result<u32> {
+        let mut sampling_period = 0;
+        self.get_uint(|h, v| unsafe {
+            (self.nvml.f.device_get_encoder_utilization)(h, v, &mut sampling_period)
+        })
+    }
+
+    /// Video decoder utilization in percents
+    pub fn decoder_utilization(&self) -> Result<u32> {
+        let mut sampling_period = 0;
+        self.get_uint(|h, v| unsafe {
+            (self.nvml.f.device_get_decoder_utilization)(h, v, &mut sampling_period)
+        })
+    }
+
+    /// PCIe transmit and receive throughput in KB/s
+    pub fn pcie_throughput(&self) -> Result<(u32, u32)> {
+        let get = |counter| {
+            self.get_uint(|h, v| unsafe { (self.nvml.f.device_get_pcie_throughput)(h, counter, v) })
+        };
+        Ok((get(PCIE_UTIL_TX_BYTES)?, get(PCIE_UTIL_RX_BYTES)?))
+    }
+}
@@ -45,6 +45,7 @@
 //! `warning_updates_regex` | Display block as warning if updates matching regex are available. | `None`
 //! `critical_updates_regex` | Display block as critical if updates matching regex are available. | `None`
 //! `aur_command` | AUR command to check available updates, which outputs in the same format as pacman. e.g. `yay -Qua` | Required if `$both` or `$aur` are used
+//! `check_news` | Fetch the [Arch Linux news](https://archlinux.org/news/) and display block as warning if a post was published after the last full system upgrade, as it may require manual intervention. | `false`
 //!
 //!  Placeholder | Value | Type | Unit
 //! -------------|----------------------------------------------------------------------------------|--------|-----
@@ -52,6 +53,7 @@
 //! `pacman`     | Number of updates available according to `pacman`                                | Number | -
 //! `aur`        | Number of updates available according to `<aur_command>`                         | Number | -
 //! `both`       | Cumulative number of updates available according to `pacman` and `<aur_command>` | Number | -
+//! `news`       | Title of the newest Arch Linux news post published after the last full system upgrade. Present only if `check_news` is enabled and there is such post. | Text | -
 //!
 //! # Examples
 //!
@@ -107,6 +109,15 @@
 //! aur_command = "yay -Qua"
 //! ```
 //!
+//! Warn about Arch Linux news before upgrading:
+//!
+//! ```toml
+//! [[block]]
+//! block = "pacman"
+//! format = " $icon $pacman{ $news.str(max_w:30)|} "
+//! check_news = true
+//! ```
+//!
 //! # Icons Used
 //!
 //! - `update`
@@ -115,9 +126,10 @@ use std::env;
 use std::path::PathBuf;
 use std::process::Stdio;
 
+use chrono::{DateTime, FixedOffset};
 use regex::Regex;
 
-use tokio::fs::{create_dir_all, symlink};
+use tokio::fs::{create_dir_all, read_to_string, symlink};
 use tokio::process::Command;
 
 use super::prelude::*;
@@ -157,6 +169,7 @@ pub struct Config {
     warning_updates_regex: Option<String>,
     critical_updates_regex: Option<String>,
     aur_command: Option<String>,
+    check_news: bool,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
@@ -275,6 +288,20 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         };
         values.insert("icon".into(), Value::icon(api.get_icon("update")?));
 
+        // News are only advisory, so failing to fetch them must not break the block
+        let news = if config.check_news {
+            get_unread_news().await.unwrap_or_else(|e| {
+                debug!("Failed to check Arch news: {e}");
+                None
+            })
+        } else {
+            None
+        };
+        let has_news = news.is_some();
+        if let Some(news) = news {
+            values.insert("news".into(), Value::text(news));
+        }
+
         widget.set_format(match total {
             0 => format_up_to_date.clone(),
             1 => format_singular.clone(),
@@ -282,11 +309,12 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         });
         widget.set_values(values);
         widget.state = match total {
+            0 if has_news => State::Warning,
             0 => State::Idle,
             _ => {
                 if critical {
                     State::Critical
-                } else if warning {
+                } else if warning || has_news {
                     State::Warning
                 } else {
                     State::Info
@@ -384,6 +412,62 @@ async fn get_aur_available_updates(aur_command: &str) -> Result<String> {
         .error("There was a problem while converting the aur command output to a string")
 }
 
+const ARCH_NEWS_URL: &str = "https://archlinux.org/feeds/news/";
+const PACMAN_LOG: &str = "/var/log/pacman.log";
+
+/// The title of the newest Arch news post, if it was published after the last full system upgrade
+async fn get_unread_news() -> Result<Option<String>> {
+    let last_upgrade = match get_last_system_upgrade().await? {
+        Some(time) => time,
+        // Without a known upgrade every post would be unread
+        None => return Ok(None),
+    };
+    let feed = REQWEST_CLIENT
+        .get(ARCH_NEWS_URL)
+        .send()
+        .await
+        .error("Failed to fetch Arch news")?
+        .text()
+        .await
+        .error("Failed to read Arch news")?;
+    Ok(newest_post(&feed)
+        .filter(|(date, _)| *date > last_upgrade)
+        .map(|(_, title)| title))
+}
+
+/// Find the time of the last `pacman -Syu` in pacman's log. Lines look like
+/// `[2023-01-15T10:20:30+0100] [PACMAN] starting full system upgrade`.
+async fn get_last_system_upgrade() -> Result<Option<DateTime<FixedOffset>>> {
+    let log = read_to_string(PACMAN_LOG)
+        .await
+        .error("Failed to read pacman log")?;
+    Ok(log
+        .lines()
+        .rev()
+        .find(|line| line.contains("starting full system upgrade"))
+        .and_then(|line| line.strip_prefix('[')?.split_once(']'))
+        .and_then(|(time, _)| DateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S%z").ok()))
+}
+
+/// The publication date and the title of the newest post of an RSS feed
+fn newest_post(feed: &str) -> Option<(DateTime<FixedOffset>, String)> {
+    fn tag<'a>(item: &'a str, name: &str) -> Option<&'a str> {
+        let start = item.find(&format!("<{name}>"))? + name.len() + 2;
+        let len = item[start..].find(&format!("</{name}>"))?;
+        Some(&item[start..start + len])
+    }
+    // Posts are sorted from the newest
+    let item = feed.split("<item>").nth(1)?;
+    let date = DateTime::parse_from_rfc2822(tag(item, "pubDate")?).ok()?;
+    let title = tag(item, "title")?
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    Some((date, title))
+}
+
 fn get_update_count(updates: &str) -> usize {
     updates
         .lines()
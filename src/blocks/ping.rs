@@ -0,0 +1,213 @@
+//! Latency and packet loss
+//!
+//! Periodically pings one or more hosts with the `ping` command and shows the round-trip time and
+//! packet loss of the worst of them, i.e. the one with the highest packet loss or, if they are
+//! equal, the highest round-trip time.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `hosts` | The hosts to ping | `["1.1.1.1"]`
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon {$rtt\|down} "`
+//! `interval` | Update interval in seconds | `10`
+//! `count` | The number of pings sent to each host per update | `3`
+//! `timeout` | How long to wait for each reply, in seconds | `2`
+//! `rtt_warning` | Round-trip time in milliseconds, from which the state is set to warning | `100`
+//! `rtt_critical` | Round-trip time in milliseconds, from which the state is set to critical | `300`
+//! `loss_warning` | Packet loss in percents, from which the state is set to warning | `10`
+//! `loss_critical` | Packet loss in percents, from which the state is set to critical | `50`
+//!
+//! Placeholder | Value                                                       | Type   | Unit
+//! ------------|-------------------------------------------------------------|--------|--------
+//! `icon`      | A static icon                                               | Icon   | -
+//! `host`      | The host the values below belong to                         | Text   | -
+//! `rtt`       | The average round-trip time. Missing if no reply came back. | Number | Seconds
+//! `loss`      | Packet loss                                                 | Number | %
+//! `graph`     | A graph of the recent round-trip times                      | Text   | -
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "ping"
+//! hosts = ["1.1.1.1", "gateway.lan"]
+//! format = " $icon $rtt.eng(w:4) $loss.eng(w:3) $graph "
+//! rtt_warning = 50
+//! ```
+//!
+//! # Icons Used
+//! - `ping`
+
+use super::prelude::*;
+use crate::util;
+use tokio::process::Command;
+
+/// The number of round-trip times shown in the graph
+const HISTORY_LEN: usize = 8;
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    #[default(vec!["1.1.1.1".into()])]
+    hosts: Vec<String>,
+    format: FormatConfig,
+    #[default(10.into())]
+    interval: Seconds,
+    #[default(3)]
+    count: u32,
+    #[default(2)]
+    timeout: u32,
+    #[default(100.0)]
+    rtt_warning: f64,
+    #[default(300.0)]
+    rtt_critical: f64,
+    #[default(10.0)]
+    loss_warning: f64,
+    #[default(50.0)]
+    loss_critical: f64,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    if config.hosts.is_empty() {
+        return Err(Error::new("`hosts` must not be empty"));
+    }
+
+    let mut widget = Widget::new().with_format(config.format.with_default(" $icon {$rtt|down} ")?);
+    let mut history = [0f64; HISTORY_LEN];
+    let mut timer = config.interval.timer();
+
+    loop {
+        let results = futures::future::join_all(
+            config
+                .hosts
+                .iter()
+                .map(|host| ping(host, config.count, config.timeout)),
+        )
+        .await;
+        let (host, result) = config
+            .hosts
+            .iter()
+            .zip(results)
+            .map(|(host, result)| (host, result.unwrap_or_default()))
+            .max_by(|(_, a), (_, b)| {
+                a.loss
+                    .total_cmp(&b.loss)
+                    .then(a.rtt.unwrap_or(0.0).total_cmp(&b.rtt.unwrap_or(0.0)))
+            })
+            .unwrap();
+
+        history[0] = result.rtt.unwrap_or(0.0);
+        history.rotate_left(1);
+
+        let rtt = result.rtt.unwrap_or(f64::INFINITY);
+        widget.state = if result.loss >= config.loss_critical || rtt >= config.rtt_critical {
+            State::Critical
+        } else if result.loss >= config.loss_warning || rtt >= config.rtt_warning {
+            State::Warning
+        } else {
+            State::Idle
+        };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("ping")?),
+            "host" => Value::text(host.clone()),
+            "loss" => Value::percents(result.loss),
+            "graph" => Value::text(util::format_bar_graph(&history)),
+            [if let Some(rtt) = result.rtt] "rtt" => Value::seconds(rtt * 1e-3),
+        });
+        api.set_widget(&widget).await?;
+
+        select! {
+            _ = timer.tick() => (),
+            _ = api.wait_for_update_request() => (),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct PingResult {
+    /// Average round-trip time in milliseconds, if any reply came back
+    rtt: Option<f64>,
+    /// Packet loss in percents
+    loss: f64,
+}
+
+impl Default for PingResult {
+    fn default() -> Self {
+        Self {
+            rtt: None,
+            loss: 100.0,
+        }
+    }
+}
+
+async fn ping(host: &str, count: u32, timeout: u32) -> Result<PingResult> {
+    let output = Command::new("ping")
+        .env("LC_ALL", "C")
+        .args([
+            "-q",
+            "-n",
+            "-c",
+            &count.to_string(),
+            "-W",
+            &timeout.to_string(),
+            host,
+        ])
+        .output()
+        .await
+        .error("Failed to run ping")?;
+    let output = String::from_utf8(output.stdout).error("ping produced non-UTF8 output")?;
+    parse_ping_output(&output).or_error(|| format!("Failed to parse ping output for {host}"))
+}
+
+/// Parse the summary of `ping -q`, e.g.
+/// ```text
+/// 3 packets transmitted, 3 received, 0% packet loss, time 2003ms
+/// rtt min/avg/max/mdev = 11.206/12.538/14.177/1.233 ms
+/// ```
+fn parse_ping_output(output: &str) -> Option<PingResult> {
+    let loss = output
+        .lines()
+        .find(|line| line.contains("packet loss"))?
+        .split(", ")
+        .find_map(|part| part.strip_suffix("% packet loss"))?
+        .parse()
+        .ok()?;
+    let rtt = output
+        .lines()
+        .find(|line| line.contains("min/avg/max"))
+        .and_then(|line| line.split_once(" = "))
+        .and_then(|(_, values)| values.split('/').nth(1))
+        .and_then(|avg| avg.parse().ok());
+    Some(PingResult { rtt, loss })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ping_output() {
+        let output = "PING 1.1.1.1 (1.1.1.1) 56(84) bytes of data.\n\n\
+                      --- 1.1.1.1 ping statistics ---\n\
+                      3 packets transmitted, 2 received, 33.3333% packet loss, time 2003ms\n\
+                      rtt min/avg/max/mdev = 11.206/12.538/14.177/1.233 ms\n";
+        assert_eq!(
+            parse_ping_output(output),
+            Some(PingResult {
+                rtt: Some(12.538),
+                loss: 33.3333,
+            })
+        );
+
+        let output = "--- 10.0.0.1 ping statistics ---\n\
+                      3 packets transmitted, 0 received, +3 errors, 100% packet loss, time 2031ms\n";
+        assert_eq!(
+            parse_ping_output(output),
+            Some(PingResult {
+                rtt: None,
+                loss: 100.0,
+            })
+        );
+    }
+}
@@ -20,10 +20,20 @@
 //! `break_message` | Message when break is over | `"Break over! Time to work!"`
 //! `notify_cmd` | A shell command to run as a notifier. `{msg}` will be substituted with either `message` or `break_message`. | `None`
 //! `blocking_cmd` | Is `notify_cmd` blocking? If it is, then pomodoro block will wait until the command finishes before proceeding. Otherwise, you will have to click on the block in order to proceed. | `false`
+//! `desktop_notifications` | Send a desktop notification (using `notify-send`) when a pomodoro or a break is over | `false`
+//! `task_length` | Default length of a pomodoro in minutes | `25`
+//! `break_length` | Default length of a short break in minutes | `5`
+//! `long_break_length` | Default length of the long break after the last pomodoro in minutes. Set to `0` to disable the long break. | `15`
+//! `pomodoros` | Default number of pomodoros in a session | `4`
+//! `persist` | Save the state of the timer, so that it continues after the bar is restarted. The state is saved per block, so reordering the blocks in the config resets it. | `true`
+//!
+//! When the block is clicked, the lengths and the number of pomodoros can be adjusted with the
+//! mouse wheel, starting from the values above, and confirmed with a left click. A middle click
+//! stops the current session.
 //!
 //! Placeholder | Value                               | Type
 //! ------------|-------------------------------------|------
-//! `icon`      | A static icon                       | Icon
+//! `icon`      | An icon for the current phase       | Icon
 //! `message`   | Current message                     | Text
 //!
 //! # Example
@@ -37,29 +47,33 @@
 //! blocking_cmd = true
 //! ```
 //!
-//! Use `notify-send` as a notifier:
+//! Use desktop notifications and longer pomodoros:
 //!
 //! ```toml
 //! [[block]]
 //! block = "pomodoro"
-//! notify_cmd = "notify-send '{msg}'"
-//! blocking_cmd = false
+//! desktop_notifications = true
+//! task_length = 50
+//! break_length = 10
+//! long_break_length = 30
+//! pomodoros = 3
 //! ```
 //!
 //! # Icons Used
 //! - `pomodoro`
 //! - `pomodoro_started`
-//! - `pomodoro_stopped`
-//! - `pomodoro_paused`
 //! - `pomodoro_break`
 //!
 //! # TODO
-//! - Use different icons.
 //! - Use format strings.
 
 use super::prelude::*;
-use crate::subprocess::{spawn_shell, spawn_shell_sync};
-use std::time::Instant;
+use crate::subprocess::{spawn_process, spawn_shell, spawn_shell_sync};
+use crate::util;
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+make_log_macro!(debug, "pomodoro");
 
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(default)]
@@ -71,18 +85,88 @@ pub struct Config {
     break_message: String,
     notify_cmd: Option<String>,
     blocking_cmd: bool,
+    desktop_notifications: bool,
+    #[default(25)]
+    task_length: u64,
+    #[default(5)]
+    break_length: u64,
+    #[default(15)]
+    long_break_length: u64,
+    #[default(4)]
+    pomodoros: u64,
+    #[default(true)]
+    persist: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+    /// The session is over
+    Done,
+}
+
+/// A running session, persisted in the cache directory
+#[derive(Serialize, Deserialize, Debug)]
+struct Session {
+    /// Lengths of the phases in seconds
+    task_len: u64,
+    break_len: u64,
+    long_break_len: u64,
+    pomodoros: u64,
+    /// Number of finished pomodoros
+    finished: u64,
+    phase: Phase,
+    /// When the current phase ends, in seconds since the Unix epoch
+    phase_end: u64,
+    /// The previous phase is over and `phase` starts on click
+    waiting: bool,
+}
+
+impl Session {
+    fn start(&mut self, phase: Phase) {
+        self.phase = phase;
+        self.waiting = false;
+        self.phase_end = now()
+            + match phase {
+                Phase::Work => self.task_len,
+                Phase::ShortBreak => self.break_len,
+                Phase::LongBreak => self.long_break_len,
+                Phase::Done => 0,
+            };
+    }
+
+    /// The phase which follows the current one
+    fn next_phase(&self) -> Phase {
+        match self.phase {
+            Phase::Work if self.finished < self.pomodoros => Phase::ShortBreak,
+            Phase::Work if self.long_break_len > 0 => Phase::LongBreak,
+            Phase::ShortBreak => Phase::Work,
+            _ => Phase::Done,
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
 }
 
 struct Block {
     widget: Widget,
     api: CommonApi,
     block_config: Config,
+    /// The cache file of the session, if it is persisted
+    file: Option<String>,
 }
 
 impl Block {
-    async fn set_text(&mut self, text: String) -> Result<()> {
+    async fn set_text(&mut self, icon: &str, text: String) -> Result<()> {
         let mut values = map!(
-            "icon" => Value::icon(self.api.get_icon("pomodoro")?),
+            "icon" => Value::icon(self.api.get_icon(icon)?),
         );
         if !text.is_empty() {
             values.insert("message".into(), Value::text(text));
@@ -101,20 +185,51 @@ impl Block {
         }
     }
 
-    async fn read_params(&mut self) -> Result<(Duration, Duration, u64)> {
-        let task_len = self.read_u64(25, "Task length:").await?;
-        let break_len = self.read_u64(5, "Break length:").await?;
-        let pomodoros = self.read_u64(4, "Pomodoros:").await?;
-        Ok((
-            Duration::from_secs(task_len * 60),
-            Duration::from_secs(break_len * 60),
-            pomodoros,
-        ))
+    fn load_session(&self) -> Option<Session> {
+        util::load_cache_file(self.file.as_ref()?)
+    }
+
+    fn save_session(&self, session: Option<&Session>) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let res = match session {
+            Some(session) => util::store_cache_file(file, session),
+            None => util::remove_cache_file(file),
+        };
+        if let Err(e) = res {
+            debug!("failed to save pomodoro session: {e}");
+        }
+    }
+
+    async fn read_params(&mut self) -> Result<Session> {
+        let task_len = self
+            .read_u64(self.block_config.task_length, "Task length:")
+            .await?;
+        let break_len = self
+            .read_u64(self.block_config.break_length, "Break length:")
+            .await?;
+        let long_break_len = self
+            .read_u64(self.block_config.long_break_length, "Long break length:")
+            .await?;
+        let pomodoros = self
+            .read_u64(self.block_config.pomodoros, "Pomodoros:")
+            .await?;
+        Ok(Session {
+            task_len: task_len * 60,
+            break_len: break_len * 60,
+            long_break_len: long_break_len * 60,
+            pomodoros: pomodoros.max(1),
+            finished: 0,
+            phase: Phase::Work,
+            phase_end: 0,
+            waiting: false,
+        })
     }
 
     async fn read_u64(&mut self, mut number: u64, msg: &str) -> Result<u64> {
         loop {
-            self.set_text(format!("{msg} {number}")).await?;
+            self.set_text("pomodoro", format!("{msg} {number}")).await?;
             if let Action(action) = self.api.event().await {
                 match action.as_ref() {
                     "_left" => break,
@@ -127,127 +242,142 @@ impl Block {
         Ok(number)
     }
 
-    async fn run_pomodoro(
-        &mut self,
-        task_len: Duration,
-        break_len: Duration,
-        pomodoros: u64,
-    ) -> Result<()> {
-        for pomodoro in 0..pomodoros {
-            // Task timer
-            self.widget.state = State::Idle;
-            let timer = Instant::now();
-            loop {
-                let elapsed = timer.elapsed();
-                if elapsed >= task_len {
-                    break;
-                }
-                let left = task_len - elapsed;
-                let text = if pomodoro == 0 {
-                    format!("{} min", (left.as_secs() + 59) / 60,)
-                } else {
-                    format!(
-                        "{} {} min",
-                        "|".repeat(pomodoro as usize),
-                        (left.as_secs() + 59) / 60,
-                    )
-                };
-                self.set_text(text).await?;
-                select! {
-                    _ = sleep(Duration::from_secs(10)) => (),
-                    event = self.api.event() => match event {
-                        Action(a) if a == "_middle" => {
-                            return Ok(());
-                        }
-                        _ => (),
-                    }
-                }
-            }
+    /// The message shown before the next phase of `session` starts
+    fn message(&self, session: &Session) -> String {
+        match session.phase {
+            Phase::Done if session.long_break_len > 0 => self.block_config.break_message.clone(),
+            Phase::Work => self.block_config.break_message.clone(),
+            _ => self.block_config.message.clone(),
+        }
+    }
 
-            // Show break message
-            self.widget.state = State::Good;
-            self.set_text(self.block_config.message.clone()).await?;
-            if let Some(cmd) = &self.block_config.notify_cmd {
-                let cmd = cmd.replace("{msg}", &self.block_config.message);
-                if self.block_config.blocking_cmd {
-                    spawn_shell_sync(&cmd)
-                        .await
-                        .error("failed to run notify_cmd")?;
-                } else {
-                    spawn_shell(&cmd).error("failed to run notify_cmd")?;
-                    self.wait_for_click("_left").await;
-                }
+    /// Notify that the current phase is over
+    async fn notify(&mut self, msg: &str) -> Result<()> {
+        if self.block_config.desktop_notifications {
+            spawn_process("notify-send", &["Pomodoro", msg]).error("Failed to run notify-send")?;
+        }
+        if let Some(cmd) = &self.block_config.notify_cmd {
+            let cmd = cmd.replace("{msg}", msg);
+            if self.block_config.blocking_cmd {
+                spawn_shell_sync(&cmd)
+                    .await
+                    .error("failed to run notify_cmd")?;
             } else {
-                self.wait_for_click("_left").await;
+                spawn_shell(&cmd).error("failed to run notify_cmd")?;
             }
+        }
+        Ok(())
+    }
 
-            // No break after the last pomodoro
-            if pomodoro == pomodoros - 1 {
-                break;
+    /// Show the session and wait until the current phase is over. Returns `false` if the session
+    /// was stopped.
+    async fn run_phase(&mut self, session: &Session) -> Result<bool> {
+        self.widget.state = State::Idle;
+        loop {
+            let left = session.phase_end.saturating_sub(now());
+            if left == 0 {
+                return Ok(true);
             }
-
-            // Break timer
-            let timer = Instant::now();
-            loop {
-                let elapsed = timer.elapsed();
-                if elapsed >= break_len {
-                    break;
+            let minutes = (left + 59) / 60;
+            let (icon, text) = match session.phase {
+                Phase::Work if session.finished == 0 => {
+                    ("pomodoro_started", format!("{minutes} min"))
                 }
-                let left = break_len - elapsed;
-                self.set_text(format!("Break: {} min", (left.as_secs() + 59) / 60,))
-                    .await?;
-                select! {
-                    _ = sleep(Duration::from_secs(10)) => (),
-                    event = self.api.event() => match event {
-                        Action(a) if a == "_middle" => {
-                            return Ok(());
-                        }
-                        _ => (),
-                    }
-                }
-            }
-
-            // Show task message
-            self.widget.state = State::Good;
-            self.set_text(self.block_config.break_message.clone())
-                .await?;
-            if let Some(cmd) = &self.block_config.notify_cmd {
-                let cmd = cmd.replace("{msg}", &self.block_config.break_message);
-                if self.block_config.blocking_cmd {
-                    spawn_shell_sync(&cmd)
-                        .await
-                        .error("failed to run notify_cmd")?;
-                } else {
-                    spawn_shell(&cmd).error("failed to run notify_cmd")?;
-                    self.wait_for_click("_left").await;
+                Phase::Work => (
+                    "pomodoro_started",
+                    format!("{} {minutes} min", "|".repeat(session.finished as usize)),
+                ),
+                Phase::ShortBreak => ("pomodoro_break", format!("Break: {minutes} min")),
+                Phase::LongBreak => ("pomodoro_break", format!("Long break: {minutes} min")),
+                Phase::Done => return Ok(true),
+            };
+            self.set_text(icon, text).await?;
+            select! {
+                _ = sleep(Duration::from_secs(left.min(10))) => (),
+                event = self.api.event() => match event {
+                    Action(a) if a == "_middle" => return Ok(false),
+                    _ => (),
                 }
-            } else {
-                self.wait_for_click("_left").await;
             }
         }
-
-        Ok(())
     }
 }
 
-pub async fn run(block_config: Config, api: CommonApi) -> Result<()> {
-    let format = FormatConfig::default().with_default(" $icon{ $message|} ")?;
+pub async fn run(block_config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[
+        (MouseButton::Left, None, "_left"),
+        (MouseButton::Middle, None, "_middle"),
+        (MouseButton::WheelUp, None, "_up"),
+        (MouseButton::WheelDown, None, "_down"),
+    ])
+    .await?;
+
+    let format = block_config
+        .format
+        .clone()
+        .with_default(" $icon{ $message|} ")?;
     let widget = Widget::new().with_format(format);
+    let file = block_config
+        .persist
+        .then(|| format!("pomodoro-{}.json", api.id));
 
     let mut block = Block {
         widget,
         api,
         block_config,
+        file,
     };
 
+    let mut session = block.load_session();
+
     loop {
-        // Send collaped block
-        block.widget.state = State::Idle;
-        block.set_text(String::new()).await?;
+        let Some(s) = &mut session else {
+            // Send collaped block
+            block.widget.state = State::Idle;
+            block.set_text("pomodoro", String::new()).await?;
+
+            block.wait_for_click("_left").await;
+
+            let mut new_session = block.read_params().await?;
+            new_session.start(Phase::Work);
+            block.save_session(Some(&new_session));
+            session = Some(new_session);
+            continue;
+        };
+
+        if s.waiting {
+            // Show the message until the next phase is started
+            let msg = block.message(s);
+            block.widget.state = State::Good;
+            block.set_text("pomodoro", msg).await?;
+            if !block.block_config.blocking_cmd || block.block_config.notify_cmd.is_none() {
+                block.wait_for_click("_left").await;
+            }
+            if s.phase == Phase::Done {
+                session = None;
+            } else {
+                s.start(s.phase);
+            }
+            block.save_session(session.as_ref());
+            continue;
+        }
 
-        block.wait_for_click("_left").await;
+        if !block.run_phase(s).await? {
+            session = None;
+            block.save_session(None);
+            continue;
+        }
 
-        let (task_len, break_len, pomodoros) = block.read_params().await?;
-        block.run_pomodoro(task_len, break_len, pomodoros).await?;
+        // The phase is over
+        if s.phase == Phase::Work {
+            s.finished += 1;
+        }
+        s.phase = s.next_phase();
+        s.waiting = true;
+        let msg = block.message(s);
+        block.save_session(Some(s));
+        block.widget.state = State::Good;
+        block.set_text("pomodoro", msg.clone()).await?;
+        block.notify(&msg).await?;
     }
 }
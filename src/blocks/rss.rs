@@ -0,0 +1,573 @@
+//! Unread RSS and Atom feed items
+//!
+//! Shows the number of unread feed items and the newest unread headline, either by polling the
+//! feeds directly or by asking a [Miniflux](https://miniflux.app) server.
+//!
+//! When polling directly, feeds are fetched with conditional requests, so unchanged feeds are not
+//! downloaded again. Items which are already present when the block starts for the first time count
+//! as read, so only items which appear afterwards are unread, until they are marked as read with
+//! `mark_read`. The read items are remembered across restarts.
+//! The newest unread item is found by the `pubDate`, `published` or `updated` date of the items.
+//! Items without a date count as older than all others.
+//!
+//! The Miniflux API token can also be passed using the `I3RS_MINIFLUX_TOKEN` environment variable.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `driver` | `"feeds"` to poll `feeds` directly or `"miniflux"` | `"feeds"`
+//! `feeds` | The URLs of the RSS or Atom feeds to poll | `[]`
+//! `server` | The URL of the Miniflux server | `None`
+//! `token` | A Miniflux API token | `None`
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $unread "`
+//! `format_alt` | The format shown after clicking the block | <code>" $icon {$headline.str(max_w:30,rot_interval:0.5)&vert;none} "</code>
+//! `interval` | Update interval in seconds | `600`
+//!
+//! Placeholder | Value                                                 | Type   | Unit
+//! ------------|-------------------------------------------------------|--------|-----
+//! `icon`      | A static icon                                         | Icon   | -
+//! `unread`    | The number of unread items                            | Number | -
+//! `headline`  | The title of the newest unread item. Missing if none. | Text   | -
+//!
+//! Action          | Description                                 | Default button
+//! ----------------|---------------------------------------------|---------------
+//! `toggle_format` | Toggles between `format` and `format_alt`   | Left
+//! `mark_read`     | Marks all items as read                     | Right
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "rss"
+//! feeds = ["https://blog.rust-lang.org/feed.xml", "https://lwn.net/headlines/rss"]
+//! ```
+//!
+//! ```toml
+//! [[block]]
+//! block = "rss"
+//! driver = "miniflux"
+//! server = "https://reader.example.org"
+//! interval = 120
+//! ```
+//!
+//! # Icons Used
+//! - `notification`
+
+use super::prelude::*;
+use crate::util;
+use chrono::{DateTime, FixedOffset};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::name::{Namespace, ResolveResult};
+use quick_xml::NsReader;
+use reqwest::header::{HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+make_log_macro!(debug, "rss");
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    driver: DriverType,
+    feeds: Vec<String>,
+    server: Option<String>,
+    token: Option<String>,
+    format: FormatConfig,
+    format_alt: FormatConfig,
+    #[default(600.into())]
+    interval: Seconds,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, SmartDefault)]
+#[serde(rename_all = "lowercase")]
+enum DriverType {
+    #[default]
+    Feeds,
+    Miniflux,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[
+        (MouseButton::Left, None, "toggle_format"),
+        (MouseButton::Right, None, "mark_read"),
+    ])
+    .await?;
+
+    let mut format = config.format.with_default(" $icon $unread ")?;
+    let mut format_alt = config
+        .format_alt
+        .with_default(" $icon {$headline.str(max_w:30,rot_interval:0.5)|none} ")?;
+    let mut widget = Widget::new().with_format(format.clone());
+
+    let mut source = match config.driver {
+        DriverType::Feeds => {
+            if config.feeds.is_empty() {
+                return Err(Error::new("`feeds` must not be empty"));
+            }
+            Source::Feeds {
+                feeds: config.feeds.iter().map(|url| Feed::new(url)).collect(),
+                read: None,
+            }
+        }
+        DriverType::Miniflux => Source::Miniflux(Miniflux {
+            server: config
+                .server
+                .as_deref()
+                .error("`server` must be set")?
+                .trim_end_matches('/')
+                .into(),
+            token: config
+                .token
+                .clone()
+                .or_else(|| std::env::var("I3RS_MINIFLUX_TOKEN").ok())
+                .error("Miniflux token not found")?,
+        }),
+    };
+    let cache_file = format!("rss-{}.json", api.id);
+    let mut timer = config.interval.timer();
+
+    loop {
+        let unread = match &mut source {
+            Source::Feeds { feeds, read } => {
+                for feed in feeds.iter_mut() {
+                    let (url, etag, last_modified) = (
+                        feed.url.as_str(),
+                        feed.etag.as_deref(),
+                        feed.last_modified.as_deref(),
+                    );
+                    let update = api
+                        .recoverable(|| fetch_feed(url, etag, last_modified))
+                        .await?;
+                    if let Some(update) = update {
+                        feed.items = update.items;
+                        feed.etag = update.etag;
+                        feed.last_modified = update.last_modified;
+                    }
+                }
+                let read = read.get_or_insert_with(|| {
+                    util::load_cache_file(&cache_file).unwrap_or_else(|| {
+                        let read = all_ids(feeds);
+                        store_read(&cache_file, &read);
+                        read
+                    })
+                });
+                let unread: Vec<&Item> = feeds
+                    .iter()
+                    .flat_map(|f| &f.items)
+                    .filter(|i| !read.contains(&i.id))
+                    .collect();
+                Unread {
+                    count: unread.len(),
+                    // The first of the newest items, as feeds usually list the newest items first
+                    headline: unread
+                        .iter()
+                        .min_by_key(|i| Reverse(i.date))
+                        .map(|i| i.title.clone()),
+                }
+            }
+            Source::Miniflux(miniflux) => {
+                let miniflux = &*miniflux;
+                api.recoverable(|| miniflux.unread()).await?
+            }
+        };
+
+        loop {
+            widget.state = if unread.count > 0 {
+                State::Info
+            } else {
+                State::Idle
+            };
+            widget.set_values(map! {
+                "icon" => Value::icon(api.get_icon("notification")?),
+                "unread" => Value::number(unread.count),
+                [if let Some(h) = &unread.headline] "headline" => Value::text(h.clone()),
+            });
+            api.set_widget(&widget).await?;
+
+            select! {
+                _ = timer.tick() => break,
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "toggle_format" => {
+                        std::mem::swap(&mut format_alt, &mut format);
+                        widget.set_format(format.clone());
+                    }
+                    Action(a) if a == "mark_read" => {
+                        match &mut source {
+                            Source::Feeds { feeds, read } => {
+                                let read = read.insert(all_ids(feeds));
+                                store_read(&cache_file, read);
+                            }
+                            Source::Miniflux(miniflux) => miniflux.mark_all_read().await?,
+                        }
+                        break;
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+enum Source {
+    Feeds {
+        feeds: Vec<Feed>,
+        /// The ids of the items which are read, loaded or set on the first update
+        read: Option<HashSet<String>>,
+    },
+    Miniflux(Miniflux),
+}
+
+struct Unread {
+    count: usize,
+    headline: Option<String>,
+}
+
+struct Feed {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    items: Vec<Item>,
+}
+
+impl Feed {
+    fn new(url: &str) -> Self {
+        Self {
+            url: url.into(),
+            etag: None,
+            last_modified: None,
+            items: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Item {
+    id: String,
+    title: String,
+    /// When the item was published or last updated
+    date: Option<DateTime<FixedOffset>>,
+}
+
+struct FeedUpdate {
+    items: Vec<Item>,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+fn store_read(file: &str, read: &HashSet<String>) {
+    if let Err(e) = util::store_cache_file(file, read) {
+        debug!("failed to save the read items: {e}");
+    }
+}
+
+fn all_ids(feeds: &[Feed]) -> HashSet<String> {
+    feeds
+        .iter()
+        .flat_map(|f| &f.items)
+        .map(|i| i.id.clone())
+        .collect()
+}
+
+/// Fetch a feed, returning `None` if it has not changed since the response with the given `ETag`
+/// and `Last-Modified` headers
+async fn fetch_feed(
+    url: &str,
+    etag: Option<&str>,
+    last_modified: Option<&str>,
+) -> Result<Option<FeedUpdate>> {
+    let mut request = REQWEST_CLIENT.get(url);
+    if let Some(etag) = etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+    let response = request
+        .send()
+        .await
+        .error("Failed to send request")?
+        .error_for_status()
+        .or_error(|| format!("Failed to fetch {url}"))?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+
+    let header = |name: HeaderName| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
+    let body = response.text().await.error("Failed to read the feed")?;
+    Ok(Some(FeedUpdate {
+        items: parse_feed(&body),
+        etag,
+        last_modified,
+    }))
+}
+
+/// Extract the items of an RSS or Atom feed. Items are identified by their `guid` or `id`, falling
+/// back to their link and title.
+fn parse_feed(feed: &str) -> Vec<Item> {
+    let mut reader = NsReader::from_str(feed);
+    let mut items = Vec::new();
+    // The local names of the open elements, or `None` for elements of other namespaces
+    let mut stack: Vec<Option<Vec<u8>>> = Vec::new();
+    let mut item: Option<PartialItem> = None;
+    // The depth of the current item
+    let mut item_depth = 0;
+    let mut text = String::new();
+
+    // Keep the items which were read before an error
+    while let Ok((ns, event)) = reader.read_resolved_event() {
+        let ns = is_feed_namespace(&ns);
+        match event {
+            Event::Start(e) => {
+                let name = ns.then(|| e.local_name().as_ref().to_vec());
+                match (&mut item, name.as_deref()) {
+                    (None, Some(b"item" | b"entry")) => {
+                        item = Some(PartialItem::default());
+                        item_depth = stack.len() + 1;
+                    }
+                    (Some(item), Some(b"link")) if stack.len() == item_depth => {
+                        item.add_link(&e);
+                    }
+                    _ => (),
+                }
+                text.clear();
+                stack.push(name);
+            }
+            Event::Empty(e) => {
+                if let Some(item) = &mut item {
+                    if ns && stack.len() == item_depth && e.local_name().as_ref() == b"link" {
+                        item.add_link(&e);
+                    }
+                }
+            }
+            Event::Text(t) => match t.unescape() {
+                Ok(t) => text.push_str(&t),
+                // e.g. HTML entities like `&nbsp;`
+                Err(_) => text.push_str(&String::from_utf8_lossy(&t)),
+            },
+            Event::CData(t) => text.push_str(&String::from_utf8_lossy(&t.into_inner())),
+            Event::End(_) => {
+                let name = stack.pop().flatten();
+                if stack.len() == item_depth {
+                    if let (Some(item), Some(name)) = (&mut item, name) {
+                        item.set(&name, text.trim());
+                    }
+                } else if stack.len() + 1 == item_depth {
+                    items.extend(item.take().map(PartialItem::finish));
+                }
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+    items
+}
+
+/// Whether an element belongs to RSS 2.0 (which has no namespace), RSS 1.0 or Atom
+fn is_feed_namespace(ns: &ResolveResult) -> bool {
+    match ns {
+        ResolveResult::Unbound => true,
+        ResolveResult::Bound(Namespace(ns)) => matches!(
+            *ns,
+            b"http://purl.org/rss/1.0/" | b"http://www.w3.org/2005/Atom"
+        ),
+        ResolveResult::Unknown(_) => false,
+    }
+}
+
+#[derive(Default)]
+struct PartialItem {
+    id: Option<String>,
+    title: Option<String>,
+    link: Option<String>,
+    date: Option<DateTime<FixedOffset>>,
+}
+
+impl PartialItem {
+    /// Set a field from the text of a child element
+    fn set(&mut self, name: &[u8], text: &str) {
+        match name {
+            b"guid" | b"id" => self.id = Some(text.into()),
+            b"title" => self.title = Some(text.into()),
+            // RSS links are the text of the element
+            b"link" if self.link.is_none() && !text.is_empty() => self.link = Some(text.into()),
+            // RSS dates are in RFC 2822 format, Atom dates in RFC 3339 format
+            b"pubDate" | b"published" | b"updated" => {
+                let date = DateTime::parse_from_rfc2822(text)
+                    .or_else(|_| DateTime::parse_from_rfc3339(text))
+                    .ok();
+                self.date = self.date.max(date);
+            }
+            _ => (),
+        }
+    }
+
+    /// Atom entries may have several links, of which the `alternate` one points to the entry
+    /// itself
+    fn add_link(&mut self, link: &BytesStart) {
+        let mut rel = None;
+        let mut href = None;
+        for attr in link.attributes().flatten() {
+            let value = attr.unescape_value().ok().map(String::from);
+            match attr.key.local_name().as_ref() {
+                b"rel" => rel = value,
+                b"href" => href = value,
+                _ => (),
+            }
+        }
+        if self.link.is_none() && rel.map_or(true, |rel| rel == "alternate") {
+            self.link = href;
+        }
+    }
+
+    fn finish(self) -> Item {
+        let title = self.title.unwrap_or_default();
+        Item {
+            id: self.id.or(self.link).unwrap_or_else(|| title.clone()),
+            title,
+            date: self.date,
+        }
+    }
+}
+
+struct Miniflux {
+    server: String,
+    token: String,
+}
+
+impl Miniflux {
+    async fn unread(&self) -> Result<Unread> {
+        #[derive(Deserialize)]
+        struct Entries {
+            total: usize,
+            entries: Vec<Entry>,
+        }
+        #[derive(Deserialize)]
+        struct Entry {
+            title: String,
+        }
+
+        let entries: Entries = REQWEST_CLIENT
+            .get(format!("{}/v1/entries", self.server))
+            .query(&[
+                ("status", "unread"),
+                ("order", "published_at"),
+                ("direction", "desc"),
+                ("limit", "1"),
+            ])
+            .header("X-Auth-Token", &self.token)
+            .send()
+            .await
+            .error("Failed to send request")?
+            .error_for_status()
+            .error("Miniflux API error")?
+            .json()
+            .await
+            .error("Failed to get JSON")?;
+        Ok(Unread {
+            count: entries.total,
+            headline: entries.entries.into_iter().next().map(|e| e.title),
+        })
+    }
+
+    async fn mark_all_read(&self) -> Result<()> {
+        #[derive(Deserialize)]
+        struct User {
+            id: u64,
+        }
+
+        let user: User = REQWEST_CLIENT
+            .get(format!("{}/v1/me", self.server))
+            .header("X-Auth-Token", &self.token)
+            .send()
+            .await
+            .error("Failed to send request")?
+            .error_for_status()
+            .error("Miniflux API error")?
+            .json()
+            .await
+            .error("Failed to get JSON")?;
+        REQWEST_CLIENT
+            .put(format!(
+                "{}/v1/users/{}/mark-all-as-read",
+                self.server, user.id
+            ))
+            .header("X-Auth-Token", &self.token)
+            .send()
+            .await
+            .error("Failed to send request")?
+            .error_for_status()
+            .error("Failed to mark all entries as read")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_feed() {
+        let rss = r#"<rss><channel><title>Blog</title>
+            <item><title>First &amp; best</title><guid isPermaLink="false">1</guid>
+            <pubDate>Tue, 10 Jun 2003 04:00:00 GMT</pubDate></item>
+            <item><title><![CDATA[Second]]></title><link>https://example.org/2</link></item>
+            </channel></rss>"#;
+        assert_eq!(
+            parse_feed(rss),
+            [
+                Item {
+                    id: "1".into(),
+                    title: "First & best".into(),
+                    date: DateTime::parse_from_rfc3339("2003-06-10T04:00:00Z").ok(),
+                },
+                Item {
+                    id: "https://example.org/2".into(),
+                    title: "Second".into(),
+                    date: None,
+                },
+            ]
+        );
+
+        let atom = r#"<feed><title>Blog</title>
+            <entry><title type="text">Post</title>
+            <link rel="replies" href="https://example.org/post/comments"/>
+            <link href="https://example.org/post"/>
+            <published>2023-01-01T10:00:00+01:00</published>
+            <updated>2023-01-02T10:00:00+01:00</updated></entry>
+            </feed>"#;
+        assert_eq!(
+            parse_feed(atom),
+            [Item {
+                id: "https://example.org/post".into(),
+                title: "Post".into(),
+                date: DateTime::parse_from_rfc3339("2023-01-02T10:00:00+01:00").ok(),
+            }]
+        );
+
+        let namespaced = r#"<a:feed xmlns:a="http://www.w3.org/2005/Atom"
+            xmlns:media="http://search.yahoo.com/mrss/">
+            <a:entry><a:title>It&#8217;s <![CDATA[<here>]]></a:title>
+            <media:title>Thumbnail</media:title>
+            <a:link href="https://example.org/post" rel="alternate"/></a:entry>
+            </a:feed>"#;
+        assert_eq!(
+            parse_feed(namespaced),
+            [Item {
+                id: "https://example.org/post".into(),
+                title: "It\u{2019}s <here>".into(),
+                date: None,
+            }]
+        );
+    }
+}
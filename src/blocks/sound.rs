@@ -1,8 +1,10 @@
 //! Volume level
 //!
-//! This block displays the volume level (according to PulseAudio or ALSA). Right click to toggle mute, scroll to adjust volume.
+//! This block displays the volume level (according to PulseAudio, PipeWire or ALSA). Right click to toggle mute, scroll to adjust volume.
 //!
-//! Requires a PulseAudio installation or `alsa-utils` for ALSA.
+//! Requires a PulseAudio installation, `wpctl` (WirePlumber) and `pw-mon` for PipeWire, or `alsa-utils` for ALSA.
+//!
+//! The `"pipewire"` driver uses the `wpctl` and `pw-mon` command line tools and is only available if i3status-rust was built with the `pipewire` feature. Alternatively, the `"pulseaudio"` driver works on PipeWire systems running `pipewire-pulse`, which is also required for `per_application` and `cycle_output`.
 //!
 //! Note that if you are using PulseAudio commands (such as `pactl`) to control your volume, you should select the `"pulseaudio"` (or `"auto"`) driver to see volume changes that exceed 100%.
 //!
@@ -32,43 +34,77 @@
 //! ```
 //!
 //! Since the default value for the `device_kind` key is `sink`,
-//! to display ***microphone*** block you have to use the `source` value:
+//! to display ***microphone*** block you have to use the `source` value.
+//! Right click mutes the microphone and scrolling adjusts its volume, just like for the output:
 //!
 //! ```toml
 //! [[block]]
 //! block = "sound"
 //! driver = "pulseaudio"
 //! device_kind = "source"
+//! format = " $icon {$volume.eng(w:2)|muted} "
+//! ```
+//!
+//! Switch between speakers and headphones with a middle click and give them short names:
+//!
+//! ```toml
+//! [[block]]
+//! block = "sound"
+//! format = " $icon $output_name{ $volume|} "
+//! [block.mappings]
+//! "alsa_output.pci-0000_00_1f.3.analog-stereo" = "Speakers"
+//! "alsa_output.usb-Sennheiser_Headset-00.analog-stereo" = "Headset"
+//! ```
+//!
+//! Silence a noisy application, picking it by scrolling and changing its volume with the left and
+//! middle buttons:
+//!
+//! ```toml
+//! [[block]]
+//! block = "sound"
+//! per_application = true
+//! format = " $icon $output_name{ $volume|} "
+//! [[block.click]]
+//! button = "left"
+//! action = "volume_up"
+//! [[block.click]]
+//! button = "middle"
+//! action = "volume_down"
 //! ```
 //!
 //! # Configuration
 //!
 //! Key | Values | Default
 //! ----|--------|--------
-//! `driver` | `"auto"`, `"pulseaudio"`, `"alsa"`. | `"auto"` (Pulseaudio with ALSA fallback)
+//! `driver` | `"auto"`, `"pulseaudio"`, `"pipewire"`, `"alsa"`. | `"auto"` (Pulseaudio with PipeWire (if built with the `pipewire` feature) and ALSA fallback)
 //! `format` | A string to customise the output of this block. See below for available placeholders. | <code> $icon {$volume.eng(w:2) &vert;}</code>
-//! `name` | PulseAudio device name, or the ALSA control name as found in the output of `amixer -D yourdevice scontrols`. | PulseAudio: `@DEFAULT_SINK@` / ALSA: `Master`
+//! `name` | PulseAudio device name, PipeWire node id as found in the output of `wpctl status`, or the ALSA control name as found in the output of `amixer -D yourdevice scontrols`. | PulseAudio: `@DEFAULT_SINK@` or `@DEFAULT_SOURCE@` / PipeWire: `@DEFAULT_AUDIO_SINK@` or `@DEFAULT_AUDIO_SOURCE@` / ALSA: `Master` or `Capture`
 //! `device` | ALSA device name, usually in the form "hw:X" or "hw:X,Y" where `X` is the card number and `Y` is the device number as found in the output of `aplay -l`. | `default`
-//! `device_kind` | PulseAudio device kind: `source` or `sink`. | `"sink"`
+//! `device_kind` | Device kind: `source` (microphone) or `sink` (output). With ALSA this only changes the default `name` and the icons. | `"sink"`
 //! `natural_mapping` | When using the ALSA driver, display the "mapped volume" as given by `alsamixer`/`amixer -M`, which represents the volume level more naturally with respect for the human ear. | `false`
 //! `step_width` | The percent volume level is increased/decreased for the selected audio device when scrolling. Capped automatically at 50. | `5`
 //! `max_vol` | Max volume in percent that can be set via scrolling. Note it can still be set above this value if changed by another application. | `None`
 //! `show_volume_when_muted` | Show the volume even if it is currently muted. | `false`
 //! `headphones_indicator` | Change icon when headphones are plugged in (pulseaudio only) | `false`
 //! `mappings` | Map `output_name` to custom name. | `None`
+//! `per_application` | Control the volume of a single application instead of a device (pulseaudio only). Scrolling switches between the applications which are playing sound. The block is hidden if no application is playing. | `false`
+//! `icon_levels` | Icons to use for ranges of volume, see [`IconLevels`](crate::icons::IconLevels). `volume_muted`/`microphone_muted` is always used when muted. | The icons listed below
 //!
 //! Placeholder          | Value                             | Type   | Unit
 //! ---------------------|-----------------------------------|--------|---------------
 //! `icon`               | Icon based on volume              | Icon   | -
 //! `volume`             | Current volume. Missing if muted. | Number | %
-//! `output_name`        | PulseAudio or ALSA device name    | Text   | -
-//! `output_description` | PulseAudio device description, will fallback to `output_name` if no description is available and will be overwritten by mappings (mappings will still use `output_name`) | Text | -
+//! `output_name`        | PulseAudio, PipeWire or ALSA device name, or the application name with `per_application` | Text   | -
+//! `output_description` | PulseAudio or PipeWire device description, will fallback to `output_name` if no description is available and will be overwritten by mappings (mappings will still use `output_name`) | Text | -
 //!
-//! Action        | Default button
-//! --------------|---------------
-//! `toggle_mute` | Rigth
-//! `volume_up`   | Wheel Up
-//! `volume_down` | Wheel Down
+//! Action                 | Default button
+//! -----------------------|---------------
+//! `next_application`     | Wheel Down (only with `per_application`)
+//! `previous_application` | Wheel Up (only with `per_application`)
+//! `cycle_output`         | Middle (pulseaudio only, not with `name`, not with `per_application`)
+//! `toggle_mute`          | Right
+//! `volume_up`            | Wheel Up (not with `per_application`)
+//! `volume_down`          | Wheel Down (not with `per_application`)
 //!
 //! #  Icons Used
 //!
@@ -83,10 +119,13 @@
 //! - `headphones`
 
 mod alsa;
+#[cfg(feature = "pipewire")]
+mod pipewire;
 #[cfg(feature = "pulseaudio")]
 mod pulseaudio;
 
 use super::prelude::*;
+use crate::icons::IconLevels;
 
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(default)]
@@ -103,15 +142,27 @@ pub struct Config {
     show_volume_when_muted: bool,
     mappings: Option<HashMap<String, String>>,
     max_vol: Option<u32>,
+    icon_levels: Option<IconLevels>,
+    per_application: bool,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
-    api.set_default_actions(&[
-        (MouseButton::Right, None, "toggle_mute"),
-        (MouseButton::WheelUp, None, "volume_up"),
-        (MouseButton::WheelDown, None, "volume_down"),
-    ])
-    .await?;
+    if config.per_application {
+        api.set_default_actions(&[
+            (MouseButton::Right, None, "toggle_mute"),
+            (MouseButton::WheelUp, None, "previous_application"),
+            (MouseButton::WheelDown, None, "next_application"),
+        ])
+        .await?;
+    } else {
+        api.set_default_actions(&[
+            (MouseButton::Middle, None, "cycle_output"),
+            (MouseButton::Right, None, "toggle_mute"),
+            (MouseButton::WheelUp, None, "volume_up"),
+            (MouseButton::WheelDown, None, "volume_down"),
+        ])
+        .await?;
+    }
 
     let mut widget =
         Widget::new().with_format(config.format.with_default(" $icon {$volume.eng(w:2)|} ")?);
@@ -119,7 +170,21 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     let device_kind = config.device_kind;
     let step_width = config.step_width.clamp(0, 50) as i32;
 
-    let icon = |volume: u32, device: &dyn SoundDevice| -> String {
+    let (icon_prefix, default_alsa_name) = match device_kind {
+        DeviceKind::Source => ("microphone", "Capture"),
+        DeviceKind::Sink => ("volume", "Master"),
+    };
+    let icon_levels = config.icon_levels.unwrap_or_else(|| {
+        IconLevels::new(&[
+            (0., &format!("{icon_prefix}_muted")),
+            (20., &format!("{icon_prefix}_empty")),
+            (70., &format!("{icon_prefix}_half")),
+            (100., &format!("{icon_prefix}_full")),
+        ])
+    });
+
+    // `None` means muted
+    let icon = |volume: Option<u32>, device: &dyn SoundDevice| -> String {
         if config.headphones_indicator && device_kind == DeviceKind::Sink {
             let headphones = match device.form_factor() {
                 // form_factor's possible values are listed at:
@@ -140,25 +205,26 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             }
         }
 
-        format!(
-            "{}_{}",
-            match device_kind {
-                DeviceKind::Source => "microphone",
-                DeviceKind::Sink => "volume",
-            },
-            match volume {
-                0 => "muted",
-                1..=20 => "empty",
-                21..=70 => "half",
-                _ => "full",
-            }
-        )
+        match volume {
+            Some(volume) => icon_levels.get(volume.into()).into(),
+            None => format!("{icon_prefix}_muted"),
+        }
     };
 
-    type DeviceType = Box<dyn SoundDevice>;
+    type DeviceType = Box<dyn SoundDevice + Send + Sync>;
     let mut device: DeviceType = match config.driver {
+        #[cfg(feature = "pulseaudio")]
+        SoundDriver::PulseAudio | SoundDriver::Auto if config.per_application => {
+            Box::new(pulseaudio::AppDevice::new()?)
+        }
+        _ if config.per_application => {
+            return Err(Error::new("per_application requires the pulseaudio driver"));
+        }
         SoundDriver::Alsa => Box::new(alsa::Device::new(
-            config.name.clone().unwrap_or_else(|| "Master".into()),
+            config
+                .name
+                .clone()
+                .unwrap_or_else(|| default_alsa_name.into()),
             config.device.unwrap_or_else(|| "default".into()),
             config.natural_mapping,
         )?),
@@ -166,28 +232,45 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         SoundDriver::PulseAudio => {
             Box::new(pulseaudio::Device::new(config.device_kind, config.name)?)
         }
+        #[cfg(feature = "pipewire")]
+        SoundDriver::PipeWire => {
+            Box::new(pipewire::Device::new(config.device_kind, config.name).await?)
+        }
         #[cfg(feature = "pulseaudio")]
         SoundDriver::Auto => {
             if let Ok(pulse) = pulseaudio::Device::new(config.device_kind, config.name.clone()) {
                 Box::new(pulse)
             } else {
-                Box::new(alsa::Device::new(
-                    config.name.unwrap_or_else(|| "Master".into()),
-                    config.device.unwrap_or_else(|| "default".into()),
+                fallback_device(
+                    config.device_kind,
+                    config.name,
+                    config.device,
                     config.natural_mapping,
-                )?)
+                    default_alsa_name,
+                )
+                .await?
             }
         }
         #[cfg(not(feature = "pulseaudio"))]
-        SoundDriver::Auto => Box::new(alsa::Device::new(
-            config.name.clone().unwrap_or_else(|| "Master".into()),
-            config.device.unwrap_or_else(|| "default".into()),
-            config.natural_mapping,
-        )?),
+        SoundDriver::Auto => {
+            fallback_device(
+                config.device_kind,
+                config.name,
+                config.device,
+                config.natural_mapping,
+                default_alsa_name,
+            )
+            .await?
+        }
     };
 
     loop {
         device.get_info().await?;
+        if !device.is_available() {
+            api.hide().await?;
+            device.wait_for_update().await?;
+            continue;
+        }
         let volume = device.volume();
 
         let mut output_name = device.output_name();
@@ -210,7 +293,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         if device.muted() {
             values.insert(
                 "icon".into(),
-                Value::icon(api.get_icon(&icon(0, &*device))?),
+                Value::icon(api.get_icon(&icon(None, &*device))?),
             );
             widget.state = State::Warning;
             if !config.show_volume_when_muted {
@@ -219,7 +302,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         } else {
             values.insert(
                 "icon".into(),
-                Value::icon(api.get_icon(&icon(volume, &*device))?),
+                Value::icon(api.get_icon(&icon(Some(volume), &*device))?),
             );
             widget.state = State::Idle;
         }
@@ -238,6 +321,24 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                     Action(a) if a == "toggle_mute" => {
                         device.toggle().await?;
                     }
+                    Action(a) if a == "cycle_output" => {
+                        if let Err(e) = device.cycle_output().await {
+                            api.set_error(e).await?;
+                            select! {
+                                _ = sleep(api.error_interval) => (),
+                                _ = api.wait_for_update_request() => (),
+                            }
+                            break;
+                        }
+                    }
+                    Action(a) if a == "next_application" => {
+                        device.select_next();
+                        break;
+                    }
+                    Action(a) if a == "previous_application" => {
+                        device.select_previous();
+                        break;
+                    }
                     Action(a) if a == "volume_up" => {
                         device.set_volume(step_width, config.max_vol).await?;
                     }
@@ -259,6 +360,28 @@ enum SoundDriver {
     Alsa,
     #[cfg(feature = "pulseaudio")]
     PulseAudio,
+    #[cfg(feature = "pipewire")]
+    PipeWire,
+}
+
+/// The device used by the `auto` driver if PulseAudio is not available
+#[cfg_attr(not(feature = "pipewire"), allow(unused_variables))]
+async fn fallback_device(
+    device_kind: DeviceKind,
+    name: Option<String>,
+    device: Option<String>,
+    natural_mapping: bool,
+    default_alsa_name: &str,
+) -> Result<Box<dyn SoundDevice + Send + Sync>> {
+    #[cfg(feature = "pipewire")]
+    if let Ok(device) = pipewire::Device::new(device_kind, name.clone()).await {
+        return Ok(Box::new(device));
+    }
+    Ok(Box::new(alsa::Device::new(
+        name.unwrap_or_else(|| default_alsa_name.into()),
+        device.unwrap_or_else(|| "default".into()),
+        natural_mapping,
+    )?))
 }
 
 #[derive(Deserialize, Debug, SmartDefault, Clone, Copy, PartialEq, Eq, Hash)]
@@ -288,6 +411,19 @@ trait SoundDevice {
     fn active_port(&self) -> Option<&str>;
     fn form_factor(&self) -> Option<&str>;
 
+    /// `false` if there is nothing to show, e.g. no application is playing in per-application mode
+    fn is_available(&self) -> bool {
+        true
+    }
+    /// Select the next application in per-application mode
+    fn select_next(&mut self) {}
+    /// Select the previous application in per-application mode
+    fn select_previous(&mut self) {}
+    /// Make the next device the default one
+    async fn cycle_output(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     async fn get_info(&mut self) -> Result<()>;
     async fn set_volume(&mut self, step: i32, max_vol: Option<u32>) -> Result<()>;
     async fn toggle(&mut self) -> Result<()>;
@@ -0,0 +1,151 @@
+use std::cmp::{max, min};
+use std::process::Stdio;
+use tokio::io::{BufReader, Lines};
+use tokio::process::{ChildStdout, Command};
+
+use super::super::prelude::*;
+use super::{DeviceKind, SoundDevice};
+
+pub(super) struct Device {
+    /// A node id or `@DEFAULT_AUDIO_SINK@`/`@DEFAULT_AUDIO_SOURCE@`, as accepted by `wpctl`
+    target: String,
+    name: String,
+    description: Option<String>,
+    form_factor: Option<String>,
+    volume: u32,
+    muted: bool,
+    monitor: Lines<BufReader<ChildStdout>>,
+}
+
+impl Device {
+    pub(super) async fn new(device_kind: DeviceKind, name: Option<String>) -> Result<Self> {
+        // Make sure that the daemon is actually running, so that the `auto` driver can fall back
+        // to ALSA if it isn't
+        wpctl(&["status"]).await?;
+
+        let target = name.unwrap_or_else(|| match device_kind {
+            DeviceKind::Sink => "@DEFAULT_AUDIO_SINK@".into(),
+            DeviceKind::Source => "@DEFAULT_AUDIO_SOURCE@".into(),
+        });
+        let monitor = Command::new("pw-mon")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .error("Failed to start pw-mon")?
+            .stdout
+            .error("Failed to pipe pw-mon output")?;
+        Ok(Device {
+            name: target.clone(),
+            target,
+            description: None,
+            form_factor: None,
+            volume: 0,
+            muted: false,
+            monitor: BufReader::new(monitor).lines(),
+        })
+    }
+}
+
+async fn wpctl(args: &[&str]) -> Result<String> {
+    let output = Command::new("wpctl")
+        .args(args)
+        .output()
+        .await
+        .error("Failed to run wpctl")?;
+    if !output.status.success() {
+        return Err(Error::new(format!(
+            "wpctl failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    String::from_utf8(output.stdout).error("wpctl output is not valid UTF-8")
+}
+
+/// Find a property in the output of `wpctl inspect`, e.g. `  * node.name = "alsa_output.pci"`
+fn property(inspect: &str, key: &str) -> Option<String> {
+    inspect.lines().find_map(|line| {
+        let (k, v) = line.trim_start_matches([' ', '*']).split_once(" = ")?;
+        (k == key).then(|| v.trim_matches('"').into())
+    })
+}
+
+#[async_trait::async_trait]
+impl SoundDevice for Device {
+    fn volume(&self) -> u32 {
+        self.volume
+    }
+
+    fn muted(&self) -> bool {
+        self.muted
+    }
+
+    fn output_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn output_description(&self) -> Option<String> {
+        self.description.clone()
+    }
+
+    fn active_port(&self) -> Option<&str> {
+        None
+    }
+
+    fn form_factor(&self) -> Option<&str> {
+        self.form_factor.as_deref()
+    }
+
+    async fn get_info(&mut self) -> Result<()> {
+        // e.g. `Volume: 0.45` or `Volume: 0.45 [MUTED]`
+        let output = wpctl(&["get-volume", &self.target]).await?;
+        let volume: f64 = output
+            .split_whitespace()
+            .nth(1)
+            .and_then(|v| v.parse().ok())
+            .error("could not parse volume")?;
+        self.volume = (volume * 100.0).round() as u32;
+        self.muted = output.contains("[MUTED]");
+
+        let inspect = wpctl(&["inspect", &self.target]).await?;
+        self.name = property(&inspect, "node.name").unwrap_or_else(|| self.target.clone());
+        self.description = property(&inspect, "node.description");
+        self.form_factor = property(&inspect, "device.form-factor");
+
+        Ok(())
+    }
+
+    async fn set_volume(&mut self, step: i32, max_vol: Option<u32>) -> Result<()> {
+        let new_vol = max(0, self.volume as i32 + step) as u32;
+        let capped_volume = if let Some(vol_cap) = max_vol {
+            min(new_vol, vol_cap)
+        } else {
+            new_vol
+        };
+        wpctl(&["set-volume", &self.target, &format!("{capped_volume}%")]).await?;
+        self.volume = capped_volume;
+        Ok(())
+    }
+
+    async fn toggle(&mut self) -> Result<()> {
+        wpctl(&["set-mute", &self.target, "toggle"]).await?;
+        self.muted = !self.muted;
+        Ok(())
+    }
+
+    async fn wait_for_update(&mut self) -> Result<()> {
+        self.monitor
+            .next_line()
+            .await
+            .error("Failed to read pw-mon output")?
+            .error("pw-mon exited")?;
+        // A single change is reported over many lines, so skip the rest of them
+        while let Ok(line) =
+            tokio::time::timeout(Duration::from_millis(100), self.monitor.next_line()).await
+        {
+            line.error("Failed to read pw-mon output")?
+                .error("pw-mon exited")?;
+        }
+        Ok(())
+    }
+}
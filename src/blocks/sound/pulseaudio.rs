@@ -1,8 +1,8 @@
 use libpulse_binding::callbacks::ListResult;
 use libpulse_binding::context::{
-    introspect::ServerInfo, introspect::SinkInfo, introspect::SourceInfo, subscribe::Facility,
-    subscribe::InterestMaskSet, subscribe::Operation as SubscribeOperation, Context, FlagSet,
-    State as PulseState,
+    introspect::ServerInfo, introspect::SinkInfo, introspect::SinkInputInfo,
+    introspect::SourceInfo, subscribe::Facility, subscribe::InterestMaskSet,
+    subscribe::Operation as SubscribeOperation, Context, FlagSet, State as PulseState,
 };
 use libpulse_binding::mainloop::standard::{IterateResult, Mainloop};
 use libpulse_binding::proplist::{properties, Proplist};
@@ -21,6 +21,7 @@ use super::{DeviceKind, SoundDevice};
 static CLIENT: Lazy<Result<Client>> = Lazy::new(Client::new);
 static EVENT_LISTENER: Lazy<Mutex<Vec<tokio::sync::mpsc::Sender<()>>>> = Lazy::new(default);
 static DEVICES: Lazy<Mutex<HashMap<(DeviceKind, String), VolInfo>>> = Lazy::new(default);
+static SINK_INPUTS: Lazy<Mutex<HashMap<u32, AppInfo>>> = Lazy::new(default);
 
 // Default device names
 pub(super) static DEFAULT_SOURCE: Lazy<Mutex<String>> =
@@ -51,6 +52,7 @@ struct Client {
 
 #[derive(Debug)]
 struct VolInfo {
+    index: u32,
     volume: ChannelVolumes,
     mute: bool,
     name: String,
@@ -66,6 +68,7 @@ impl TryFrom<&SourceInfo<'_>> for VolInfo {
         match source_info.name.as_ref() {
             None => Err(()),
             Some(name) => Ok(VolInfo {
+                index: source_info.index,
                 volume: source_info.volume,
                 mute: source_info.mute,
                 name: name.to_string(),
@@ -87,6 +90,7 @@ impl TryFrom<&SinkInfo<'_>> for VolInfo {
         match sink_info.name.as_ref() {
             None => Err(()),
             Some(name) => Ok(VolInfo {
+                index: sink_info.index,
                 volume: sink_info.volume,
                 mute: sink_info.mute,
                 name: name.to_string(),
@@ -101,13 +105,42 @@ impl TryFrom<&SinkInfo<'_>> for VolInfo {
     }
 }
 
+/// A stream of an application playing to a sink
+#[derive(Debug)]
+struct AppInfo {
+    volume: ChannelVolumes,
+    mute: bool,
+    name: String,
+}
+
+impl From<&SinkInputInfo<'_>> for AppInfo {
+    fn from(sink_input_info: &SinkInputInfo) -> Self {
+        AppInfo {
+            volume: sink_input_info.volume,
+            mute: sink_input_info.mute,
+            name: sink_input_info
+                .proplist
+                .get_str(properties::APPLICATION_NAME)
+                .or_else(|| sink_input_info.name.as_ref().map(|n| n.to_string()))
+                .unwrap_or_default(),
+        }
+    }
+}
+
 #[derive(Debug)]
 enum ClientRequest {
     GetDefaultDevice,
     GetInfoByIndex(DeviceKind, u32),
     GetInfoByName(DeviceKind, String),
+    GetInfoList(DeviceKind),
+    SetDefaultDevice(DeviceKind, String),
+    MoveSinkInput(u32, String),
     SetVolumeByName(DeviceKind, String, ChannelVolumes),
     SetMuteByName(DeviceKind, String, bool),
+    GetSinkInputs,
+    GetSinkInputByIndex(u32),
+    SetSinkInputVolume(u32, ChannelVolumes),
+    SetSinkInputMute(u32, bool),
 }
 
 impl Connection {
@@ -223,6 +256,21 @@ impl Client {
                                         Client::source_info_callback,
                                     );
                                 }
+                                GetInfoList(DeviceKind::Sink) => {
+                                    introspector.get_sink_info_list(Client::sink_info_callback);
+                                }
+                                GetInfoList(DeviceKind::Source) => {
+                                    introspector.get_source_info_list(Client::source_info_callback);
+                                }
+                                SetDefaultDevice(DeviceKind::Sink, name) => {
+                                    connection.context.set_default_sink(&name, |_| {});
+                                }
+                                SetDefaultDevice(DeviceKind::Source, name) => {
+                                    connection.context.set_default_source(&name, |_| {});
+                                }
+                                MoveSinkInput(index, sink) => {
+                                    introspector.move_sink_input_by_name(index, &sink, None);
+                                }
                                 SetVolumeByName(DeviceKind::Sink, name, volumes) => {
                                     introspector.set_sink_volume_by_name(&name, &volumes, None);
                                 }
@@ -235,6 +283,22 @@ impl Client {
                                 SetMuteByName(DeviceKind::Source, name, mute) => {
                                     introspector.set_source_mute_by_name(&name, mute, None);
                                 }
+                                GetSinkInputs => {
+                                    introspector
+                                        .get_sink_input_info_list(Client::sink_input_info_callback);
+                                }
+                                GetSinkInputByIndex(index) => {
+                                    introspector.get_sink_input_info(
+                                        index,
+                                        Client::sink_input_info_callback,
+                                    );
+                                }
+                                SetSinkInputVolume(index, volumes) => {
+                                    introspector.set_sink_input_volume(index, &volumes, None);
+                                }
+                                SetSinkInputMute(index, mute) => {
+                                    introspector.set_sink_input_mute(index, mute, None);
+                                }
                             };
 
                             // send request and receive response
@@ -260,7 +324,10 @@ impl Client {
                     .context
                     .set_subscribe_callback(Some(Box::new(Client::subscribe_callback)));
                 connection.context.subscribe(
-                    InterestMaskSet::SERVER | InterestMaskSet::SINK | InterestMaskSet::SOURCE,
+                    InterestMaskSet::SERVER
+                        | InterestMaskSet::SINK
+                        | InterestMaskSet::SOURCE
+                        | InterestMaskSet::SINK_INPUT,
                     |_| {},
                 );
 
@@ -327,9 +394,20 @@ impl Client {
         }
     }
 
+    fn sink_input_info_callback(result: ListResult<&SinkInputInfo>) {
+        if let ListResult::Item(info) = result {
+            SINK_INPUTS
+                .lock()
+                .unwrap()
+                .insert(info.index, AppInfo::from(info));
+
+            Client::send_update_event();
+        }
+    }
+
     fn subscribe_callback(
         facility: Option<Facility>,
-        _operation: Option<SubscribeOperation>,
+        operation: Option<SubscribeOperation>,
         index: u32,
     ) {
         match facility {
@@ -338,11 +416,29 @@ impl Client {
                 Facility::Server => {
                     Client::send(ClientRequest::GetDefaultDevice).ok();
                 }
-                Facility::Sink => {
-                    Client::send(ClientRequest::GetInfoByIndex(DeviceKind::Sink, index)).ok();
+                Facility::Sink | Facility::Source => {
+                    let device_kind = if facility == Facility::Sink {
+                        DeviceKind::Sink
+                    } else {
+                        DeviceKind::Source
+                    };
+                    if operation == Some(SubscribeOperation::Removed) {
+                        DEVICES
+                            .lock()
+                            .unwrap()
+                            .retain(|(kind, _), info| *kind != device_kind || info.index != index);
+                        Client::send_update_event();
+                    } else {
+                        Client::send(ClientRequest::GetInfoByIndex(device_kind, index)).ok();
+                    }
                 }
-                Facility::Source => {
-                    Client::send(ClientRequest::GetInfoByIndex(DeviceKind::Source, index)).ok();
+                Facility::SinkInput => {
+                    if operation == Some(SubscribeOperation::Removed) {
+                        SINK_INPUTS.lock().unwrap().remove(&index);
+                        Client::send_update_event();
+                    } else {
+                        Client::send(ClientRequest::GetSinkInputByIndex(index)).ok();
+                    }
                 }
                 _ => {}
             },
@@ -356,6 +452,27 @@ impl Client {
     }
 }
 
+/// Change all channels by `step` percents, but not above `max_vol` percents
+fn apply_step(volume: &mut ChannelVolumes, step: i32, max_vol: Option<u32>) {
+    let step = (step as f32 * Volume::NORMAL.0 as f32 / 100.0).round() as i32;
+    for vol in volume.get_mut().iter_mut() {
+        let uncapped_vol = max(0, vol.0 as i32 + step) as u32;
+        let capped_vol = if let Some(vol_cap) = max_vol {
+            min(
+                uncapped_vol,
+                (vol_cap as f32 * Volume::NORMAL.0 as f32 / 100.0).round() as u32,
+            )
+        } else {
+            uncapped_vol
+        };
+        vol.0 = min(capped_vol, Volume::MAX.0);
+    }
+}
+
+fn volume_percents(volume: ChannelVolumes) -> u32 {
+    (volume.avg().0 as f32 / Volume::NORMAL.0 as f32 * 100.0).round() as u32
+}
+
 impl Device {
     pub(super) fn new(device_kind: DeviceKind, name: Option<String>) -> Result<Self> {
         let (tx, rx) = tokio::sync::mpsc::channel(32);
@@ -376,6 +493,11 @@ impl Device {
         };
 
         Client::send(ClientRequest::GetInfoByName(device_kind, device.name()))?;
+        // Needed to switch between devices
+        Client::send(ClientRequest::GetInfoList(device_kind))?;
+        if device_kind == DeviceKind::Sink {
+            Client::send(ClientRequest::GetSinkInputs)?;
+        }
 
         Ok(device)
     }
@@ -388,7 +510,7 @@ impl Device {
 
     fn volume(&mut self, volume: ChannelVolumes) {
         self.volume = Some(volume);
-        self.volume_avg = (volume.avg().0 as f32 / Volume::NORMAL.0 as f32 * 100.0).round() as u32;
+        self.volume_avg = volume_percents(volume);
     }
 }
 
@@ -435,20 +557,7 @@ impl SoundDevice for Device {
     async fn set_volume(&mut self, step: i32, max_vol: Option<u32>) -> Result<()> {
         let mut volume = self.volume.error("Volume unknown")?;
 
-        // apply step to volumes
-        let step = (step as f32 * Volume::NORMAL.0 as f32 / 100.0).round() as i32;
-        for vol in volume.get_mut().iter_mut() {
-            let uncapped_vol = max(0, vol.0 as i32 + step) as u32;
-            let capped_vol = if let Some(vol_cap) = max_vol {
-                min(
-                    uncapped_vol,
-                    (vol_cap as f32 * Volume::NORMAL.0 as f32 / 100.0).round() as u32,
-                )
-            } else {
-                uncapped_vol
-            };
-            vol.0 = min(capped_vol, Volume::MAX.0);
-        }
+        apply_step(&mut volume, step, max_vol);
 
         // update volumes
         self.volume(volume);
@@ -473,6 +582,176 @@ impl SoundDevice for Device {
         Ok(())
     }
 
+    async fn cycle_output(&mut self) -> Result<()> {
+        // Changing the default device would not change the device shown by this block
+        if self.name.is_some() {
+            return Err(Error::new(
+                "cycle_output is not available with a fixed `name`",
+            ));
+        }
+        let next = {
+            let devices = DEVICES.lock().unwrap();
+            let mut names: Vec<&str> = devices
+                .keys()
+                .filter(|(kind, _)| *kind == self.device_kind)
+                .map(|(_, name)| name.as_str())
+                .collect();
+            names.sort_unstable();
+            let current = self.device_kind.default_name();
+            names
+                .iter()
+                .find(|&&name| name > current.as_str())
+                .or_else(|| names.first())
+                .error("No devices found")?
+                .to_string()
+        };
+
+        Client::send(ClientRequest::SetDefaultDevice(
+            self.device_kind,
+            next.clone(),
+        ))?;
+
+        // Streams which were explicitly routed to the previous device would otherwise stay there
+        if self.device_kind == DeviceKind::Sink {
+            let indices: Vec<u32> = SINK_INPUTS.lock().unwrap().keys().copied().collect();
+            for index in indices {
+                Client::send(ClientRequest::MoveSinkInput(index, next.clone()))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn wait_for_update(&mut self) -> Result<()> {
+        self.updates
+            .recv()
+            .await
+            .error("Failed to receive new update")
+    }
+}
+
+/// The volume of one application at a time, which can be switched with `select_next`
+pub(super) struct AppDevice {
+    /// Index of the selected sink input
+    selected: Option<u32>,
+    name: String,
+    volume: Option<ChannelVolumes>,
+    muted: bool,
+    updates: tokio::sync::mpsc::Receiver<()>,
+}
+
+impl AppDevice {
+    pub(super) fn new() -> Result<Self> {
+        let (tx, rx) = tokio::sync::mpsc::channel(32);
+        EVENT_LISTENER.lock().unwrap().push(tx);
+
+        Client::send(ClientRequest::GetSinkInputs)?;
+
+        Ok(AppDevice {
+            selected: None,
+            name: String::new(),
+            volume: None,
+            muted: false,
+            updates: rx,
+        })
+    }
+
+    fn sorted_indices() -> Vec<u32> {
+        let mut indices: Vec<u32> = SINK_INPUTS.lock().unwrap().keys().copied().collect();
+        indices.sort_unstable();
+        indices
+    }
+}
+
+#[async_trait::async_trait]
+impl SoundDevice for AppDevice {
+    fn volume(&self) -> u32 {
+        self.volume.map_or(0, volume_percents)
+    }
+
+    fn muted(&self) -> bool {
+        self.muted
+    }
+
+    fn output_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn output_description(&self) -> Option<String> {
+        None
+    }
+
+    fn active_port(&self) -> Option<&str> {
+        None
+    }
+
+    fn form_factor(&self) -> Option<&str> {
+        None
+    }
+
+    fn is_available(&self) -> bool {
+        self.selected.is_some()
+    }
+
+    fn select_next(&mut self) {
+        let indices = Self::sorted_indices();
+        self.selected = self
+            .selected
+            .and_then(|s| indices.iter().find(|&&i| i > s))
+            .or_else(|| indices.first())
+            .copied();
+    }
+
+    fn select_previous(&mut self) {
+        let indices = Self::sorted_indices();
+        self.selected = self
+            .selected
+            .and_then(|s| indices.iter().rev().find(|&&i| i < s))
+            .or_else(|| indices.last())
+            .copied();
+    }
+
+    async fn get_info(&mut self) -> Result<()> {
+        let sink_inputs = SINK_INPUTS.lock().unwrap();
+
+        // The selected application may have stopped playing
+        if self
+            .selected
+            .map_or(true, |s| !sink_inputs.contains_key(&s))
+        {
+            self.selected = sink_inputs.keys().min().copied();
+        }
+
+        match self.selected.and_then(|s| sink_inputs.get(&s)) {
+            Some(info) => {
+                self.volume = Some(info.volume);
+                self.muted = info.mute;
+                self.name = info.name.clone();
+            }
+            None => {
+                self.volume = None;
+                self.muted = false;
+                self.name.clear();
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn set_volume(&mut self, step: i32, max_vol: Option<u32>) -> Result<()> {
+        let index = self.selected.error("No application selected")?;
+        let mut volume = self.volume.error("Volume unknown")?;
+        apply_step(&mut volume, step, max_vol);
+        self.volume = Some(volume);
+        Client::send(ClientRequest::SetSinkInputVolume(index, volume))
+    }
+
+    async fn toggle(&mut self) -> Result<()> {
+        let index = self.selected.error("No application selected")?;
+        self.muted = !self.muted;
+        Client::send(ClientRequest::SetSinkInputMute(index, self.muted))
+    }
+
     async fn wait_for_update(&mut self) -> Result<()> {
         self.updates
             .recv()
@@ -2,18 +2,32 @@
 //!
 //! This block which requires [`speedtest-cli`](https://github.com/sivel/speedtest-cli).
 //!
+//! A speedtest can use a lot of data, so with `interval = "never"` the test only runs when the
+//! block is clicked. A spinner is shown while the test is running. The last result is saved, so it
+//! is shown together with its age after the bar is restarted.
+//!
 //! # Configuration
 //!
 //! Key | Values | Default
 //! ----|--------|--------
-//! `format` | A string to customise the output of this block. See below for available placeholders. | `" ^icon_ping $ping ^icon_net_down $speed_down ^icon_net_up $speed_up "`
-//! `interval` | Update interval in seconds | `1800`
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" {$spinner \|}{^icon_ping $ping ^icon_net_down $speed_down ^icon_net_up $speed_up\|^icon_ping -} "`
+//! `interval` | Update interval in seconds, or `"never"` to only run the test on click | `1800`
+//! `persist` | Save the last result, so that it is shown after the bar is restarted. The result is saved per block, so reordering the blocks in the config resets it. | `true`
+//!
+//! Placeholder  | Value                                    | Type   | Unit
+//! -------------|------------------------------------------|--------|---------------
+//! `ping`       | Ping delay                               | Number | Seconds
+//! `speed_down` | Download speed                           | Number | Bits per second
+//! `speed_up`   | Upload speed                             | Number | Bits per second
+//! `age`        | Time since the last test, e.g. `"3h"`    | Text   | -
+//! `spinner`    | Animated spinner, while a test runs      | Text   | -
+//! `running`    | Present while a test runs                | Flag   | -
+//!
+//! `ping`, `speed_down`, `speed_up` and `age` are missing until the first test has finished.
 //!
-//! Placeholder  | Value          | Type   | Unit
-//! -------------|----------------|--------|---------------
-//! `ping`       | Ping delay     | Number | Seconds
-//! `speed_down` | Download speed | Number | Bits per second
-//! `speed_up`   | Upload speed   | Number | Bits per second
+//! Action | Description         | Default button
+//! -------|---------------------|---------------
+//! `run`  | Run a speedtest now | Left
 //!
 //! # Example
 //!
@@ -35,57 +49,186 @@
 //! format = " $speed_down.eng(w:4,u:B) $speed_up(w:4,u:B) "
 //! ```
 //!
+//! Only run on click, on a metered connection
+//!
+//! ```toml
+//! [[block]]
+//! block = "speedtest"
+//! interval = "never"
+//! format = " {$spinner|$speed_down.eng(w:4) ($age ago)|click to test} "
+//! ```
+//!
 //! # Icons Used
 //! - `ping`
 //! - `net_down`
 //! - `net_up`
 
 use super::prelude::*;
+use crate::util;
+use serde::de::{self, Deserializer};
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::process::Command;
 
+make_log_macro!(debug, "speedtest");
+
+const SPINNER: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(default)]
 pub struct Config {
     format: FormatConfig,
-    #[default(1800.into())]
-    interval: Seconds,
+    #[default(Interval::Every(1800.into()))]
+    interval: Interval,
+    #[default(true)]
+    persist: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Interval {
+    Every(Seconds),
+    Never,
+}
+
+impl<'de> Deserialize<'de> for Interval {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Seconds(Seconds),
+            Keyword(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Seconds(seconds) => Ok(Self::Every(seconds)),
+            Raw::Keyword(k) if k == "never" => Ok(Self::Never),
+            Raw::Keyword(k) => Err(de::Error::custom(format!("'{k}' is not a valid interval"))),
+        }
+    }
+}
+
+/// The last result, persisted in the cache directory
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+struct LastResult {
+    output: SpeedtestCliOutput,
+    /// Unix timestamp of the end of the test
+    time: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => "now".into(),
+        60..=3_599 => format!("{}m", seconds / 60),
+        3_600..=86_399 => format!("{}h", seconds / 3_600),
+        _ => format!("{}d", seconds / 86_400),
+    }
+}
+
+fn result_values(result: Option<&LastResult>) -> Values {
+    match result {
+        Some(result) => map! {
+            "ping" => Value::seconds(result.output.ping * 1e-3),
+            "speed_down" => Value::bits(result.output.download),
+            "speed_up" => Value::bits(result.output.upload),
+            "age" => Value::text(format_age(now().saturating_sub(result.time))),
+        },
+        None => Values::new(),
+    }
+}
+
+async fn run_speedtest() -> Result<SpeedtestCliOutput> {
+    let output = Command::new("speedtest-cli")
+        .arg("--json")
+        .output()
+        .await
+        .error("failed to run 'speedtest-cli'")?
+        .stdout;
+    let output = std::str::from_utf8(&output).error("'speedtest-cli' produced non-UTF8 outupt")?;
+    serde_json::from_str(output).error("'speedtest-cli' produced wrong JSON")
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
-    let mut widget =
-        Widget::new().with_format(config.format.with_default(
-            " ^icon_ping $ping ^icon_net_down $speed_down ^icon_net_up $speed_up ",
-        )?);
+    api.set_default_actions(&[(MouseButton::Left, None, "run")])
+        .await?;
 
-    let mut command = Command::new("speedtest-cli");
-    command.arg("--json");
+    let mut widget = Widget::new().with_format(config.format.with_default(
+        " {$spinner |}{^icon_ping $ping ^icon_net_down $speed_down ^icon_net_up $speed_up|^icon_ping -} ",
+    )?);
+
+    let file = config.persist.then(|| format!("speedtest-{}.json", api.id));
+    let mut last: Option<LastResult> = file.as_deref().and_then(util::load_cache_file);
+
+    let mut timer = match config.interval {
+        Interval::Every(interval) => Some(interval.timer()),
+        Interval::Never => None,
+    };
+    // Run right away, unless the saved result is recent enough or tests only run on click
+    let mut run_now = match (config.interval, &last) {
+        (Interval::Never, _) => false,
+        (Interval::Every(interval), Some(last)) => {
+            now().saturating_sub(last.time) >= interval.seconds()
+        }
+        (Interval::Every(_), None) => true,
+    };
 
     loop {
-        let output = command
-            .output()
-            .await
-            .error("failed to run 'speedtest-cli'")?
-            .stdout;
-        let output =
-            std::str::from_utf8(&output).error("'speedtest-cli' produced non-UTF8 outupt")?;
-        let output: SpeedtestCliOutput =
-            serde_json::from_str(output).error("'speedtest-cli' produced wrong JSON")?;
-
-        widget.set_values(map! {
-            "ping" => Value::seconds(output.ping * 1e-3),
-            "speed_down" => Value::bits(output.download),
-            "speed_up" => Value::bits(output.upload),
-        });
+        if run_now {
+            let test = run_speedtest();
+            tokio::pin!(test);
+            let mut spinner = Seconds(Duration::from_millis(100)).timer();
+            let mut frame = 0;
+            let output = loop {
+                let mut values = result_values(last.as_ref());
+                values.insert("running".into(), Value::flag());
+                values.insert("spinner".into(), Value::text(SPINNER[frame].into()));
+                widget.set_values(values);
+                api.set_widget(&widget).await?;
+
+                select! {
+                    output = &mut test => break output,
+                    _ = spinner.tick() => frame = (frame + 1) % SPINNER.len(),
+                }
+            };
+            let result = LastResult {
+                output: output?,
+                time: now(),
+            };
+            if let Some(file) = &file {
+                if let Err(e) = util::store_cache_file(file, &result) {
+                    debug!("failed to save speedtest result: {e}");
+                }
+            }
+            last = Some(result);
+            if let Some(timer) = &mut timer {
+                timer.reset();
+            }
+        }
+
+        widget.set_values(result_values(last.as_ref()));
         api.set_widget(&widget).await?;
 
         select! {
-            _ = sleep(config.interval.0) => (),
-            _ = api.wait_for_update_request() => (),
+            Some(_) = util::tick_optional(&mut timer) => run_now = true,
+            // Keep `age` up to date
+            _ = sleep(Duration::from_secs(60)) => run_now = false,
+            event = api.event() => {
+                // Only refresh the age of the result on other events
+                run_now = matches!(event, UpdateRequest) || matches!(&event, Action(a) if a == "run");
+            }
         }
     }
 }
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 struct SpeedtestCliOutput {
     /// Download speed in bits per second
     download: f64,
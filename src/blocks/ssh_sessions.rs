@@ -0,0 +1,131 @@
+//! Active inbound SSH sessions
+//!
+//! Counts the remote login sessions listed by `who`, i.e. those with a remote host, which are
+//! usually SSH sessions. The block is in the warning state while any session exists, and clicking
+//! it switches to `format_alt`, which by default shows the source addresses.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $count "`
+//! `format_alt` | The format shown after clicking the block | `" $icon {$addresses\|none} "`
+//! `interval` | Update interval in seconds | `10`
+//!
+//! Placeholder | Value                                          | Type   | Unit
+//! ------------|------------------------------------------------|--------|-----
+//! `icon`      | A static icon                                  | Icon   | -
+//! `count`     | The number of remote sessions                  | Number | -
+//! `addresses` | The distinct source addresses, comma separated | Text   | -
+//! `users`     | The distinct logged in users, comma separated  | Text   | -
+//!
+//! Action          | Description                               | Default button
+//! ----------------|-------------------------------------------|---------------
+//! `toggle_format` | Toggles between `format` and `format_alt` | Left
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "ssh_sessions"
+//! format = " $icon {$count $users|} "
+//! ```
+//!
+//! # Icons Used
+//! - `net_wired`
+
+use super::prelude::*;
+use tokio::process::Command;
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    format: FormatConfig,
+    format_alt: FormatConfig,
+    #[default(10.into())]
+    interval: Seconds,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[(MouseButton::Left, None, "toggle_format")])
+        .await?;
+
+    let mut format = config.format.with_default(" $icon $count ")?;
+    let mut format_alt = config
+        .format_alt
+        .with_default(" $icon {$addresses|none} ")?;
+    let mut widget = Widget::new().with_format(format.clone());
+    let mut timer = config.interval.timer();
+
+    loop {
+        let sessions = api.recoverable(remote_sessions).await?;
+        let mut addresses: Vec<&str> = Vec::new();
+        let mut users: Vec<&str> = Vec::new();
+        for session in &sessions {
+            if !addresses.contains(&session.address.as_str()) {
+                addresses.push(&session.address);
+            }
+            if !users.contains(&session.user.as_str()) {
+                users.push(&session.user);
+            }
+        }
+
+        widget.state = if sessions.is_empty() {
+            State::Idle
+        } else {
+            State::Warning
+        };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("net_wired")?),
+            "count" => Value::number(sessions.len()),
+            [if !addresses.is_empty()] "addresses" => Value::text(addresses.join(", ")),
+            [if !users.is_empty()] "users" => Value::text(users.join(", ")),
+        });
+        api.set_widget(&widget).await?;
+
+        loop {
+            select! {
+                _ = timer.tick() => break,
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "toggle_format" => {
+                        std::mem::swap(&mut format_alt, &mut format);
+                        widget.set_format(format.clone());
+                        break;
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+struct Session {
+    user: String,
+    address: String,
+}
+
+/// The sessions listed by `who` with a remote host
+async fn remote_sessions() -> Result<Vec<Session>> {
+    let output = Command::new("who")
+        .env("LC_ALL", "C")
+        .output()
+        .await
+        .error("Failed to run who")?;
+    let output = String::from_utf8(output.stdout).error("who produced non-UTF8 output")?;
+    Ok(output.lines().filter_map(parse_who_line).collect())
+}
+
+/// Parse a line of `who`, e.g. `alice    pts/1        2023-05-04 10:12 (192.0.2.7)`. Local
+/// sessions have no host, an X display like `(:0)` or a tmux pane like `(tmux(1234).%0)` instead.
+fn parse_who_line(line: &str) -> Option<Session> {
+    let user = line.split_whitespace().next()?;
+    let host = line.trim_end().strip_suffix(')')?.split_once('(')?.1;
+    if host.is_empty() || host.starts_with(':') || host.starts_with("tmux(") {
+        return None;
+    }
+    Some(Session {
+        user: user.into(),
+        address: host.into(),
+    })
+}
@@ -0,0 +1,161 @@
+//! Stopwatch
+//!
+//! A stopwatch which is started and stopped by clicking the block. Lap times can be taken while
+//! it runs. The elapsed time and laps are saved, so that a running stopwatch keeps running after
+//! the bar is restarted.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `format` | A string to customise the output of this block. See below for available placeholders. | <code>" $icon {$hours:&vert;}$minutes:$seconds{ lap $laps: $lap&vert;} "</code>
+//! `persist` | Save the state of the stopwatch, so that it continues after the bar is restarted. The state is saved per block, so reordering the blocks in the config resets it. | `true`
+//!
+//! Placeholder | Value                                                                 | Type   | Unit
+//! ------------|-----------------------------------------------------------------------|--------|-----
+//! `icon`      | A static icon                                                         | Icon   | -
+//! `hours`     | The elapsed hours. Missing if less than an hour.                      | Text   | h
+//! `minutes`   | The elapsed minutes                                                   | Text   | mn
+//! `seconds`   | The elapsed seconds                                                   | Text   | s
+//! `lap`       | The time of the last lap, e.g. `"01:23"`. Missing if there are none.  | Text   | -
+//! `laps`      | The number of laps. Missing if there are none.                        | Number | -
+//! `running`   | Present if the stopwatch is running                                   | Flag   | -
+//!
+//! Action       | Description                           | Default button
+//! -------------|---------------------------------------|---------------
+//! `start_stop` | Start or stop the stopwatch           | Left
+//! `lap`        | Take a lap time                       | Middle
+//! `reset`      | Stop the stopwatch and clear the time | Right
+//!
+//! # Example
+//!
+//! ```toml
+//! [[block]]
+//! block = "stopwatch"
+//! format = " {$running $icon |}$minutes:$seconds "
+//! ```
+//!
+//! # Icons Used
+//! - `time`
+
+use super::prelude::*;
+use crate::util;
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+make_log_macro!(debug, "stopwatch");
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(deny_unknown_fields, default)]
+pub struct Config {
+    format: FormatConfig,
+    #[default(true)]
+    persist: bool,
+}
+
+/// The state of the stopwatch, persisted in the cache directory. Times are in milliseconds.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Stopwatch {
+    /// The time elapsed before the stopwatch was last started
+    elapsed: u64,
+    /// When the stopwatch was started, since the Unix epoch, if it is running
+    started: Option<u64>,
+    /// The elapsed time at the end of each lap
+    laps: Vec<u64>,
+}
+
+impl Stopwatch {
+    fn elapsed(&self, now: u64) -> u64 {
+        self.elapsed + self.started.map_or(0, |s| now.saturating_sub(s))
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Format milliseconds like `"01:23"` or `"1:02:03"`
+fn format_time(millis: u64) -> String {
+    let seconds = millis / 1000;
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[
+        (MouseButton::Left, None, "start_stop"),
+        (MouseButton::Middle, None, "lap"),
+        (MouseButton::Right, None, "reset"),
+    ])
+    .await?;
+
+    let format = config
+        .format
+        .with_default(" $icon {$hours:|}$minutes:$seconds{ lap $laps: $lap|} ")?;
+    let mut widget = Widget::new().with_format(format);
+
+    let file = config.persist.then(|| format!("stopwatch-{}.json", api.id));
+    let mut stopwatch: Stopwatch = file
+        .as_deref()
+        .and_then(util::load_cache_file)
+        .unwrap_or_default();
+
+    let interval: Seconds = 1.into();
+    let mut timer = interval.timer();
+
+    loop {
+        let is_running = stopwatch.started.is_some();
+        let seconds = stopwatch.elapsed(now()) / 1000;
+        let last_lap = match stopwatch.laps.as_slice() {
+            [.., previous, last] => Some(last - previous),
+            [last] => Some(*last),
+            [] => None,
+        };
+
+        widget.state = if is_running { State::Info } else { State::Idle };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("time")?),
+            "minutes" => Value::text(format!("{:02}", seconds / 60 % 60)),
+            "seconds" => Value::text(format!("{:02}", seconds % 60)),
+            [if seconds >= 3600] "hours" => Value::text(format!("{:02}", seconds / 3600)),
+            [if let Some(lap) = last_lap] "lap" => Value::text(format_time(lap)),
+            [if !stopwatch.laps.is_empty()] "laps" => Value::number(stopwatch.laps.len()),
+            [if is_running] "running" => Value::flag(),
+        });
+        api.set_widget(&widget).await?;
+
+        select! {
+            _ = timer.tick(), if is_running => (),
+            event = api.event() => match event {
+                UpdateRequest => (),
+                Action(action) => {
+                    let now = now();
+                    match action.as_ref() {
+                        "start_stop" => match stopwatch.started.take() {
+                            Some(started) => stopwatch.elapsed += now.saturating_sub(started),
+                            None => stopwatch.started = Some(now),
+                        },
+                        "lap" if is_running => stopwatch.laps.push(stopwatch.elapsed(now)),
+                        "reset" => stopwatch = Stopwatch::default(),
+                        _ => continue,
+                    }
+                    if let Some(file) = &file {
+                        if let Err(e) = util::store_cache_file(file, &stopwatch) {
+                            debug!("failed to save the stopwatch: {e}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
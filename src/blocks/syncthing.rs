@@ -0,0 +1,192 @@
+//! Syncthing status
+//!
+//! Shows how far the local folders are in sync, the number of items which still need to be synced
+//! and the number of connected devices, using the Syncthing REST API. The block is in the warning
+//! state while items need to be synced and in the critical state if any folder has errors.
+//!
+//! The API key can be found in the settings of the Syncthing web UI. It can also be passed using
+//! the `I3RS_SYNCTHING_API_KEY` environment variable.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `url` | The address of the Syncthing GUI and REST API | `"http://localhost:8384"`
+//! `api_key` | The API key | `None`
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $completion {$need_items\|} "`
+//! `interval` | Update interval in seconds | `10`
+//!
+//! Placeholder  | Value                                                               | Type   | Unit
+//! -------------|---------------------------------------------------------------------|--------|------
+//! `icon`       | A static icon                                                       | Icon   | -
+//! `completion` | How much of the data of all folders is in sync                      | Number | %
+//! `need_items` | The number of items which still need to be synced. Missing if none. | Number | -
+//! `need_bytes` | The amount of data which still needs to be synced                   | Number | Bytes
+//! `devices`    | The number of connected devices                                     | Number | -
+//! `errors`     | The number of folder errors, e.g. files which failed to sync        | Number | -
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "syncthing"
+//! api_key = "..."
+//! format = " $icon $devices $completion.eng(w:3) "
+//! ```
+//!
+//! # Icons Used
+//! - `update`
+
+use super::prelude::*;
+use std::collections::HashMap;
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    #[default("http://localhost:8384".into())]
+    url: String,
+    api_key: Option<String>,
+    format: FormatConfig,
+    #[default(10.into())]
+    interval: Seconds,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    let mut widget = Widget::new().with_format(
+        config
+            .format
+            .with_default(" $icon $completion {$need_items|} ")?,
+    );
+    let client = Client {
+        url: config.url.trim_end_matches('/').into(),
+        api_key: config
+            .api_key
+            .clone()
+            .or_else(|| std::env::var("I3RS_SYNCTHING_API_KEY").ok())
+            .error("Syncthing API key not found")?,
+    };
+    let mut timer = config.interval.timer();
+
+    loop {
+        let status = api.recoverable(|| client.status()).await?;
+
+        let completion = if status.global_bytes == 0 {
+            100.0
+        } else {
+            100.0 * status.global_bytes.saturating_sub(status.need_bytes) as f64
+                / status.global_bytes as f64
+        };
+        widget.state = if status.errors > 0 {
+            State::Critical
+        } else if status.need_items > 0 {
+            State::Warning
+        } else {
+            State::Idle
+        };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("update")?),
+            "completion" => Value::percents(completion),
+            "need_bytes" => Value::bytes(status.need_bytes),
+            "devices" => Value::number(status.devices),
+            "errors" => Value::number(status.errors),
+            [if status.need_items > 0] "need_items" => Value::number(status.need_items),
+        });
+        api.set_widget(&widget).await?;
+
+        select! {
+            _ = timer.tick() => (),
+            _ = api.wait_for_update_request() => (),
+        }
+    }
+}
+
+struct Client {
+    url: String,
+    api_key: String,
+}
+
+/// The combined status of all folders
+#[derive(Default)]
+struct Status {
+    global_bytes: u64,
+    need_bytes: u64,
+    need_items: u64,
+    errors: usize,
+    devices: usize,
+}
+
+#[derive(Deserialize)]
+struct FolderConfig {
+    id: String,
+    #[serde(default)]
+    paused: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct FolderCompletion {
+    global_bytes: u64,
+    need_bytes: u64,
+    need_items: u64,
+}
+
+#[derive(Deserialize)]
+struct FolderErrors {
+    errors: Option<Vec<serde::de::IgnoredAny>>,
+}
+
+#[derive(Deserialize)]
+struct Connections {
+    connections: HashMap<String, Connection>,
+}
+
+#[derive(Deserialize)]
+struct Connection {
+    connected: bool,
+}
+
+impl Client {
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T> {
+        REQWEST_CLIENT
+            .get(format!("{}{path}", self.url))
+            .query(query)
+            .header("X-API-Key", &self.api_key)
+            .send()
+            .await
+            .error("Failed to send request")?
+            .error_for_status()
+            .error("Syncthing API error")?
+            .json()
+            .await
+            .error("Failed to get JSON")
+    }
+
+    async fn status(&self) -> Result<Status> {
+        let mut status = Status::default();
+
+        let folders: Vec<FolderConfig> = self.get("/rest/config/folders", &[]).await?;
+        for folder in folders.iter().filter(|f| !f.paused) {
+            // `/rest/db/status` would be much more expensive for Syncthing to compute
+            let query = [("folder", folder.id.as_str())];
+            let completion: FolderCompletion = self.get("/rest/db/completion", &query).await?;
+            status.global_bytes += completion.global_bytes;
+            status.need_bytes += completion.need_bytes;
+            status.need_items += completion.need_items;
+            let errors: FolderErrors = self.get("/rest/folder/errors", &query).await?;
+            status.errors += errors.errors.map_or(0, |e| e.len());
+        }
+
+        let connections: Connections = self.get("/rest/system/connections", &[]).await?;
+        status.devices = connections
+            .connections
+            .values()
+            .filter(|c| c.connected)
+            .count();
+
+        Ok(status)
+    }
+}
@@ -0,0 +1,175 @@
+//! Tailscale connection status
+//!
+//! Shows the state of the local Tailscale node, its MagicDNS name and the exit node in use, as
+//! reported by `tailscale status --json`.
+//!
+//! Connecting and disconnecting uses `tailscale up` and `tailscale down`, which require the user
+//! to be allowed to operate tailscaled, e.g. with `tailscale set --operator=$USER`.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $state{ via $exit_node\|} "`
+//! `interval` | Update interval in seconds | `10`
+//! `exit_node` | The name or IP address of the exit node to enable with the `toggle_exit_node` action | `None`
+//!
+//! Placeholder | Value                                                                  | Type   | Unit
+//! ------------|------------------------------------------------------------------------|--------|-----
+//! `icon`      | A static icon                                                          | Icon   | -
+//! `state`     | The state of the node, e.g. `"Running"`, `"Stopped"` or `"NeedsLogin"` | Text   | -
+//! `dns_name`  | The MagicDNS name of this node                                         | Text   | -
+//! `tailnet`   | The name of the tailnet                                                | Text   | -
+//! `exit_node` | The host name of the exit node in use, if any                          | Text   | -
+//! `peers`     | The number of online peers                                             | Number | -
+//!
+//! Action             | Description                                    | Default button
+//! -------------------|------------------------------------------------|---------------
+//! `toggle`           | Connect or disconnect                          | Left
+//! `toggle_exit_node` | Start or stop using the configured `exit_node` | Right
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "tailscale"
+//! format = " $icon {$dns_name|$state} "
+//! exit_node = "home-router"
+//! ```
+//!
+//! # Icons Used
+//! - `net_vpn`
+
+use super::prelude::*;
+use std::collections::HashMap;
+use tokio::process::Command;
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    format: FormatConfig,
+    #[default(10.into())]
+    interval: Seconds,
+    exit_node: Option<String>,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[
+        (MouseButton::Left, None, "toggle"),
+        (MouseButton::Right, None, "toggle_exit_node"),
+    ])
+    .await?;
+
+    let mut widget = Widget::new().with_format(
+        config
+            .format
+            .with_default(" $icon $state{ via $exit_node|} ")?,
+    );
+    let mut timer = config.interval.timer();
+
+    loop {
+        let status = api.recoverable(Status::new).await?;
+        let running = status.backend_state == "Running";
+        let exit_node = status.exit_node();
+
+        widget.state = match status.backend_state.as_str() {
+            "Running" => State::Good,
+            "NeedsLogin" | "NeedsMachineAuth" => State::Warning,
+            _ => State::Idle,
+        };
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("net_vpn")?),
+            "state" => Value::text(status.backend_state.clone()),
+            "peers" => Value::number(status.peer.values().filter(|p| p.online).count()),
+            [if let Some(s) = &status.this] "dns_name" => Value::text(s.dns_name.trim_end_matches('.').into()),
+            [if let Some(t) = &status.current_tailnet] "tailnet" => Value::text(t.name.clone()),
+            [if let Some(e) = exit_node] "exit_node" => Value::text(e.host_name.clone()),
+        });
+        api.set_widget(&widget).await?;
+
+        loop {
+            select! {
+                _ = timer.tick() => break,
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "toggle" => {
+                        run_tailscale(&[if running { "down" } else { "up" }]).await?;
+                        break;
+                    }
+                    Action(a) if a == "toggle_exit_node" => {
+                        if let Some(node) = &config.exit_node {
+                            let arg = if exit_node.is_some() {
+                                "--exit-node=".into()
+                            } else {
+                                format!("--exit-node={node}")
+                            };
+                            run_tailscale(&["set", &arg]).await?;
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Status {
+    backend_state: String,
+    #[serde(rename = "Self")]
+    this: Option<Peer>,
+    current_tailnet: Option<Tailnet>,
+    #[serde(default)]
+    peer: HashMap<String, Peer>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Peer {
+    host_name: String,
+    #[serde(rename = "DNSName")]
+    dns_name: String,
+    #[serde(default)]
+    online: bool,
+    #[serde(default)]
+    exit_node: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct Tailnet {
+    name: String,
+}
+
+impl Status {
+    async fn new() -> Result<Self> {
+        let output = Command::new("tailscale")
+            .args(["status", "--json"])
+            .output()
+            .await
+            .error("Failed to run `tailscale status`")?;
+        serde_json::from_slice(&output.stdout).error("Failed to parse `tailscale status` output")
+    }
+
+    /// The peer which is currently used as the exit node
+    fn exit_node(&self) -> Option<&Peer> {
+        self.peer.values().find(|p| p.exit_node)
+    }
+}
+
+async fn run_tailscale(args: &[&str]) -> Result<()> {
+    let output = Command::new("tailscale")
+        .args(args)
+        .output()
+        .await
+        .error("Failed to run tailscale")?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(Error::new(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
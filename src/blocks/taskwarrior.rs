@@ -1,6 +1,8 @@
 //! The number of tasks from the taskwarrior list
 //!
 //! Clicking the right mouse button on the icon cycles the view of the block through the user's filters.
+//! The actions which start, stop or complete tasks have no default button, so they have to be bound
+//! with `[[block.click]]` to be used.
 //!
 //! # Configuration
 //!
@@ -21,10 +23,16 @@
 //! `filter_name` | The name of current filter                  | Text   | -
 //! `done`        | Present only if `count` is zero             | Flag   | -
 //! `single`      | Present only if `count` is one              | Flag   | -
+//! `active`      | The description of the active (started) task. Present only if a task is active | Text | -
+//! `top`         | The description of the most urgent task matching current filter. Present only if there is such a task | Text | -
 //!
-//! Action        | Default button
-//! --------------|---------------
-//! `next_filter` | Right
+//! Action        | Description                                                   | Default button
+//! --------------|---------------------------------------------------------------|---------------
+//! `next_filter` | Switch to the next filter                                     | Right
+//! `toggle`      | Stop the active task, or start the top task if none is active | -
+//! `start`       | Start the top task                                            | -
+//! `stop`        | Stop the active task                                          | -
+//! `done`        | Mark the active task, or the top task if none is active, done | -
 //!
 //! # Example
 //!
@@ -46,6 +54,27 @@
 //! filter = "project:some-project +PENDING"
 //! ```
 //!
+//! Show the active task, or the next task to work on, start or stop it with a left click and mark it
+//! done with a middle click:
+//!
+//! ```toml
+//! [[block]]
+//! block = "taskwarrior"
+//! format = " $icon {$active|$top|$done{All done}} "
+//! [[block.filters]]
+//! name = "inbox"
+//! filter = "+PENDING +inbox"
+//! [[block.filters]]
+//! name = "work"
+//! filter = "+PENDING project:work"
+//! [[block.click]]
+//! button = "left"
+//! action = "toggle"
+//! [[block.click]]
+//! button = "middle"
+//! action = "done"
+//! ```
+//!
 //! # Icons Used
 //! - `tasks`
 
@@ -86,11 +115,12 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     api.set_default_actions(&[(MouseButton::Right, None, "next_filter")])
         .await?;
 
-    let mut widget = Widget::new().with_format(
-        config
-            .format
-            .with_default(" $icon $done|$count.eng(w:1) ")?,
-    );
+    let format = config
+        .format
+        .with_default(" $icon $done|$count.eng(w:1) ")?;
+    let show_active = format.contains_key("active");
+    let show_top = format.contains_key("top");
+    let mut widget = Widget::new().with_format(format);
 
     let mut filters = config.filters.iter().cycle();
     let mut filter = filters.next().error("`filters` is empty")?;
@@ -105,6 +135,16 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
 
     loop {
         let number_of_tasks = get_number_of_tasks(&filter.filter).await?;
+        let active = if show_active {
+            get_active_task().await?
+        } else {
+            None
+        };
+        let top = if show_top {
+            get_top_task(&filter.filter).await?
+        } else {
+            None
+        };
 
         if number_of_tasks != 0 || !config.hide_when_zero {
             widget.set_values(map! {
@@ -113,6 +153,8 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                 "filter_name" => Value::text(filter.name.clone()),
                 [if number_of_tasks == 0] "done" => Value::flag(),
                 [if number_of_tasks == 1] "single" => Value::flag(),
+                [if let Some(task) = active] "active" => Value::text(task.description),
+                [if let Some(task) = top] "top" => Value::text(task.description),
             });
 
             widget.state = if number_of_tasks >= config.critical_threshold {
@@ -135,6 +177,24 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                 Action(a) if a == "next_filter" => {
                     filter = filters.next().unwrap();
                 }
+                Action(a) => {
+                    let (task, command) = match a.as_ref() {
+                        "toggle" => match get_active_task().await? {
+                            Some(task) => (Some(task), "stop"),
+                            None => (get_top_task(&filter.filter).await?, "start"),
+                        },
+                        "start" => (get_top_task(&filter.filter).await?, "start"),
+                        "stop" => (get_active_task().await?, "stop"),
+                        "done" => match get_active_task().await? {
+                            Some(task) => (Some(task), "done"),
+                            None => (get_top_task(&filter.filter).await?, "done"),
+                        },
+                        _ => (None, ""),
+                    };
+                    if let Some(task) = task {
+                        modify_task(&task.uuid, command).await?;
+                    }
+                }
                 _ => (),
             }
         }
@@ -155,6 +215,53 @@ async fn get_number_of_tasks(filter: &str) -> Result<u32> {
         .error("could not parse the result of taskwarrior")
 }
 
+#[derive(Deserialize, Debug)]
+struct Task {
+    uuid: String,
+    description: String,
+    status: String,
+    #[serde(default)]
+    urgency: f64,
+}
+
+async fn export_tasks(filter: &str) -> Result<Vec<Task>> {
+    let output = Command::new("task")
+        .args(["rc.gc=off", "rc.json.array=on", filter, "export"])
+        .output()
+        .await
+        .error("failed to run taskwarrior for exporting tasks")?
+        .stdout;
+    serde_json::from_slice(&output).error("could not parse the tasks exported by taskwarrior")
+}
+
+async fn get_active_task() -> Result<Option<Task>> {
+    Ok(export_tasks("+ACTIVE").await?.into_iter().next())
+}
+
+/// The most urgent pending task matching `filter`
+async fn get_top_task(filter: &str) -> Result<Option<Task>> {
+    Ok(export_tasks(filter)
+        .await?
+        .into_iter()
+        .filter(|task| task.status == "pending")
+        .max_by(|a, b| a.urgency.total_cmp(&b.urgency)))
+}
+
+/// Run `task <uuid> <command>`, e.g. `start`, `stop` or `done`
+async fn modify_task(uuid: &str, command: &str) -> Result<()> {
+    let status = Command::new("task")
+        .args(["rc.confirmation=off", uuid, command])
+        .output()
+        .await
+        .or_error(|| format!("failed to run 'task {command}'"))?
+        .status;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::new(format!("'task {command}' failed")))
+    }
+}
+
 #[derive(Deserialize, Debug, Default, Clone)]
 #[serde(deny_unknown_fields)]
 struct Filter {
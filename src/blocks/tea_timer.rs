@@ -1,5 +1,11 @@
 //! Timer
 //!
+//! Counts down from a time set by clicking or scrolling the block, or to a `target` date and time,
+//! which starts as soon as the block does. When the timer reaches zero, `done_cmd` is run.
+//!
+//! For a `target` which is only a time of day, the timer runs to its next occurrence. Once it is
+//! over or has been reset, `reset` starts it again towards the next occurrence.
+//!
 //! # Configuration
 //!
 //! Key | Values | Default
@@ -7,6 +13,7 @@
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon {$minutes:$seconds |}"`
 //! `increment` | The numbers of seconds to add each time the block is clicked. | 30
 //! `done_cmd` | A command to run in `sh` when timer finishes. | None
+//! `target` | A date and time to count down to, as `"YYYY-MM-DD HH:MM"` or `"HH:MM"` | None
 //!
 //! Placeholder      | Value                                                          | Type   | Unit
 //! -----------------|----------------------------------------------------------------|--------|---------------
@@ -14,6 +21,7 @@
 //! `hours`          | The hours remaining on the timer                               | Text   | h
 //! `minutes`        | The minutes remaining on the timer                             | Text   | mn
 //! `seconds`        | The seconds remaining on the timer                             | Text   | s
+//! `paused`         | Present if the timer is paused                                 | Flag   | -
 //!
 //! `hours`, `minutes`, and `seconds` are unset when the timer is inactive.
 //!
@@ -21,9 +29,10 @@
 //! ------------|---------------
 //! `increment` | Left / Wheel Up
 //! `decrement` | Wheel Down
+//! `pause`     | Middle
 //! `reset`     | Right
 //!
-//! # Example
+//! # Examples
 //!
 //! ```toml
 //! [[block]]
@@ -32,12 +41,20 @@
 //! done_cmd = "notify-send 'Timer Finished'"
 //! ```
 //!
+//! ```toml
+//! [[block]]
+//! block = "tea_timer"
+//! format = " $icon Standup {$hours:$minutes:$seconds |}"
+//! target = "09:30"
+//! done_cmd = "notify-send 'Standup starts now'"
+//! ```
+//!
 //! # Icons Used
 //! - `tea`
 
 use super::prelude::*;
 use crate::subprocess::spawn_shell;
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Local, NaiveDateTime, NaiveTime, TimeZone};
 
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(deny_unknown_fields, default)]
@@ -45,6 +62,14 @@ pub struct Config {
     format: FormatConfig,
     increment: Option<i64>,
     done_cmd: Option<String>,
+    target: Option<String>,
+}
+
+#[derive(Clone, Copy)]
+enum TimerState {
+    Inactive,
+    Running { end: DateTime<Local> },
+    Paused { remaining: Duration },
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
@@ -52,6 +77,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         (MouseButton::Left, None, "increment"),
         (MouseButton::WheelUp, None, "increment"),
         (MouseButton::WheelDown, None, "decrement"),
+        (MouseButton::Middle, None, "pause"),
         (MouseButton::Right, None, "reset"),
     ])
     .await?;
@@ -63,29 +89,37 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     let mut widget = Widget::new().with_format(format);
 
     let increment = Duration::seconds(config.increment.unwrap_or(30));
-    let mut timer_end = Utc::now();
-
-    let mut timer_was_active = false;
+    let mut state = match &config.target {
+        Some(target) => count_down_to(target, Local::now())?,
+        None => TimerState::Inactive,
+    };
 
     loop {
-        let remaining_time = timer_end - Utc::now();
-        let is_timer_active = remaining_time > Duration::zero();
-
-        if !is_timer_active && timer_was_active {
-            if let Some(cmd) = &config.done_cmd {
-                spawn_shell(cmd).error("done_cmd error")?;
+        let now = Local::now();
+        if let TimerState::Running { end } = state {
+            if end <= now {
+                state = TimerState::Inactive;
+                if let Some(cmd) = &config.done_cmd {
+                    spawn_shell(cmd).error("done_cmd error")?;
+                }
             }
         }
-        timer_was_active = is_timer_active;
 
-        let (hours, minutes, seconds) = if is_timer_active {
-            (
+        let remaining_time = match state {
+            TimerState::Inactive => None,
+            TimerState::Running { end } => Some(end - now),
+            TimerState::Paused { remaining } => Some(remaining),
+        };
+        let is_timer_active = remaining_time.is_some();
+        let is_paused = matches!(state, TimerState::Paused { .. });
+
+        let (hours, minutes, seconds) = match remaining_time {
+            Some(remaining_time) => (
                 remaining_time.num_hours(),
                 remaining_time.num_minutes() % 60,
                 remaining_time.num_seconds() % 60,
-            )
-        } else {
-            (0, 0, 0)
+            ),
+            None => (0, 0, 0),
         };
 
         widget.set_values(map!(
@@ -93,25 +127,82 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             [if is_timer_active] "hours" => Value::text(format!("{hours:02}")),
             [if is_timer_active] "minutes" => Value::text(format!("{minutes:02}")),
             [if is_timer_active] "seconds" => Value::text(format!("{seconds:02}")),
+            [if is_paused] "paused" => Value::flag(),
         ));
 
         api.set_widget(&widget).await?;
 
+        let is_running = matches!(state, TimerState::Running { .. });
         tokio::select! {
-            _ = timer.tick(), if is_timer_active => (),
+            _ = timer.tick(), if is_running => (),
             event = api.event() => match event {
                 UpdateRequest => (),
                 Action(action) => {
-                    let now = Utc::now();
-                    match action.as_ref() {
-                        "increment" if is_timer_active => timer_end += increment,
-                        "increment" => timer_end = now + increment,
-                        "decrement" if is_timer_active => timer_end -= increment,
-                        "reset" => timer_end = now,
-                        _ => (),
-                    }
+                    let now = Local::now();
+                    state = match (action.as_ref(), state) {
+                        ("increment", TimerState::Inactive) => TimerState::Running {
+                            end: now + increment,
+                        },
+                        ("increment", TimerState::Running { end }) => TimerState::Running {
+                            end: end + increment,
+                        },
+                        ("increment", TimerState::Paused { remaining }) => TimerState::Paused {
+                            remaining: remaining + increment,
+                        },
+                        ("decrement", TimerState::Running { end }) => TimerState::Running {
+                            end: end - increment,
+                        },
+                        ("decrement", TimerState::Paused { remaining })
+                            if remaining > increment =>
+                        {
+                            TimerState::Paused {
+                                remaining: remaining - increment,
+                            }
+                        }
+                        ("decrement", TimerState::Paused { .. }) => TimerState::Inactive,
+                        ("pause", TimerState::Running { end }) => TimerState::Paused {
+                            remaining: end - now,
+                        },
+                        ("pause", TimerState::Paused { remaining }) => TimerState::Running {
+                            end: now + remaining,
+                        },
+                        ("reset", TimerState::Inactive) => match &config.target {
+                            Some(target) => count_down_to(target, now)?,
+                            None => TimerState::Inactive,
+                        },
+                        ("reset", _) => TimerState::Inactive,
+                        (_, state) => state,
+                    };
                 },
             }
         }
     }
 }
+
+/// Run the timer until `target`, unless it has already passed. A time of day is taken as its next
+/// occurrence after `now`.
+fn count_down_to(target: &str, now: DateTime<Local>) -> Result<TimerState> {
+    let end = if let Ok(time) = NaiveDateTime::parse_from_str(target, "%Y-%m-%d %H:%M") {
+        Local
+            .from_local_datetime(&time)
+            .earliest()
+            .error("Invalid target")?
+    } else {
+        let time = NaiveTime::parse_from_str(target, "%H:%M")
+            .error("`target` must be formatted as \"YYYY-MM-DD HH:MM\" or \"HH:MM\"")?;
+        let mut date = now.date_naive();
+        loop {
+            if let Some(end) = Local.from_local_datetime(&date.and_time(time)).earliest() {
+                if end > now {
+                    break end;
+                }
+            }
+            date = date.succ_opt().error("Invalid target")?;
+        }
+    };
+    Ok(if end > now {
+        TimerState::Running { end }
+    } else {
+        TimerState::Inactive
+    })
+}
@@ -5,7 +5,7 @@
 //! This block has two modes: "collapsed", which uses only color as an indicator, and "expanded",
 //! which shows the content of a `format` string. The average, minimum, and maximum temperatures
 //! are computed using all sensors displayed by `sensors`, or optionally filtered by `chip` and
-//! `inputs`.
+//! `inputs`, or by `sensors`.
 //!
 //! Requires `libsensors` and appropriate kernel modules for your hardware.
 //!
@@ -28,16 +28,26 @@
 //! `warning` | Maximum temperature to set state to warning. Beyond this temperature, state is set to critical | `80` °C (`176` °F)
 //! `chip` | Narrows the results to a given chip name. `*` may be used as a wildcard. | None
 //! `inputs` | Narrows the results to individual inputs reported by each chip. | None
+//! `fan_profiles` | Fan profiles to cycle through with `cycle_fan_profile`, e.g. `["quiet", "balanced", "performance"]` | `[]`
+//! `fan_profile_cmd` | A shell command which switches the fan profile, usually through a privileged helper. `{profile}` will be substituted with the profile to switch to. | `None`
+//! `sensors` | A list of sensors to use, as `"<chip>/<input>"` or just `"<chip>"` for all inputs of a chip. `<chip>` is the hwmon name (e.g. `k10temp`, the part of the chip name before the first `-`) and `<input>` is the input label. Both may contain `*` and `?` wildcards. | None
 //!
 //! Action          | Description                               | Default button
 //! ----------------|-------------------------------------------|---------------
 //! `toggle_format` | Toggles between `format` and `format_alt` | Left
+//! `cycle_fan_profile` | Switches to the next fan profile using `fan_profile_cmd` | Right
 //!
 //! Placeholder | Value                                | Type   | Unit
 //! ------------|--------------------------------------|--------|--------
 //! `min`       | Minimum temperature among all inputs | Number | Degrees
 //! `average`   | Average temperature among all inputs | Number | Degrees
 //! `max`       | Maximum temperature among all inputs | Number | Degrees
+//! `<chip>_<input>` | Temperature of a single input, e.g. `k10temp_tctl` or `nvme_composite`. The names are lowercased and characters other than letters and digits are replaced by `_`. If several chips have the same name, `_2`, `_3`, ... is appended. | Number | Degrees
+//! `fan`       | Maximum speed among all fans | Number | RPM
+//! `<chip>_<fan>` | Speed of a single fan, named like the temperature inputs, e.g. `thinkpad_fan1` | Number | RPM
+//! `fan_profile` | The current fan profile. Read from `/sys/firmware/acpi/platform_profile` if available, otherwise the last profile set by the block. | Text | -
+//!
+//! Fans are only filtered by `chip`, not by `inputs` or `sensors`.
 //!
 //! Note that when block is collapsed, no placeholders are provided.
 //!
@@ -52,13 +62,37 @@
 //! chip = "*-isa-*"
 //! ```
 //!
+//! Only use the CPU package and NVMe temperatures:
+//!
+//! ```toml
+//! [[block]]
+//! block = "temperature"
+//! format = " $icon $max max (cpu $k10temp_tctl, ssd $nvme_composite) "
+//! sensors = ["k10temp/Tctl", "nvme*/Composite"]
+//! ```
+//!
+//! Show the fan speed and switch the ACPI platform profile (supported by `thinkpad_acpi` and
+//! `asus-wmi`, among others) on right click:
+//!
+//! ```toml
+//! [[block]]
+//! block = "temperature"
+//! format = " $icon $max $fan RPM $fan_profile "
+//! fan_profiles = ["low-power", "balanced", "performance"]
+//! fan_profile_cmd = "echo {profile} | pkexec tee /sys/firmware/acpi/platform_profile"
+//! ```
+//!
 //! # Icons Used
 //! - `thermometer`
 
 use super::prelude::*;
-use sensors::FeatureType::SENSORS_FEATURE_TEMP;
+use crate::subprocess::spawn_shell_sync;
+use crate::util::read_file;
+use sensors::FeatureType::{SENSORS_FEATURE_FAN, SENSORS_FEATURE_TEMP};
 use sensors::Sensors;
-use sensors::SubfeatureType::SENSORS_SUBFEATURE_TEMP_INPUT;
+use sensors::SubfeatureType::{SENSORS_SUBFEATURE_FAN_INPUT, SENSORS_SUBFEATURE_TEMP_INPUT};
+
+const PLATFORM_PROFILE_PATH: &str = "/sys/firmware/acpi/platform_profile";
 
 const DEFAULT_GOOD: f64 = 20.0;
 const DEFAULT_IDLE: f64 = 45.0;
@@ -79,6 +113,9 @@ pub struct Config {
     warning: Option<f64>,
     chip: Option<String>,
     inputs: Option<Vec<String>>,
+    sensors: Option<Vec<String>>,
+    fan_profiles: Vec<String>,
+    fan_profile_cmd: Option<String>,
 }
 
 #[derive(Deserialize, Debug, SmartDefault, Clone, Copy, PartialEq, Eq)]
@@ -100,8 +137,11 @@ impl TemperatureScale {
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
-    api.set_default_actions(&[(MouseButton::Left, None, "toggle_format")])
-        .await?;
+    api.set_default_actions(&[
+        (MouseButton::Left, None, "toggle_format"),
+        (MouseButton::Right, None, "cycle_fan_profile"),
+    ])
+    .await?;
 
     let mut format = config
         .format
@@ -125,12 +165,18 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         .warning
         .unwrap_or_else(|| config.scale.from_celsius(DEFAULT_WARN));
 
+    // The last profile set by `cycle_fan_profile`, used if the platform profile is not available
+    let mut fan_profile: Option<String> = None;
+
     loop {
         // Perhaps it's better to just Box::leak() once and don't clone() every time?
         let chip = config.chip.clone();
         let inputs = config.inputs.clone();
-        let temp = tokio::task::spawn_blocking(move || {
+        let patterns = config.sensors.clone();
+        let (temp, names, fans) = tokio::task::spawn_blocking(move || {
             let mut vals = Vec::new();
+            let mut names = Vec::new();
+            let mut fans = Vec::new();
             let sensors = Sensors::new();
             let chips = match &chip {
                 Some(chip) => sensors
@@ -138,22 +184,46 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                     .error("Failed to create chip iterator")?,
                 None => sensors.into_iter(),
             };
+            let mut chip_names: HashMap<String, usize> = HashMap::new();
             for chip in chips {
+                let prefix = chip.prefix().to_owned();
+                let count = chip_names.entry(prefix.clone()).or_default();
+                *count += 1;
+                let chip_name = match *count {
+                    1 => prefix.clone(),
+                    n => format!("{prefix}_{n}"),
+                };
                 for feat in chip {
+                    if *feat.feature_type() == SENSORS_FEATURE_FAN {
+                        let label = feat.get_label().error("Failed to get input label")?;
+                        if let Some(Ok(rpm)) = feat
+                            .get_subfeature(SENSORS_SUBFEATURE_FAN_INPUT)
+                            .map(|s| s.get_value())
+                        {
+                            fans.push((placeholder_name(&chip_name, &label), rpm));
+                        }
+                        continue;
+                    }
                     if *feat.feature_type() != SENSORS_FEATURE_TEMP {
                         continue;
                     }
+                    let label = feat.get_label().error("Failed to get input label")?;
                     if let Some(inputs) = &inputs {
-                        let label = feat.get_label().error("Failed to get input label")?;
                         if !inputs.contains(&label) {
                             continue;
                         }
                     }
+                    if let Some(patterns) = &patterns {
+                        if !patterns.iter().any(|p| sensor_matches(p, &prefix, &label)) {
+                            continue;
+                        }
+                    }
                     for subfeat in feat {
                         if *subfeat.subfeature_type() == SENSORS_SUBFEATURE_TEMP_INPUT {
                             if let Ok(value) = subfeat.get_value() {
                                 if (-100.0..=150.0).contains(&value) {
                                     vals.push(config.scale.from_celsius(value));
+                                    names.push(placeholder_name(&chip_name, &label));
                                 } else {
                                     eprintln!(
                                         "Temperature ({value}) outside of range ([-100, 150])"
@@ -164,7 +234,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                     }
                 }
             }
-            Ok(vals)
+            Ok((vals, names, fans))
         })
         .await
         .error("Failed to join tokio task")??;
@@ -189,13 +259,27 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             _ => State::Critical,
         };
 
+        if let Ok(profile) = read_file(PLATFORM_PROFILE_PATH).await {
+            fan_profile = Some(profile.trim().to_owned());
+        }
+        let max_fan = fans.iter().map(|(_, rpm)| *rpm).reduce(f64::max);
+
         'outer: loop {
-            widget.set_values(map! {
+            let mut values = map! {
                 "icon" => Value::icon(api.get_icon("thermometer")?),
                 "average" => Value::degrees(avg_temp),
                 "min" => Value::degrees(min_temp),
                 "max" => Value::degrees(max_temp),
-            });
+                [if let Some(rpm) = max_fan] "fan" => Value::number(rpm),
+                [if let Some(p) = &fan_profile] "fan_profile" => Value::text(p.clone()),
+            };
+            for (name, temp) in names.iter().zip(&temp) {
+                values.insert(name.clone().into(), Value::degrees(*temp));
+            }
+            for (name, rpm) in &fans {
+                values.insert(name.clone().into(), Value::number(*rpm));
+            }
+            widget.set_values(values);
 
             api.set_widget(&widget).await?;
 
@@ -211,6 +295,20 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                                 break;
                             }
                         }
+                        Action(a) if a == "cycle_fan_profile" => {
+                            let (profiles, cmd) = (&config.fan_profiles, &config.fan_profile_cmd);
+                            if let (Some(cmd), false) = (cmd, profiles.is_empty()) {
+                                let next = fan_profile
+                                    .as_ref()
+                                    .and_then(|p| profiles.iter().position(|x| x == p))
+                                    .map_or(0, |i| (i + 1) % profiles.len());
+                                spawn_shell_sync(&cmd.replace("{profile}", &profiles[next]))
+                                    .await
+                                    .error("failed to run fan_profile_cmd")?;
+                                fan_profile = Some(profiles[next].clone());
+                                break 'outer;
+                            }
+                        }
                         _ => (),
                     }
                 }
@@ -218,3 +316,79 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         }
     }
 }
+
+/// Check whether `pattern` (`"<chip>"` or `"<chip>/<label>"`) matches an input of a chip
+fn sensor_matches(pattern: &str, chip: &str, label: &str) -> bool {
+    match pattern.split_once('/') {
+        Some((chip_pattern, label_pattern)) => {
+            wildcard_match(chip_pattern, chip) && wildcard_match(label_pattern, label)
+        }
+        None => wildcard_match(pattern, chip),
+    }
+}
+
+/// Match `text` against a pattern where `*` matches any sequence and `?` any single character
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` in the pattern and the text position it was tried at
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    backtrack = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn placeholder_name(chip: &str, label: &str) -> String {
+    format!("{chip}_{label}")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("nvme*", "nvme"));
+        assert!(wildcard_match("*temp", "k10temp"));
+        assert!(wildcard_match("k?0te*p", "k10temp"));
+        assert!(!wildcard_match("nvme", "nvme1"));
+        assert!(!wildcard_match("*a*b", "aab_"));
+    }
+
+    #[test]
+    fn sensors() {
+        assert!(sensor_matches("k10temp/Tctl", "k10temp", "Tctl"));
+        assert!(!sensor_matches("k10temp/Tctl", "k10temp", "Tccd1"));
+        assert!(sensor_matches("k10temp/Tccd*", "k10temp", "Tccd1"));
+        assert!(sensor_matches("nvme*", "nvme", "Composite"));
+        assert_eq!(placeholder_name("nvme_2", "Sensor 1"), "nvme_2_sensor_1");
+    }
+}
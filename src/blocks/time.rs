@@ -6,12 +6,25 @@
 //! ----|--------|--------
 //! `format` | Format string. See [chrono docs](https://docs.rs/chrono/0.3.0/chrono/format/strftime/index.html#specifiers) for all options. | `" $icon %a %d/%m %R "`
 //! `interval` | Update interval in seconds | `10`
-//! `timezone` | A timezone specifier (e.g. "Europe/Lisbon") | Local timezone
+//! `timezone` | A timezone specifier (e.g. "Europe/Lisbon"), a table with `timezone` and `label` keys, or a list of those | Local timezone
+//! `show_all` | Show the time in every timezone at once instead of one at a time | `false`
+//! `separator` | Text between the timezones when `show_all` is enabled | `" "`
 //! `locale` | Locale to apply when formatting the time | System locale
 //!
 //! Placeholder   | Value                                       | Type   | Unit
 //! --------------|---------------------------------------------|--------|-----
 //! `icon`        | A static icon                               | Icon   | -
+//! `timezone`    | The label of the timezone, defaults to its name | Text | -
+//! `hebrew_date` | The date in the Hebrew calendar, e.g. "1 Tishri 5784" | Text | -
+//! `persian_date` | The date in the Persian (Solar Hijri) calendar, e.g. "1 Farvardin 1403" | Text | -
+//! `japanese_era` | The Japanese era and year, e.g. "Reiwa 8" | Text | -
+//!
+//! With several timezones, the format is applied to each of them. The calendar placeholders always
+//! use the date in the currently selected timezone.
+//!
+//! Action          | Default button
+//! ----------------|---------------
+//! `next_timezone` | Left
 //!
 //! # Example
 //!
@@ -25,27 +38,90 @@
 //! short = " $icon %R "
 //! ```
 //!
+//! Click to cycle between timezones:
+//!
+//! ```toml
+//! [[block]]
+//! block = "time"
+//! format = " $icon %R $timezone "
+//! timezone = [
+//!     { timezone = "Europe/Helsinki", label = "HEL" },
+//!     { timezone = "America/New_York", label = "NYC" },
+//!     "UTC",
+//! ]
+//! ```
+//!
+//! Show the date in the Persian calendar next to the Gregorian one:
+//!
+//! ```toml
+//! [[block]]
+//! block = "time"
+//! format = " $icon %d/%m ($persian_date) %R "
+//! ```
+//!
+//! Or show all timezones at once:
+//!
+//! ```toml
+//! [[block]]
+//! block = "time"
+//! format = "%R $timezone"
+//! timezone = ["Europe/Helsinki", "America/New_York"]
+//! show_all = true
+//! separator = " | "
+//! ```
+//!
 //! # Icons Used
 //! - `time`
 
 use chrono::offset::{Local, Utc};
-use chrono::Locale;
+use chrono::{Datelike, Locale, NaiveDate};
 use chrono_tz::Tz;
 
 use super::prelude::*;
 use crate::formatting::config::DummyConfig;
 
+mod calendars;
+
 #[derive(Deserialize, Debug, SmartDefault)]
 #[serde(default)]
 pub struct Config {
     format: DummyConfig,
     #[default(1.into())]
     interval: Seconds,
-    timezone: Option<Tz>,
+    timezone: Option<Timezones>,
+    show_all: bool,
+    #[default(" ".into())]
+    separator: String,
     locale: Option<String>,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum Timezones {
+    Single(TimezoneEntry),
+    Multiple(Vec<TimezoneEntry>),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum TimezoneEntry {
+    Name(Tz),
+    Labeled { timezone: Tz, label: String },
+}
+
+impl TimezoneEntry {
+    fn into_labeled(self) -> (Option<Tz>, String) {
+        match self {
+            Self::Name(tz) => (Some(tz), tz.name().into()),
+            Self::Labeled { timezone, label } => (Some(timezone), label),
+        }
+    }
+}
+
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[(MouseButton::Left, None, "next_timezone")])
+        .await?;
+
     let mut widget = Widget::new();
 
     let format = config
@@ -55,7 +131,19 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         .unwrap_or(" $icon %a %d/%m %R ");
     let format_short = config.format.short.as_deref();
 
-    let timezone = config.timezone;
+    let timezones: Vec<(Option<Tz>, String)> = match config.timezone {
+        None => vec![(None, "Local".into())],
+        Some(Timezones::Single(entry)) => vec![entry.into_labeled()],
+        Some(Timezones::Multiple(entries)) if entries.is_empty() => {
+            return Err(Error::new("timezone list must not be empty"));
+        }
+        Some(Timezones::Multiple(entries)) => entries
+            .into_iter()
+            .map(TimezoneEntry::into_labeled)
+            .collect(),
+    };
+    let mut current = 0;
+
     let locale = match config.locale.as_deref() {
         Some(locale) => Some(locale.try_into().ok().error("invalid locale")?),
         None => None,
@@ -64,27 +152,87 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     let mut timer = config.interval.timer();
 
     loop {
-        if timezone.is_none() {
+        if timezones.iter().any(|(tz, _)| tz.is_none()) {
             // Update timezone because `chrono` will not do that for us.
             // https://github.com/chronotope/chrono/issues/272
             unsafe { tzset() };
         }
 
-        let full_time = get_time(format, timezone, locale);
-        let short_time = format_short
-            .map(|f| get_time(f, timezone, locale))
-            .unwrap_or_else(|| "".into());
+        let shown = if config.show_all {
+            &timezones[..]
+        } else {
+            &timezones[current..=current]
+        };
+        let render = |format: &str| {
+            shown
+                .iter()
+                .map(|(tz, label)| get_time(&with_label(format, label), *tz, locale))
+                .collect::<Vec<_>>()
+                .join(&config.separator)
+        };
+        let full_time = render(format);
+        let short_time = format_short.map(render).unwrap_or_else(|| "".into());
 
         widget.set_format(FormatConfig::default().with_defaults(&full_time, &short_time)?);
-        widget.set_values(map!("icon" => Value::icon(api.get_icon("time")?)));
+        let date = get_date(timezones[current].0);
+        let fixed = date.num_days_from_ce() as i64;
+        let year = date.year() as i64;
+        let hebrew = calendars::hebrew_from_fixed(fixed);
+        let persian = calendars::persian_from_fixed(year, fixed);
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("time")?),
+            "hebrew_date" => Value::text(format!(
+                "{} {} {}",
+                hebrew.day,
+                calendars::hebrew_month_name(hebrew),
+                hebrew.year
+            )),
+            "persian_date" => Value::text(format!(
+                "{} {} {}",
+                persian.day,
+                calendars::persian_month_name(persian),
+                persian.year
+            )),
+            [if let Some((era, era_year)) = calendars::japanese_era(year, fixed)]
+                "japanese_era" => Value::text(format!("{era} {era_year}")),
+        });
 
         api.set_widget(&widget).await?;
 
         tokio::select! {
             _ = timer.tick() => (),
-            _ = api.wait_for_update_request() => (),
+            event = api.event() => match event {
+                UpdateRequest => (),
+                Action(a) if a == "next_timezone" => {
+                    current = (current + 1) % timezones.len();
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Replace `$timezone` in the format with the label, escaped for both strftime and our formatter
+fn with_label(format: &str, label: &str) -> String {
+    let mut escaped = String::with_capacity(label.len());
+    for c in label.chars() {
+        match c {
+            '%' => escaped.push_str("%%"),
+            '$' | '^' | '{' | '}' | '|' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
         }
     }
+    format.replace("$timezone", &escaped)
+}
+
+fn get_date(timezone: Option<Tz>) -> NaiveDate {
+    match timezone {
+        Some(tz) => Utc::now().with_timezone(&tz).date_naive(),
+        None => Local::now().date_naive(),
+    }
 }
 
 fn get_time(format: &str, timezone: Option<Tz>, locale: Option<Locale>) -> String {
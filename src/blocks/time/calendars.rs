@@ -0,0 +1,363 @@
+//! Conversions from the Gregorian calendar to other calendar systems.
+//!
+//! Dates are handled as fixed day numbers, where 0001-01-01 (Gregorian) is day 1. This matches
+//! `chrono::Datelike::num_days_from_ce`. The Hebrew calendar follows "Calendrical Calculations"
+//! by Reingold and Dershowitz; the Persian one uses the jalaali algorithm, which matches the
+//! official calendar between 1800 and 2256 CE.
+
+/// A date in some calendar system, months start at 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+fn is_gregorian_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+pub fn fixed_from_gregorian(year: i64, month: u32, day: u32) -> i64 {
+    let y = year - 1;
+    let correction = match month {
+        1 | 2 => 0,
+        _ if is_gregorian_leap_year(year) => -1,
+        _ => -2,
+    };
+    365 * y + y.div_euclid(4) - y.div_euclid(100)
+        + y.div_euclid(400)
+        + (367 * month as i64 - 362) / 12
+        + correction
+        + day as i64
+}
+
+// Hebrew calendar
+
+const HEBREW_EPOCH: i64 = -1373427;
+const TISHRI: u32 = 7;
+
+const HEBREW_MONTHS: [&str; 13] = [
+    "Nisan",
+    "Iyyar",
+    "Sivan",
+    "Tammuz",
+    "Av",
+    "Elul",
+    "Tishri",
+    "Marheshvan",
+    "Kislev",
+    "Tevet",
+    "Shevat",
+    "Adar",
+    "Adar II",
+];
+
+fn is_hebrew_leap_year(year: i64) -> bool {
+    (7 * year + 1).rem_euclid(19) < 7
+}
+
+fn last_month_of_hebrew_year(year: i64) -> u32 {
+    if is_hebrew_leap_year(year) {
+        13
+    } else {
+        12
+    }
+}
+
+fn hebrew_calendar_elapsed_days(year: i64) -> i64 {
+    let months_elapsed = (235 * year - 234).div_euclid(19);
+    let parts_elapsed = 12084 + 13753 * months_elapsed;
+    let days = 29 * months_elapsed + parts_elapsed.div_euclid(25920);
+    if (3 * (days + 1)).rem_euclid(7) < 3 {
+        days + 1
+    } else {
+        days
+    }
+}
+
+fn hebrew_year_length_correction(year: i64) -> i64 {
+    let ny0 = hebrew_calendar_elapsed_days(year - 1);
+    let ny1 = hebrew_calendar_elapsed_days(year);
+    let ny2 = hebrew_calendar_elapsed_days(year + 1);
+    if ny2 - ny1 == 356 {
+        2
+    } else if ny1 - ny0 == 382 {
+        1
+    } else {
+        0
+    }
+}
+
+fn hebrew_new_year(year: i64) -> i64 {
+    HEBREW_EPOCH + hebrew_calendar_elapsed_days(year) + hebrew_year_length_correction(year)
+}
+
+fn last_day_of_hebrew_month(month: u32, year: i64) -> u32 {
+    let days_in_year = hebrew_new_year(year + 1) - hebrew_new_year(year);
+    let long_marheshvan = matches!(days_in_year, 355 | 385);
+    let short_kislev = matches!(days_in_year, 353 | 383);
+    match month {
+        2 | 4 | 6 | 10 | 13 => 29,
+        12 if !is_hebrew_leap_year(year) => 29,
+        8 if !long_marheshvan => 29,
+        9 if short_kislev => 29,
+        _ => 30,
+    }
+}
+
+fn fixed_from_hebrew(date: Date) -> i64 {
+    let month_days = |months: std::ops::Range<u32>| -> i64 {
+        months
+            .map(|m| last_day_of_hebrew_month(m, date.year) as i64)
+            .sum()
+    };
+    // The year starts in Tishri, but months are numbered from Nisan
+    let elapsed = if date.month < TISHRI {
+        month_days(TISHRI..last_month_of_hebrew_year(date.year) + 1) + month_days(1..date.month)
+    } else {
+        month_days(TISHRI..date.month)
+    };
+    hebrew_new_year(date.year) + elapsed + date.day as i64 - 1
+}
+
+pub fn hebrew_from_fixed(fixed: i64) -> Date {
+    let approx = ((fixed - HEBREW_EPOCH) as f64 / (35975351. / 98496.)).floor() as i64 + 1;
+    let mut year = approx - 1;
+    while hebrew_new_year(year + 1) <= fixed {
+        year += 1;
+    }
+    let start = if fixed
+        < fixed_from_hebrew(Date {
+            year,
+            month: 1,
+            day: 1,
+        }) {
+        TISHRI
+    } else {
+        1
+    };
+    let month = (start..)
+        .find(|&month| {
+            let day = last_day_of_hebrew_month(month, year);
+            fixed <= fixed_from_hebrew(Date { year, month, day })
+        })
+        .unwrap();
+    let day = (fixed
+        - fixed_from_hebrew(Date {
+            year,
+            month,
+            day: 1,
+        })) as u32
+        + 1;
+    Date { year, month, day }
+}
+
+pub fn hebrew_month_name(date: Date) -> &'static str {
+    match date.month {
+        12 if is_hebrew_leap_year(date.year) => "Adar I",
+        month => HEBREW_MONTHS[month as usize - 1],
+    }
+}
+
+// Persian (Solar Hijri) calendar
+
+const PERSIAN_BREAKS: [i64; 20] = [
+    -61, 9, 38, 199, 426, 686, 756, 818, 1111, 1181, 1210, 1635, 2060, 2097, 2192, 2262, 2324,
+    2394, 2456, 3178,
+];
+
+const PERSIAN_MONTHS: [&str; 12] = [
+    "Farvardin",
+    "Ordibehesht",
+    "Khordad",
+    "Tir",
+    "Mordad",
+    "Shahrivar",
+    "Mehr",
+    "Aban",
+    "Azar",
+    "Dey",
+    "Bahman",
+    "Esfand",
+];
+
+/// Returns whether `year` is a leap year and the day in March of its first day
+fn persian_year_info(year: i64) -> (bool, u32) {
+    let gregorian_year = year + 621;
+    let mut leap_persian = -14;
+    let mut prev_break = PERSIAN_BREAKS[0];
+    let mut jump = 0;
+    for &next_break in &PERSIAN_BREAKS[1..] {
+        jump = next_break - prev_break;
+        if year < next_break {
+            break;
+        }
+        leap_persian += jump / 33 * 8 + jump % 33 / 4;
+        prev_break = next_break;
+    }
+    let mut n = year - prev_break;
+    leap_persian += n / 33 * 8 + (n % 33 + 3) / 4;
+    if jump % 33 == 4 && jump - n == 4 {
+        leap_persian += 1;
+    }
+    let leap_gregorian = gregorian_year / 4 - (gregorian_year / 100 + 1) * 3 / 4 - 150;
+    let march = 20 + leap_persian - leap_gregorian;
+    if jump - n < 6 {
+        n = n - jump + (jump + 4) / 33 * 33;
+    }
+    let leap = ((n + 1) % 33 - 1) % 4 == 0;
+    (leap, march as u32)
+}
+
+/// Convert a Gregorian date (as `gregorian_year` and its fixed day number) to the Persian calendar
+pub fn persian_from_fixed(gregorian_year: i64, fixed: i64) -> Date {
+    let mut year = gregorian_year - 621;
+    let (_, march) = persian_year_info(year);
+    let mut k = fixed - fixed_from_gregorian(gregorian_year, 3, march);
+    if k >= 0 {
+        if k <= 185 {
+            return Date {
+                year,
+                month: 1 + (k / 31) as u32,
+                day: (k % 31) as u32 + 1,
+            };
+        }
+        k -= 186;
+    } else {
+        year -= 1;
+        k += 179;
+        if persian_year_info(year).0 {
+            k += 1;
+        }
+    }
+    Date {
+        year,
+        month: 7 + (k / 30) as u32,
+        day: (k % 30) as u32 + 1,
+    }
+}
+
+pub fn persian_month_name(date: Date) -> &'static str {
+    PERSIAN_MONTHS[date.month as usize - 1]
+}
+
+// Japanese eras
+
+/// Era names and their first days
+const JAPANESE_ERAS: [(&str, (i64, u32, u32)); 5] = [
+    ("Reiwa", (2019, 5, 1)),
+    ("Heisei", (1989, 1, 8)),
+    ("Showa", (1926, 12, 25)),
+    ("Taisho", (1912, 7, 30)),
+    ("Meiji", (1868, 10, 23)),
+];
+
+/// The era name and the year within the era, or `None` for dates before the Meiji era
+pub fn japanese_era(gregorian_year: i64, fixed: i64) -> Option<(&'static str, i64)> {
+    JAPANESE_ERAS
+        .iter()
+        .find(|(_, (y, m, d))| fixed_from_gregorian(*y, *m, *d) <= fixed)
+        .map(|(name, (start_year, _, _))| (*name, gregorian_year - start_year + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gregorian() {
+        assert_eq!(fixed_from_gregorian(1, 1, 1), 1);
+        assert_eq!(fixed_from_gregorian(1945, 11, 12), 710347);
+    }
+
+    #[test]
+    fn hebrew() {
+        let date = |year, month, day| Date { year, month, day };
+        // Rosh Hashanah 5784
+        let d = hebrew_from_fixed(fixed_from_gregorian(2023, 9, 16));
+        assert_eq!(d, date(5784, 7, 1));
+        // Passover 5784
+        let d = hebrew_from_fixed(fixed_from_gregorian(2024, 4, 23));
+        assert_eq!(d, date(5784, 1, 15));
+        let d = hebrew_from_fixed(fixed_from_gregorian(2024, 3, 24));
+        assert_eq!(d, date(5784, 13, 14));
+        assert_eq!(hebrew_month_name(d), "Adar II");
+        let d = hebrew_from_fixed(fixed_from_gregorian(2023, 3, 7));
+        assert_eq!(d, date(5783, 12, 14));
+        assert_eq!(hebrew_month_name(d), "Adar");
+
+        let hebrew = |y, m, d| hebrew_from_fixed(fixed_from_gregorian(y, m, d));
+        // From "Calendrical Calculations"
+        assert_eq!(hebrew(1945, 11, 12), date(5706, 9, 7));
+        assert_eq!(hebrew(1999, 9, 11), date(5760, 7, 1));
+        // Yom Kippur 5784, Hanukkah 5784, Purim 5785, Passover 5785 and Rosh Hashanah 5785/5786
+        assert_eq!(hebrew(2023, 9, 25), date(5784, 7, 10));
+        assert_eq!(hebrew(2023, 12, 8), date(5784, 9, 25));
+        assert_eq!(hebrew(2025, 3, 14), date(5785, 12, 14));
+        assert_eq!(hebrew(2025, 4, 13), date(5785, 1, 15));
+        assert_eq!(hebrew(2024, 10, 3), date(5785, 7, 1));
+        assert_eq!(hebrew(2025, 9, 23), date(5786, 7, 1));
+
+        // Every day between 1900 and 2100 converts back to the same day
+        for fixed in fixed_from_gregorian(1900, 1, 1)..fixed_from_gregorian(2100, 1, 1) {
+            assert_eq!(fixed_from_hebrew(hebrew_from_fixed(fixed)), fixed);
+        }
+    }
+
+    #[test]
+    fn persian() {
+        let date = |year, month, day| Date { year, month, day };
+        let persian = |y, m, d| persian_from_fixed(y, fixed_from_gregorian(y, m, d));
+        assert_eq!(persian(2024, 3, 20), date(1403, 1, 1));
+        assert_eq!(persian(2024, 3, 19), date(1402, 12, 29));
+        assert_eq!(persian(2023, 3, 21), date(1402, 1, 1));
+        assert_eq!(persian(2025, 3, 20), date(1403, 12, 30));
+        assert_eq!(persian(2023, 9, 23), date(1402, 7, 1));
+        assert_eq!(persian(2024, 1, 1), date(1402, 10, 11));
+        // Nowruz, the Islamic Revolution day and Yalda
+        assert_eq!(persian(1979, 3, 21), date(1358, 1, 1));
+        assert_eq!(persian(2020, 3, 20), date(1399, 1, 1));
+        assert_eq!(persian(2021, 3, 21), date(1400, 1, 1));
+        assert_eq!(persian(2025, 3, 21), date(1404, 1, 1));
+        assert_eq!(persian(1979, 2, 11), date(1357, 11, 22));
+        assert_eq!(persian(2023, 12, 22), date(1402, 10, 1));
+
+        // Every day between 1900 and 2100 follows the previous one
+        let mut prev = persian(1899, 12, 31);
+        for fixed in fixed_from_gregorian(1900, 1, 1)..fixed_from_gregorian(2100, 1, 1) {
+            let year = (1900..2100)
+                .rev()
+                .find(|&y| fixed_from_gregorian(y, 1, 1) <= fixed)
+                .unwrap();
+            let d = persian_from_fixed(year, fixed);
+            let month_len = match prev.month {
+                1..=6 => 31,
+                7..=11 => 30,
+                _ => 29 + persian_year_info(prev.year).0 as u32,
+            };
+            let expected = if prev.day < month_len {
+                date(prev.year, prev.month, prev.day + 1)
+            } else if prev.month < 12 {
+                date(prev.year, prev.month + 1, 1)
+            } else {
+                date(prev.year + 1, 1, 1)
+            };
+            assert_eq!(d, expected);
+            prev = d;
+        }
+    }
+
+    #[test]
+    fn japanese() {
+        let era = |y, m, d| japanese_era(y, fixed_from_gregorian(y, m, d));
+        assert_eq!(era(2019, 5, 1), Some(("Reiwa", 1)));
+        assert_eq!(era(2019, 4, 30), Some(("Heisei", 31)));
+        assert_eq!(era(2026, 10, 16), Some(("Reiwa", 8)));
+        assert_eq!(era(1989, 1, 7), Some(("Showa", 64)));
+        assert_eq!(era(1989, 1, 8), Some(("Heisei", 1)));
+        assert_eq!(era(1926, 12, 24), Some(("Taisho", 15)));
+        assert_eq!(era(1912, 7, 29), Some(("Meiji", 45)));
+        assert_eq!(era(1912, 7, 30), Some(("Taisho", 1)));
+        assert_eq!(era(1800, 1, 1), None);
+    }
+}
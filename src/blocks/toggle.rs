@@ -4,8 +4,10 @@
 //! (`command_on`). If these command exit with a non-zero status, the block will not be toggled and
 //! the block state will be changed to give a visual warning of the failure. You also need to
 //! specify a command to determine the state of the toggle (`command_state`). When the command outputs
-//! nothing, the toggle is disabled, otherwise enabled. By specifying the interval property you can
-//! let the command_state be executed continuously.
+//! nothing, the toggle is disabled, otherwise enabled. If `state_from_exit_code` is set, the toggle
+//! is enabled when the command exits successfully instead. By specifying the interval property you
+//! can let the command_state be executed continuously, so that the block reflects changes made
+//! outside of it. While `command_on` or `command_off` is running, the block is in the warning state.
 //!
 //! To run those commands, the shell form `$SHELL` environment variable is used. If such variable
 //! is not presented, `sh` is used.
@@ -18,9 +20,10 @@
 //! `command_on` | Shell command to enable the toggle | Yes | N/A
 //! `command_off` | Shell command to disable the toggle | Yes | N/A
 //! `command_state` | Shell command to determine the state. Empty output => No, otherwise => Yes. | **Required**
+//! `state_from_exit_code` | Determine the state from the exit code of `command_state` (zero => Yes, non-zero => No) instead of its output | `false`
 //! `icon_on` | Icon override for the toggle button while on | `"toggle_on"`
 //! `icon_off` | Icon override for the toggle button while off | `"toggle_off"`
-//! `interval` | Update interval in seconds. If not set, `command_state` will run only on click. | None
+//! `interval` | Update interval in seconds. If not set, `command_state` will run only on click. `state_interval` is accepted as an alias. | None
 //!
 //! Placeholder   | Value                                       | Type   | Unit
 //! --------------|---------------------------------------------|--------|-----
@@ -44,6 +47,19 @@
 //! interval = 5
 //! ```
 //!
+//! Toggle a systemd user service, checking its state every 10 seconds:
+//!
+//! ```toml
+//! [[block]]
+//! block = "toggle"
+//! format = " $icon syncthing "
+//! command_state = "systemctl --user is-active --quiet syncthing"
+//! state_from_exit_code = true
+//! command_on = "systemctl --user start syncthing"
+//! command_off = "systemctl --user stop syncthing"
+//! state_interval = 10
+//! ```
+//!
 //! # Icons Used
 //! - `toggle_off`
 //! - `toggle_on`
@@ -62,8 +78,10 @@ pub struct Config {
     icon_on: Option<String>,
     #[serde(default)]
     icon_off: Option<String>,
-    #[serde(default)]
+    #[serde(default, alias = "state_interval")]
     interval: Option<u64>,
+    #[serde(default)]
+    state_from_exit_code: bool,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
@@ -88,10 +106,14 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             .output()
             .await
             .error("Failed to run command_state")?;
-        let is_toggled = !std::str::from_utf8(&output.stdout)
-            .error("The output of command_state is invalid UTF-8")?
-            .trim()
-            .is_empty();
+        let is_toggled = if config.state_from_exit_code {
+            output.status.success()
+        } else {
+            !std::str::from_utf8(&output.stdout)
+                .error("The output of command_state is invalid UTF-8")?
+                .trim()
+                .is_empty()
+        };
 
         widget.set_values(map!(
             "icon" => Value::icon(
@@ -100,58 +122,38 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         ));
         api.set_widget(&widget).await?;
 
-        // TODO: try not to duplicate code
         loop {
-            match interval {
-                Some(interval) => {
-                    select! {
-                        _ = sleep(interval) => break,
-                        event = api.event() => match event {
-                            UpdateRequest => break,
-                            Action(a) if a == "toggle" => {
-                                let cmd = if is_toggled {
-                                    &config.command_off
-                                } else {
-                                    &config.command_on
-                                };
-                                let output = Command::new(&shell)
-                                    .args(["-c", cmd])
-                                    .output()
-                                    .await
-                                    .error("Failed to run command")?;
-                                if output.status.success() {
-                                    widget.state = State::Idle;
-                                    break;
-                                } else {
-                                    widget.state = State::Critical;
-                                }
-                            }
-                            _ => (),
-                        }
+            let event = match interval {
+                Some(interval) => select! {
+                    _ = sleep(interval) => break,
+                    event = api.event() => event,
+                },
+                None => api.event().await,
+            };
+            match event {
+                UpdateRequest => break,
+                Action(a) if a == "toggle" => {
+                    let cmd = if is_toggled {
+                        &config.command_off
+                    } else {
+                        &config.command_on
+                    };
+                    widget.state = State::Warning;
+                    api.set_widget(&widget).await?;
+                    let output = Command::new(&shell)
+                        .args(["-c", cmd])
+                        .output()
+                        .await
+                        .error("Failed to run command")?;
+                    if output.status.success() {
+                        widget.state = State::Idle;
+                        break;
+                    } else {
+                        widget.state = State::Critical;
+                        api.set_widget(&widget).await?;
                     }
                 }
-                None => match api.event().await {
-                    UpdateRequest => break,
-                    Action(a) if a == "toggle" => {
-                        let cmd = if is_toggled {
-                            &config.command_off
-                        } else {
-                            &config.command_on
-                        };
-                        let output = Command::new(&shell)
-                            .args(["-c", cmd])
-                            .output()
-                            .await
-                            .error("Failed to run command")?;
-                        if output.status.success() {
-                            widget.state = State::Idle;
-                            break;
-                        } else {
-                            widget.state = State::Critical;
-                        }
-                    }
-                    _ => (),
-                },
+                _ => (),
             }
         }
     }
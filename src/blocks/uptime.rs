@@ -1,7 +1,9 @@
 //! System's uptime
 //!
 //! This block displays system uptime in terms of two biggest units, so minutes and seconds, or
-//! hours and minutes or days and hours or weeks and days.
+//! hours and minutes or days and hours or weeks and days. With `precision = "full"`, all units down
+//! to minutes are shown instead. The individual units and the time of boot are also available as
+//! placeholders, for building the text yourself.
 //!
 //! # Configuration
 //!
@@ -9,11 +11,18 @@
 //! -----------|----------------------------|--------
 //! `format` | A string to customise the output of this block. See below for available placeholders | `" $icon $text "`
 //! `interval` | Update interval in seconds | `60`
+//! `precision` | How `text` is built: `"compact"` for the two biggest units, e.g. `"3d 4h"`, or `"full"` for every unit down to minutes, e.g. `"3d 4h 12m"` | `"compact"`
+//! `boot_time_format` | Format of the `boot_time` placeholder. See [chrono docs](https://docs.rs/chrono/0.3.0/chrono/format/strftime/index.html#specifiers) for all options. | `"%a %d/%m %R"`
 //!
-//! Placeholder   | Value                   | Type   | Unit
-//! --------------|-------------------------|--------|-----
-//! `icon`        | A static icon           | Icon   | -
-//! `text`        | Current uptime          | Text   | -
+//! Placeholder   | Value                                   | Type   | Unit
+//! --------------|-----------------------------------------|--------|-----
+//! `icon`        | A static icon                           | Icon   | -
+//! `text`        | Current uptime                          | Text   | -
+//! `days`        | Whole days of uptime                    | Number | -
+//! `hours`       | Hours of uptime, past the whole days    | Number | -
+//! `minutes`     | Minutes of uptime, past the whole hours | Number | -
+//! `seconds`     | Total uptime in seconds                 | Number | -
+//! `boot_time`   | When the system was booted              | Text   | -
 //!
 //! # Example
 //!
@@ -23,6 +32,13 @@
 //! interval = 3600 # update every hour
 //! ```
 //!
+//! ```toml
+//! [[block]]
+//! block = "uptime"
+//! format = " $icon $days days $hours hours, since $boot_time "
+//! boot_time_format = "%d/%m"
+//! ```
+//!
 //! # Used Icons
 //! - `uptime`
 //!
@@ -30,6 +46,7 @@
 //! - Add `time` or `dur` formatter to `src/formatting/formatter.rs`
 
 use super::prelude::*;
+use chrono::Local;
 use tokio::fs::read_to_string;
 
 #[derive(Deserialize, Debug, SmartDefault)]
@@ -38,6 +55,17 @@ pub struct Config {
     format: FormatConfig,
     #[default(60.into())]
     interval: Seconds,
+    precision: Precision,
+    #[default("%a %d/%m %R".into())]
+    boot_time_format: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, SmartDefault)]
+#[serde(rename_all = "lowercase")]
+enum Precision {
+    #[default]
+    Compact,
+    Full,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
@@ -47,12 +75,14 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         let uptime = read_to_string("/proc/uptime")
             .await
             .error("Failed to read /proc/uptime")?;
-        let mut seconds: u64 = uptime
+        let total: u64 = uptime
             .split('.')
             .next()
             .and_then(|u| u.parse().ok())
             .error("/proc/uptime has invalid content")?;
+        let boot_time = Local::now() - chrono::Duration::seconds(total as i64);
 
+        let mut seconds = total;
         let weeks = seconds / 604_800;
         seconds %= 604_800;
         let days = seconds / 86_400;
@@ -62,19 +92,35 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         let minutes = seconds / 60;
         seconds %= 60;
 
-        let text = if weeks > 0 {
-            format!("{weeks}w {days}d")
-        } else if days > 0 {
-            format!("{days}d {hours}h")
-        } else if hours > 0 {
-            format!("{hours}h {minutes}m")
-        } else {
-            format!("{minutes}m {seconds}s")
+        let text = match config.precision {
+            Precision::Compact if weeks > 0 => format!("{weeks}w {days}d"),
+            Precision::Compact if days > 0 => format!("{days}d {hours}h"),
+            Precision::Compact if hours > 0 => format!("{hours}h {minutes}m"),
+            Precision::Compact => format!("{minutes}m {seconds}s"),
+            Precision::Full => {
+                let units = [(weeks, "w"), (days, "d"), (hours, "h"), (minutes, "m")];
+                let text = units
+                    .iter()
+                    .skip_while(|(n, _)| *n == 0)
+                    .map(|(n, unit)| format!("{n}{unit}"))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if text.is_empty() {
+                    format!("{seconds}s")
+                } else {
+                    text
+                }
+            }
         };
 
         widget.set_values(map! {
           "icon" => Value::icon(api.get_icon("uptime")?),
-          "text" => Value::text(text)
+          "text" => Value::text(text),
+          "days" => Value::number(total / 86_400),
+          "hours" => Value::number(hours),
+          "minutes" => Value::number(minutes),
+          "seconds" => Value::number(total),
+          "boot_time" => Value::text(boot_time.format(&config.boot_time_format).to_string()),
         });
         api.set_widget(&widget).await?;
 
@@ -0,0 +1,198 @@
+//! Active VPN connections
+//!
+//! Shows the names of the active VPN connections. With NetworkManager, these can be regular (e.g.
+//! OpenVPN) or WireGuard connections. The Mullvad driver shows the relay in use and its country,
+//! as reported by `mullvad status --json`. Other providers can be queried with the `command`
+//! driver, whose `status_cmd` prints one line per active connection: its name, optionally
+//! followed by a tab and its country. It prints nothing while disconnected.
+//!
+//! If `connection` is set, the block is in the warning state while that connection is not active,
+//! and clicking the block brings it up or down. The Mullvad and `command` drivers have only one
+//! connection, which clicking the block connects or disconnects regardless of `connection`.
+//! With `required = true`, the block is in the critical state while disconnected.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `driver` | Which VPN manager to use. One of `"networkmanager"`, `"mullvad"` or `"command"`. | `"networkmanager"`
+//! `format` | A string to customise the output of this block while a VPN is active. See below for available placeholders. | `" $icon $name "`
+//! `disconnected_format` | A string to customise the output of this block while no VPN is active. See below for available placeholders. | `" $icon "`
+//! `connection` | The name of the connection which is expected to be active | `None`
+//! `separator` | The separator between the names of several active connections | `", "`
+//! `required` | Whether to set the state to critical while no VPN (or not `connection`) is active | `false`
+//! `interval` | Update interval in seconds for the `mullvad` and `command` drivers | `10`
+//! `status_cmd` | For the `command` driver: a shell command listing the active connections | `None`
+//! `connect_cmd` | For the `command` driver: a shell command connecting the VPN | `None`
+//! `disconnect_cmd` | For the `command` driver: a shell command disconnecting the VPN | `None`
+//!
+//! Placeholder | Value                                                | Type   | Unit
+//! ------------|------------------------------------------------------|--------|-----
+//! `icon`      | A static icon                                        | Icon   | -
+//! `name`      | The names of the active VPN connections              | Text   | -
+//! `count`     | The number of active VPN connections                 | Number | -
+//! `country`   | The country of the first active connection, if known | Text   | -
+//!
+//! Action   | Description                                 | Default button
+//! ---------|---------------------------------------------|---------------
+//! `toggle` | Bring the configured `connection` up or down | Left
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "vpn"
+//! connection = "work"
+//! disconnected_format = " $icon down "
+//! ```
+//!
+//! ```toml
+//! [[block]]
+//! block = "vpn"
+//! driver = "mullvad"
+//! format = " $icon $name ($country) "
+//! required = true
+//! ```
+//!
+//! ```toml
+//! [[block]]
+//! block = "vpn"
+//! driver = "command"
+//! status_cmd = "protonvpn-cli status | awk '/^Server:/ { print $2 }'"
+//! connect_cmd = "protonvpn-cli connect --fastest"
+//! disconnect_cmd = "protonvpn-cli disconnect"
+//! ```
+//!
+//! # Icons Used
+//! - `net_vpn`
+
+mod command;
+mod mullvad;
+mod nm;
+
+use super::prelude::*;
+use command::CommandDriver;
+use mullvad::Mullvad;
+use nm::NetworkManager;
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    driver: DriverType,
+    format: FormatConfig,
+    disconnected_format: FormatConfig,
+    connection: Option<String>,
+    #[default(", ".into())]
+    separator: String,
+    required: bool,
+    #[default(10.into())]
+    interval: Seconds,
+    status_cmd: Option<String>,
+    connect_cmd: Option<String>,
+    disconnect_cmd: Option<String>,
+}
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(rename_all = "lowercase")]
+enum DriverType {
+    #[default]
+    NetworkManager,
+    Mullvad,
+    Command,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[(MouseButton::Left, None, "toggle")])
+        .await?;
+
+    let format = config.format.with_default(" $icon $name ")?;
+    let disconnected_format = config.disconnected_format.with_default(" $icon ")?;
+    let mut widget = Widget::new();
+
+    let mut driver: Box<dyn Driver + Send + Sync> = match config.driver {
+        DriverType::NetworkManager => Box::new(NetworkManager::new().await?),
+        DriverType::Mullvad => Box::new(Mullvad::new(config.interval)),
+        DriverType::Command => Box::new(CommandDriver::new(
+            config
+                .status_cmd
+                .clone()
+                .error("`status_cmd` is required by the command driver")?,
+            config.connect_cmd.clone(),
+            config.disconnect_cmd.clone(),
+            config.interval,
+        )),
+    };
+
+    loop {
+        let active = api.recoverable(|| driver.active_connections()).await?;
+
+        let expected_active = match &config.connection {
+            Some(c) if driver.has_named_connections() => Some(active.iter().any(|a| &a.name == c)),
+            _ => None,
+        };
+        widget.state = match expected_active {
+            Some(false) if config.required => State::Critical,
+            Some(false) => State::Warning,
+            None if active.is_empty() && config.required => State::Critical,
+            _ if active.is_empty() => State::Idle,
+            _ => State::Good,
+        };
+        widget.set_format(if active.is_empty() {
+            disconnected_format.clone()
+        } else {
+            format.clone()
+        });
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("net_vpn")?),
+            "count" => Value::number(active.len()),
+            [if !active.is_empty()] "name" => Value::text(
+                active.iter().map(|a| a.name.as_str()).collect::<Vec<_>>().join(&config.separator),
+            ),
+            [if let Some(country) = active.first().and_then(|a| a.country.clone())] "country" => Value::text(country),
+        });
+        api.set_widget(&widget).await?;
+
+        loop {
+            select! {
+                res = driver.wait_for_change() => {
+                    res?;
+                    break;
+                }
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "toggle" => {
+                        if !driver.has_named_connections() {
+                            driver.toggle_connection(None).await?;
+                            break;
+                        } else if let Some(connection) = &config.connection {
+                            driver.toggle_connection(Some(connection)).await?;
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+struct Connection {
+    name: String,
+    country: Option<String>,
+}
+
+#[async_trait]
+trait Driver {
+    /// The active VPN connections
+    async fn active_connections(&self) -> Result<Vec<Connection>>;
+
+    /// Whether there are several connections which are told apart by name. If not, `name` is
+    /// always `None` in `toggle_connection`.
+    fn has_named_connections(&self) -> bool {
+        false
+    }
+
+    /// Bring the connection with the given name up if it is inactive, and down otherwise
+    async fn toggle_connection(&mut self, name: Option<&str>) -> Result<()>;
+
+    async fn wait_for_change(&mut self) -> Result<()>;
+}
@@ -0,0 +1,74 @@
+use super::{Connection, Driver};
+use crate::blocks::prelude::*;
+use crate::subprocess::spawn_shell_sync;
+use tokio::process::Command;
+
+/// Queries and controls a VPN with user-provided shell commands
+pub(super) struct CommandDriver {
+    status_cmd: String,
+    connect_cmd: Option<String>,
+    disconnect_cmd: Option<String>,
+    timer: tokio::time::Interval,
+}
+
+impl CommandDriver {
+    pub(super) fn new(
+        status_cmd: String,
+        connect_cmd: Option<String>,
+        disconnect_cmd: Option<String>,
+        interval: Seconds,
+    ) -> Self {
+        Self {
+            status_cmd,
+            connect_cmd,
+            disconnect_cmd,
+            timer: interval.timer(),
+        }
+    }
+}
+
+#[async_trait]
+impl Driver for CommandDriver {
+    async fn active_connections(&self) -> Result<Vec<Connection>> {
+        let output = Command::new("sh")
+            .args(["-c", &self.status_cmd])
+            .output()
+            .await
+            .error("Failed to run `status_cmd`")?;
+        let output =
+            String::from_utf8(output.stdout).error("`status_cmd` produced non-UTF8 output")?;
+        Ok(output
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let (name, country) = match line.split_once('\t') {
+                    Some((name, country)) => (name, Some(country.trim().into())),
+                    None => (line, None),
+                };
+                Connection {
+                    name: name.trim().into(),
+                    country,
+                }
+            })
+            .collect())
+    }
+
+    async fn toggle_connection(&mut self, _name: Option<&str>) -> Result<()> {
+        let cmd = if self.active_connections().await?.is_empty() {
+            &self.connect_cmd
+        } else {
+            &self.disconnect_cmd
+        };
+        if let Some(cmd) = cmd {
+            spawn_shell_sync(cmd)
+                .await
+                .or_error(|| format!("Failed to run '{cmd}'"))?;
+        }
+        Ok(())
+    }
+
+    async fn wait_for_change(&mut self) -> Result<()> {
+        self.timer.tick().await;
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use super::{Connection, Driver};
+use crate::blocks::prelude::*;
+use tokio::process::Command;
+
+pub(super) struct Mullvad {
+    timer: tokio::time::Interval,
+}
+
+impl Mullvad {
+    pub(super) fn new(interval: Seconds) -> Self {
+        Self {
+            timer: interval.timer(),
+        }
+    }
+
+    async fn status() -> Result<Status> {
+        let output = Command::new("mullvad")
+            .args(["status", "--json"])
+            .output()
+            .await
+            .error("Failed to run `mullvad status`")?;
+        serde_json::from_slice(&output.stdout).error("Failed to parse `mullvad status` output")
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Status {
+    state: String,
+    details: Option<Details>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Details {
+    location: Option<Location>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Location {
+    country: Option<String>,
+    hostname: Option<String>,
+}
+
+#[async_trait]
+impl Driver for Mullvad {
+    async fn active_connections(&self) -> Result<Vec<Connection>> {
+        let status = Self::status().await?;
+        if status.state != "connected" {
+            return Ok(Vec::new());
+        }
+        let location = status.details.and_then(|d| d.location);
+        let (name, country) = match location {
+            Some(l) => (l.hostname, l.country),
+            None => (None, None),
+        };
+        Ok(vec![Connection {
+            name: name.unwrap_or_else(|| "Mullvad".into()),
+            country,
+        }])
+    }
+
+    async fn toggle_connection(&mut self, _name: Option<&str>) -> Result<()> {
+        let connected = !self.active_connections().await?.is_empty();
+        let output = Command::new("mullvad")
+            .arg(if connected { "disconnect" } else { "connect" })
+            .output()
+            .await
+            .error("Failed to run mullvad")?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(Error::new(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ))
+        }
+    }
+
+    async fn wait_for_change(&mut self) -> Result<()> {
+        self.timer.tick().await;
+        Ok(())
+    }
+}
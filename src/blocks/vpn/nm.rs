@@ -0,0 +1,223 @@
+use super::{Connection, Driver};
+use crate::blocks::prelude::*;
+use zbus::dbus_proxy;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue};
+use zbus::{MatchRule, MessageStream};
+
+const NM_SERVICE: &str = "org.freedesktop.NetworkManager";
+const NM_PATH: &str = "/org/freedesktop/NetworkManager";
+
+pub(super) struct NetworkManager {
+    conn: zbus::Connection,
+    manager: ManagerProxy<'static>,
+    settings: SettingsProxy<'static>,
+    /// Changes of the list of active connections
+    properties_changed: MessageStream,
+    /// Changes of the state of active connections, e.g. from activating to activated
+    state_changed: MessageStream,
+}
+
+impl NetworkManager {
+    pub(super) async fn new() -> Result<Self> {
+        let conn = new_system_dbus_connection().await?;
+        let manager = ManagerProxy::builder(&conn)
+            .cache_properties(zbus::CacheProperties::No)
+            .build()
+            .await
+            .error("Failed to create ManagerProxy")?;
+        let settings = SettingsProxy::new(&conn)
+            .await
+            .error("Failed to create SettingsProxy")?;
+        let properties_changed = MessageStream::for_match_rule(
+            MatchRule::builder()
+                .msg_type(zbus::MessageType::Signal)
+                .sender(NM_SERVICE)
+                .and_then(|x| x.interface("org.freedesktop.DBus.Properties"))
+                .and_then(|x| x.member("PropertiesChanged"))
+                .and_then(|x| x.path(NM_PATH))
+                .unwrap()
+                .build(),
+            &conn,
+            None,
+        )
+        .await
+        .error("Failed to add match rule")?;
+        let state_changed = MessageStream::for_match_rule(
+            MatchRule::builder()
+                .msg_type(zbus::MessageType::Signal)
+                .sender(NM_SERVICE)
+                .and_then(|x| x.interface("org.freedesktop.NetworkManager.Connection.Active"))
+                .and_then(|x| x.member("StateChanged"))
+                .unwrap()
+                .build(),
+            &conn,
+            None,
+        )
+        .await
+        .error("Failed to add match rule")?;
+        Ok(Self {
+            conn,
+            manager,
+            settings,
+            properties_changed,
+            state_changed,
+        })
+    }
+
+    /// Active VPN and WireGuard connections, with their names
+    async fn active_vpns(&self) -> Result<Vec<(String, OwnedObjectPath)>> {
+        let paths = self
+            .manager
+            .active_connections()
+            .await
+            .error("Failed to get active connections")?;
+        let mut vpns = Vec::new();
+        for path in paths {
+            let active = ActiveConnectionProxy::builder(&self.conn)
+                .cache_properties(zbus::CacheProperties::No)
+                .path(path.clone())
+                .error("Invalid object path")?
+                .build()
+                .await
+                .error("Failed to create ActiveConnectionProxy")?;
+            // The connection may have gone away in the meantime
+            let (Ok(conn_type), Ok(id)) = (active.type_().await, active.id().await) else {
+                continue;
+            };
+            if conn_type == "vpn" || conn_type == "wireguard" {
+                vpns.push((id, path));
+            }
+        }
+        Ok(vpns)
+    }
+
+    /// Find the saved connection with the given name
+    async fn find_connection(&self, name: &str) -> Result<OwnedObjectPath> {
+        let paths = self
+            .settings
+            .list_connections()
+            .await
+            .error("Failed to list connections")?;
+        for path in paths {
+            let connection = SettingsConnectionProxy::builder(&self.conn)
+                .path(path.clone())
+                .error("Invalid object path")?
+                .build()
+                .await
+                .error("Failed to create SettingsConnectionProxy")?;
+            let settings = match connection.get_settings().await {
+                Ok(settings) => settings,
+                Err(_) => continue,
+            };
+            let id = settings
+                .get("connection")
+                .and_then(|c| c.get("id"))
+                .and_then(|id| id.downcast_ref::<str>());
+            if id == Some(name) {
+                return Ok(path);
+            }
+        }
+        Err(Error::new(format!("Connection '{name}' not found")))
+    }
+}
+
+#[async_trait]
+impl Driver for NetworkManager {
+    async fn active_connections(&self) -> Result<Vec<Connection>> {
+        Ok(self
+            .active_vpns()
+            .await?
+            .into_iter()
+            .map(|(name, _)| Connection {
+                name,
+                country: None,
+            })
+            .collect())
+    }
+
+    fn has_named_connections(&self) -> bool {
+        true
+    }
+
+    async fn toggle_connection(&mut self, name: Option<&str>) -> Result<()> {
+        let Some(name) = name else { return Ok(()) };
+        let active = self.active_vpns().await?;
+        if let Some((_, path)) = active.iter().find(|(id, _)| id == name) {
+            self.manager
+                .deactivate_connection(path)
+                .await
+                .error("Failed to deactivate connection")?;
+        } else {
+            let connection = self.find_connection(name).await?;
+            // Let NetworkManager choose the device
+            let any = ObjectPath::from_static_str_unchecked("/");
+            self.manager
+                .activate_connection(&connection, &any, &any)
+                .await
+                .error("Failed to activate connection")?;
+        }
+        Ok(())
+    }
+
+    async fn wait_for_change(&mut self) -> Result<()> {
+        select! {
+            msg = self.properties_changed.next() => {
+                msg.error("Stream ended unexpectedly")?
+                    .error("Failed to receive signal")?;
+            }
+            msg = self.state_changed.next() => {
+                msg.error("Stream ended unexpectedly")?
+                    .error("Failed to receive signal")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager"
+)]
+trait Manager {
+    fn activate_connection(
+        &self,
+        connection: &ObjectPath<'_>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> zbus::Result<OwnedObjectPath>;
+
+    fn deactivate_connection(&self, active_connection: &ObjectPath<'_>) -> zbus::Result<()>;
+
+    #[dbus_proxy(property)]
+    fn active_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager.Connection.Active",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait ActiveConnection {
+    #[dbus_proxy(property)]
+    fn id(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(property, name = "Type")]
+    fn type_(&self) -> zbus::Result<String>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager.Settings",
+    default_service = "org.freedesktop.NetworkManager",
+    default_path = "/org/freedesktop/NetworkManager/Settings"
+)]
+trait Settings {
+    fn list_connections(&self) -> zbus::Result<Vec<OwnedObjectPath>>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.NetworkManager.Settings.Connection",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+trait SettingsConnection {
+    fn get_settings(&self) -> zbus::Result<HashMap<String, HashMap<String, OwnedValue>>>;
+}
@@ -2,6 +2,9 @@
 //!
 //! [Watson](http://tailordev.github.io/Watson/) is a simple CLI time tracking application. This block will show the name of your current active project, tags and optionally recorded time. Clicking the widget will toggle the `show_time` variable dynamically.
 //!
+//! If `projects` is set, a project can be chosen with the mouse wheel and tracked with a right
+//! click, which stops the current frame first. A middle click stops the current frame.
+//!
 //! # Configuration
 //!
 //! Key | Values | Default
@@ -10,14 +13,21 @@
 //! `show_time` | Whether to show recorded time. | `false`
 //! `state_path` | Path to the Watson state file. Supports path expansions e.g. `~`. | `$XDG_CONFIG_HOME/watson/state`
 //! `interval` | Update interval, in seconds. | `60`
+//! `projects` | A list of projects which can be started from the block | `[]`
 //!
-//! Placeholder   | Value                   | Type   | Unit
-//! --------------|-------------------------|--------|-----
-//! `text`        | Current activity        | Text   | -
+//! Placeholder   | Value                                                   | Type   | Unit
+//! --------------|---------------------------------------------------------|--------|-----
+//! `text`        | Current activity                                        | Text   | -
+//! `elapsed`     | Time tracked in the current frame, as `H:MM`            | Text   | -
+//! `selected`    | The project chosen from `projects`                      | Text   | -
 //!
-//! Action             | Description                     | Default button
-//! -------------------|---------------------------------|---------------
-//! `toggle_show_time` | Toggle the value of `show_time` | Left
+//! Action             | Description                                           | Default button
+//! -------------------|-------------------------------------------------------|---------------
+//! `toggle_show_time` | Toggle the value of `show_time`                       | Left
+//! `start`            | Stop the current frame and start the selected project | Right
+//! `stop`             | Stop the current frame                                | Middle
+//! `next_project`     | Select the next project                               | Wheel Down
+//! `prev_project`     | Select the previous project                           | Wheel Up
 //!
 //! # Example
 //!
@@ -28,15 +38,24 @@
 //! state_path = "~/.config/watson/state"
 //! ```
 //!
-//! # TODO
-//! - Extend functionality: start / stop watson using this block
+//! Switch between projects from the bar:
+//!
+//! ```toml
+//! [[block]]
+//! block = "watson"
+//! format = " {$text $elapsed|idle} > $selected |"
+//! projects = ["work", "reading", "i3status-rust"]
+//! interval = 10
+//! ```
 
 use chrono::{offset::Local, DateTime};
 use dirs_next::config_dir;
 use inotify::{Inotify, WatchMask};
 use serde::de::Deserializer;
 use std::path::PathBuf;
+use std::process::Stdio;
 use tokio::fs::read_to_string;
+use tokio::process::Command;
 
 use super::prelude::*;
 
@@ -48,15 +67,23 @@ pub struct Config {
     #[default(60.into())]
     interval: Seconds,
     show_time: bool,
+    projects: Vec<String>,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
-    api.set_default_actions(&[(MouseButton::Left, None, "toggle_show_time")])
-        .await?;
+    api.set_default_actions(&[
+        (MouseButton::Left, None, "toggle_show_time"),
+        (MouseButton::Right, None, "start"),
+        (MouseButton::Middle, None, "stop"),
+        (MouseButton::WheelDown, None, "next_project"),
+        (MouseButton::WheelUp, None, "prev_project"),
+    ])
+    .await?;
 
     let mut widget = Widget::new().with_format(config.format.with_default(" $text |")?);
 
     let mut show_time = config.show_time;
+    let mut selected = 0;
 
     let (state_dir, state_file, state_path) = match config.state_path {
         Some(p) => {
@@ -91,32 +118,44 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
             .await
             .error("Failed to read state file")?;
         let state = serde_json::from_str(&state).error("Fnable to deserialize state")?;
-        match state {
-            state @ WatsonState::Active { .. } => {
+        let mut values = match state {
+            WatsonState::Active { start, .. } => {
+                let elapsed = Local::now() - start;
                 widget.state = State::Good;
-                widget.set_values(map!(
-                  "text" => Value::text(state.format(show_time, "started", format_delta_past))
-                ));
+                let values = map!(
+                  "text" => Value::text(state.format(show_time, "started", format_delta_past)),
+                  "elapsed" => Value::text(format!(
+                      "{}:{:02}",
+                      elapsed.num_hours(),
+                      elapsed.num_minutes() % 60
+                  )),
+                );
                 prev_state = Some(state);
+                values
             }
             WatsonState::Idle {} => {
-                if let Some(prev @ WatsonState::Active { .. }) = &prev_state {
+                let values = if let Some(prev @ WatsonState::Active { .. }) = &prev_state {
                     // The previous state was active, which means that we just now stopped the time
                     // tracking. This means that we could show some statistics.
-                    widget.state = State::Idle;
-                    widget.set_values(map!(
+                    map!(
                       "text" => Value::text(prev.format(true, "stopped", format_delta_after))
-                    ));
+                    )
                 } else {
                     // File is empty which means that there is currently no active time tracking,
                     // and the previous state wasn't time tracking neither so we reset the
                     // contents.
-                    widget.state = State::Idle;
-                    widget.set_values(Values::default());
-                }
+                    Values::default()
+                };
+                widget.state = State::Idle;
                 prev_state = Some(state);
+                values
             }
+        };
+
+        if let Some(project) = config.projects.get(selected) {
+            values.insert("selected".into(), Value::text(project.clone()));
         }
+        widget.set_values(values);
 
         api.set_widget(&widget).await?;
 
@@ -135,6 +174,30 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                         show_time = !show_time;
                         break;
                     }
+                    Action(a) if a == "next_project" || a == "prev_project" => {
+                        if !config.projects.is_empty() {
+                            let len = config.projects.len();
+                            selected = if a == "next_project" {
+                                (selected + 1) % len
+                            } else {
+                                (selected + len - 1) % len
+                            };
+                            break;
+                        }
+                    }
+                    Action(a) if a == "stop" => {
+                        if matches!(prev_state, Some(WatsonState::Active { .. })) {
+                            run_watson(&["stop"]).await?;
+                        }
+                    }
+                    Action(a) if a == "start" => {
+                        if let Some(project) = config.projects.get(selected) {
+                            if matches!(prev_state, Some(WatsonState::Active { .. })) {
+                                run_watson(&["stop"]).await?;
+                            }
+                            run_watson(&["start", project]).await?;
+                        }
+                    }
                     _ => (),
                 }
             }
@@ -142,6 +205,21 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
     }
 }
 
+async fn run_watson(args: &[&str]) -> Result<()> {
+    let status = Command::new("watson")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .error("Failed to run watson")?
+        .status;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::new(format!("'watson {}' failed", args.join(" "))))
+    }
+}
+
 fn format_delta_past(delta: &chrono::Duration) -> String {
     let spans = &[
         ("week", delta.num_weeks()),
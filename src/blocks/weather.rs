@@ -1,13 +1,17 @@
 //! Current weather
 //!
 //! This block displays local weather and temperature information. In order to use this block, you
-//! will need access to a supported weather API service. At the time of writing, OpenWeatherMap and
-//! met.no are supported.
+//! will need access to a supported weather API service. At the time of writing, OpenWeatherMap,
+//! met.no and Open-Meteo are supported.
 //!
 //! Configuring this block requires configuring a weather service, which may require API keys and
-//! other parameters.
+//! other parameters. met.no and Open-Meteo work without an API key.
 //!
-//! If using the `autolocate` feature, set the autolocate update interval such that you do not exceed ipapi.co's free daily limit of 1000 hits. Or use `autolocate_interval = "once"` to only run on initialization.
+//! With `autolocate`, the location is looked up using GeoClue2 if it is available, falling back to
+//! the ipapi.co IP location service otherwise. By default the location is only looked up again when
+//! NetworkManager reports a change of the network, or when the block is updated manually. The last
+//! known location is cached, so it survives restarts of the bar. If you set `autolocate_interval`,
+//! keep it long enough that you do not exceed ipapi.co's free daily limit of 1000 hits.
 //!
 //! # Configuration
 //!
@@ -16,8 +20,11 @@
 //! `service` | The configuration of a weather service (see below). | **Required**
 //! `format` | A string to customise the output of this block. See below for available placeholders. Text may need to be escaped, refer to [Escaping Text](#escaping-text). | `" $icon $weather $temp "`
 //! `interval` | Update interval, in seconds. | `600`
-//! `autolocate` | Gets your location using the ipapi.co IP location service (no API key required). If the API call fails then the block will fallback to `city_id` or `place`. | `false`
-//! `autolocate_interval` | Update interval for `autolocate` in seconds or "once" | `interval`
+//! `autolocate` | Gets your location using GeoClue2 or the ipapi.co IP location service (no API key required). If the API call fails then the block will fallback to `city_id` or `place`. | `false`
+//! `autolocate_interval` | Update interval for `autolocate` in seconds or "once". If not set, the location is updated when the network changes. | `None`
+//! `geoclue` | Whether to ask GeoClue2 for the location before falling back to ipapi.co. | `true`
+//! `format_alt` | If set, block will switch between `format` and `format_alt` on every click | `None`
+//! `forecast_days` | Number of upcoming days listed by the `forecast` placeholder. | `3`
 //!
 //! # OpenWeatherMap Options
 //!
@@ -33,6 +40,8 @@
 //! `units` | Either `"metric"` or `"imperial"`. | No | `"metric"`
 //! `lang` | Language code. See [here](https://openweathermap.org/current#multi). Currently only affects `weather_verbose` key. | No | `"en"`
 //!
+//! Setting `city_id = "auto"` is a shorthand for `autolocate = true`.
+//!
 //! One of `city_id`, `place` or `coordinates` is required. If more than one are supplied, `city_id` takes precedence over `place` which takes place over `coordinates`.
 //!
 //! The options `api_key`, `city_id`, `place` can be omitted from configuration,
@@ -50,6 +59,16 @@
 //!
 //! Met.no does not support location name.
 //!
+//! # Open-Meteo Options
+//!
+//! Key | Values | Required | Default
+//! ----|--------|----------|--------
+//! `name` | `openmeteo`. | Yes | None
+//! `coordinates` | GPS latitude longitude coordinates as a tuple, example: `["39.2362","9.3317"]` | Required if `autolocate = false` | None
+//! `units` | Either `"metric"` or `"imperial"`. | No | `"metric"`
+//!
+//! Open-Meteo does not support location name.
+//!
 //! # Available Format Keys
 //!
 //!  Key              | Value                                                              | Type   | Unit
@@ -64,6 +83,20 @@
 //! `wind`            | Wind speed                                                         | Number | -
 //! `wind_kmh`        | Wind speed. The wind speed in km/h                                 | Number | -
 //! `direction`       | Wind direction, e.g. "NE"                                          | Text   | -
+//! `temp_max_today`  | Highest temperature forecast for today                             | Number | degrees
+//! `temp_min_today`  | Lowest temperature forecast for today                              | Number | degrees
+//! `rain_next_3h`    | Precipitation expected in the next three hours                     | Number | mm
+//! `forecast_icon`   | Icon representing the weather expected in the next few hours       | Icon   | -
+//! `forecast`        | Icon, lowest and highest temperature of each of the upcoming days  | Text   | -
+//! `sunrise`         | Time of today's sunrise, e.g. "06:42" (not available with met.no)  | Text   | -
+//! `sunset`          | Time of today's sunset, e.g. "19:13" (not available with met.no)   | Text   | -
+//!
+//! The forecast is only requested from the service if one of the forecast placeholders is used in
+//! `format` or `format_alt`, which for OpenWeatherMap costs a second API call per update.
+//!
+//! Action          | Description                               | Default button
+//! ----------------|-------------------------------------------|---------------
+//! `toggle_format` | Toggles between `format` and `format_alt` | Left
 //!
 //! # Example
 //!
@@ -80,6 +113,29 @@
 //! units = "metric"
 //! ```
 //!
+//! Show the weather at the current location without an API key:
+//!
+//! ```toml
+//! [[block]]
+//! block = "weather"
+//! autolocate = true
+//! [block.service]
+//! name = "openmeteo"
+//! ```
+//!
+//! Show today's range and the rain to expect, and the next days on click:
+//!
+//! ```toml
+//! [[block]]
+//! block = "weather"
+//! format = " $icon $temp ($temp_min_today - $temp_max_today) $rain_next_3h mm "
+//! format_alt = " $forecast_icon $forecast "
+//! forecast_days = 4
+//! [block.service]
+//! name = "openmeteo"
+//! coordinates = ["52.52", "13.41"]
+//! ```
+//!
 //! # Used Icons
 //!
 //! - `weather_sun` (when weather is reported as "Clear")
@@ -89,12 +145,20 @@
 //! - `weather_snow` (when weather is reported as "Snow")
 //! - `weather_default` (in all other cases)
 
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Timelike};
+use serde::Serialize;
 use std::fmt;
 use std::sync::Arc;
+use zbus::MatchRule;
 
 use super::prelude::*;
+use crate::util;
+
+make_log_macro!(debug, "weather");
 
+mod geoclue;
 mod met_no;
+mod open_meteo;
 mod open_weather_map;
 
 const IP_API_URL: &str = "https://ipapi.co/json";
@@ -105,20 +169,46 @@ pub struct Config {
     interval: Seconds,
     #[serde(default)]
     format: FormatConfig,
+    format_alt: Option<FormatConfig>,
+    #[serde(default = "default_forecast_days")]
+    forecast_days: usize,
     service: WeatherService,
     #[serde(default)]
     autolocate: bool,
     autolocate_interval: Option<Seconds>,
+    #[serde(default = "default_geoclue")]
+    geoclue: bool,
 }
 
 fn default_interval() -> Seconds {
     Seconds::new(600)
 }
 
+fn default_forecast_days() -> usize {
+    3
+}
+
+fn default_geoclue() -> bool {
+    true
+}
+
+const FORECAST_KEYS: &[&str] = &[
+    "temp_max_today",
+    "temp_min_today",
+    "rain_next_3h",
+    "forecast_icon",
+    "forecast",
+    "sunrise",
+    "sunset",
+];
+
 #[async_trait]
 trait WeatherProvider {
-    async fn get_weather(&self, autolocated_location: Option<Coordinates>)
-        -> Result<WeatherResult>;
+    async fn get_weather(
+        &self,
+        autolocated_location: Option<Coordinates>,
+        need_forecast: bool,
+    ) -> Result<WeatherResult>;
 }
 
 #[derive(Deserialize, Debug)]
@@ -126,8 +216,10 @@ trait WeatherProvider {
 enum WeatherService {
     OpenWeatherMap(open_weather_map::Config),
     MetNo(met_no::Config),
+    OpenMeteo(open_meteo::Config),
 }
 
+#[derive(Clone, Copy)]
 enum WeatherIcon {
     Sun,
     Rain,
@@ -138,7 +230,7 @@ enum WeatherIcon {
 }
 
 impl WeatherIcon {
-    fn to_icon_str(&self) -> &str {
+    fn to_icon_str(self) -> &'static str {
         match self {
             Self::Sun => "weather_sun",
             Self::Rain => "weather_rain",
@@ -161,11 +253,77 @@ struct WeatherResult {
     wind_kmh: f64,
     wind_direction: String,
     icon: WeatherIcon,
+    sunrise: Option<DateTime<Local>>,
+    sunset: Option<DateTime<Local>>,
+    forecast: Option<Forecast>,
+}
+
+struct Forecast {
+    temp_max_today: f64,
+    temp_min_today: f64,
+    rain_next_3h: f64,
+    icon: WeatherIcon,
+    /// The upcoming days, not including today
+    days: Vec<ForecastDay>,
+}
+
+struct ForecastDay {
+    date: NaiveDate,
+    temp_min: f64,
+    temp_max: f64,
+    icon: WeatherIcon,
+}
+
+impl Forecast {
+    /// Build a forecast from time steps of `(time, temp_min, temp_max, icon)`, ordered by time.
+    ///
+    /// The icon of a day is the one of the step closest to noon.
+    fn from_steps(
+        steps: impl IntoIterator<Item = (DateTime<Local>, f64, f64, WeatherIcon)>,
+        rain_next_3h: f64,
+    ) -> Option<Self> {
+        let mut steps = steps.into_iter().peekable();
+        let icon = steps.peek()?.3;
+
+        let mut days: Vec<(ForecastDay, u32)> = Vec::new();
+        for (time, temp_min, temp_max, icon) in steps {
+            let from_noon = time.hour().abs_diff(12);
+            match days.last_mut() {
+                Some((day, noon)) if day.date == time.date_naive() => {
+                    day.temp_min = day.temp_min.min(temp_min);
+                    day.temp_max = day.temp_max.max(temp_max);
+                    if from_noon < *noon {
+                        day.icon = icon;
+                        *noon = from_noon;
+                    }
+                }
+                _ => days.push((
+                    ForecastDay {
+                        date: time.date_naive(),
+                        temp_min,
+                        temp_max,
+                        icon,
+                    },
+                    from_noon,
+                )),
+            }
+        }
+
+        let mut days = days.into_iter().map(|(day, _)| day);
+        let today = days.next()?;
+        Some(Self {
+            temp_max_today: today.temp_max,
+            temp_min_today: today.temp_min,
+            rain_next_3h,
+            icon,
+            days: days.collect(),
+        })
+    }
 }
 
 impl WeatherResult {
-    fn into_values(self, api: &CommonApi) -> Result<Values> {
-        Ok(map! {
+    fn into_values(self, api: &CommonApi, forecast_days: usize) -> Result<Values> {
+        let mut values = map! {
             "icon" => Value::icon(api.get_icon(self.icon.to_icon_str())?),
             "location" => Value::text(self.location),
             "temp" => Value::degrees(self.temp),
@@ -176,93 +334,129 @@ impl WeatherResult {
             "wind" => Value::number(self.wind),
             "wind_kmh" => Value::number(self.wind_kmh),
             "direction" => Value::text(self.wind_direction),
-        })
+            [if let Some(t) = self.sunrise] "sunrise" => Value::text(t.format("%H:%M").to_string()),
+            [if let Some(t) = self.sunset] "sunset" => Value::text(t.format("%H:%M").to_string()),
+        };
+        if let Some(forecast) = self.forecast {
+            let mut days = Vec::new();
+            for day in forecast.days.iter().take(forecast_days) {
+                days.push(format!(
+                    "{} {} {:.0}°/{:.0}°",
+                    api.get_icon(day.icon.to_icon_str())?,
+                    day.date.format("%a"),
+                    day.temp_min,
+                    day.temp_max
+                ));
+            }
+            values.extend(map! {
+                "temp_max_today" => Value::degrees(forecast.temp_max_today),
+                "temp_min_today" => Value::degrees(forecast.temp_min_today),
+                "rain_next_3h" => Value::number(forecast.rain_next_3h),
+                "forecast_icon" => Value::icon(api.get_icon(forecast.icon.to_icon_str())?),
+                "forecast" => Value::text(days.join(" ")),
+            });
+        }
+        Ok(values)
     }
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
-    let mut widget =
-        Widget::new().with_format(config.format.with_default(" $icon $weather $temp ")?);
+    api.set_default_actions(&[(MouseButton::Left, None, "toggle_format")])
+        .await?;
+
+    let mut format = config.format.with_default(" $icon $weather $temp ")?;
+    let mut format_alt = match config.format_alt {
+        Some(f) => Some(f.with_default("")?),
+        None => None,
+    };
+    let need_forecast = FORECAST_KEYS.iter().any(|key| {
+        format.contains_key(key) || format_alt.as_ref().map_or(false, |f| f.contains_key(key))
+    });
+    let mut widget = Widget::new().with_format(format.clone());
+
+    let autolocate = config.autolocate
+        || matches!(&config.service, WeatherService::OpenWeatherMap(c) if c.city_id_is_auto());
 
     let provider: Box<dyn WeatherProvider + Send + Sync> = match config.service {
         WeatherService::MetNo(config) => Box::new(met_no::Service::new(&mut api, config).await?),
         WeatherService::OpenWeatherMap(config) => Box::new(open_weather_map::Service::new(config)),
+        WeatherService::OpenMeteo(config) => Box::new(open_meteo::Service::new(config)),
     };
 
-    if config.autolocate {
-        // The default behavior is to mirror `interval`
-        let autolocate_interval = match config.autolocate_interval {
-            Some(s) => s,
-            None => config.interval,
-        };
-
-        if autolocate_interval == config.interval {
-            // In the case where `autolocate_interval` matches `interval` merge both actions.
-            loop {
-                let location = api.recoverable(find_ip_location).await?;
-                let data = api
-                    .recoverable(|| provider.get_weather(Some(location)))
-                    .await?;
-                widget.set_values(data.into_values(&api)?);
-                api.set_widget(&widget).await?;
-
-                select! {
-                    _ = sleep(config.interval.0) => (),
-                    _ = api.wait_for_update_request() => (),
-                }
-            }
-        } else {
-            // Two timers, one to rerender the block and the other to update the location.
-            let mut interval = config.interval.timer();
-            let mut autolocate_interval = autolocate_interval.timer();
-
-            // Initial pass
-            let mut location = api.recoverable(find_ip_location).await?;
-            let data = api
-                .recoverable(|| provider.get_weather(Some(location)))
-                .await?;
-            widget.set_values(data.into_values(&api)?);
-            api.set_widget(&widget).await?;
-
-            loop {
-                select! {
-                    biased; // if both timers `tick()` autolocate should run first
-                    _ = autolocate_interval.tick() => {
-                        location = api.recoverable(find_ip_location).await?;
-                    }
-                    _ = interval.tick() => {
-                        let data = api
-                            .recoverable(|| provider.get_weather(Some(location)))
-                            .await?;
-                        widget.set_values(data.into_values(&api)?);
-                        api.set_widget(&widget).await?;
-                    },
-                    // On update request autolocate and update the block.
-                    _ = api.wait_for_update_request() => {
-                        location = api.recoverable(find_ip_location).await?;
+    let mut interval = config.interval.timer();
+    let mut autolocate_interval = config
+        .autolocate_interval
+        .filter(|_| autolocate)
+        .map(Seconds::timer);
+    let mut network_changes = if autolocate && autolocate_interval.is_none() {
+        network_changes().await
+    } else {
+        Box::pin(futures::stream::pending())
+    };
 
-                        let data = api
-                            .recoverable(|| provider.get_weather(Some(location)))
-                            .await?;
-                        widget.set_values(data.into_values(&api)?);
-                        api.set_widget(&widget).await?;
+    let mut location = None;
+    let mut relocate = autolocate;
+    if autolocate {
+        if let Some(cached) = util::load_cache_file(LOCATION_CACHE_FILE) {
+            location = Some(cached);
+            relocate = false;
+        }
+    }
 
-                        // both intervals should be reset after a manual sync
-                        autolocate_interval.reset();
-                        interval.reset();
-                    },
-                }
+    loop {
+        if relocate {
+            let found = api.recoverable(|| find_location(config.geoclue)).await?;
+            if let Err(e) = util::store_cache_file(LOCATION_CACHE_FILE, &found) {
+                debug!("failed to save location: {e}");
             }
+            location = Some(found);
         }
-    } else {
-        loop {
-            let data = api.recoverable(|| provider.get_weather(None)).await?;
-            widget.set_values(data.into_values(&api)?);
-            api.set_widget(&widget).await?;
+        let data = api
+            .recoverable(|| provider.get_weather(location, need_forecast))
+            .await?;
+        widget.set_values(data.into_values(&api, config.forecast_days)?);
+        api.set_widget(&widget).await?;
 
+        loop {
             select! {
-                _ = sleep(config.interval.0) => (),
-                _ = api.wait_for_update_request() => ()
+                biased; // if both timers `tick()` autolocate should run first
+                Some(_) = util::tick_optional(&mut autolocate_interval) => {
+                    relocate = true;
+                    break;
+                }
+                _ = interval.tick() => {
+                    relocate = false;
+                    break;
+                }
+                _ = network_changes.next() => {
+                    // avoid too frequent updates
+                    let _ = tokio::time::timeout(Duration::from_secs(1), async {
+                        loop { let _ = network_changes.next().await; }
+                    }).await;
+                    relocate = true;
+                    interval.reset();
+                    break;
+                }
+                event = api.event() => match event {
+                    // On update request autolocate and update the block.
+                    UpdateRequest => {
+                        relocate = autolocate;
+                        // both intervals should be reset after a manual sync
+                        if let Some(timer) = &mut autolocate_interval {
+                            timer.reset();
+                        }
+                        interval.reset();
+                        break;
+                    }
+                    Action(a) if a == "toggle_format" => {
+                        if let Some(format_alt) = &mut format_alt {
+                            std::mem::swap(format_alt, &mut format);
+                            widget.set_format(format.clone());
+                            api.set_widget(&widget).await?;
+                        }
+                    }
+                    _ => (),
+                }
             }
         }
     }
@@ -276,13 +470,58 @@ enum UnitSystem {
     Imperial,
 }
 
-#[derive(Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, Copy)]
 struct Coordinates {
     latitude: f64,
     longitude: f64,
 }
 
-// TODO: might be good to allow for different geolocation services to be used, similar to how we have `service` for the weather API
+async fn find_location(geoclue: bool) -> Result<Coordinates> {
+    if geoclue {
+        match geoclue::find_location().await {
+            Ok(location) => return Ok(location),
+            Err(e) => {
+                debug!("GeoClue failed, falling back to ipapi.co: {e}");
+            }
+        }
+    }
+    find_ip_location().await
+}
+
+/// The last known location is shared by all weather blocks
+const LOCATION_CACHE_FILE: &str = "weather_location.json";
+
+/// A stream which yields whenever NetworkManager reports a change of the network. If
+/// NetworkManager is not available, the stream never yields.
+async fn network_changes() -> Pin<Box<dyn Stream<Item = ()>>> {
+    let stream = async {
+        let dbus = new_system_dbus_connection().await?;
+        let proxy = zbus::fdo::DBusProxy::new(&dbus)
+            .await
+            .error("Failed to create DBusProxy")?;
+        proxy
+            .add_match_rule(
+                MatchRule::builder()
+                    .msg_type(zbus::MessageType::Signal)
+                    .path("/org/freedesktop/NetworkManager")
+                    .and_then(|x| x.interface("org.freedesktop.DBus.Properties"))
+                    .and_then(|x| x.member("PropertiesChanged"))
+                    .unwrap()
+                    .build(),
+            )
+            .await
+            .error("Failed to add match")?;
+        Ok::<_, Error>(zbus::MessageStream::from(dbus))
+    };
+    match stream.await {
+        Ok(stream) => Box::pin(stream.map(|_| ())),
+        Err(e) => {
+            debug!("not watching for network changes: {e}");
+            Box::pin(futures::stream::pending())
+        }
+    }
+}
+
 async fn find_ip_location() -> Result<Coordinates> {
     #[derive(Deserialize)]
     struct ApiResponse {
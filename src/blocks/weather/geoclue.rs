@@ -0,0 +1,115 @@
+use super::*;
+use zbus::dbus_proxy;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath};
+
+const DESKTOP_ID: &str = "i3status-rust";
+/// GeoClue's `GCLUE_ACCURACY_LEVEL_CITY`
+const ACCURACY_LEVEL_CITY: u32 = 4;
+/// How long to wait for GeoClue to come up with a location
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Ask GeoClue2 for the current location
+pub(super) async fn find_location() -> Result<Coordinates> {
+    let conn = new_system_dbus_connection().await?;
+    let manager = ManagerProxy::new(&conn)
+        .await
+        .error("Failed to create GeoClue manager proxy")?;
+    let client_path = manager
+        .get_client()
+        .await
+        .error("Failed to get GeoClue client")?;
+    let client = ClientProxy::builder(&conn)
+        .path(client_path)
+        .error("Failed to set GeoClue client path")?
+        .build()
+        .await
+        .error("Failed to create GeoClue client proxy")?;
+
+    client
+        .set_desktop_id(DESKTOP_ID)
+        .await
+        .error("Failed to set GeoClue desktop id")?;
+    client
+        .set_requested_accuracy_level(ACCURACY_LEVEL_CITY)
+        .await
+        .error("Failed to set GeoClue accuracy level")?;
+
+    let mut updates = client
+        .receive_location_updated()
+        .await
+        .error("Failed to subscribe to GeoClue location updates")?;
+    client
+        .start()
+        .await
+        .error("Failed to start GeoClue client")?;
+    let update = tokio::time::timeout(TIMEOUT, updates.next()).await;
+    // The location is only needed once, so there is no point in keeping GeoClue busy
+    let _ = client.stop().await;
+    let update = update
+        .error("GeoClue did not provide a location in time")?
+        .error("GeoClue location updates ended")?;
+    let args = update
+        .args()
+        .error("Failed to parse GeoClue location update")?;
+
+    let location = LocationProxy::builder(&conn)
+        .path(args.new_location().to_owned())
+        .error("Failed to set GeoClue location path")?
+        .build()
+        .await
+        .error("Failed to create GeoClue location proxy")?;
+    Ok(Coordinates {
+        latitude: location
+            .latitude()
+            .await
+            .error("Failed to get latitude from GeoClue")?,
+        longitude: location
+            .longitude()
+            .await
+            .error("Failed to get longitude from GeoClue")?,
+    })
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.GeoClue2.Manager",
+    default_service = "org.freedesktop.GeoClue2",
+    default_path = "/org/freedesktop/GeoClue2/Manager"
+)]
+trait Manager {
+    fn get_client(&self) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.GeoClue2.Client",
+    default_service = "org.freedesktop.GeoClue2"
+)]
+trait Client {
+    fn start(&self) -> zbus::Result<()>;
+
+    fn stop(&self) -> zbus::Result<()>;
+
+    #[dbus_proxy(property)]
+    fn set_desktop_id(&self, id: &str) -> zbus::Result<()>;
+
+    #[dbus_proxy(property)]
+    fn set_requested_accuracy_level(&self, level: u32) -> zbus::Result<()>;
+
+    #[dbus_proxy(signal)]
+    fn location_updated(
+        &self,
+        old_location: ObjectPath<'_>,
+        new_location: ObjectPath<'_>,
+    ) -> zbus::Result<()>;
+}
+
+#[dbus_proxy(
+    interface = "org.freedesktop.GeoClue2.Location",
+    default_service = "org.freedesktop.GeoClue2"
+)]
+trait Location {
+    #[dbus_proxy(property)]
+    fn latitude(&self) -> zbus::Result<f64>;
+
+    #[dbus_proxy(property)]
+    fn longitude(&self) -> zbus::Result<f64>;
+}
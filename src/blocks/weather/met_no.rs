@@ -59,7 +59,7 @@ struct ForecastProperties {
 #[derive(Deserialize, Debug)]
 struct ForecastTimeStep {
     data: ForecastData,
-    // time: String,
+    time: String,
 }
 
 #[derive(Deserialize, Debug)]
@@ -67,7 +67,7 @@ struct ForecastData {
     instant: ForecastModelInstant,
     // next_12_hours: ForecastModelPeriod,
     next_1_hours: Option<ForecastModelPeriod>,
-    // next_6_hours: ForecastModelPeriod,
+    next_6_hours: Option<ForecastModelPeriod>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -78,6 +78,13 @@ struct ForecastModelInstant {
 #[derive(Deserialize, Debug)]
 struct ForecastModelPeriod {
     summary: ForecastSummary,
+    #[serde(default)]
+    details: ForecastPeriodDetails,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct ForecastPeriodDetails {
+    precipitation_amount: Option<f64>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -121,7 +128,11 @@ fn translate(legend: &LegendsStore, summary: &str, lang: &ApiLanguage) -> String
 
 #[async_trait]
 impl WeatherProvider for Service {
-    async fn get_weather(&self, location: Option<Coordinates>) -> Result<WeatherResult> {
+    async fn get_weather(
+        &self,
+        location: Option<Coordinates>,
+        need_forecast: bool,
+    ) -> Result<WeatherResult> {
         let Config {
             coordinates,
             altitude,
@@ -166,6 +177,41 @@ impl WeatherProvider for Service {
             .unwrap();
         let translated = translate(&self.legend, summary, lang);
 
+        let forecast = if need_forecast {
+            let timeseries = &data.properties.timeseries;
+            let rain_next_3h = timeseries
+                .iter()
+                .take(3)
+                .filter_map(|step| {
+                    step.data
+                        .next_1_hours
+                        .as_ref()?
+                        .details
+                        .precipitation_amount
+                })
+                .sum();
+            Forecast::from_steps(
+                timeseries.iter().filter_map(|step| {
+                    let time = DateTime::parse_from_rfc3339(&step.time).ok()?;
+                    let temp = step.data.instant.details.air_temperature?;
+                    let period = step
+                        .data
+                        .next_1_hours
+                        .as_ref()
+                        .or(step.data.next_6_hours.as_ref())?;
+                    Some((
+                        time.with_timezone(&Local),
+                        temp,
+                        temp,
+                        weather_to_icon(period.summary.symbol_code.split('_').next()?),
+                    ))
+                }),
+                rain_next_3h,
+            )
+        } else {
+            None
+        };
+
         let temp = instant.air_temperature.unwrap_or_default();
         let humidity = instant.relative_humidity.unwrap_or_default();
         let wind_speed = instant.wind_speed.unwrap_or_default();
@@ -181,6 +227,9 @@ impl WeatherProvider for Service {
             wind_kmh: instant.wind_speed.unwrap_or_default() * 3.6,
             wind_direction: convert_wind_direction(instant.wind_from_direction).into(),
             icon: weather_to_icon(summary),
+            sunrise: None,
+            sunset: None,
+            forecast,
         })
     }
 }
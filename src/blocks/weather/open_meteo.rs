@@ -0,0 +1,185 @@
+use super::*;
+
+const FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "name", rename_all = "lowercase")]
+pub(super) struct Config {
+    coordinates: Option<(String, String)>,
+    #[serde(default)]
+    units: UnitSystem,
+}
+
+pub(super) struct Service {
+    config: Config,
+}
+
+impl Service {
+    pub(super) fn new(config: Config) -> Self {
+        Self { config }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ApiResponse {
+    current: ApiCurrent,
+    daily: ApiDaily,
+    hourly: Option<ApiHourly>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ApiDaily {
+    time: Vec<i64>,
+    sunrise: Vec<i64>,
+    sunset: Vec<i64>,
+    temperature_2m_max: Option<Vec<f64>>,
+    temperature_2m_min: Option<Vec<f64>>,
+    weather_code: Option<Vec<u8>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ApiHourly {
+    precipitation: Vec<f64>,
+    weather_code: Vec<u8>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ApiCurrent {
+    temperature_2m: f64,
+    apparent_temperature: f64,
+    relative_humidity_2m: f64,
+    weather_code: u8,
+    wind_speed_10m: f64,
+    wind_direction_10m: Option<f64>,
+}
+
+#[async_trait]
+impl WeatherProvider for Service {
+    async fn get_weather(
+        &self,
+        location: Option<Coordinates>,
+        need_forecast: bool,
+    ) -> Result<WeatherResult> {
+        let (lat, lon) = location
+            .as_ref()
+            .map(|loc| (loc.latitude.to_string(), loc.longitude.to_string()))
+            .or_else(|| self.config.coordinates.clone())
+            .error("No location given")?;
+
+        let (temperature_unit, wind_speed_unit) = match self.config.units {
+            UnitSystem::Metric => ("celsius", "ms"),
+            UnitSystem::Imperial => ("fahrenheit", "mph"),
+        };
+
+        // Refer to https://open-meteo.com/en/docs
+        let querystr: HashMap<&str, String> = map! {
+            "latitude" => lat,
+            "longitude" => lon,
+            "current" => "temperature_2m,apparent_temperature,relative_humidity_2m,weather_code,wind_speed_10m,wind_direction_10m",
+            "daily" => if need_forecast {
+                "sunrise,sunset,temperature_2m_max,temperature_2m_min,weather_code"
+            } else {
+                "sunrise,sunset"
+            },
+            [if need_forecast] "hourly" => "precipitation,weather_code",
+            [if need_forecast] "forecast_hours" => "3",
+            "forecast_days" => "8",
+            "temperature_unit" => temperature_unit,
+            "wind_speed_unit" => wind_speed_unit,
+            "timeformat" => "unixtime",
+            "timezone" => "auto",
+        };
+
+        let data: ApiResponse = REQWEST_CLIENT
+            .get(FORECAST_URL)
+            .query(&querystr)
+            .send()
+            .await
+            .error("Forecast request failed")?
+            .json()
+            .await
+            .error("Forecast request failed")?;
+
+        let current = data.current;
+        let (weather, icon) = weather_code_to_weather(current.weather_code);
+
+        let daily = data.daily;
+        let timestamp = |t: &i64| Local.timestamp_opt(*t, 0).single();
+        let forecast = match (
+            data.hourly,
+            daily.temperature_2m_min,
+            daily.temperature_2m_max,
+            daily.weather_code,
+        ) {
+            (Some(hourly), Some(temp_min), Some(temp_max), Some(codes)) => {
+                let mut days = daily
+                    .time
+                    .iter()
+                    .zip(temp_min.into_iter().zip(temp_max))
+                    .zip(codes)
+                    .filter_map(|((time, (temp_min, temp_max)), code)| {
+                        Some(ForecastDay {
+                            date: timestamp(time)?.date_naive(),
+                            temp_min,
+                            temp_max,
+                            icon: weather_code_to_weather(code).1,
+                        })
+                    });
+                let today = days.next().error("Forecast is empty")?;
+                Some(Forecast {
+                    temp_max_today: today.temp_max,
+                    temp_min_today: today.temp_min,
+                    rain_next_3h: hourly.precipitation.iter().sum(),
+                    // WMO codes roughly grow with severity, so show the worst of the next hours
+                    icon: hourly
+                        .weather_code
+                        .iter()
+                        .max()
+                        .map_or(WeatherIcon::Default, |&code| {
+                            weather_code_to_weather(code).1
+                        }),
+                    days: days.collect(),
+                })
+            }
+            _ => None,
+        };
+
+        Ok(WeatherResult {
+            location: "Unknown".to_string(),
+            temp: current.temperature_2m,
+            apparent: current.apparent_temperature,
+            humidity: current.relative_humidity_2m,
+            weather: weather.into(),
+            weather_verbose: weather.into(),
+            wind: current.wind_speed_10m,
+            wind_kmh: current.wind_speed_10m
+                * match self.config.units {
+                    UnitSystem::Metric => 3.6,
+                    UnitSystem::Imperial => 3.6 * 0.447,
+                },
+            wind_direction: convert_wind_direction(current.wind_direction_10m).into(),
+            icon,
+            sunrise: daily.sunrise.first().and_then(timestamp),
+            sunset: daily.sunset.first().and_then(timestamp),
+            forecast,
+        })
+    }
+}
+
+/// Map a WMO weather interpretation code to a description and an icon
+fn weather_code_to_weather(code: u8) -> (&'static str, WeatherIcon) {
+    match code {
+        0 => ("Clear sky", WeatherIcon::Sun),
+        1 => ("Mainly clear", WeatherIcon::Sun),
+        2 => ("Partly cloudy", WeatherIcon::Clouds),
+        3 => ("Overcast", WeatherIcon::Clouds),
+        45 | 48 => ("Fog", WeatherIcon::Clouds),
+        51..=57 => ("Drizzle", WeatherIcon::Rain),
+        61..=67 => ("Rain", WeatherIcon::Rain),
+        71..=77 => ("Snow", WeatherIcon::Snow),
+        80..=82 => ("Rain showers", WeatherIcon::Rain),
+        85 | 86 => ("Snow showers", WeatherIcon::Snow),
+        95..=99 => ("Thunderstorm", WeatherIcon::Thunder),
+        _ => ("Unknown", WeatherIcon::Default),
+    }
+}
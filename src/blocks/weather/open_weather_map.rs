@@ -1,6 +1,7 @@
 use super::*;
 
 pub(super) const URL: &str = "https://api.openweathermap.org/data/2.5/weather";
+pub(super) const FORECAST_URL: &str = "https://api.openweathermap.org/data/2.5/forecast";
 pub(super) const API_KEY_ENV: &str = "OPENWEATHERMAP_API_KEY";
 pub(super) const CITY_ID_ENV: &str = "OPENWEATHERMAP_CITY_ID";
 pub(super) const PLACE_ENV: &str = "OPENWEATHERMAP_PLACE";
@@ -25,6 +26,13 @@ pub(super) struct Service {
     config: Config,
 }
 
+impl Config {
+    /// Whether `city_id = "auto"` asks for the location to be looked up
+    pub(super) fn city_id_is_auto(&self) -> bool {
+        self.city_id.as_deref() == Some("auto")
+    }
+}
+
 impl Service {
     pub(super) fn new(config: Config) -> Self {
         Self { config }
@@ -50,6 +58,39 @@ struct ApiResponse {
     main: ApiMain,
     wind: ApiWind,
     name: String,
+    sys: ApiSys,
+}
+
+#[derive(Deserialize, Debug)]
+struct ApiSys {
+    sunrise: i64,
+    sunset: i64,
+}
+
+#[derive(Deserialize, Debug)]
+struct ApiForecastResponse {
+    list: Vec<ApiForecastStep>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ApiForecastStep {
+    dt: i64,
+    main: ApiForecastMain,
+    weather: Vec<ApiWeather>,
+    rain: Option<ApiPrecipitation>,
+    snow: Option<ApiPrecipitation>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ApiForecastMain {
+    temp_min: f64,
+    temp_max: f64,
+}
+
+#[derive(Deserialize, Debug)]
+struct ApiPrecipitation {
+    #[serde(rename = "3h", default)]
+    three_hours: f64,
 }
 
 #[derive(Deserialize, Debug)]
@@ -73,7 +114,11 @@ struct ApiWeather {
 
 #[async_trait]
 impl WeatherProvider for Service {
-    async fn get_weather(&self, autolocated: Option<Coordinates>) -> Result<WeatherResult> {
+    async fn get_weather(
+        &self,
+        autolocated: Option<Coordinates>,
+        need_forecast: bool,
+    ) -> Result<WeatherResult> {
         let api_key = self.config.api_key.as_ref().or_error(|| {
             format!("missing key 'service.api_key' and environment variable {API_KEY_ENV}",)
         })?;
@@ -86,19 +131,26 @@ impl WeatherProvider for Service {
                     .as_ref()
                     .map(|(lat, lon)| format!("lat={lat}&lon={lon}"))
             })
-            .or_else(|| self.config.city_id.as_ref().map(|x| format!("id={x}")))
+            .or_else(|| {
+                self.config
+                    .city_id
+                    .as_ref()
+                    .filter(|_| !self.config.city_id_is_auto())
+                    .map(|x| format!("id={x}"))
+            })
             .or_else(|| self.config.place.as_ref().map(|x| format!("q={x}")))
             .error("no location was provided")?;
 
         // Refer to https://openweathermap.org/current
-        let url = format!(
-            "{URL}?{location_query}&appid={api_key}&units={units}&lang={lang}",
+        let query = format!(
+            "{location_query}&appid={api_key}&units={units}&lang={lang}",
             units = match self.config.units {
                 UnitSystem::Metric => "metric",
                 UnitSystem::Imperial => "imperial",
             },
             lang = self.config.lang,
         );
+        let url = format!("{URL}?{query}");
 
         let data: ApiResponse = REQWEST_CLIENT
             .get(url)
@@ -109,6 +161,35 @@ impl WeatherProvider for Service {
             .await
             .error("Forecast request failed")?;
 
+        let forecast = if need_forecast {
+            // Refer to https://openweathermap.org/forecast5
+            let forecast: ApiForecastResponse = REQWEST_CLIENT
+                .get(format!("{FORECAST_URL}?{query}"))
+                .send()
+                .await
+                .error("Forecast request failed")?
+                .json()
+                .await
+                .error("Forecast request failed")?;
+            let rain_next_3h = forecast.list.first().map_or(0.0, |step| {
+                step.rain.as_ref().map_or(0.0, |r| r.three_hours)
+                    + step.snow.as_ref().map_or(0.0, |s| s.three_hours)
+            });
+            Forecast::from_steps(
+                forecast.list.iter().filter_map(|step| {
+                    Some((
+                        Local.timestamp_opt(step.dt, 0).single()?,
+                        step.main.temp_min,
+                        step.main.temp_max,
+                        weather_to_icon(&step.weather.first()?.main),
+                    ))
+                }),
+                rain_next_3h,
+            )
+        } else {
+            None
+        };
+
         Ok(WeatherResult {
             location: data.name,
             temp: data.main.temp,
@@ -123,14 +204,21 @@ impl WeatherProvider for Service {
                     UnitSystem::Imperial => 3.6 * 0.447,
                 },
             wind_direction: convert_wind_direction(data.wind.deg).into(),
-            icon: match data.weather[0].main.as_str() {
-                "Clear" => WeatherIcon::Sun,
-                "Rain" | "Drizzle" => WeatherIcon::Rain,
-                "Clouds" | "Fog" | "Mist" => WeatherIcon::Clouds,
-                "Thunderstorm" => WeatherIcon::Thunder,
-                "Snow" => WeatherIcon::Snow,
-                _ => WeatherIcon::Default,
-            },
+            icon: weather_to_icon(&data.weather[0].main),
+            sunrise: Local.timestamp_opt(data.sys.sunrise, 0).single(),
+            sunset: Local.timestamp_opt(data.sys.sunset, 0).single(),
+            forecast,
         })
     }
 }
+
+fn weather_to_icon(weather: &str) -> WeatherIcon {
+    match weather {
+        "Clear" => WeatherIcon::Sun,
+        "Rain" | "Drizzle" => WeatherIcon::Rain,
+        "Clouds" | "Fog" | "Mist" => WeatherIcon::Clouds,
+        "Thunderstorm" => WeatherIcon::Thunder,
+        "Snow" => WeatherIcon::Snow,
+        _ => WeatherIcon::Default,
+    }
+}
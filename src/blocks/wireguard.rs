@@ -0,0 +1,188 @@
+//! WireGuard interface status
+//!
+//! Shows whether a WireGuard interface is up, the endpoint of its peer and how long ago the last
+//! handshake happened. The interface is looked up in `/sys/class/net` and the peer information
+//! comes from `wg show`, so this works without NetworkManager.
+//!
+//! `wg show` needs to run as root to see the peers, so `dump_cmd` usually has to go through a
+//! privileged helper, e.g. `sudo` with a `NOPASSWD` rule for that one command. If it fails, only
+//! the state of the interface is shown.
+//!
+//! # Configuration
+//!
+//! Key | Values | Default
+//! ----|--------|--------
+//! `interface` | The name of the WireGuard interface | **Required**
+//! `format` | A string to customise the output of this block while the interface is up. See below for available placeholders. | `" $icon $interface{ $handshake\|} "`
+//! `format_down` | A string to customise the output of this block while the interface is down. | `" $icon $interface "`
+//! `interval` | Update interval in seconds | `10`
+//! `stale_handshake` | Age of the last handshake in seconds, from which the state is set to warning | `180`
+//! `dump_cmd` | A shell command printing the output of `wg show <interface> dump`. `{interface}` is substituted with `interface`. | `"wg show {interface} dump"`
+//! `up_cmd` | A shell command bringing the interface up | `"wg-quick up {interface}"`
+//! `down_cmd` | A shell command bringing the interface down | `"wg-quick down {interface}"`
+//!
+//! Placeholder | Value                                                 | Type   | Unit
+//! ------------|-------------------------------------------------------|--------|-----
+//! `icon`      | A static icon                                         | Icon   | -
+//! `interface` | The name of the interface                             | Text   | -
+//! `endpoint`  | The endpoint of the peer, if known                    | Text   | -
+//! `handshake` | Time since the last handshake, e.g. `"42s"` or `"3m"` | Text   | -
+//!
+//! Action   | Description                    | Default button
+//! ---------|--------------------------------|---------------
+//! `toggle` | Bring the interface up or down | Left
+//!
+//! # Examples
+//!
+//! ```toml
+//! [[block]]
+//! block = "wireguard"
+//! interface = "wg0"
+//! format = " $icon $endpoint{ ($handshake)|} "
+//! dump_cmd = "sudo wg show {interface} dump"
+//! up_cmd = "pkexec wg-quick up {interface}"
+//! down_cmd = "pkexec wg-quick down {interface}"
+//! ```
+//!
+//! # Icons Used
+//! - `net_vpn`
+
+use super::prelude::*;
+use crate::subprocess::spawn_shell_sync;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::process::Command;
+
+#[derive(Deserialize, Debug, SmartDefault)]
+#[serde(default)]
+pub struct Config {
+    interface: String,
+    format: FormatConfig,
+    format_down: FormatConfig,
+    #[default(10.into())]
+    interval: Seconds,
+    #[default(180)]
+    stale_handshake: u64,
+    #[default("wg show {interface} dump".into())]
+    dump_cmd: String,
+    #[default("wg-quick up {interface}".into())]
+    up_cmd: String,
+    #[default("wg-quick down {interface}".into())]
+    down_cmd: String,
+}
+
+pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
+    api.set_default_actions(&[(MouseButton::Left, None, "toggle")])
+        .await?;
+
+    if config.interface.is_empty() {
+        return Err(Error::new("`interface` must be set"));
+    }
+
+    let format = config
+        .format
+        .with_default(" $icon $interface{ $handshake|} ")?;
+    let format_down = config.format_down.with_default(" $icon $interface ")?;
+    let mut widget = Widget::new();
+
+    let sys_path = format!("/sys/class/net/{}", config.interface);
+    let mut timer = config.interval.timer();
+
+    loop {
+        let up = Path::new(&sys_path).exists();
+        let peer = if up {
+            latest_peer(&config.dump_cmd.replace("{interface}", &config.interface)).await
+        } else {
+            None
+        };
+        let handshake_age = peer
+            .as_ref()
+            .and_then(|p| p.latest_handshake)
+            .map(|t| now().saturating_sub(t));
+
+        widget.state = match (up, handshake_age) {
+            (false, _) => State::Idle,
+            (true, Some(age)) if age < config.stale_handshake => State::Good,
+            (true, _) => State::Warning,
+        };
+        widget.set_format(if up {
+            format.clone()
+        } else {
+            format_down.clone()
+        });
+        widget.set_values(map! {
+            "icon" => Value::icon(api.get_icon("net_vpn")?),
+            "interface" => Value::text(config.interface.clone()),
+            [if let Some(e) = peer.and_then(|p| p.endpoint)] "endpoint" => Value::text(e),
+            [if let Some(age) = handshake_age] "handshake" => Value::text(format_age(age)),
+        });
+        api.set_widget(&widget).await?;
+
+        loop {
+            select! {
+                _ = timer.tick() => break,
+                event = api.event() => match event {
+                    UpdateRequest => break,
+                    Action(a) if a == "toggle" => {
+                        let cmd = if up { &config.down_cmd } else { &config.up_cmd };
+                        spawn_shell_sync(&cmd.replace("{interface}", &config.interface))
+                            .await
+                            .error("Failed to run wg-quick")?;
+                        break;
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+}
+
+struct Peer {
+    endpoint: Option<String>,
+    /// Unix timestamp of the last handshake
+    latest_handshake: Option<u64>,
+}
+
+/// The peer with the most recent handshake, parsed from the output of `wg show <interface> dump`
+async fn latest_peer(dump_cmd: &str) -> Option<Peer> {
+    let output = Command::new("sh")
+        .args(["-c", dump_cmd])
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let output = String::from_utf8(output.stdout).ok()?;
+
+    // The first line describes the interface itself, every other line is a peer:
+    // public-key preshared-key endpoint allowed-ips latest-handshake transfer-rx transfer-tx persistent-keepalive
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let endpoint = *fields.get(2)?;
+            let latest_handshake: u64 = fields.get(4)?.parse().ok()?;
+            Some(Peer {
+                endpoint: (endpoint != "(none)").then(|| endpoint.into()),
+                latest_handshake: (latest_handshake != 0).then_some(latest_handshake),
+            })
+        })
+        .max_by_key(|peer| peer.latest_handshake)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{seconds}s"),
+        60..=3_599 => format!("{}m", seconds / 60),
+        3_600..=86_399 => format!("{}h", seconds / 3_600),
+        _ => format!("{}d", seconds / 86_400),
+    }
+}
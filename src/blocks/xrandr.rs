@@ -2,6 +2,10 @@
 //!
 //! X11 screen information (name, brightness, resolution). With a click you can toggle through your active screens and with wheel up and down you can adjust the selected screens brightness. Regarding brightness control, xrandr changes the brightness of the display using gamma rather than changing the brightness in hardware, so if that is not desirable then consider using the `backlight` block instead.
 //!
+//! Display layouts can be switched from the block too: either a list of `profiles`, each being a
+//! shell command which sets up the outputs (usually a call to `xrandr`), or the profiles saved with
+//! [autorandr](https://github.com/phillipberndt/autorandr) if `autorandr = true`.
+//!
 //! NOTE: Some users report issues (e.g. [here](https://github.com/greshake/i3status-rust/issues/274) and [here](https://github.com/greshake/i3status-rust/issues/668) when using this block. The cause is currently unknown, however setting a higher update interval may help.
 //!
 //! # Configuration
@@ -11,21 +15,27 @@
 //! `format` | A string to customise the output of this block. See below for available placeholders. | `" $icon $display $brightness_icon $brightness "`
 //! `step_width` | The steps brightness is in/decreased for the selected screen (When greater than 50 it gets limited to 50). | `5`
 //! `interval` | Update interval in seconds. | `5`
+//! `profiles` | A list of layouts, each with a `name` and a shell `command` applying it. | `[]`
+//! `autorandr` | Use the profiles saved with autorandr instead of `profiles`. | `false`
 //!
-//! Placeholder       | Value                        | Type   | Unit
-//! ------------------|------------------------------|--------|---------------
-//! `icon`            | A static icon                | Icon   | -
-//! `display`         | The name of a monitor        | Text   | -
-//! `brightness`      | The brightness of a monitor  | Number | %
-//! `brightness_icon` | A static icon                | Icon   | -
-//! `resolution`      | The resolution of a monitor  | Text   | -
-//! `res_icon`        | A static icon                | Icon   | -
+//! Placeholder       | Value                         | Type   | Unit
+//! ------------------|-------------------------------|--------|-----
+//! `icon`            | A static icon                 | Icon   | -
+//! `display`         | The name of a monitor         | Text   | -
+//! `brightness`      | The brightness of a monitor   | Number | %
+//! `brightness_icon` | A static icon                 | Icon   | -
+//! `resolution`      | The resolution of a monitor   | Text   | -
+//! `res_icon`        | A static icon                 | Icon   | -
+//! `refresh_rate`    | The refresh rate of a monitor | Number | Hz
+//! `profile`         | The active profile, if known  | Text   | -
 //!
 //! Action            | Default button
 //! ------------------|---------------
 //! `cycle_outputs`   | Left
 //! `brightness_up`   | Wheel Up
 //! `brightness_down` | Wheel Down
+//! `next_profile`    | Right
+//! `prev_profile`    | -
 //!
 //! # Example
 //!
@@ -35,13 +45,27 @@
 //! format = " $icon $brightness $resolution "
 //! ```
 //!
+//! Switch between a laptop screen and an external monitor with a right click:
+//!
+//! ```toml
+//! [[block]]
+//! block = "xrandr"
+//! format = " $icon {$profile|$display} $resolution@$refresh_rate.eng(w:3) "
+//! [[block.profiles]]
+//! name = "laptop"
+//! command = "xrandr --output eDP-1 --auto --output HDMI-1 --off"
+//! [[block.profiles]]
+//! name = "docked"
+//! command = "xrandr --output eDP-1 --off --output HDMI-1 --auto"
+//! ```
+//!
 //! # Used Icons
 //! - `xrandr`
 //! - `backlight_full`
 //! - `resolution`
 
 use super::prelude::*;
-use crate::subprocess::spawn_shell;
+use crate::subprocess::{spawn_shell, spawn_shell_sync};
 use regex::RegexSet;
 use tokio::process::Command;
 
@@ -53,6 +77,15 @@ pub struct Config {
     format: FormatConfig,
     #[default(5)]
     step_width: u32,
+    profiles: Vec<Profile>,
+    autorandr: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct Profile {
+    name: String,
+    command: String,
 }
 
 pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
@@ -60,6 +93,7 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
         (MouseButton::Left, None, "cycle_outputs"),
         (MouseButton::WheelUp, None, "brightness_up"),
         (MouseButton::WheelDown, None, "brightness_down"),
+        (MouseButton::Right, None, "next_profile"),
     ])
     .await?;
 
@@ -71,12 +105,24 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
 
     let mut cur_indx = 0;
     let mut timer = config.interval.timer();
+    // The last applied entry of `profiles`, we can't tell which one is active otherwise
+    let mut applied_profile: Option<usize> = None;
 
     loop {
         let mut monitors = get_monitors().await?;
         if cur_indx > monitors.len() {
             cur_indx = 0;
         }
+        let refresh_rates = get_refresh_rates().await?;
+
+        let (profiles, active_profile) = if config.autorandr {
+            get_autorandr_profiles().await?
+        } else {
+            (
+                config.profiles.iter().map(|p| p.name.clone()).collect(),
+                applied_profile,
+            )
+        };
 
         loop {
             widget.set_values(if let Some(mon) = monitors.get(cur_indx) {
@@ -88,6 +134,8 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                     "resolution" => Value::text(mon.resolution.clone()),
                     "icon" => Value::icon(api.get_icon("xrandr")?),
                     "res_icon" => Value::icon(api.get_icon("resolution")?),
+                    [if let Some(rate) = refresh_rates.get(&mon.name)] "refresh_rate" => Value::hertz(*rate),
+                    [if let Some(i) = active_profile] "profile" => Value::text(profiles[i].clone()),
                 }
             } else {
                 default()
@@ -113,6 +161,30 @@ pub async fn run(config: Config, mut api: CommonApi) -> Result<()> {
                             monitor.set_brightness(bright);
                         }
                     }
+                    Action(a) if a == "next_profile" || a == "prev_profile" => {
+                        if !profiles.is_empty() {
+                            let len = profiles.len();
+                            let next = match (active_profile, a == "next_profile") {
+                                (Some(i), true) => (i + 1) % len,
+                                (Some(i), false) => (i + len - 1) % len,
+                                (None, true) => 0,
+                                (None, false) => len - 1,
+                            };
+                            if config.autorandr {
+                                Command::new("autorandr")
+                                    .args(["--load", &profiles[next]])
+                                    .output()
+                                    .await
+                                    .error("Failed to run autorandr")?;
+                            } else {
+                                spawn_shell_sync(&config.profiles[next].command)
+                                    .await
+                                    .error("Failed to apply profile")?;
+                                applied_profile = Some(next);
+                            }
+                            break;
+                        }
+                    }
                     _ => (),
                 }
             }
@@ -137,6 +209,60 @@ impl Monitor {
     }
 }
 
+/// The profiles known to autorandr and the index of the active one
+async fn get_autorandr_profiles() -> Result<(Vec<String>, Option<usize>)> {
+    let list = Command::new("autorandr")
+        .arg("--list")
+        .output()
+        .await
+        .error("Failed to run autorandr")?
+        .stdout;
+    let list = String::from_utf8(list).error("autorandr produced non-UTF8 output")?;
+    let profiles: Vec<String> = list.lines().map(Into::into).collect();
+
+    let current = Command::new("autorandr")
+        .arg("--current")
+        .output()
+        .await
+        .error("Failed to run autorandr")?
+        .stdout;
+    let current = String::from_utf8(current).error("autorandr produced non-UTF8 output")?;
+    let active = current
+        .lines()
+        .next()
+        .and_then(|name| profiles.iter().position(|p| p == name));
+
+    Ok((profiles, active))
+}
+
+/// The current refresh rate of each output, which `xrandr` marks with a `*`
+async fn get_refresh_rates() -> Result<HashMap<String, f64>> {
+    let output = Command::new("xrandr")
+        .arg("--current")
+        .output()
+        .await
+        .error("Failed to collect xrandr outputs")?
+        .stdout;
+    let output = String::from_utf8(output).error("xrandr produced non-UTF8 output")?;
+
+    let mut rates = HashMap::new();
+    let mut output_name = None;
+    for line in output.lines() {
+        if !line.starts_with(' ') {
+            output_name = line.split_ascii_whitespace().next();
+        } else if let Some(name) = output_name {
+            if let Some(rate) = line
+                .split_ascii_whitespace()
+                .find(|token| token.contains('*'))
+                .and_then(|token| token.trim_end_matches(['*', '+']).parse().ok())
+            {
+                rates.insert(name.into(), rate);
+            }
+        }
+    }
+    Ok(rates)
+}
+
 macro_rules! unwrap_or_break {
     ($e: expr) => {
         match $e {
@@ -5,7 +5,7 @@ use serde::Deserialize;
 
 use crate::errors::{Result, ResultExt};
 use crate::protocol::i3bar_event::I3BarEvent;
-use crate::subprocess::{spawn_shell, spawn_shell_sync};
+use crate::subprocess::{spawn_shell_sync_with_env, spawn_shell_with_env};
 
 /// Can be one of `left`, `middle`, `right`, `wheel_up`, `wheel_down`, `forward`, `back`, or
 /// `double_left`.
@@ -26,6 +26,23 @@ pub enum MouseButton {
     DoubleLeft,
 }
 
+impl MouseButton {
+    /// The X11 button number, as used by i3bar and i3blocks
+    pub fn number(self) -> u8 {
+        use MouseButton::*;
+        match self {
+            Left | DoubleLeft => 1,
+            Middle => 2,
+            Right => 3,
+            WheelUp => 4,
+            WheelDown => 5,
+            Back => 8,
+            Forward => 9,
+            Unknown => 0,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PostActions {
     pub action: Option<String>,
@@ -36,7 +53,11 @@ pub struct PostActions {
 pub struct ClickHandler(Vec<ClickConfigEntry>);
 
 impl ClickHandler {
-    pub async fn handle(&self, event: &I3BarEvent) -> Result<PostActions> {
+    /// Run the command and return the action bound to this click.
+    ///
+    /// The command gets the click details and the current text of the block (`full_text`) in
+    /// the same environment variables as i3blocks sets.
+    pub async fn handle(&self, event: &I3BarEvent, full_text: &str) -> Result<PostActions> {
         Ok(
             match self
                 .0
@@ -45,10 +66,22 @@ impl ClickHandler {
             {
                 Some(entry) => {
                     if let Some(cmd) = &entry.cmd {
+                        let env = [
+                            ("BLOCK_BUTTON", event.button.number().to_string()),
+                            ("BLOCK_INSTANCE", event.instance.clone().unwrap_or_default()),
+                            ("BLOCK_X", event.x.to_string()),
+                            ("BLOCK_Y", event.y.to_string()),
+                            ("BLOCK_RELATIVE_X", event.relative_x.to_string()),
+                            ("BLOCK_RELATIVE_Y", event.relative_y.to_string()),
+                            ("BLOCK_WIDTH", event.width.to_string()),
+                            ("BLOCK_HEIGHT", event.height.to_string()),
+                            ("BLOCK_MODIFIERS", event.modifiers.join(",")),
+                            ("BLOCK_FULL_TEXT", full_text.to_owned()),
+                        ];
                         if entry.sync {
-                            spawn_shell_sync(cmd).await
+                            spawn_shell_sync_with_env(cmd, &env).await
                         } else {
-                            spawn_shell(cmd)
+                            spawn_shell_with_env(cmd, &env)
                         }
                         .or_error(|| {
                             format!("'{:?}' button handler: Failed to run '{cmd}", event.button)
@@ -1,13 +1,12 @@
-use serde::{Deserialize, Deserializer};
+use serde::Deserialize;
 use smart_default::SmartDefault;
 use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::blocks::BlockConfig;
 use crate::click::ClickHandler;
-use crate::errors::*;
 use crate::formatting::config::Config as FormatConfig;
-use crate::icons::Icons;
+use crate::icons::{Icons, IconsConfig};
 use crate::themes::{Theme, ThemeOverrides, ThemeUserConfig};
 
 #[derive(Deserialize, Debug, SmartDefault)]
@@ -16,6 +15,11 @@ pub struct Config {
     #[serde(flatten)]
     pub shared: SharedConfig,
 
+    /// The theme configuration. It is kept around to be able to rebuild the theme at runtime.
+    pub theme: Option<ThemeUserConfig>,
+    /// The icons configuration. It is kept around to be able to reload the icons at runtime.
+    pub icons: IconsConfig,
+
     /// Set to `true` to invert mouse wheel direction
     pub invert_scrolling: bool,
 
@@ -27,6 +31,14 @@ pub struct Config {
     #[default(" $full_error_message ".parse().unwrap())]
     pub error_fullscreen_format: FormatConfig,
 
+    /// A shell command to run when a block enters the error state. `{block}` and `{error}` are
+    /// substituted with the shell-quoted block's name and error message, which are also passed in
+    /// the `I3RS_BLOCK` and `I3RS_ERROR` environment variables.
+    pub on_error: Option<String>,
+    /// The minimum delay (s) between two `on_error` invocations for the same block
+    #[default(60)]
+    pub on_error_interval: u64,
+
     #[serde(rename = "block")]
     pub blocks: Vec<BlockConfigEntry>,
 }
@@ -34,8 +46,9 @@ pub struct Config {
 #[derive(Deserialize, Debug, Clone, SmartDefault)]
 #[serde(default)]
 pub struct SharedConfig {
-    #[serde(deserialize_with = "deserialize_theme_config")]
+    #[serde(skip)]
     pub theme: Arc<Theme>,
+    #[serde(skip)]
     pub icons: Arc<Icons>,
     #[default(Arc::new("{icon}".into()))]
     pub icons_format: Arc<String>,
@@ -68,6 +81,7 @@ pub struct CommonBlockConfig {
     pub theme_overrides: Option<ThemeOverrides>,
     pub icons_overrides: Option<HashMap<String, String>>,
     pub merge_with_next: bool,
+    pub group: Option<String>,
 
     #[default(5)]
     pub error_interval: u64,
@@ -76,12 +90,3 @@ pub struct CommonBlockConfig {
 
     pub if_command: Option<String>,
 }
-
-fn deserialize_theme_config<'de, D>(deserializer: D) -> Result<Arc<Theme>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let theme_config = ThemeUserConfig::deserialize(deserializer)?;
-    let theme = Theme::try_from(theme_config).serde_error()?;
-    Ok(Arc::new(theme))
-}
@@ -59,6 +59,17 @@
 //!
 //! No arguments.
 //!
+//! # Value-based colors
+//!
+//! Any formatter of a number also accepts `color_from` and `color_to`. The text is then colored
+//! with a gradient between these two colors, where `0` maps to `color_from` and `100` maps to
+//! `color_to`, so percentages such as CPU utilization can smoothly shift from green to red. The
+//! colors are either theme colors (e.g. `good_bg`, see
+//! [themes](https://github.com/greshake/i3status-rust/blob/master/doc/themes.md#available-theme-overrides))
+//! or hex codes without the leading `#`. For example:
+//! `$utilization.eng(w:3, color_from:good_bg, color_to:critical_bg)` or
+//! `$temperature.eng(color_from:00ff00, color_to:ff0000)`.
+//!
 //! # Handling missing placeholders and incorrect types
 //!
 //! Some blocks allow missing placeholders, for example [bluetooth](crate::blocks::bluetooth)'s
@@ -96,6 +107,7 @@ use std::sync::Arc;
 
 use crate::config::SharedConfig;
 use crate::errors::*;
+use crate::themes::color::Rgba;
 use template::FormatTemplate;
 use value::Value;
 
@@ -151,11 +163,18 @@ impl From<String> for Fragment {
 
 impl Fragment {
     pub fn formated_text(&self) -> String {
-        match (self.metadata.italic, self.metadata.underline) {
+        let text = match (self.metadata.italic, self.metadata.underline) {
             (true, true) => format!("<i><u>{}</u></i>", self.text),
             (false, true) => format!("<u>{}</u>", self.text),
             (true, false) => format!("<i>{}</i>", self.text),
             (false, false) => self.text.clone(),
+        };
+        match self.metadata.color {
+            Some(c) => format!(
+                "<span foreground='#{:02X}{:02X}{:02X}'>{text}</span>",
+                c.r, c.g, c.b
+            ),
+            None => text,
         }
     }
 }
@@ -165,6 +184,7 @@ pub struct Metadata {
     pub instance: Option<&'static str>,
     pub underline: bool,
     pub italic: bool,
+    pub color: Option<Rgba>,
 }
 
 impl Metadata {
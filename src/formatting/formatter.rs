@@ -8,6 +8,8 @@ use super::unit::Unit;
 use super::value::ValueInner as Value;
 use crate::errors::*;
 use crate::escape::CollectEscaped;
+use crate::themes::color::Color;
+use crate::themes::Theme;
 
 const DEFAULT_STR_MIN_WIDTH: usize = 0;
 const DEFAULT_STR_MAX_WIDTH: usize = usize::MAX;
@@ -45,9 +47,38 @@ pub trait Formatter: Debug + Send + Sync {
     fn interval(&self) -> Option<Duration> {
         None
     }
+
+    /// The color of the formatted text, if any
+    fn color(&self, _val: &Value, _theme: &Theme) -> Result<Option<Color>> {
+        Ok(None)
+    }
 }
 
 pub fn new_formatter(name: &str, args: &[Arg]) -> Result<Box<dyn Formatter>> {
+    let mut color_from = None;
+    let mut color_to = None;
+    let mut other_args = Vec::new();
+    for arg in args {
+        match arg.key {
+            "color_from" => color_from = Some(arg.val.to_string()),
+            "color_to" => color_to = Some(arg.val.to_string()),
+            _ => other_args.push(Arg {
+                key: arg.key,
+                val: arg.val,
+            }),
+        }
+    }
+    let inner = new_plain_formatter(name, &other_args)?;
+    match (color_from, color_to) {
+        (None, None) => Ok(inner),
+        (Some(from), Some(to)) => Ok(Box::new(GradientFormatter { inner, from, to })),
+        _ => Err(Error::new(
+            "'color_from' and 'color_to' must be set together",
+        )),
+    }
+}
+
+fn new_plain_formatter(name: &str, args: &[Arg]) -> Result<Box<dyn Formatter>> {
     match name {
         "str" => {
             let mut min_width = DEFAULT_STR_MIN_WIDTH;
@@ -124,6 +155,43 @@ pub fn new_formatter(name: &str, args: &[Arg]) -> Result<Box<dyn Formatter>> {
     }
 }
 
+/// Colors the text of a number based on its value, see [`Color::gradient`]. The colors are either
+/// names of theme colors (e.g. `good_bg`) or hex codes without the leading `#`.
+#[derive(Debug)]
+pub struct GradientFormatter {
+    inner: Box<dyn Formatter>,
+    from: String,
+    to: String,
+}
+
+fn resolve_color(color: &str, theme: &Theme) -> Result<Color> {
+    match theme.get_color(color) {
+        Some(color) => Ok(color),
+        None => format!("#{color}").parse(),
+    }
+}
+
+impl Formatter for GradientFormatter {
+    fn format(&self, val: &Value) -> Result<String> {
+        self.inner.format(val)
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        self.inner.interval()
+    }
+
+    fn color(&self, val: &Value, theme: &Theme) -> Result<Option<Color>> {
+        match val {
+            Value::Number { val, .. } => Ok(Some(Color::gradient(
+                resolve_color(&self.from, theme)?,
+                resolve_color(&self.to, theme)?,
+                *val,
+            ))),
+            _ => Ok(None),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct StrFormatter {
     min_width: usize,
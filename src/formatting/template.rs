@@ -99,7 +99,11 @@ impl TokenList {
                         .map(Box::as_ref)
                         .unwrap_or_else(|| value.default_formatter());
                     let formatted = formatter.format(&value.inner)?;
-                    if value.metadata == cur.metadata {
+                    let mut metadata = value.metadata;
+                    if let Some(color) = formatter.color(&value.inner, &config.theme)? {
+                        metadata.color = color.to_rgba();
+                    }
+                    if metadata == cur.metadata {
                         cur.text.push_str(&formatted);
                     } else {
                         if !cur.text.is_empty() {
@@ -107,7 +111,7 @@ impl TokenList {
                         }
                         cur = Fragment {
                             text: formatted,
-                            metadata: value.metadata,
+                            metadata,
                         };
                     }
                 }
@@ -1,6 +1,7 @@
 use super::formatter;
 use super::unit::Unit;
 use super::Metadata;
+use crate::themes::color::Color;
 
 #[derive(Debug, Clone)]
 pub struct Value {
@@ -104,6 +105,12 @@ impl Value {
         self
     }
 
+    /// Set the color of the text, e.g. using [`Color::gradient`]
+    pub fn color(mut self, color: Color) -> Self {
+        self.metadata.color = color.to_rgba();
+        self
+    }
+
     pub fn default_formatter(&self) -> &'static dyn formatter::Formatter {
         match &self.inner {
             ValueInner::Text(_) | ValueInner::Icon(_) => &formatter::DEFAULT_STRING_FORMATTER,
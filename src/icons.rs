@@ -1,10 +1,9 @@
 use crate::errors::*;
 use crate::util;
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
-#[derive(Deserialize, Debug, Clone)]
-#[serde(try_from = "IconsConfigRaw")]
+#[derive(Debug, Clone)]
 pub struct Icons(pub HashMap<String, String>);
 
 impl Default for Icons {
@@ -125,23 +124,210 @@ impl Icons {
     }
 }
 
-#[derive(Deserialize, Default)]
+/// Print a table of icon names and their values in the given icon sets. All available sets are
+/// printed if `sets` is empty.
+pub fn print_list(sets: &[String]) -> Result<()> {
+    let sets = if sets.is_empty() {
+        let mut sets = vec!["none".to_string()];
+        sets.extend(util::list_files("icons", "toml"));
+        sets
+    } else {
+        sets.to_vec()
+    };
+    let icons = sets
+        .iter()
+        .map(|set| Icons::from_file(set))
+        .collect::<Result<Vec<_>>>()?;
+    let names: BTreeSet<&str> = icons
+        .iter()
+        .flat_map(|icons| icons.0.keys())
+        .map(String::as_str)
+        .collect();
+
+    println!("icon\t{}", sets.join("\t"));
+    for name in names {
+        let values: Vec<&str> = icons
+            .iter()
+            .map(|icons| icons.0.get(name).map_or("-", String::as_str))
+            .collect();
+        println!("{name}\t{}", values.join("\t"));
+    }
+    Ok(())
+}
+
+/// The `[icons]` section of the config. It is kept around to be able to reload the icon sets at
+/// runtime.
+#[derive(Deserialize, Debug, Clone, Default)]
 #[serde(deny_unknown_fields, default)]
-struct IconsConfigRaw {
-    icons: Option<String>,
+pub struct IconsConfig {
+    icons: Option<IconSetNames>,
     overrides: Option<HashMap<String, String>>,
 }
 
-impl TryFrom<IconsConfigRaw> for Icons {
-    type Error = Error;
+/// One icon set, or a list of icon sets which are merged in order. Later sets may define only a
+/// subset of icons, the rest is taken from the previous ones.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+enum IconSetNames {
+    Single(String),
+    Multiple(Vec<String>),
+}
 
-    fn try_from(raw: IconsConfigRaw) -> Result<Self, Self::Error> {
-        let mut icons = Self::from_file(raw.icons.as_deref().unwrap_or("none"))?;
-        if let Some(overrides) = raw.overrides {
-            for icon in overrides {
-                icons.0.insert(icon.0, icon.1);
-            }
+impl IconsConfig {
+    /// Load the icon sets and apply the overrides
+    pub fn build(&self) -> Result<Icons> {
+        let names = match &self.icons {
+            None => vec!["none".to_string()],
+            Some(IconSetNames::Single(name)) => vec![name.clone()],
+            Some(IconSetNames::Multiple(names)) => names.clone(),
+        };
+        let mut icons = Icons(HashMap::new());
+        for name in names {
+            icons.apply_overrides(Icons::from_file(&name)?.0);
+        }
+        if let Some(overrides) = &self.overrides {
+            icons.apply_overrides(overrides.clone());
         }
         Ok(icons)
     }
 }
+
+/// Maps a numeric value to one of several icons. Each level is used for values up to and
+/// including its `up_to`. The last level may omit `up_to` to cover all the remaining values;
+/// otherwise values above the last breakpoint use the last icon as well.
+///
+/// In the config this is a list of tables, for example:
+///
+/// ```toml
+/// icon_levels = [
+///     { up_to = 20, icon = "volume_empty" },
+///     { up_to = 70, icon = "volume_half" },
+///     { icon = "volume_full" },
+/// ]
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+#[serde(try_from = "Vec<IconLevel>")]
+pub struct IconLevels(Vec<IconLevel>);
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct IconLevel {
+    up_to: Option<f64>,
+    icon: String,
+}
+
+impl TryFrom<Vec<IconLevel>> for IconLevels {
+    type Error = Error;
+
+    fn try_from(levels: Vec<IconLevel>) -> Result<Self, Self::Error> {
+        if levels.is_empty() {
+            return Err(Error::new("icon_levels must not be empty"));
+        }
+        let breakpoints: Vec<f64> = levels.iter().filter_map(|l| l.up_to).collect();
+        if breakpoints.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(Error::new("icon_levels must be sorted by 'up_to'"));
+        }
+        if levels[..levels.len() - 1].iter().any(|l| l.up_to.is_none()) {
+            return Err(Error::new("only the last of icon_levels may omit 'up_to'"));
+        }
+        Ok(Self(levels))
+    }
+}
+
+impl IconLevels {
+    /// Create levels from `(up_to, icon)` pairs. The last icon is used for all values above the
+    /// last breakpoint.
+    pub fn new(levels: &[(f64, &str)]) -> Self {
+        Self(
+            levels
+                .iter()
+                .map(|&(up_to, icon)| IconLevel {
+                    up_to: Some(up_to),
+                    icon: icon.into(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Split the range `0..=max` evenly between `icons`
+    pub fn evenly(icons: &[&str], max: f64) -> Self {
+        let len = icons.len() as f64;
+        Self(
+            icons
+                .iter()
+                .enumerate()
+                .map(|(i, icon)| IconLevel {
+                    up_to: Some(max * (i + 1) as f64 / len),
+                    icon: (*icon).into(),
+                })
+                .collect(),
+        )
+    }
+
+    /// `bat_10`..`bat_90` and `bat_full`, used by the blocks which show a battery level
+    pub fn battery() -> Self {
+        Self::new(&[
+            (10., "bat_10"),
+            (20., "bat_20"),
+            (30., "bat_30"),
+            (40., "bat_40"),
+            (50., "bat_50"),
+            (60., "bat_60"),
+            (70., "bat_70"),
+            (80., "bat_80"),
+            (90., "bat_90"),
+            (100., "bat_full"),
+        ])
+    }
+
+    /// Reverse the order of the icons, keeping the breakpoints
+    pub fn inverted(mut self) -> Self {
+        let mut icons: Vec<String> = self.0.iter().map(|l| l.icon.clone()).collect();
+        for level in &mut self.0 {
+            level.icon = icons.pop().unwrap();
+        }
+        self
+    }
+
+    /// The name of the icon for `value`
+    pub fn get(&self, value: f64) -> &str {
+        self.0
+            .iter()
+            .find(|level| level.up_to.map_or(true, |up_to| value <= up_to))
+            .unwrap_or_else(|| self.0.last().unwrap())
+            .icon
+            .as_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn icon_levels() {
+        let levels = IconLevels::battery();
+        assert_eq!(levels.get(0.), "bat_10");
+        assert_eq!(levels.get(10.), "bat_10");
+        assert_eq!(levels.get(10.5), "bat_20");
+        assert_eq!(levels.get(100.), "bat_full");
+        assert_eq!(levels.get(150.), "bat_full");
+
+        let levels = IconLevels::evenly(&["low", "med", "high"], 1.);
+        assert_eq!(levels.get(0.2), "low");
+        assert_eq!(levels.get(0.5), "med");
+        assert_eq!(levels.get(0.9), "high");
+        let levels = levels.inverted();
+        assert_eq!(levels.get(0.2), "high");
+        assert_eq!(levels.get(0.9), "low");
+
+        let levels: IconLevels = toml::from_str::<HashMap<String, IconLevels>>(
+            "levels = [{ up_to = 5, icon = \"a\" }, { icon = \"b\" }]",
+        )
+        .unwrap()
+        .remove("levels")
+        .unwrap();
+        assert_eq!(levels.get(5.), "a");
+        assert_eq!(levels.get(6.), "b");
+    }
+}
@@ -19,7 +19,7 @@ mod themes;
 mod widget;
 mod wrappers;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use formatting::value::Value;
 use futures::future::{abortable, FutureExt};
 use futures::stream::futures_unordered::FuturesUnordered;
@@ -28,12 +28,13 @@ use once_cell::sync::Lazy;
 use protocol::i3bar_block::I3BarBlock;
 use protocol::i3bar_event::I3BarEvent;
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::future::Future;
 use std::pin::Pin;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::process::Command;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
 
 use blocks::{BlockEvent, BlockFuture, CommonApi};
 use click::{ClickHandler, MouseButton};
@@ -42,8 +43,11 @@ use config::{BlockConfigEntry, Config};
 use errors::*;
 use escape::CollectEscaped;
 use formatting::{scheduling, Format};
+use icons::IconsConfig;
 use protocol::i3bar_event::events_stream;
 use signals::{signals_stream, Signal};
+use themes::switch::Variant;
+use themes::{Theme, ThemeOverrides, ThemeUserConfig};
 use widget::{State, Widget};
 
 pub type BoxedFuture<T> = Pin<Box<dyn Future<Output = T>>>;
@@ -60,7 +64,7 @@ pub static REQWEST_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
 });
 
 #[derive(Debug, Parser)]
-#[clap(author, about, version = env!("VERSION"))]
+#[clap(author, about, version = env!("VERSION"), args_conflicts_with_subcommands = true)]
 struct CliArgs {
     /// Sets a TOML config file
     #[clap(default_value = "config.toml")]
@@ -74,6 +78,26 @@ struct CliArgs {
     /// The maximum number of blocking threads spawned by tokio
     #[clap(long = "threads", short = 'j', default_value = "2")]
     blocking_threads: usize,
+    #[clap(subcommand)]
+    command: Option<CliCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+enum CliCommand {
+    /// Inspect icon sets
+    Icons {
+        #[clap(subcommand)]
+        command: IconsCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum IconsCommand {
+    /// Print the icons defined by each icon set
+    List {
+        /// Icon sets to list. All available sets are listed if none are given
+        sets: Vec<String>,
+    },
 }
 
 fn main() {
@@ -81,6 +105,17 @@ fn main() {
     let args = CliArgs::parse();
     let blocking_threads = args.blocking_threads;
 
+    if let Some(CliCommand::Icons {
+        command: IconsCommand::List { sets },
+    }) = &args.command
+    {
+        if let Err(error) = icons::print_list(sets) {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     if !args.no_init {
         protocol::init(args.never_pause);
     }
@@ -94,8 +129,10 @@ fn main() {
             let config_path = util::find_file(&args.config, None, Some("toml"))
                 .or_error(|| format!("Configuration file '{}' not found", args.config))?;
             let mut config: Config = util::deserialize_toml_file(&config_path)?;
+            config.shared.icons = Arc::new(config.icons.build()?);
             let blocks = std::mem::take(&mut config.blocks);
             let mut bar = BarState::new(config);
+            bar.init_theme().await?;
             for block_config in blocks {
                 bar.spawn_block(block_config).await?;
             }
@@ -135,9 +172,13 @@ pub struct Block {
     default_actions: &'static [(MouseButton, Option<&'static str>, &'static str)],
     signal: Option<i32>,
     shared_config: SharedConfig,
+    shared_config_sender: watch::Sender<SharedConfig>,
+    theme_overrides: Option<ThemeOverrides>,
+    icons_overrides: Option<HashMap<String, String>>,
 
     error_format: Format,
     error_fullscreen_format: Format,
+    last_error_hook: Option<Instant>,
 
     state: BlockState,
 }
@@ -200,6 +241,10 @@ pub enum RequestCmd {
 pub struct RenderedBlock {
     segments: Vec<I3BarBlock>,
     merge_with_next: bool,
+    /// The block's own theme, used to draw the separator in front of it
+    theme: Arc<Theme>,
+    /// The name of the visual group this block belongs to
+    group: Option<String>,
 }
 
 struct BarState {
@@ -218,10 +263,16 @@ struct BarState {
 
     signals_stream: BoxedStream<Signal>,
     events_stream: BoxedStream<I3BarEvent>,
+
+    icons_config: IconsConfig,
+    theme_config: Option<ThemeUserConfig>,
+    theme_variant: Option<Variant>,
+    theme_variants: BoxedStream<Variant>,
+    theme_reloads: BoxedStream<()>,
 }
 
 impl BarState {
-    fn new(config: Config) -> Self {
+    fn new(mut config: Config) -> Self {
         let (request_sender, request_receiver) = mpsc::channel(64);
         let (widget_updates_sender, widget_updates_stream) = scheduling::manage_widgets_updates();
         Self {
@@ -242,10 +293,89 @@ impl BarState {
                 Duration::from_millis(config.double_click_delay),
             ),
 
+            icons_config: std::mem::take(&mut config.icons),
+            theme_config: config.theme.take(),
+            theme_variant: None,
+            theme_variants: futures::stream::pending().boxed_local(),
+            theme_reloads: futures::stream::pending().boxed_local(),
+
             config,
         }
     }
 
+    /// Load the initial theme and start watching for theme variant and palette changes, if
+    /// configured
+    async fn init_theme(&mut self) -> Result<()> {
+        let theme_config = match &self.theme_config {
+            Some(theme_config) => theme_config,
+            None => return Ok(()),
+        };
+        if let Some(switch) = &theme_config.switch {
+            // Make sure that both variants can be loaded
+            theme_config.build(Some(Variant::Light))?;
+            theme_config.build(Some(Variant::Dark))?;
+            self.theme_variants = switch.variant_stream().await?;
+            // Don't block the startup if the variant can't be determined right away (e.g. the
+            // command doesn't print `light` or `dark`). Use `theme` until it can.
+            let first = tokio::time::timeout(Duration::from_secs(5), self.theme_variants.next());
+            self.theme_variant = first.await.unwrap_or_else(|_| {
+                log::warn!("Could not determine the theme variant, using the default theme");
+                None
+            });
+        }
+        if let Some(palette) = &theme_config.palette {
+            self.theme_reloads = palette.watch()?;
+        }
+        self.config.shared.theme = Arc::new(theme_config.build(self.theme_variant)?);
+        Ok(())
+    }
+
+    /// Rebuild the theme from the theme config
+    fn reload_theme(&mut self) -> Result<()> {
+        if let Some(theme_config) = &self.theme_config {
+            self.config.shared.theme = Arc::new(theme_config.build(self.theme_variant)?);
+            self.update_blocks_shared_config()?;
+        }
+        Ok(())
+    }
+
+    /// Re-read the theme and icon files. Icons which the blocks have already resolved are
+    /// replaced on their next update.
+    fn reload_theme_and_icons(&mut self) -> Result<()> {
+        let icons = self.icons_config.build()?;
+        let theme = match &self.theme_config {
+            Some(theme_config) => Some(theme_config.build(self.theme_variant)?),
+            None => None,
+        };
+        self.config.shared.icons = Arc::new(icons);
+        if let Some(theme) = theme {
+            self.config.shared.theme = Arc::new(theme);
+        }
+        self.update_blocks_shared_config()
+    }
+
+    /// Pass the bar's theme and icons to all the blocks, preserving per-block overrides, and
+    /// re-render them
+    fn update_blocks_shared_config(&mut self) -> Result<()> {
+        for (block, _) in &mut self.blocks {
+            let mut shared_config = self.config.shared.clone();
+            shared_config.icons_format = block.shared_config.icons_format.clone();
+            if let Some(theme_overrides) = &block.theme_overrides {
+                Arc::make_mut(&mut shared_config.theme).apply_overrides(theme_overrides.clone())?;
+            }
+            if let Some(icons_overrides) = &block.icons_overrides {
+                Arc::make_mut(&mut shared_config.icons).apply_overrides(icons_overrides.clone());
+            }
+            let _ = block.shared_config_sender.send(shared_config.clone());
+            block.shared_config = shared_config;
+        }
+        for id in 0..self.blocks.len() {
+            self.render_block(id)?;
+        }
+        self.render();
+        Ok(())
+    }
+
     async fn spawn_block(&mut self, block_config: BlockConfigEntry) -> Result<()> {
         if let Some(cmd) = &block_config.common.if_command {
             if !Command::new("sh")
@@ -266,18 +396,19 @@ impl BarState {
         if let Some(icons_format) = block_config.common.icons_format {
             shared_config.icons_format = Arc::new(icons_format);
         }
-        if let Some(theme_overrides) = block_config.common.theme_overrides {
-            Arc::make_mut(&mut shared_config.theme).apply_overrides(theme_overrides)?;
+        if let Some(theme_overrides) = &block_config.common.theme_overrides {
+            Arc::make_mut(&mut shared_config.theme).apply_overrides(theme_overrides.clone())?;
         }
-        if let Some(icons_overrides) = block_config.common.icons_overrides {
-            Arc::make_mut(&mut shared_config.icons).apply_overrides(icons_overrides);
+        if let Some(icons_overrides) = &block_config.common.icons_overrides {
+            Arc::make_mut(&mut shared_config.icons).apply_overrides(icons_overrides.clone());
         }
 
         let (event_sender, event_receiver) = mpsc::channel(64);
+        let (shared_config_sender, shared_config_receiver) = watch::channel(shared_config.clone());
 
         let api = CommonApi {
             id: self.blocks.len(),
-            shared_config: shared_config.clone(),
+            shared_config: shared_config_receiver,
             event_receiver,
 
             request_sender: self.request_sender.clone(),
@@ -308,9 +439,13 @@ impl BarState {
             default_actions: &[],
             signal: block_config.common.signal,
             shared_config,
+            shared_config_sender,
+            theme_overrides: block_config.common.theme_overrides,
+            icons_overrides: block_config.common.icons_overrides,
 
             error_format,
             error_fullscreen_format,
+            last_error_hook: None,
 
             state: BlockState::None,
         };
@@ -320,11 +455,13 @@ impl BarState {
                 Ok(res) => res,
                 Err(_aborted) => Ok(()),
             })));
-        self.blocks.push((block, block_name));
         self.blocks_render_cache.push(RenderedBlock {
             segments: Vec::new(),
             merge_with_next: block_config.common.merge_with_next,
+            theme: block.shared_config.theme.clone(),
+            group: block_config.common.group,
         });
+        self.blocks.push((block, block_name));
 
         Ok(())
     }
@@ -345,18 +482,55 @@ impl BarState {
                 }
             }
             RequestCmd::SetError(error) => {
+                self.run_error_hook(request.block_id, &error);
+                let block = &mut self.blocks[request.block_id].0;
                 block.set_error(self.fullscreen_block == Some(request.block_id), error);
             }
             RequestCmd::SetDefaultActions(actions) => {
                 block.default_actions = actions;
             }
         }
-        block.notify_intervals();
+        self.blocks[request.block_id].0.notify_intervals();
+    }
+
+    /// Run the `on_error` hook if the block has just entered the error state
+    fn run_error_hook(&mut self, id: usize, error: &Error) {
+        let cmd = match &self.config.on_error {
+            Some(cmd) => cmd,
+            None => return,
+        };
+        let (block, block_name) = &mut self.blocks[id];
+        if matches!(block.state, BlockState::Error { .. }) {
+            return;
+        }
+        let interval = Duration::from_secs(self.config.on_error_interval);
+        if block
+            .last_error_hook
+            .map_or(false, |last| last.elapsed() < interval)
+        {
+            return;
+        }
+        block.last_error_hook = Some(Instant::now());
+
+        let error = error.to_string();
+        let cmd = cmd
+            .replace("{block}", &subprocess::shell_quote(block_name))
+            .replace("{error}", &subprocess::shell_quote(&error));
+        let env = [
+            ("I3RS_BLOCK", block_name.to_string()),
+            ("I3RS_ERROR", error),
+        ];
+        // stderr is read by i3bar, so log the failure instead
+        if let Err(e) = subprocess::spawn_shell_with_env(&cmd, &env) {
+            log::warn!("Failed to run on_error hook: {e}");
+        }
     }
 
     fn render_block(&mut self, id: usize) -> Result<()> {
         let (block, block_type) = &mut self.blocks[id];
-        let data = &mut self.blocks_render_cache[id].segments;
+        let cache = &mut self.blocks_render_cache[id];
+        cache.theme = block.shared_config.theme.clone();
+        let data = &mut cache.segments;
         match &block.state {
             BlockState::None => {
                 data.clear();
@@ -402,11 +576,16 @@ impl BarState {
             }
             // Handle clicks
             Some(event) = self.events_stream.next() => {
+                let full_text: String = self
+                    .blocks_render_cache
+                    .get(event.id)
+                    .map(|cache| cache.segments.iter().map(|s| s.full_text.as_str()).collect())
+                    .unwrap_or_default();
                 let (block, block_type) = self.blocks.get_mut(event.id).error("Events receiver: ID out of bounds")?;
                 match &mut block.state {
                     BlockState::None => (),
                     BlockState::Normal { .. } => {
-                        let post_actions = block.click_handler.handle(&event).await.in_block(block_type, event.id)?;
+                        let post_actions = block.click_handler.handle(&event, &full_text).await.in_block(block_type, event.id)?;
                         if let Some(sender) = &block.event_sender {
                             if let Some(action) = post_actions.action {
                                 let _ = sender.send(BlockEvent::Action(Cow::Owned(action))).await;
@@ -435,9 +614,29 @@ impl BarState {
                 }
                 Ok(())
             }
+            // Handle theme variant changes. Keep the current theme if the new one fails to load.
+            Some(variant) = self.theme_variants.next() => {
+                let previous = self.theme_variant.replace(variant);
+                if let Err(e) = self.reload_theme() {
+                    log::warn!("Failed to switch to the {variant:?} theme: {e}");
+                    self.theme_variant = previous;
+                }
+                Ok(())
+            }
+            // Handle palette changes. The file may be rewritten in several steps, so a failure
+            // here is not fatal.
+            Some(()) = self.theme_reloads.next() => {
+                if let Err(e) = self.reload_theme() {
+                    log::warn!("Failed to reload theme: {e}");
+                }
+                Ok(())
+            }
             // Handle signals
             Some(signal) = self.signals_stream.next() => match signal {
                 Signal::Usr1 => {
+                    if let Err(e) = self.reload_theme_and_icons() {
+                        log::warn!("Failed to reload theme and icons: {e}");
+                    }
                     for (block, _) in &self.blocks {
                         if let Some(sender) = &block.event_sender {
                             let _ = sender.send(BlockEvent::UpdateRequest).await;
@@ -474,6 +673,8 @@ impl BarState {
                         }
 
                         block.abort();
+                        self.run_error_hook(id, &error);
+                        let block = &mut self.blocks[id].0;
                         block.set_error(self.fullscreen_block == Some(id), error);
                         block.notify_intervals();
 
@@ -43,6 +43,10 @@ const IF_OPER_DORMANT: u8 = 5;
 /// Interface is operational up and can be used.
 const IF_OPER_UP: u8 = 6;
 
+// Source: include/uapi/linux/rtnetlink.h
+const RTNLGRP_IPV4_ROUTE: u32 = 7;
+const RTNLGRP_IPV6_ROUTE: u32 = 11;
+
 #[derive(Debug)]
 pub struct NetDevice {
     pub iface: Interface,
@@ -93,21 +97,11 @@ impl NetDevice {
         let ip = ipv4(&mut sock, iface.index).await?;
         let ipv6 = ipv6(&mut sock, iface.index).await?;
 
-        // TODO: use netlink for the these too
-        // I don't believe that this should ever change, so set it now:
-        let path = Path::new("/sys/class/net").join(&iface.name);
-        let tun = iface.name.starts_with("tun")
-            || iface.name.starts_with("tap")
-            || path.join("tun_flags").exists();
-        let (wg, ppp) = util::read_file(path.join("uevent"))
-            .await
-            .map_or((false, false), |c| {
-                (c.contains("wireguard"), c.contains("ppp"))
-            });
+        let tun_wg_ppp = is_vpn(&iface.name).await;
 
         let icon = if wifi_info.is_some() {
             "net_wireless"
-        } else if tun || wg || ppp {
+        } else if tun_wg_ppp {
             "net_vpn"
         } else if iface.name == "lo" {
             "net_loopback"
@@ -121,10 +115,36 @@ impl NetDevice {
             ip,
             ipv6,
             icon,
-            tun_wg_ppp: tun | wg | ppp,
+            tun_wg_ppp,
         }))
     }
 
+    /// Returns the name of a VPN interface (tun, tap, wireguard or ppp) which carries all traffic,
+    /// i.e. has a default route (in any routing table) or routes covering the whole address space,
+    /// like the `0.0.0.0/1` and `128.0.0.0/1` routes used by OpenVPN.
+    pub async fn vpn_name() -> Result<Option<String>> {
+        let mut sock = NlSocket::new(
+            NlSocketHandle::connect(NlFamily::Route, None, &[]).error("Socket error")?,
+        )
+        .error("Socket error")?;
+
+        let full_tunnels = get_full_tunnel_interfaces(&mut sock)
+            .await
+            .map_err(BoxErrorWrapper)
+            .error("Failed to get routes")?;
+        let ifaces = get_interfaces(&mut sock)
+            .await
+            .map_err(BoxErrorWrapper)
+            .error("Failed to fetch interfaces")?;
+
+        for iface in ifaces {
+            if full_tunnels.contains(&iface.index) && is_vpn(&iface.name).await {
+                return Ok(Some(iface.name));
+            }
+        }
+        Ok(None)
+    }
+
     pub fn is_up(&self) -> bool {
         self.iface.is_up || self.tun_wg_ppp
     }
@@ -146,6 +166,19 @@ impl NetDevice {
     }
 }
 
+async fn is_vpn(iface: &str) -> bool {
+    // TODO: use netlink for the these too
+    let path = Path::new("/sys/class/net").join(iface);
+    let tun =
+        iface.starts_with("tun") || iface.starts_with("tap") || path.join("tun_flags").exists();
+    let (wg, ppp) = util::read_file(path.join("uevent"))
+        .await
+        .map_or((false, false), |c| {
+            (c.contains("wireguard"), c.contains("ppp"))
+        });
+    tun || wg || ppp
+}
+
 impl WifiInfo {
     async fn new(if_index: i32) -> Result<Option<Self>> {
         /// <https://github.com/torvalds/linux/blob/9ff9b0d392ea08090cd1780fb196f36dbb586529/drivers/net/wireless/intel/ipw2x00/ipw2200.c#L4322-L4334>
@@ -195,6 +228,44 @@ impl WifiInfo {
     }
 }
 
+/// A netlink socket which is notified every time a route is added or removed
+pub struct RouteChanges {
+    sock: NlSocket,
+    buf: Vec<u8>,
+}
+
+impl RouteChanges {
+    pub fn new() -> Result<Self> {
+        let sock = NlSocket::new(
+            NlSocketHandle::connect(
+                NlFamily::Route,
+                None,
+                &[RTNLGRP_IPV4_ROUTE, RTNLGRP_IPV6_ROUTE],
+            )
+            .error("Socket error")?,
+        )
+        .error("Socket error")?;
+        Ok(Self {
+            sock,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Wait for the next route change.
+    ///
+    /// # Cancel safety
+    ///
+    /// This method is cancel safe.
+    pub async fn wait(&mut self) -> Result<()> {
+        self.sock
+            .recv::<u16, Rtmsg>(&mut self.buf)
+            .await
+            .map_err(|e| BoxErrorWrapper(Box::new(e)))
+            .error("Failed to receive route changes")?;
+        Ok(())
+    }
+}
+
 #[derive(Debug, Default, Clone, Copy)]
 pub struct InterfaceStats {
     pub rx_bytes: u64,
@@ -326,7 +397,8 @@ async fn get_default_interface(
     ))
     .await?;
 
-    let mut default_index = 0;
+    // The default route with the lowest metric is the one in use, the others are fallbacks
+    let mut default_route: Option<(i32, u32)> = None;
 
     recv_until_done!(sock, msg: Rtmsg => {
         if msg.rtm_type != Rtn::Unicast {
@@ -334,19 +406,63 @@ async fn get_default_interface(
         }
         let mut index = None;
         let mut is_default = false;
+        let mut metric = 0;
         for attr in msg.rtattrs.iter() {
             match attr.rta_type {
                 Rta::Oif => index = Some(attr.get_payload_as::<i32>()?),
                 Rta::Gateway => is_default = true,
+                Rta::Priority => metric = attr.get_payload_as::<u32>()?,
                 _ => (),
             }
         }
-        if is_default && default_index == 0 {
-            default_index = index.unwrap();
+        if is_default && default_route.map_or(true, |(_, m)| metric < m) {
+            default_route = Some((index.unwrap(), metric));
+        }
+    });
+
+    Ok(default_route.map_or(0, |(index, _)| index))
+}
+
+/// Indices of the interfaces which have a default route or a route covering half of the address
+/// space
+async fn get_full_tunnel_interfaces(
+    sock: &mut NlSocket,
+) -> Result<Vec<i32>, Box<dyn StdError + Send + Sync + 'static>> {
+    sock.send(&Nlmsghdr::new(
+        None,
+        Rtm::Getroute,
+        NlmFFlags::new(&[NlmF::Request, NlmF::Dump]),
+        None,
+        None,
+        NlPayload::Payload(Rtmsg {
+            rtm_family: RtAddrFamily::Inet,
+            rtm_dst_len: 0,
+            rtm_src_len: 0,
+            rtm_tos: 0,
+            rtm_table: RtTable::Unspec,
+            rtm_protocol: Rtprot::Unspec,
+            rtm_scope: RtScope::Universe,
+            rtm_type: Rtn::Unspec,
+            rtm_flags: RtmFFlags::empty(),
+            rtattrs: RtBuffer::new(),
+        }),
+    ))
+    .await?;
+
+    let mut indices = Vec::new();
+
+    recv_until_done!(sock, msg: Rtmsg => {
+        if msg.rtm_type != Rtn::Unicast || msg.rtm_dst_len > 1 {
+            continue;
+        }
+        for attr in msg.rtattrs.iter() {
+            if attr.rta_type == Rta::Oif {
+                indices.push(attr.get_payload_as::<i32>()?);
+            }
         }
     });
 
-    Ok(default_index)
+    Ok(indices)
 }
 
 async fn ip_payload(
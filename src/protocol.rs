@@ -5,7 +5,7 @@ use std::borrow::Borrow;
 
 use crate::config::SharedConfig;
 use crate::themes::color::Color;
-use crate::themes::separator::Separator;
+use crate::themes::separator::{Separator, SeparatorDirection};
 use crate::RenderedBlock;
 
 use i3bar_block::I3BarBlock;
@@ -36,23 +36,37 @@ where
 
     let mut logical_block_i = 0;
 
-    for widgets in blocks
+    let blocks: Vec<&RenderedBlock> = blocks
         .iter()
         .map(|x| x.borrow())
         .filter(|x| !x.segments.is_empty())
-        .cloned()
-    {
+        .collect();
+
+    for (i, widgets) in blocks.iter().enumerate() {
         let RenderedBlock {
             mut segments,
             merge_with_next,
-        } = widgets;
+            theme,
+            group,
+        } = (*widgets).clone();
+
+        // Blocks in a group share the group's background. The style is taken from the block's own
+        // theme, so that it can be changed with `theme_overrides`.
+        let group_style = group.as_ref().and_then(|g| theme.groups.get(g));
+        let prev_group = i.checked_sub(1).and_then(|j| blocks[j].group.as_ref());
+        let next_group = blocks.get(i + 1).and_then(|b| b.group.as_ref());
 
         for segment in &mut segments {
             segment.name = Some(logical_block_i.to_string());
 
-            // Apply tint for all widgets of every second block
-            // TODO: Allow for other non-additive tints
-            if alt {
+            if let Some(style) = group_style {
+                segment.background = style.bg;
+                if style.fg != Color::None {
+                    segment.color = style.fg;
+                }
+            } else if alt {
+                // Apply tint for all widgets of every second block
+                // TODO: Allow for other non-additive tints
                 segment.background = segment.background + config.theme.alternating_tint_bg;
                 segment.color = segment.color + config.theme.alternating_tint_fg;
             }
@@ -62,33 +76,52 @@ where
             alt = !alt;
         }
 
-        if !merge_with_next {
-            if let Separator::Custom(separator) = &config.theme.separator {
-                // The first widget's BG is used to get the FG color for the current separator
-                let sep_fg = if config.theme.separator_fg == Color::Auto {
-                    segments.first().unwrap().background
-                } else {
-                    config.theme.separator_fg
-                };
-
-                // The separator's BG is the last block's last widget's BG
-                let sep_bg = if config.theme.separator_bg == Color::Auto {
-                    last_bg
-                } else {
-                    config.theme.separator_bg
-                };
+        if let Some(style) = group_style {
+            // Open the group with its start cap. There are no separators inside of a group.
+            if prev_group != group.as_ref() {
+                rendered_blocks.push(I3BarBlock {
+                    full_text: style.start.clone(),
+                    background: last_bg,
+                    color: style.bg,
+                    ..Default::default()
+                });
+            }
+        } else if !merge_with_next {
+            if let Separator::Custom(separator) = &theme.separator {
+                let first_bg = segments.first().unwrap().background;
+
+                // Blocks with identical backgrounds may be visually merged
+                let merged = theme.merge_same_bg && logical_block_i > 0 && first_bg == last_bg;
+
+                if !merged {
+                    // The color of the separator's "body" is the current block's first widget's
+                    // BG for left-pointing separators and the last block's last widget's BG for
+                    // right-pointing ones. The other side takes the other color.
+                    let (body, other) = match theme.separator_direction {
+                        SeparatorDirection::Left => (first_bg, last_bg),
+                        SeparatorDirection::Right => (last_bg, first_bg),
+                    };
+                    let sep_fg = if theme.separator_fg == Color::Auto {
+                        body
+                    } else {
+                        theme.separator_fg
+                    };
+                    let sep_bg = if theme.separator_bg == Color::Auto {
+                        other
+                    } else {
+                        theme.separator_bg
+                    };
+
+                    rendered_blocks.push(I3BarBlock {
+                        full_text: separator.clone(),
+                        background: sep_bg,
+                        color: sep_fg,
+                        ..Default::default()
+                    });
+                }
 
                 // The last widget's BG is used to get the BG color for the next separator
                 last_bg = segments.last().unwrap().background;
-
-                let separator = I3BarBlock {
-                    full_text: separator.clone(),
-                    background: sep_bg,
-                    color: sep_fg,
-                    ..Default::default()
-                };
-
-                rendered_blocks.push(separator);
             } else {
                 // Re-add native separator on last widget for native theme
                 segments.last_mut().unwrap().separator = None;
@@ -98,6 +131,24 @@ where
 
         rendered_blocks.extend(segments);
 
+        if let Some(style) = group_style {
+            if next_group != group.as_ref() {
+                let mut end = I3BarBlock {
+                    full_text: style.end.clone(),
+                    background: Color::None,
+                    color: style.bg,
+                    ..Default::default()
+                };
+                // Re-add native separator after the group for native theme
+                if let Separator::Native = theme.separator {
+                    end.separator = None;
+                    end.separator_block_width = None;
+                }
+                rendered_blocks.push(end);
+                last_bg = Color::None;
+            }
+        }
+
         if !merge_with_next {
             logical_block_i += 1;
         }
@@ -112,5 +163,19 @@ where
         });
     }
 
+    // Flatten translucent colors for bars which do not support transparency. The text is blended
+    // against the (already flattened) background of its block.
+    let blend_bg = config.theme.blend_bg;
+    if blend_bg != Color::None {
+        for block in &mut rendered_blocks {
+            block.background = block.background.blend_over(blend_bg);
+            block.color = block.color.blend_over(if block.background == Color::None {
+                blend_bg
+            } else {
+                block.background
+            });
+        }
+    }
+
     println!("{},", serde_json::to_string(&rendered_blocks).unwrap());
 }
@@ -10,11 +10,29 @@ use tokio::io::{AsyncBufReadExt, BufReader};
 use crate::click::MouseButton;
 use crate::BoxedStream;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub struct I3BarEvent {
     pub id: usize,
     pub instance: Option<String>,
     pub button: MouseButton,
+    /// Coordinates of the click relative to the top left corner of the root window
+    pub x: i32,
+    pub y: i32,
+    /// Coordinates of the click relative to the top left corner of the block
+    pub relative_x: i32,
+    pub relative_y: i32,
+    /// Size of the block
+    pub width: i32,
+    pub height: i32,
+    /// Modifier keys held during the click, e.g. `Shift` or `Mod4`
+    pub modifiers: Vec<String>,
+}
+
+impl I3BarEvent {
+    /// Whether both events were sent by the same button on the same widget
+    fn same_target(&self, other: &Self) -> bool {
+        self.id == other.id && self.instance == other.instance && self.button == other.button
+    }
 }
 
 fn unprocessed_events_stream(invert_scrolling: bool) -> BoxedStream<I3BarEvent> {
@@ -38,6 +56,20 @@ fn unprocessed_events_stream(invert_scrolling: bool) -> BoxedStream<I3BarEvent>
             struct I3BarEventRaw {
                 instance: Option<String>,
                 button: MouseButton,
+                #[serde(default)]
+                x: i32,
+                #[serde(default)]
+                y: i32,
+                #[serde(default)]
+                relative_x: i32,
+                #[serde(default)]
+                relative_y: i32,
+                #[serde(default)]
+                width: i32,
+                #[serde(default)]
+                height: i32,
+                #[serde(default)]
+                modifiers: Vec<String>,
             }
 
             let event: I3BarEventRaw = serde_json::from_str(line).unwrap();
@@ -65,6 +97,13 @@ fn unprocessed_events_stream(invert_scrolling: bool) -> BoxedStream<I3BarEvent>
                 id,
                 instance,
                 button,
+                x: event.x,
+                y: event.y,
+                relative_x: event.relative_x,
+                relative_y: event.relative_y,
+                width: event.width,
+                height: event.height,
+                modifiers: event.modifiers,
             };
 
             break Some((event, lines));
@@ -89,7 +128,7 @@ pub fn events_stream(
         if event.button == MouseButton::Left && !double_click_delay.is_zero() {
             if let Ok(new_event) = tokio::time::timeout(double_click_delay, events.next()).await {
                 let new_event = new_event?;
-                if event == new_event {
+                if event.same_target(&new_event) {
                     event.button = MouseButton::DoubleLeft;
                 } else {
                     return Some((event, (events, Some(new_event))));
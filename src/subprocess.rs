@@ -2,10 +2,19 @@ use std::io;
 use std::os::unix::process::CommandExt;
 use std::process::{Command, Stdio};
 
+/// Quote a string so that the shell passes it as a single argument
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
 /// Spawn a new detached process
 pub fn spawn_process(cmd: &str, args: &[&str]) -> io::Result<()> {
     let mut proc = Command::new(cmd);
     proc.args(args);
+    spawn_detached(proc)
+}
+
+fn spawn_detached(mut proc: Command) -> io::Result<()> {
     proc.stdin(Stdio::null());
     proc.stdout(Stdio::null());
     // Safety: libc::daemon() is async-signal-safe
@@ -27,9 +36,22 @@ pub fn spawn_shell(cmd: &str) -> io::Result<()> {
     spawn_process("sh", &["-c", cmd])
 }
 
+/// Spawn a new detached shell with additional environment variables
+pub fn spawn_shell_with_env(cmd: &str, env: &[(&str, String)]) -> io::Result<()> {
+    let mut proc = Command::new("sh");
+    proc.args(["-c", cmd]);
+    proc.envs(env.iter().map(|(k, v)| (k, v)));
+    spawn_detached(proc)
+}
+
 pub async fn spawn_shell_sync(cmd: &str) -> io::Result<()> {
+    spawn_shell_sync_with_env(cmd, &[]).await
+}
+
+pub async fn spawn_shell_sync_with_env(cmd: &str, env: &[(&str, String)]) -> io::Result<()> {
     tokio::process::Command::new("sh")
         .args(["-c", cmd])
+        .envs(env.iter().map(|(k, v)| (k, v)))
         .stdin(Stdio::null())
         .stdout(Stdio::null())
         .spawn()?
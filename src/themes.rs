@@ -1,13 +1,20 @@
 pub mod color;
+pub mod group;
+pub mod palette;
 pub mod separator;
+pub mod switch;
 
 use serde::Deserialize;
+use std::collections::HashMap;
 
 use crate::errors::*;
 use crate::util;
 use crate::widget::State;
 use color::Color;
-use separator::Separator;
+use group::GroupStyle;
+use palette::PaletteConfig;
+use separator::{Separator, SeparatorDirection};
+use switch::{SwitchConfig, Variant};
 
 #[derive(Deserialize, Debug, Clone, Default)]
 #[serde(deny_unknown_fields, default)]
@@ -23,11 +30,18 @@ pub struct Theme {
     pub critical_bg: Color,
     pub critical_fg: Color,
     pub separator: Separator,
+    pub separator_direction: SeparatorDirection,
     pub separator_bg: Color,
     pub separator_fg: Color,
+    pub merge_same_bg: bool,
     pub alternating_tint_bg: Color,
     pub alternating_tint_fg: Color,
     pub end_separator: Separator,
+    /// The background of the bar. If set, translucent colors are blended against it, for bars
+    /// which do not support transparency.
+    pub blend_bg: Color,
+    /// Styles of block groups, by name. Blocks join a group with the `group` option.
+    pub groups: HashMap<String, GroupStyle>,
 }
 
 impl Theme {
@@ -41,6 +55,28 @@ impl Theme {
         }
     }
 
+    /// Get a color of the theme by its name, e.g. `good_bg`
+    pub fn get_color(&self, name: &str) -> Option<Color> {
+        Some(match name {
+            "idle_bg" => self.idle_bg,
+            "idle_fg" => self.idle_fg,
+            "info_bg" => self.info_bg,
+            "info_fg" => self.info_fg,
+            "good_bg" => self.good_bg,
+            "good_fg" => self.good_fg,
+            "warning_bg" => self.warning_bg,
+            "warning_fg" => self.warning_fg,
+            "critical_bg" => self.critical_bg,
+            "critical_fg" => self.critical_fg,
+            "separator_bg" => self.separator_bg,
+            "separator_fg" => self.separator_fg,
+            "alternating_tint_bg" => self.alternating_tint_bg,
+            "alternating_tint_fg" => self.alternating_tint_fg,
+            "blend_bg" => self.blend_bg,
+            _ => return None,
+        })
+    }
+
     pub fn apply_overrides(&mut self, overrides: ThemeOverrides) -> Result<()> {
         let copy = self.clone();
 
@@ -50,6 +86,34 @@ impl Theme {
         if let Some(end_separator) = overrides.end_separator {
             self.end_separator = end_separator;
         }
+        if let Some(separator_direction) = overrides.separator_direction {
+            self.separator_direction = separator_direction;
+        }
+        if let Some(merge_same_bg) = overrides.merge_same_bg {
+            self.merge_same_bg = merge_same_bg;
+        }
+        if let Some(groups) = overrides.groups {
+            self.groups.extend(groups);
+        }
+
+        // Per-state tables are applied first, so that individual colors take precedence
+        macro_rules! apply_state {
+            ($state:tt, $bg:tt, $fg:tt) => {
+                if let Some(colors) = overrides.$state {
+                    if let Some(color) = colors.bg {
+                        self.$bg = color.eval(&copy)?;
+                    }
+                    if let Some(color) = colors.fg {
+                        self.$fg = color.eval(&copy)?;
+                    }
+                }
+            };
+        }
+        apply_state!(idle, idle_bg, idle_fg);
+        apply_state!(info, info_bg, info_fg);
+        apply_state!(good, good_bg, good_fg);
+        apply_state!(warning, warning_bg, warning_fg);
+        apply_state!(critical, critical_bg, critical_fg);
 
         macro_rules! apply {
             ($prop:tt) => {
@@ -72,20 +136,54 @@ impl Theme {
         apply!(separator_fg);
         apply!(alternating_tint_bg);
         apply!(alternating_tint_fg);
+        apply!(blend_bg);
 
         Ok(())
     }
 }
 
-#[derive(Deserialize, Default)]
+#[derive(Deserialize, Debug, Clone, Default)]
 #[serde(deny_unknown_fields, default)]
 pub struct ThemeUserConfig {
     theme: Option<String>,
+    light: Option<String>,
+    dark: Option<String>,
+    pub switch: Option<SwitchConfig>,
+    pub palette: Option<PaletteConfig>,
     overrides: Option<ThemeOverrides>,
 }
 
+impl ThemeUserConfig {
+    /// Load the theme for the given variant, then apply the palette and the overrides. Falls back
+    /// to `theme` if the variant-specific theme is not set.
+    pub fn build(&self, variant: Option<Variant>) -> Result<Theme> {
+        let name = match variant {
+            Some(Variant::Light) => self.light.as_deref(),
+            Some(Variant::Dark) => self.dark.as_deref(),
+            None => None,
+        }
+        .or(self.theme.as_deref())
+        .unwrap_or("plain");
+        let file = util::find_file(name, Some("themes"), Some("toml"))
+            .or_error(|| format!("Theme '{name}' not found"))?;
+        let mut theme: Theme = util::deserialize_toml_file(file)?;
+        if let Some(palette) = &self.palette {
+            palette.load()?.apply(&mut theme);
+        }
+        if let Some(overrides) = &self.overrides {
+            theme.apply_overrides(overrides.clone())?;
+        }
+        Ok(theme)
+    }
+}
+
 #[derive(Deserialize, Debug, Clone, Default)]
 pub struct ThemeOverrides {
+    idle: Option<StateColors>,
+    info: Option<StateColors>,
+    good: Option<StateColors>,
+    warning: Option<StateColors>,
+    critical: Option<StateColors>,
     idle_bg: Option<ColorOrLink>,
     idle_fg: Option<ColorOrLink>,
     info_bg: Option<ColorOrLink>,
@@ -97,26 +195,23 @@ pub struct ThemeOverrides {
     critical_bg: Option<ColorOrLink>,
     critical_fg: Option<ColorOrLink>,
     separator: Option<Separator>,
+    separator_direction: Option<SeparatorDirection>,
     separator_bg: Option<ColorOrLink>,
     separator_fg: Option<ColorOrLink>,
+    merge_same_bg: Option<bool>,
     alternating_tint_bg: Option<ColorOrLink>,
     alternating_tint_fg: Option<ColorOrLink>,
+    blend_bg: Option<ColorOrLink>,
     end_separator: Option<Separator>,
+    groups: Option<HashMap<String, GroupStyle>>,
 }
 
-impl TryFrom<ThemeUserConfig> for Theme {
-    type Error = Error;
-
-    fn try_from(user_config: ThemeUserConfig) -> Result<Self, Self::Error> {
-        let name = user_config.theme.as_deref().unwrap_or("plain");
-        let file = util::find_file(name, Some("themes"), Some("toml"))
-            .or_error(|| format!("Theme '{name}' not found"))?;
-        let mut theme: Theme = util::deserialize_toml_file(file)?;
-        if let Some(overrides) = user_config.overrides {
-            theme.apply_overrides(overrides)?;
-        }
-        Ok(theme)
-    }
+/// Colors of a single state, e.g. `warning = { bg = "#ff8800", fg = "#000000" }`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct StateColors {
+    bg: Option<ColorOrLink>,
+    fg: Option<ColorOrLink>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -130,23 +225,9 @@ impl ColorOrLink {
     fn eval(self, theme: &Theme) -> Result<Color> {
         Ok(match self {
             Self::Color(c) => c,
-            Self::Link { link } => match link.as_str() {
-                "idle_bg" => theme.idle_bg,
-                "idle_fg" => theme.idle_fg,
-                "info_bg" => theme.info_bg,
-                "info_fg" => theme.info_fg,
-                "good_bg" => theme.good_bg,
-                "good_fg" => theme.good_fg,
-                "warning_bg" => theme.warning_bg,
-                "warning_fg" => theme.warning_fg,
-                "critical_bg" => theme.critical_bg,
-                "critical_fg" => theme.critical_fg,
-                "separator_bg" => theme.separator_bg,
-                "separator_fg" => theme.separator_fg,
-                "alternating_tint_bg" => theme.alternating_tint_bg,
-                "alternating_tint_fg" => theme.alternating_tint_fg,
-                _ => return Err(Error::new(format!("{link} is not a correct theme color"))),
-            },
+            Self::Link { link } => theme
+                .get_color(&link)
+                .or_error(|| format!("{link} is not a correct theme color"))?,
         })
     }
 }
@@ -36,6 +36,32 @@ impl Rgba {
         let [r, g, b, a] = hex.to_be_bytes();
         Self { r, g, b, a }
     }
+
+    /// Linear interpolation between `self` (at `t = 0`) and `other` (at `t = 1`). `t` is clamped to
+    /// this range.
+    pub fn lerp(self, other: Rgba, t: f64) -> Self {
+        let t = t.clamp(0., 1.);
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        Self::new(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+            mix(self.a, other.a),
+        )
+    }
+
+    /// Composite this color over `background`. The alpha of `background` is ignored, so the result
+    /// is always opaque.
+    pub fn blend_over(self, background: Rgba) -> Self {
+        let a = self.a as u32;
+        let mix = |fg: u8, bg: u8| ((fg as u32 * a + bg as u32 * (255 - a) + 127) / 255) as u8;
+        Self::new(
+            mix(self.r, background.r),
+            mix(self.g, background.g),
+            mix(self.b, background.b),
+            255,
+        )
+    }
 }
 
 impl Add for Rgba {
@@ -178,6 +204,33 @@ impl Color {
     pub fn skip_ser(&self) -> bool {
         matches!(self, Self::None | Self::Auto)
     }
+
+    pub fn to_rgba(self) -> Option<Rgba> {
+        match self {
+            Self::None | Self::Auto => None,
+            Self::Rgba(rgba) => Some(rgba),
+            Self::Hsva(hsva) => Some(hsva.into()),
+        }
+    }
+
+    /// Interpolate between `from` (at `0`) and `to` (at `100`), e.g. to shift from green to red as
+    /// the utilization grows. `value` is clamped to this range. Returns `none` if any of the colors
+    /// is `none` or `auto`.
+    pub fn gradient(from: Color, to: Color, value: f64) -> Color {
+        match (from.to_rgba(), to.to_rgba()) {
+            (Some(from), Some(to)) => Self::Rgba(from.lerp(to, value / 100.)),
+            _ => Self::None,
+        }
+    }
+
+    /// Composite this color over `background`, producing an opaque color. `None` and `auto` are
+    /// returned as is, and nothing is done if `background` is `None` or `auto`.
+    pub fn blend_over(self, background: Color) -> Color {
+        match (self.to_rgba(), background.to_rgba()) {
+            (Some(fg), Some(bg)) => Self::Rgba(fg.blend_over(bg)),
+            _ => self,
+        }
+    }
 }
 
 impl Add for Color {
@@ -268,3 +321,40 @@ impl<'de> Deserialize<'de> for Color {
         deserializer.deserialize_any(ColorVisitor)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blend() {
+        let bg = Rgba::from_hex(0x000000ff);
+        assert_eq!(
+            Rgba::from_hex(0xffffff80).blend_over(bg),
+            Rgba::from_hex(0x808080ff)
+        );
+        assert_eq!(
+            Rgba::from_hex(0x123456ff).blend_over(bg),
+            Rgba::from_hex(0x123456ff)
+        );
+        let color: Color = "#ff000000".parse().unwrap();
+        assert_eq!(
+            color.blend_over("#00ff00".parse().unwrap()),
+            "#00ff00".parse::<Color>().unwrap()
+        );
+        assert_eq!(color.blend_over(Color::None), color);
+    }
+
+    #[test]
+    fn gradient() {
+        let from: Color = "#00ff00".parse().unwrap();
+        let to: Color = "#ff0000".parse().unwrap();
+        assert_eq!(Color::gradient(from, to, -10.), from);
+        assert_eq!(Color::gradient(from, to, 100.), to);
+        assert_eq!(
+            Color::gradient(from, to, 50.),
+            Color::Rgba(Rgba::from_hex(0x808000ff))
+        );
+        assert_eq!(Color::gradient(from, Color::None, 50.), Color::None);
+    }
+}
@@ -0,0 +1,18 @@
+use serde::Deserialize;
+use smart_default::SmartDefault;
+
+use super::color::Color;
+
+/// A shared background for a group of adjacent blocks. The group is drawn as one "pill", with the
+/// `start` and `end` glyphs (half circles by default) as its caps.
+#[derive(Deserialize, Debug, Clone, SmartDefault)]
+#[serde(deny_unknown_fields, default)]
+pub struct GroupStyle {
+    pub bg: Color,
+    /// Replaces the foreground of the blocks in the group, unless it is `none`
+    pub fg: Color,
+    #[default("\u{e0b6}".into())]
+    pub start: String,
+    #[default("\u{e0b4}".into())]
+    pub end: String,
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::Command;
+
+use futures::stream::StreamExt;
+use inotify::{Inotify, WatchMask};
+use serde::Deserialize;
+
+use super::color::Color;
+use super::Theme;
+use crate::errors::*;
+use crate::wrappers::ShellString;
+use crate::BoxedStream;
+
+/// An external source of colors which are mapped onto the theme
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "source", rename_all = "lowercase", deny_unknown_fields)]
+pub enum PaletteConfig {
+    /// A base16 scheme in YAML format
+    Base16 { file: ShellString },
+    /// Colors generated by pywal
+    Pywal {
+        #[serde(default = "default_pywal_file")]
+        file: ShellString,
+    },
+    /// X resources as reported by `xrdb -query`
+    Xresources,
+}
+
+fn default_pywal_file() -> ShellString {
+    ShellString::new("~/.cache/wal/colors.json")
+}
+
+/// The subset of colors used by the theme
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    pub background: Color,
+    pub foreground: Color,
+    pub red: Color,
+    pub green: Color,
+    pub yellow: Color,
+    pub blue: Color,
+}
+
+impl Palette {
+    /// Map the palette onto the theme's state colors. Separator colors are replaced only if they
+    /// are not set to `auto`.
+    pub fn apply(&self, theme: &mut Theme) {
+        theme.idle_bg = self.background;
+        theme.idle_fg = self.foreground;
+        theme.info_bg = self.blue;
+        theme.info_fg = self.background;
+        theme.good_bg = self.green;
+        theme.good_fg = self.background;
+        theme.warning_bg = self.yellow;
+        theme.warning_fg = self.background;
+        theme.critical_bg = self.red;
+        theme.critical_fg = self.background;
+        if theme.separator_bg != Color::Auto {
+            theme.separator_bg = self.background;
+        }
+        if theme.separator_fg != Color::Auto {
+            theme.separator_fg = self.foreground;
+        }
+    }
+}
+
+impl PaletteConfig {
+    /// The file which contains the palette, if any
+    pub fn path(&self) -> Result<Option<PathBuf>> {
+        match self {
+            Self::Base16 { file } | Self::Pywal { file } => {
+                Ok(Some(PathBuf::from(file.expand()?.as_ref())))
+            }
+            Self::Xresources => Ok(None),
+        }
+    }
+
+    pub fn load(&self) -> Result<Palette> {
+        if let Self::Xresources = self {
+            let output = Command::new("xrdb")
+                .arg("-query")
+                .output()
+                .error("Failed to run xrdb")?;
+            let output = String::from_utf8(output.stdout).error("xrdb produced non-UTF8 output")?;
+            return parse_xresources(&output);
+        }
+        let path = self.path()?.error("Palette has no file")?;
+        let contents = std::fs::read_to_string(&path)
+            .or_error(|| format!("Failed to read palette file {}", path.display()))?;
+        match self {
+            Self::Base16 { .. } => parse_base16(&contents),
+            Self::Pywal { .. } => parse_pywal(&contents),
+            Self::Xresources => unreachable!(),
+        }
+    }
+
+    /// Returns an infinite stream which yields every time the palette file is (re)written. X
+    /// resources cannot be watched, so the stream never yields for them.
+    pub fn watch(&self) -> Result<BoxedStream<()>> {
+        let path = match self.path()? {
+            Some(path) => path,
+            None => return Ok(futures::stream::pending().boxed_local()),
+        };
+        let file_name: OsString = path
+            .file_name()
+            .error("Palette path has no file name")?
+            .into();
+        let dir = path.parent().error("Palette path has no parent")?;
+
+        // Watch the directory, because the file is often replaced rather than modified
+        let mut notify = Inotify::init().error("Failed to start inotify")?;
+        notify
+            .add_watch(
+                dir,
+                WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::CREATE,
+            )
+            .error("Failed to watch palette directory")?;
+        let updates = notify
+            .event_stream([0; 1024])
+            .error("Failed to create event stream")?;
+
+        Ok(updates
+            .filter_map(move |event| {
+                let is_palette =
+                    matches!(event, Ok(event) if event.name.as_ref() == Some(&file_name));
+                async move { is_palette.then_some(()) }
+            })
+            .boxed_local())
+    }
+}
+
+fn parse_color(color: &str) -> Result<Color> {
+    format!("#{}", color.trim_start_matches('#')).parse()
+}
+
+/// Parse a base16 scheme. This is not a general purpose YAML parser, it only understands flat
+/// `key: value` pairs, which is enough for base16 schemes.
+fn parse_base16(contents: &str) -> Result<Palette> {
+    let mut colors = HashMap::new();
+    for line in contents.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some(kv) => kv,
+            None => continue,
+        };
+        let value = value.trim();
+        let value = match value.strip_prefix(['"', '\'']) {
+            Some(quoted) => quoted.split(['"', '\'']).next().unwrap_or_default(),
+            None => value.split(['#', ' ']).next().unwrap_or_default(),
+        };
+        colors.insert(key.trim(), value);
+    }
+    let color = |name: &str| -> Result<Color> {
+        parse_color(
+            colors
+                .get(name)
+                .or_error(|| format!("base16 scheme is missing '{name}'"))?,
+        )
+    };
+    Ok(Palette {
+        background: color("base00")?,
+        foreground: color("base05")?,
+        red: color("base08")?,
+        yellow: color("base0A")?,
+        green: color("base0B")?,
+        blue: color("base0D")?,
+    })
+}
+
+/// Build a palette from terminal colors (`background`, `foreground` and `color0`-`color15`)
+fn terminal_palette(colors: &HashMap<&str, &str>, source: &str) -> Result<Palette> {
+    let color = |name: &str| -> Result<Color> {
+        parse_color(
+            colors
+                .get(name)
+                .or_error(|| format!("{source} is missing '{name}'"))?,
+        )
+    };
+    Ok(Palette {
+        background: color("background")?,
+        foreground: color("foreground")?,
+        red: color("color1")?,
+        green: color("color2")?,
+        yellow: color("color3")?,
+        blue: color("color4")?,
+    })
+}
+
+fn parse_pywal(contents: &str) -> Result<Palette> {
+    #[derive(Deserialize)]
+    struct PywalColors {
+        special: HashMap<String, String>,
+        colors: HashMap<String, String>,
+    }
+    let pywal: PywalColors =
+        serde_json::from_str(contents).error("Failed to parse pywal colors")?;
+    let colors = pywal
+        .special
+        .iter()
+        .chain(&pywal.colors)
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    terminal_palette(&colors, "pywal colors")
+}
+
+/// Only the resources which apply to all clients (`*.color0` or `*color0`) are used
+fn parse_xresources(contents: &str) -> Result<Palette> {
+    let colors = contents
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter_map(|(key, value)| {
+            let key = key.trim().strip_prefix('*')?;
+            Some((key.strip_prefix('.').unwrap_or(key), value.trim()))
+        })
+        .collect();
+    terminal_palette(&colors, "X resources")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base16() {
+        let scheme = r##"
+scheme: "Default Dark"
+author: "Chris Kempson (http://chriskempson.com)"
+base00: "181818"
+base05: 'd8d8d8'
+base08: ab4642 # red
+base0A: "#f7ca88"
+base0B: "a1b56c"
+base0D: "7cafc2"
+"##;
+        let palette = parse_base16(scheme).unwrap();
+        assert_eq!(palette.background, "#181818".parse::<Color>().unwrap());
+        assert_eq!(palette.foreground, "#d8d8d8".parse::<Color>().unwrap());
+        assert_eq!(palette.red, "#ab4642".parse::<Color>().unwrap());
+        assert_eq!(palette.yellow, "#f7ca88".parse::<Color>().unwrap());
+    }
+
+    #[test]
+    fn xresources() {
+        let resources = "*.background:\t#1d1f21\n*.foreground:\t#c5c8c6\n*color1:\t#cc6666\n\
+            *.color2:\t#b5bd68\n*.color3:\t#f0c674\n*.color4:\t#81a2be\nURxvt.color1:\t#000000\n";
+        let palette = parse_xresources(resources).unwrap();
+        assert_eq!(palette.background, "#1d1f21".parse::<Color>().unwrap());
+        assert_eq!(palette.red, "#cc6666".parse::<Color>().unwrap());
+        assert_eq!(palette.blue, "#81a2be".parse::<Color>().unwrap());
+    }
+}
@@ -12,6 +12,19 @@ pub enum Separator {
     Custom(String),
 }
 
+/// Which side of the separator takes the color of the block it belongs to.
+///
+/// With `left` (the default, suitable for glyphs like `\ue0b2`) the separator's foreground is the
+/// background of the following block. With `right` (suitable for glyphs like `\ue0b0`) the
+/// separator's foreground is the background of the preceding block.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, SmartDefault)]
+#[serde(rename_all = "lowercase")]
+pub enum SeparatorDirection {
+    #[default]
+    Left,
+    Right,
+}
+
 impl FromStr for Separator {
     type Err = Error;
 
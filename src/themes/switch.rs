@@ -0,0 +1,204 @@
+use chrono::{Local, NaiveTime};
+use futures::stream::StreamExt;
+use serde::Deserialize;
+use tokio::process::Command;
+use zbus::zvariant::Value;
+
+use crate::errors::*;
+use crate::util::new_dbus_connection;
+use crate::wrappers::Seconds;
+use crate::BoxedStream;
+
+/// A variant of the theme which can be selected automatically
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Light,
+    Dark,
+}
+
+/// Defines what drives the switching between `light` and `dark` themes
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "mode", rename_all = "lowercase", deny_unknown_fields)]
+pub enum SwitchConfig {
+    /// Switch based on the time of day
+    Time {
+        #[serde(default = "default_light_start")]
+        light_start: String,
+        #[serde(default = "default_dark_start")]
+        dark_start: String,
+    },
+    /// Follow the `color-scheme` setting of the freedesktop settings portal
+    Portal,
+    /// Periodically run a command which prints either `light` or `dark`
+    Command {
+        command: String,
+        #[serde(default = "default_interval")]
+        interval: Seconds,
+    },
+}
+
+fn default_light_start() -> String {
+    "07:00".into()
+}
+
+fn default_dark_start() -> String {
+    "19:00".into()
+}
+
+fn default_interval() -> Seconds {
+    60.into()
+}
+
+impl SwitchConfig {
+    /// Returns an infinite stream of theme variants. The first item is the current variant, the
+    /// following ones are yielded only when the variant changes.
+    pub async fn variant_stream(&self) -> Result<BoxedStream<Variant>> {
+        match self {
+            Self::Time {
+                light_start,
+                dark_start,
+            } => {
+                let light_start = parse_time(light_start)?;
+                let dark_start = parse_time(dark_start)?;
+                Ok(polling_stream(Seconds::new(60), move || async move {
+                    Some(variant_at(Local::now().time(), light_start, dark_start))
+                }))
+            }
+            Self::Portal => portal_stream().await,
+            Self::Command { command, interval } => {
+                let command = command.clone();
+                Ok(polling_stream(*interval, move || {
+                    let command = command.clone();
+                    async move { run_command(&command).await }
+                }))
+            }
+        }
+    }
+}
+
+fn parse_time(time: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .or_error(|| format!("'{time}' is not a valid time, expected HH:MM"))
+}
+
+fn variant_at(time: NaiveTime, light_start: NaiveTime, dark_start: NaiveTime) -> Variant {
+    let is_light = if light_start <= dark_start {
+        time >= light_start && time < dark_start
+    } else {
+        time >= light_start || time < dark_start
+    };
+    if is_light {
+        Variant::Light
+    } else {
+        Variant::Dark
+    }
+}
+
+/// Call `f` every `interval` and yield its result if it differs from the previous one
+fn polling_stream<F, Fut>(interval: Seconds, f: F) -> BoxedStream<Variant>
+where
+    F: Fn() -> Fut + 'static,
+    Fut: std::future::Future<Output = Option<Variant>> + 'static,
+{
+    let mut timer = tokio::time::interval(interval.0);
+    timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    futures::stream::unfold((timer, None, f), |(mut timer, last, f)| async move {
+        loop {
+            timer.tick().await;
+            if let Some(variant) = f().await {
+                if last != Some(variant) {
+                    return Some((variant, (timer, Some(variant), f)));
+                }
+            }
+        }
+    })
+    .boxed_local()
+}
+
+async fn run_command(command: &str) -> Option<Variant> {
+    let output = match Command::new("sh").args(["-c", command]).output().await {
+        Ok(output) => output,
+        Err(e) => {
+            log::warn!("Failed to run theme switch command: {e}");
+            return None;
+        }
+    };
+    match String::from_utf8_lossy(&output.stdout).trim() {
+        "light" => Some(Variant::Light),
+        "dark" => Some(Variant::Dark),
+        other => {
+            log::warn!("Theme switch command printed '{other}', expected 'light' or 'dark'");
+            None
+        }
+    }
+}
+
+async fn portal_stream() -> Result<BoxedStream<Variant>> {
+    let conn = new_dbus_connection().await?;
+    let proxy = SettingsProxy::new(&conn)
+        .await
+        .error("Failed to create SettingsProxy")?;
+    let changes = proxy
+        .receive_setting_changed()
+        .await
+        .error("Failed to monitor settings portal")?;
+    let current = proxy
+        .read("org.freedesktop.appearance", "color-scheme")
+        .await
+        .error("Failed to read color-scheme from settings portal")?;
+    let current = color_scheme_variant(&current);
+
+    let changes = changes.filter_map(|signal| async move {
+        let args = signal.args().ok()?;
+        (*args.namespace() == "org.freedesktop.appearance" && *args.key() == "color-scheme")
+            .then(|| color_scheme_variant(args.value()))
+    });
+    Ok(futures::stream::once(async move { current })
+        .chain(changes)
+        .scan(None, |last, variant| {
+            let changed = *last != Some(variant);
+            *last = Some(variant);
+            async move { Some(changed.then_some(variant)) }
+        })
+        .filter_map(|variant| async move { variant })
+        .boxed_local())
+}
+
+/// `1` means "prefer dark", everything else is treated as light
+fn color_scheme_variant(value: &Value) -> Variant {
+    match value {
+        Value::Value(inner) => color_scheme_variant(inner),
+        Value::U32(1) => Variant::Dark,
+        _ => Variant::Light,
+    }
+}
+
+#[zbus::dbus_proxy(
+    interface = "org.freedesktop.portal.Settings",
+    default_service = "org.freedesktop.portal.Desktop",
+    default_path = "/org/freedesktop/portal/desktop"
+)]
+trait Settings {
+    fn read(&self, namespace: &str, key: &str) -> zbus::Result<zbus::zvariant::OwnedValue>;
+
+    #[dbus_proxy(signal)]
+    fn setting_changed(&self, namespace: &str, key: &str, value: Value<'_>) -> zbus::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_variants() {
+        let t = |s| parse_time(s).unwrap();
+        let day = |time| variant_at(t(time), t("07:00"), t("19:00"));
+        assert_eq!(day("12:00"), Variant::Light);
+        assert_eq!(day("19:00"), Variant::Dark);
+        assert_eq!(day("03:00"), Variant::Dark);
+        // Light period wraps around midnight
+        let night = |time| variant_at(t(time), t("20:00"), t("06:00"));
+        assert_eq!(night("23:00"), Variant::Light);
+        assert_eq!(night("12:00"), Variant::Dark);
+    }
+}
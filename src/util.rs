@@ -2,8 +2,9 @@ use std::fs::File;
 use std::io::{self, BufReader, Read};
 use std::path::{Path, PathBuf};
 
-use dirs_next::{config_dir, data_dir};
+use dirs_next::{cache_dir, config_dir, data_dir};
 use serde::de::DeserializeOwned;
+use serde::Serialize;
 use tokio::io::AsyncReadExt;
 use tokio::process::Command;
 
@@ -65,6 +66,69 @@ pub fn find_file(file: &str, subdir: Option<&str>, extension: Option<&str>) -> O
     None
 }
 
+/// Lists the names (without extension) of all files with the given extension in `subdir` of the
+/// standard locations, see [`find_file`].
+pub fn list_files(subdir: &str, extension: &str) -> Vec<String> {
+    let mut dirs = Vec::new();
+    if let Some(xdg_config) = config_dir() {
+        dirs.push(xdg_config.join("i3status-rust").join(subdir));
+    }
+    if let Some(xdg_data) = data_dir() {
+        dirs.push(xdg_data.join("i3status-rust").join(subdir));
+    }
+    dirs.push(PathBuf::from("/usr/share/i3status-rust").join(subdir));
+
+    let mut names: Vec<String> = dirs
+        .iter()
+        .filter_map(|dir| dir.read_dir().ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == extension))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// The path of `file` in `$XDG_CACHE_HOME/i3status-rust` (e.g. `~/.cache/i3status-rust`)
+fn cache_file_path(file: &str) -> Result<PathBuf> {
+    let mut path = cache_dir().error("Failed to find the cache directory")?;
+    path.push("i3status-rust");
+    path.push(file);
+    Ok(path)
+}
+
+/// Reads a value stored with [`store_cache_file`]. Returns `None` if the file does not exist or
+/// is not valid.
+pub fn load_cache_file<T: DeserializeOwned>(file: &str) -> Option<T> {
+    let data = std::fs::read_to_string(cache_file_path(file).ok()?).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+/// Stores `value` as JSON in `file` in the cache directory, so that it survives restarts of the
+/// bar.
+pub fn store_cache_file<T: Serialize>(file: &str, value: &T) -> Result<()> {
+    let path = cache_file_path(file)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).or_error(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let data = serde_json::to_string(value).error("Failed to serialize")?;
+    std::fs::write(&path, data).or_error(|| format!("Failed to write {}", path.display()))
+}
+
+/// Removes `file` from the cache directory, if it exists.
+pub fn remove_cache_file(file: &str) -> Result<()> {
+    let path = cache_file_path(file)?;
+    match std::fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            Err(e).or_error(|| format!("Failed to remove {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
 pub async fn new_dbus_connection() -> Result<zbus::Connection> {
     zbus::Connection::session()
         .await
@@ -77,23 +141,6 @@ pub async fn new_system_dbus_connection() -> Result<zbus::Connection> {
         .error("Failed to open DBus system connection")
 }
 
-pub fn battery_level_icon(level: u8, charging: bool) -> &'static str {
-    match (level, charging) {
-        // TODO: use different charging icons
-        (_, true) => "bat_charging",
-        (0..=10, _) => "bat_10",
-        (11..=20, _) => "bat_20",
-        (21..=30, _) => "bat_30",
-        (31..=40, _) => "bat_40",
-        (41..=50, _) => "bat_50",
-        (51..=60, _) => "bat_60",
-        (61..=70, _) => "bat_70",
-        (71..=80, _) => "bat_80",
-        (81..=90, _) => "bat_90",
-        _ => "bat_full",
-    }
-}
-
 pub fn deserialize_toml_file<T, P>(path: P) -> Result<T>
 where
     T: DeserializeOwned,
@@ -139,6 +186,18 @@ pub async fn has_command(command: &str) -> Result<bool> {
         .map(|status| status.success())
 }
 
+/// Ticks the timer if there is one, otherwise never completes. Meant for optional timers in
+/// `select!`, e.g. `Some(_) = util::tick_optional(&mut timer) => ...`.
+pub async fn tick_optional(timer: &mut Option<tokio::time::Interval>) -> Option<()> {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
+            Some(())
+        }
+        None => std::future::pending().await,
+    }
+}
+
 /// # Example
 ///
 /// ```
@@ -194,6 +253,12 @@ macro_rules! make_log_macro {
     };
 }
 
+/// Appends `elem` to a fixed-size history, dropping the oldest element
+pub fn push_to_hist<T>(hist: &mut [T], elem: T) {
+    hist[0] = elem;
+    hist.rotate_left(1);
+}
+
 pub fn format_bar_graph(content: &[f64]) -> String {
     // (x * one eighth block) https://en.wikipedia.org/wiki/Block_Elements
     static BARS: [char; 8] = [
@@ -258,6 +323,22 @@ mod tests {
         assert!(!tokio_test::block_on(has_command("thequickbrownfoxjumpsoverthelazydog")).unwrap());
     }
 
+    #[test]
+    fn test_push_to_hist() {
+        let mut hist = [0; 4];
+        assert_eq!(&hist, &[0, 0, 0, 0]);
+        push_to_hist(&mut hist, 1);
+        assert_eq!(&hist, &[0, 0, 0, 1]);
+        push_to_hist(&mut hist, 3);
+        assert_eq!(&hist, &[0, 0, 1, 3]);
+        push_to_hist(&mut hist, 0);
+        assert_eq!(&hist, &[0, 1, 3, 0]);
+        push_to_hist(&mut hist, 10);
+        assert_eq!(&hist, &[1, 3, 0, 10]);
+        push_to_hist(&mut hist, 2);
+        assert_eq!(&hist, &[3, 0, 10, 2]);
+    }
+
     #[test]
     fn test_flags() {
         assert!(country_flag_from_iso_code("ES") == "🇪🇸");